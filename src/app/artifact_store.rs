@@ -1,12 +1,16 @@
+use std::collections::HashSet;
 use std::fs::File;
 use std::io::{self, Read as _, Write as _};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::Context as _;
 use async_trait::async_trait;
 use base64::Engine as _;
 use sha2::Digest as _;
 use tokio::fs;
+use tokio::sync::RwLock;
 
 #[async_trait]
 pub trait ArtifactStore: Send + Sync {
@@ -18,7 +22,61 @@ pub trait ArtifactStore: Send + Sync {
         workspace_dir: &Path,
     ) -> anyhow::Result<PathBuf>;
 
-    async fn generate_download_url(&self, job_id: &str, ttl_secs: u32) -> anyhow::Result<String>;
+    async fn generate_download_url(
+        &self,
+        job_id: &str,
+        artifact: ArtifactKind,
+        ttl_secs: u32,
+    ) -> anyhow::Result<String>;
+
+    /// Cheap readiness probe: fails when a new artifact could not be stored right now.
+    async fn health_check(&self) -> anyhow::Result<()>;
+}
+
+/// Downloadable outputs of a finished job.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArtifactKind {
+    /// `artifact.zip` with `book.md`, `book.epub` and `assets/`.
+    #[default]
+    Zip,
+    /// The bundled `book.md`.
+    Markdown,
+    /// `book.epub` (absent when the job skipped EPUB generation).
+    Epub,
+}
+
+impl ArtifactKind {
+    /// File name in the workspace (and object name suffix in GCS).
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ArtifactKind::Zip => "artifact.zip",
+            ArtifactKind::Markdown => "book.md",
+            ArtifactKind::Epub => "book.epub",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            ArtifactKind::Zip => "application/zip",
+            ArtifactKind::Markdown => "text/markdown; charset=utf-8",
+            ArtifactKind::Epub => "application/epub+zip",
+        }
+    }
+}
+
+/// Creates `dir` if needed and round-trips a small probe file through it.
+pub async fn probe_writable_dir(dir: &Path) -> anyhow::Result<()> {
+    fs::create_dir_all(dir)
+        .await
+        .with_context(|| format!("create dir: {}", dir.display()))?;
+    let probe = dir.join(format!(".readyz-{}", uuid::Uuid::new_v4()));
+    fs::write(&probe, b"ok")
+        .await
+        .with_context(|| format!("write probe file: {}", probe.display()))?;
+    fs::remove_file(&probe)
+        .await
+        .with_context(|| format!("remove probe file: {}", probe.display()))?;
+    Ok(())
 }
 
 #[derive(Debug, Clone)]
@@ -75,27 +133,147 @@ impl ArtifactStore for LocalFsArtifactStore {
         Ok(artifact_path)
     }
 
-    async fn generate_download_url(&self, job_id: &str, _ttl_secs: u32) -> anyhow::Result<String> {
-        Ok(format!("/artifacts/{job_id}"))
+    async fn generate_download_url(
+        &self,
+        job_id: &str,
+        artifact: ArtifactKind,
+        _ttl_secs: u32,
+    ) -> anyhow::Result<String> {
+        Ok(match artifact {
+            ArtifactKind::Zip => format!("/artifacts/{job_id}"),
+            ArtifactKind::Markdown | ArtifactKind::Epub => {
+                format!("/jobs/{job_id}/{}", artifact.file_name())
+            }
+        })
+    }
+
+    async fn health_check(&self) -> anyhow::Result<()> {
+        probe_writable_dir(&self.jobs_dir()).await
+    }
+}
+
+const METADATA_ATTEMPTS: u32 = 3;
+const METADATA_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+const METADATA_REQUEST_TIMEOUT: Duration = Duration::from_secs(3);
+const METADATA_INITIAL_BACKOFF: Duration = Duration::from_millis(200);
+
+/// GETs a metadata server URL, retrying connection errors, timeouts, `429` and `5xx` with
+/// exponential backoff. Cold GCE instances often answer the first few requests slowly or not
+/// at all.
+async fn get_metadata(client: &reqwest::Client, url: &str) -> anyhow::Result<reqwest::Response> {
+    let mut backoff = METADATA_INITIAL_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        let error = match client
+            .get(url)
+            .header("Metadata-Flavor", "Google")
+            .timeout(METADATA_REQUEST_TIMEOUT)
+            .send()
+            .await
+        {
+            Ok(resp) if resp.status().is_success() => return Ok(resp),
+            Ok(resp)
+                if resp.status().is_server_error()
+                    || resp.status() == reqwest::StatusCode::TOO_MANY_REQUESTS =>
+            {
+                anyhow::anyhow!("metadata request failed ({})", resp.status())
+            }
+            Ok(resp) => anyhow::bail!("metadata request failed ({})", resp.status()),
+            Err(err) => anyhow::Error::new(err).context("send metadata request"),
+        };
+        if attempt >= METADATA_ATTEMPTS {
+            return Err(error.context(format!("metadata request gave up after {attempt} attempts")));
+        }
+        tracing::warn!(url, attempt, error = %format!("{error:#}"), "retrying metadata request");
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+        attempt += 1;
+    }
+}
+
+/// Custom object metadata key holding an uploaded file's sha256 (hex).
+const ARTIFACT_SHA256_METADATA_KEY: &str = "sitebookify-sha256";
+
+/// How long before expiry a cached access token is refreshed.
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone)]
+pub struct AccessToken {
+    pub token: String,
+    pub expires_in: Duration,
+}
+
+/// Where [`GcsArtifactStore`] gets OAuth access tokens (the metadata server by default).
+#[async_trait]
+pub trait AccessTokenFetcher: std::fmt::Debug + Send + Sync {
+    async fn fetch_access_token(&self) -> anyhow::Result<AccessToken>;
+}
+
+#[derive(Debug, Clone)]
+struct MetadataTokenFetcher {
+    client: reqwest::Client,
+}
+
+#[async_trait]
+impl AccessTokenFetcher for MetadataTokenFetcher {
+    async fn fetch_access_token(&self) -> anyhow::Result<AccessToken> {
+        #[derive(Debug, serde::Deserialize)]
+        struct TokenResponse {
+            access_token: String,
+            #[serde(default)]
+            expires_in: u64,
+        }
+
+        let url = "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+        let resp = get_metadata(&self.client, url)
+            .await
+            .context("request metadata access token")?;
+        let token: TokenResponse = resp.json().await.context("parse metadata token json")?;
+        Ok(AccessToken {
+            token: token.access_token,
+            expires_in: Duration::from_secs(token.expires_in),
+        })
     }
 }
 
+#[derive(Debug, Clone)]
+struct CachedAccessToken {
+    token: String,
+    refresh_at: Instant,
+}
+
 #[derive(Debug, Clone)]
 pub struct GcsArtifactStore {
     base_dir: PathBuf,
     bucket: String,
     client: reqwest::Client,
+    token_fetcher: Arc<dyn AccessTokenFetcher>,
+    access_token_cache: Arc<RwLock<Option<CachedAccessToken>>>,
 }
 
 impl GcsArtifactStore {
     pub fn new(base_dir: impl Into<PathBuf>, bucket: impl Into<String>) -> Self {
+        let client = reqwest::Client::builder()
+            .connect_timeout(METADATA_CONNECT_TIMEOUT)
+            .build()
+            .unwrap_or_default();
         Self {
             base_dir: base_dir.into(),
             bucket: bucket.into(),
-            client: reqwest::Client::new(),
+            token_fetcher: Arc::new(MetadataTokenFetcher {
+                client: client.clone(),
+            }),
+            client,
+            access_token_cache: Arc::new(RwLock::new(None)),
         }
     }
 
+    /// Replaces the metadata-server token source (e.g. with a fixed token in tests).
+    pub fn with_token_fetcher(mut self, token_fetcher: Arc<dyn AccessTokenFetcher>) -> Self {
+        self.token_fetcher = token_fetcher;
+        self
+    }
+
     fn jobs_dir(&self) -> PathBuf {
         self.base_dir.join("jobs")
     }
@@ -104,46 +282,39 @@ impl GcsArtifactStore {
         self.jobs_dir().join(job_id)
     }
 
-    fn object_name(&self, job_id: &str) -> String {
-        format!("jobs/{job_id}/artifact.zip")
+    fn object_name(&self, job_id: &str, artifact: ArtifactKind) -> String {
+        format!("jobs/{job_id}/{}", artifact.file_name())
     }
 
+    /// Cached access token, refetched once it is within [`TOKEN_REFRESH_MARGIN`] of expiry.
     async fn access_token(&self) -> anyhow::Result<String> {
-        #[derive(Debug, serde::Deserialize)]
-        struct TokenResponse {
-            access_token: String,
+        if let Some(cached) = self.access_token_cache.read().await.as_ref()
+            && cached.refresh_at > Instant::now()
+        {
+            return Ok(cached.token.clone());
         }
 
-        let url = "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
-        let resp = self
-            .client
-            .get(url)
-            .header("Metadata-Flavor", "Google")
-            .send()
-            .await
-            .context("request metadata access token")?;
-        if !resp.status().is_success() {
-            anyhow::bail!("metadata token request failed ({})", resp.status());
+        let mut cache = self.access_token_cache.write().await;
+        if let Some(cached) = cache.as_ref()
+            && cached.refresh_at > Instant::now()
+        {
+            return Ok(cached.token.clone());
         }
-        let token: TokenResponse = resp.json().await.context("parse metadata token json")?;
-        Ok(token.access_token)
+
+        let fetched_at = Instant::now();
+        let token = self.token_fetcher.fetch_access_token().await?;
+        *cache = Some(CachedAccessToken {
+            token: token.token.clone(),
+            refresh_at: fetched_at + token.expires_in.saturating_sub(TOKEN_REFRESH_MARGIN),
+        });
+        Ok(token.token)
     }
 
     async fn service_account_email(&self) -> anyhow::Result<String> {
         let url = "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/email";
-        let resp = self
-            .client
-            .get(url)
-            .header("Metadata-Flavor", "Google")
-            .send()
+        let resp = get_metadata(&self.client, url)
             .await
             .context("request metadata service account email")?;
-        if !resp.status().is_success() {
-            anyhow::bail!(
-                "metadata service account email request failed ({})",
-                resp.status()
-            );
-        }
         let text = resp.text().await.context("read metadata email response")?;
         Ok(text.trim().to_string())
     }
@@ -191,26 +362,57 @@ impl GcsArtifactStore {
         Ok(signature)
     }
 
-    async fn upload_zip(&self, object_name: &str, local_zip_path: &Path) -> anyhow::Result<()> {
+    /// Uploads a file unless the object already carries the same content hash.
+    async fn upload_object(
+        &self,
+        object_name: &str,
+        local_path: &Path,
+        content_type: &str,
+    ) -> anyhow::Result<()> {
         let access_token = self.access_token().await.context("get access token")?;
-        let object_name_encoded = percent_encode_rfc3986(object_name);
+        let bytes = tokio::fs::read(local_path)
+            .await
+            .with_context(|| format!("read upload file: {}", local_path.display()))?;
+        let sha256 = crate::raw_store::sha256_hex(&bytes);
+
+        match self.remote_sha256(&access_token, object_name).await {
+            Ok(Some(remote)) if remote == sha256 => {
+                tracing::info!(object_name, sha256, "object unchanged; skip gcs upload");
+                return Ok(());
+            }
+            Ok(_) => {}
+            Err(err) => {
+                tracing::warn!(
+                    object_name,
+                    ?err,
+                    "failed to read artifact metadata; uploading"
+                );
+            }
+        }
+
         let url = format!(
-            "https://storage.googleapis.com/upload/storage/v1/b/{bucket}/o?uploadType=media&name={object_name_encoded}",
+            "https://storage.googleapis.com/upload/storage/v1/b/{bucket}/o?uploadType=multipart",
             bucket = self.bucket
         );
-
-        let bytes = tokio::fs::read(local_zip_path)
-            .await
-            .with_context(|| format!("read zip: {}", local_zip_path.display()))?;
+        let metadata = serde_json::json!({
+            "name": object_name,
+            "contentType": content_type,
+            "metadata": { ARTIFACT_SHA256_METADATA_KEY: sha256 },
+        });
+        let boundary = format!("sitebookify-{}", uuid::Uuid::new_v4().simple());
+        let body = multipart_related_body(&boundary, &metadata, content_type, &bytes);
         let resp = self
             .client
             .post(url)
             .bearer_auth(access_token)
-            .header(reqwest::header::CONTENT_TYPE, "application/zip")
-            .body(bytes)
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                format!("multipart/related; boundary={boundary}"),
+            )
+            .body(body)
             .send()
             .await
-            .context("upload artifact to gcs")?;
+            .with_context(|| format!("upload object to gcs: {object_name}"))?;
         if !resp.status().is_success() {
             let status = resp.status();
             let body = resp.text().await.unwrap_or_default();
@@ -219,6 +421,43 @@ impl GcsArtifactStore {
         Ok(())
     }
 
+    /// Content hash recorded on an uploaded artifact, or `None` when the object does not exist
+    /// (or predates the hash metadata).
+    async fn remote_sha256(
+        &self,
+        access_token: &str,
+        object_name: &str,
+    ) -> anyhow::Result<Option<String>> {
+        #[derive(Debug, serde::Deserialize)]
+        struct ObjectResource {
+            #[serde(default)]
+            metadata: Option<std::collections::HashMap<String, String>>,
+        }
+
+        let url = format!(
+            "https://storage.googleapis.com/storage/v1/b/{bucket}/o/{object}?fields=metadata",
+            bucket = self.bucket,
+            object = percent_encode_rfc3986(object_name)
+        );
+        let resp = self
+            .client
+            .get(url)
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .context("get artifact object metadata")?;
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !resp.status().is_success() {
+            anyhow::bail!("gcs object metadata request failed ({})", resp.status());
+        }
+        let object: ObjectResource = resp.json().await.context("parse object metadata json")?;
+        Ok(object
+            .metadata
+            .and_then(|mut metadata| metadata.remove(ARTIFACT_SHA256_METADATA_KEY)))
+    }
+
     async fn signed_download_url(
         &self,
         service_account_email: &str,
@@ -296,7 +535,11 @@ impl ArtifactStore for GcsArtifactStore {
     }
 
     fn artifact_uri(&self, job_id: &str) -> String {
-        format!("gs://{}/{}", self.bucket, self.object_name(job_id))
+        format!(
+            "gs://{}/{}",
+            self.bucket,
+            self.object_name(job_id, ArtifactKind::Zip)
+        )
     }
 
     async fn create_zip_from_workspace(
@@ -309,25 +552,39 @@ impl ArtifactStore for GcsArtifactStore {
             .with_context(|| format!("create job dir: {}", self.job_dir(job_id).display()))?;
 
         let artifact_path = self.artifact_path(job_id);
-        let workspace_dir = workspace_dir.to_path_buf();
+        let workspace_dir_for_blocking = workspace_dir.to_path_buf();
         let artifact_path_for_blocking = artifact_path.clone();
 
         tokio::task::spawn_blocking(move || {
-            create_zip_from_workspace_blocking(&workspace_dir, &artifact_path_for_blocking)
+            create_zip_from_workspace_blocking(
+                &workspace_dir_for_blocking,
+                &artifact_path_for_blocking,
+            )
         })
         .await
         .context("join zip task")??;
 
-        let object_name = self.object_name(job_id);
-        tracing::info!(
-            bucket = %self.bucket,
-            object = %object_name,
-            path = %artifact_path.display(),
-            "uploading artifact to gcs"
-        );
-        self.upload_zip(&object_name, &artifact_path)
-            .await
-            .context("upload zip")?;
+        // The zip, plus its book files as separate objects so each can get its own signed URL.
+        let uploads = [
+            (ArtifactKind::Zip, artifact_path.clone()),
+            (ArtifactKind::Markdown, workspace_dir.join("book.md")),
+            (ArtifactKind::Epub, workspace_dir.join("book.epub")),
+        ];
+        for (artifact, path) in uploads {
+            if artifact != ArtifactKind::Zip && !path.exists() {
+                continue;
+            }
+            let object_name = self.object_name(job_id, artifact);
+            tracing::info!(
+                bucket = %self.bucket,
+                object = %object_name,
+                path = %path.display(),
+                "uploading artifact to gcs"
+            );
+            self.upload_object(&object_name, &path, artifact.content_type())
+                .await
+                .with_context(|| format!("upload {}", artifact.file_name()))?;
+        }
 
         if let Err(err) = tokio::fs::remove_file(&artifact_path).await {
             tracing::warn!(path = %artifact_path.display(), ?err, "failed to remove local artifact zip after upload");
@@ -336,12 +593,17 @@ impl ArtifactStore for GcsArtifactStore {
         Ok(artifact_path)
     }
 
-    async fn generate_download_url(&self, job_id: &str, ttl_secs: u32) -> anyhow::Result<String> {
+    async fn generate_download_url(
+        &self,
+        job_id: &str,
+        artifact: ArtifactKind,
+        ttl_secs: u32,
+    ) -> anyhow::Result<String> {
         let service_account_email = self
             .service_account_email()
             .await
             .context("get service account email")?;
-        let object_name = self.object_name(job_id);
+        let object_name = self.object_name(job_id, artifact);
         self.signed_download_url(
             &service_account_email,
             &object_name,
@@ -350,6 +612,32 @@ impl ArtifactStore for GcsArtifactStore {
         )
         .await
     }
+
+    async fn health_check(&self) -> anyhow::Result<()> {
+        // Zips are staged locally before upload.
+        probe_writable_dir(&self.jobs_dir()).await?;
+        self.access_token()
+            .await
+            .context("fetch metadata access token")?;
+        Ok(())
+    }
+}
+
+/// `multipart/related` body for a GCS upload that sets object metadata and content at once.
+fn multipart_related_body(
+    boundary: &str,
+    metadata: &serde_json::Value,
+    content_type: &str,
+    content: &[u8],
+) -> Vec<u8> {
+    let mut body = format!(
+        "--{boundary}\r\nContent-Type: application/json; charset=UTF-8\r\n\r\n{metadata}\r\n\
+--{boundary}\r\nContent-Type: {content_type}\r\n\r\n"
+    )
+    .into_bytes();
+    body.extend_from_slice(content);
+    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+    body
 }
 
 fn create_zip_from_workspace_blocking(workspace_dir: &Path, out_zip: &Path) -> anyhow::Result<()> {
@@ -362,9 +650,11 @@ fn create_zip_from_workspace_blocking(workspace_dir: &Path, out_zip: &Path) -> a
         File::create(out_zip).with_context(|| format!("create zip: {}", out_zip.display()))?;
     let mut zip = zip::ZipWriter::new(file);
 
+    // Fixed timestamps and sorted entries keep the zip byte-identical across re-runs.
     let options = zip::write::SimpleFileOptions::default()
         .compression_method(zip::CompressionMethod::Deflated)
-        .unix_permissions(0o644);
+        .unix_permissions(0o644)
+        .last_modified_time(zip::DateTime::default());
 
     zip.start_file("book.md", options)
         .context("zip start_file book.md")?;
@@ -383,20 +673,36 @@ fn create_zip_from_workspace_blocking(workspace_dir: &Path, out_zip: &Path) -> a
 
     let assets_dir = workspace_dir.join("assets");
     if assets_dir.exists() {
-        add_dir_recursive(&mut zip, &assets_dir, Path::new("assets"), options)
-            .context("zip add assets")?;
+        add_dir_recursive(
+            &mut zip,
+            &assets_dir,
+            &assets_dir,
+            Path::new("assets"),
+            options,
+            &mut HashSet::new(),
+        )
+        .context("zip add assets")?;
     }
 
     zip.finish().context("zip finish")?;
     Ok(())
 }
 
+/// Adds `dir` (somewhere under `base`) to the zip. Symlinks are followed only while they stay
+/// inside `base`, so a link in `assets/` cannot pull in files from elsewhere on the host, and
+/// never into a directory in `ancestors` (the canonical directories being zipped above `dir`).
 fn add_dir_recursive<W: io::Write + io::Seek>(
     zip: &mut zip::ZipWriter<W>,
+    base: &Path,
     dir: &Path,
     zip_prefix: &Path,
     options: zip::write::SimpleFileOptions,
+    ancestors: &mut HashSet<PathBuf>,
 ) -> anyhow::Result<()> {
+    let canonical = dir
+        .canonicalize()
+        .with_context(|| format!("resolve dir: {}", dir.display()))?;
+    ancestors.insert(canonical.clone());
     let mut entries = std::fs::read_dir(dir)
         .with_context(|| format!("read dir: {}", dir.display()))?
         .collect::<Result<Vec<_>, _>>()
@@ -404,16 +710,35 @@ fn add_dir_recursive<W: io::Write + io::Seek>(
     entries.sort_by_key(|e| e.file_name());
 
     for entry in entries {
-        let path = entry.path();
+        let mut path = entry.path();
         let name = entry.file_name();
         let zip_path = zip_prefix.join(name.to_string_lossy().as_ref());
 
-        let file_type = entry.file_type().context("read file type")?;
+        let mut file_type = entry.file_type().context("read file type")?;
+        if file_type.is_symlink() {
+            let Some(target) = crate::book::symlink_target_within(base, &path) else {
+                tracing::warn!(path = %path.display(), base = %base.display(), "skipping symlink that leaves the zipped directory");
+                continue;
+            };
+            file_type = std::fs::metadata(&target)
+                .with_context(|| format!("stat symlink target: {}", target.display()))?
+                .file_type();
+            path = target;
+        }
+
+        if file_type.is_dir()
+            && path
+                .canonicalize()
+                .is_ok_and(|dir| ancestors.contains(&dir))
+        {
+            tracing::warn!(path = %path.display(), "skipping symlink that loops back to a parent directory");
+            continue;
+        }
         if file_type.is_dir() {
             // Ensure the directory entry exists in the zip.
             zip.add_directory(zip_path.to_string_lossy(), options)
                 .with_context(|| format!("zip add_directory: {}", zip_path.display()))?;
-            add_dir_recursive(zip, &path, &zip_path, options)?;
+            add_dir_recursive(zip, base, &path, &zip_path, options, ancestors)?;
             continue;
         }
 
@@ -431,6 +756,7 @@ fn add_dir_recursive<W: io::Write + io::Seek>(
             .with_context(|| format!("zip write: {}", zip_path.display()))?;
     }
 
+    ancestors.remove(&canonical);
     Ok(())
 }
 
@@ -481,11 +807,260 @@ mod tests {
         assert_eq!(percent_encode_path("a/b c"), "a/b%20c");
     }
 
+    #[tokio::test]
+    async fn get_metadata_retries_transient_failures() -> anyhow::Result<()> {
+        let server = tiny_http::Server::http("127.0.0.1:0").expect("start server");
+        let url = format!("http://{}/token", server.server_addr());
+        let handle = std::thread::spawn(move || {
+            let mut flavors = Vec::new();
+            for status in [503, 500, 200] {
+                let request = server.recv().expect("recv request");
+                flavors.push(
+                    request
+                        .headers()
+                        .iter()
+                        .find(|h| h.field.equiv("Metadata-Flavor"))
+                        .map(|h| h.value.to_string()),
+                );
+                let _ = request
+                    .respond(tiny_http::Response::from_string("token").with_status_code(status));
+            }
+            flavors
+        });
+
+        let resp = get_metadata(&reqwest::Client::new(), &url).await?;
+        assert_eq!(resp.text().await?, "token");
+        let flavors = handle.join().expect("join server");
+        assert_eq!(flavors, vec![Some("Google".to_owned()); 3]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_metadata_does_not_retry_client_errors() {
+        let server = tiny_http::Server::http("127.0.0.1:0").expect("start server");
+        let url = format!("http://{}/email", server.server_addr());
+        let handle = std::thread::spawn(move || {
+            let request = server.recv().expect("recv request");
+            let _ = request.respond(tiny_http::Response::from_string("no").with_status_code(404));
+            server
+                .recv_timeout(Duration::from_millis(500))
+                .expect("recv")
+                .is_some()
+        });
+
+        let err = get_metadata(&reqwest::Client::new(), &url)
+            .await
+            .expect_err("404 should fail");
+        assert!(err.to_string().contains("404"), "{err:#}");
+        assert!(
+            !handle.join().expect("join server"),
+            "404 must not be retried"
+        );
+    }
+
+    #[derive(Debug)]
+    struct CountingTokenFetcher {
+        expires_in: Duration,
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl AccessTokenFetcher for CountingTokenFetcher {
+        async fn fetch_access_token(&self) -> anyhow::Result<AccessToken> {
+            let n = self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(AccessToken {
+                token: format!("token-{n}"),
+                expires_in: self.expires_in,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn gcs_access_token_is_cached_until_near_expiry() -> anyhow::Result<()> {
+        let fetcher = Arc::new(CountingTokenFetcher {
+            expires_in: Duration::from_secs(3600),
+            calls: Default::default(),
+        });
+        let store = GcsArtifactStore::new("data", "bucket").with_token_fetcher(fetcher.clone());
+        assert_eq!(store.access_token().await?, "token-0");
+        assert_eq!(store.access_token().await?, "token-0");
+        assert_eq!(fetcher.calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        let short_lived = Arc::new(CountingTokenFetcher {
+            expires_in: TOKEN_REFRESH_MARGIN,
+            calls: Default::default(),
+        });
+        let store = GcsArtifactStore::new("data", "bucket").with_token_fetcher(short_lived.clone());
+        assert_eq!(store.access_token().await?, "token-0");
+        assert_eq!(store.access_token().await?, "token-1");
+        Ok(())
+    }
+
+    #[test]
+    fn workspace_zip_is_byte_identical_across_runs() -> anyhow::Result<()> {
+        let temp = tempfile::TempDir::new()?;
+        let workspace = temp.path().join("workspace");
+        std::fs::create_dir_all(workspace.join("assets").join("img"))?;
+        std::fs::write(workspace.join("assets").join("img").join("a.png"), b"png")?;
+        let book_src = workspace.join("book").join("src");
+        std::fs::create_dir_all(&book_src)?;
+        std::fs::write(
+            book_src.join("SUMMARY.md"),
+            "# Summary\n\n- [Intro](ch01.md)\n",
+        )?;
+        std::fs::write(book_src.join("ch01.md"), "# Intro\n\nHello.\n")?;
+        // Each job run writes book.md and builds book.epub afresh.
+        let build_workspace = || -> anyhow::Result<()> {
+            std::fs::write(workspace.join("book.md"), "# Book\n")?;
+            crate::epub::create_from_mdbook(
+                &workspace.join("book"),
+                &workspace.join("book.epub"),
+                &crate::epub::CreateEpubOptions {
+                    force: true,
+                    ..Default::default()
+                },
+            )
+        };
+
+        build_workspace()?;
+        let first = temp.path().join("first.zip");
+        create_zip_from_workspace_blocking(&workspace, &first)?;
+        std::thread::sleep(Duration::from_millis(1100));
+        build_workspace()?;
+        let second = temp.path().join("second.zip");
+        create_zip_from_workspace_blocking(&workspace, &second)?;
+
+        let names = zip::ZipArchive::new(File::open(&first)?)?
+            .file_names()
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+        assert!(names.iter().any(|name| name == "book.epub"), "{names:?}");
+
+        assert_eq!(std::fs::read(&first)?, std::fs::read(&second)?);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn workspace_zip_skips_symlinks_that_leave_assets() -> anyhow::Result<()> {
+        let temp = tempfile::TempDir::new()?;
+        let secret = temp.path().join("secret.txt");
+        std::fs::write(&secret, "do not ship")?;
+        let workspace = temp.path().join("workspace");
+        let assets = workspace.join("assets");
+        std::fs::create_dir_all(assets.join("img"))?;
+        std::fs::write(workspace.join("book.md"), "# Book\n")?;
+        std::fs::write(assets.join("img").join("a.png"), b"png")?;
+        std::os::unix::fs::symlink(&secret, assets.join("leak.txt"))?;
+        std::os::unix::fs::symlink(temp.path(), assets.join("up"))?;
+        std::os::unix::fs::symlink(&assets, assets.join("img").join("loop"))?;
+        std::os::unix::fs::symlink(assets.join("img").join("a.png"), assets.join("alias.png"))?;
+
+        let out = temp.path().join("out.zip");
+        create_zip_from_workspace_blocking(&workspace, &out)?;
+
+        let mut archive = zip::ZipArchive::new(File::open(&out)?)?;
+        let mut names = archive.file_names().map(str::to_owned).collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "assets/alias.png",
+                "assets/img/",
+                "assets/img/a.png",
+                "book.md"
+            ]
+        );
+        let mut alias = String::new();
+        archive
+            .by_name("assets/alias.png")?
+            .read_to_string(&mut alias)?;
+        assert_eq!(alias, "png");
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn workspace_zip_stops_at_mutually_linked_directories() -> anyhow::Result<()> {
+        let temp = tempfile::TempDir::new()?;
+        let workspace = temp.path().join("workspace");
+        let assets = workspace.join("assets");
+        std::fs::create_dir_all(assets.join("a"))?;
+        std::fs::create_dir_all(assets.join("b"))?;
+        std::fs::write(workspace.join("book.md"), "# Book\n")?;
+        std::fs::write(assets.join("a").join("a.png"), b"a")?;
+        std::fs::write(assets.join("b").join("b.png"), b"b")?;
+        std::os::unix::fs::symlink("../b", assets.join("a").join("x"))?;
+        std::os::unix::fs::symlink("../a", assets.join("b").join("y"))?;
+
+        let out = temp.path().join("out.zip");
+        create_zip_from_workspace_blocking(&workspace, &out)?;
+
+        let archive = zip::ZipArchive::new(File::open(&out)?)?;
+        let mut names = archive.file_names().map(str::to_owned).collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "assets/a/",
+                "assets/a/a.png",
+                "assets/a/x/",
+                "assets/a/x/b.png",
+                "assets/b/",
+                "assets/b/b.png",
+                "assets/b/y/",
+                "assets/b/y/a.png",
+                "book.md"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn multipart_related_body_wraps_metadata_and_content() {
+        let body = multipart_related_body(
+            "b",
+            &serde_json::json!({ "name": "jobs/x/artifact.zip" }),
+            "application/zip",
+            b"PK",
+        );
+        assert_eq!(
+            String::from_utf8(body).unwrap(),
+            "--b\r\nContent-Type: application/json; charset=UTF-8\r\n\r\n\
+{\"name\":\"jobs/x/artifact.zip\"}\r\n--b\r\nContent-Type: application/zip\r\n\r\nPK\r\n--b--\r\n"
+        );
+    }
+
     #[tokio::test]
     async fn local_fs_download_url_is_app_route() -> anyhow::Result<()> {
         let store = LocalFsArtifactStore::new("data");
-        let url = store.generate_download_url("job-123", 3600).await?;
+        let url = store
+            .generate_download_url("job-123", ArtifactKind::Zip, 3600)
+            .await?;
         assert_eq!(url, "/artifacts/job-123");
+        let url = store
+            .generate_download_url("job-123", ArtifactKind::Markdown, 3600)
+            .await?;
+        assert_eq!(url, "/jobs/job-123/book.md");
+        let url = store
+            .generate_download_url("job-123", ArtifactKind::Epub, 3600)
+            .await?;
+        assert_eq!(url, "/jobs/job-123/book.epub");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn local_fs_health_check_probes_jobs_dir() -> anyhow::Result<()> {
+        let temp = tempfile::TempDir::new()?;
+        let store = LocalFsArtifactStore::new(temp.path());
+        store.health_check().await?;
+        let jobs_dir = temp.path().join("jobs");
+        assert!(jobs_dir.is_dir());
+        assert_eq!(std::fs::read_dir(&jobs_dir)?.count(), 0);
+
+        let blocked = LocalFsArtifactStore::new(temp.path().join("file"));
+        std::fs::write(temp.path().join("file"), b"not a dir")?;
+        assert!(blocked.health_check().await.is_err());
         Ok(())
     }
 
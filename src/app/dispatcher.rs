@@ -48,6 +48,11 @@ impl InProcessJobDispatcher {
     pub fn new(queue: InProcessQueue, runner: Arc<JobRunner>) -> Self {
         Self { queue, runner }
     }
+
+    /// See [`JobRunner::request_cancel`].
+    pub fn request_cancel(&self, job_id: &str) -> bool {
+        self.runner.request_cancel(job_id)
+    }
 }
 
 #[async_trait]
@@ -57,8 +62,7 @@ impl JobDispatcher for InProcessJobDispatcher {
         let job_id = job_id.to_string();
         self.queue.spawn(async move {
             runner.run_job(&job_id).await;
-        });
-        Ok(())
+        })
     }
 }
 
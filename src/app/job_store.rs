@@ -7,9 +7,9 @@ use anyhow::Context as _;
 use async_trait::async_trait;
 use reqwest::StatusCode;
 use tokio::fs;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 
-use crate::app::model::{Job, StartJobRequest};
+use crate::app::model::{Job, JobStatus, StartJobRequest};
 
 #[async_trait]
 pub trait JobStore: Send + Sync {
@@ -17,18 +17,25 @@ pub trait JobStore: Send + Sync {
     async fn get(&self, job_id: &str) -> anyhow::Result<Option<Job>>;
     async fn get_request(&self, job_id: &str) -> anyhow::Result<Option<StartJobRequest>>;
     async fn put(&self, job: &Job) -> anyhow::Result<()>;
+    /// Saves `job` only if the stored job's status is one of `expected`, atomically against
+    /// concurrent writes. Returns whether it was saved (`false` also for a missing job).
+    async fn put_if_status(&self, job: &Job, expected: &[JobStatus]) -> anyhow::Result<bool>;
     async fn list_job_ids(&self) -> anyhow::Result<Vec<String>>;
 }
 
 #[derive(Debug, Clone)]
 pub struct LocalFsJobStore {
     base_dir: PathBuf,
+    /// Held by every `job.json` write, so `put_if_status` reads and writes without a `put`
+    /// landing in between.
+    write_lock: Arc<Mutex<()>>,
 }
 
 impl LocalFsJobStore {
     pub fn new(base_dir: impl Into<PathBuf>) -> Self {
         Self {
             base_dir: base_dir.into(),
+            write_lock: Arc::new(Mutex::new(())),
         }
     }
 
@@ -81,6 +88,7 @@ impl JobStore for LocalFsJobStore {
     }
 
     async fn put(&self, job: &Job) -> anyhow::Result<()> {
+        let _write = self.write_lock.lock().await;
         fs::create_dir_all(self.job_dir(&job.job_id))
             .await
             .with_context(|| format!("create job dir: {}", self.job_dir(&job.job_id).display()))?;
@@ -90,6 +98,21 @@ impl JobStore for LocalFsJobStore {
         Ok(())
     }
 
+    async fn put_if_status(&self, job: &Job, expected: &[JobStatus]) -> anyhow::Result<bool> {
+        let _write = self.write_lock.lock().await;
+        let path = self.job_json_path(&job.job_id);
+        let stored: Option<Job> = read_json(&path)
+            .await
+            .with_context(|| format!("read: {}", path.display()))?;
+        if !stored.is_some_and(|stored| expected.contains(&stored.status)) {
+            return Ok(false);
+        }
+        write_json_atomic(&path, job)
+            .await
+            .context("write job.json")?;
+        Ok(true)
+    }
+
     async fn list_job_ids(&self) -> anyhow::Result<Vec<String>> {
         let jobs_dir = self.jobs_dir();
         let mut entries = match fs::read_dir(&jobs_dir).await {
@@ -207,34 +230,65 @@ impl GcsJobStore {
         object_name: &str,
         value: &T,
     ) -> anyhow::Result<()> {
+        self.upload_json_if_generation(object_name, value, None)
+            .await
+            .map(|_| ())
+    }
+
+    /// Uploads `value`; with `if_generation_match`, only while the object is still at that
+    /// generation. Returns `false` when the precondition failed.
+    async fn upload_json_if_generation<T: serde::Serialize>(
+        &self,
+        object_name: &str,
+        value: &T,
+        if_generation_match: Option<&str>,
+    ) -> anyhow::Result<bool> {
         let access_token = self.access_token().await.context("get access token")?;
         let url = format!(
             "https://storage.googleapis.com/upload/storage/v1/b/{bucket}/o",
             bucket = self.bucket
         );
         let body = serde_json::to_vec_pretty(value).context("serialize json")?;
-        let resp = self
+        let mut req = self
             .client
             .post(url)
             .bearer_auth(access_token)
-            .query(&[("uploadType", "media"), ("name", object_name)])
+            .query(&[("uploadType", "media"), ("name", object_name)]);
+        if let Some(generation) = if_generation_match {
+            req = req.query(&[("ifGenerationMatch", generation)]);
+        }
+        let resp = req
             .header(reqwest::header::CONTENT_TYPE, "application/json")
             .body(body)
             .send()
             .await
             .with_context(|| format!("upload object: gs://{}/{}", self.bucket, object_name))?;
+        if resp.status() == StatusCode::PRECONDITION_FAILED {
+            return Ok(false);
+        }
         if !resp.status().is_success() {
             let status = resp.status();
             let body = resp.text().await.unwrap_or_default();
             anyhow::bail!("gcs upload failed ({status}): {body}");
         }
-        Ok(())
+        Ok(true)
     }
 
     async fn download_json<T: serde::de::DeserializeOwned>(
         &self,
         object_name: &str,
     ) -> anyhow::Result<Option<T>> {
+        Ok(self
+            .download_json_with_generation(object_name)
+            .await?
+            .map(|(value, _)| value))
+    }
+
+    /// Downloads `object_name` along with its generation (`x-goog-generation`).
+    async fn download_json_with_generation<T: serde::de::DeserializeOwned>(
+        &self,
+        object_name: &str,
+    ) -> anyhow::Result<Option<(T, String)>> {
         let access_token = self.access_token().await.context("get access token")?;
         let object_name_encoded = percent_encode_rfc3986(object_name);
         let url = format!(
@@ -258,9 +312,15 @@ impl GcsJobStore {
             anyhow::bail!("gcs download failed ({status}): {body}");
         }
 
+        let generation = resp
+            .headers()
+            .get("x-goog-generation")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned)
+            .ok_or_else(|| anyhow::anyhow!("gcs download has no x-goog-generation header"))?;
         let bytes = resp.bytes().await.context("read gcs response body")?;
         let value = serde_json::from_slice::<T>(&bytes).context("parse json")?;
-        Ok(Some(value))
+        Ok(Some((value, generation)))
     }
 }
 
@@ -295,6 +355,30 @@ impl JobStore for GcsJobStore {
         Ok(())
     }
 
+    async fn put_if_status(&self, job: &Job, expected: &[JobStatus]) -> anyhow::Result<bool> {
+        let object_name = self.job_json_object(&job.job_id);
+        // A lost race re-reads the job: the other write may have left an expected status.
+        loop {
+            let Some((stored, generation)) = self
+                .download_json_with_generation::<Job>(&object_name)
+                .await
+                .context("download job.json")?
+            else {
+                return Ok(false);
+            };
+            if !expected.contains(&stored.status) {
+                return Ok(false);
+            }
+            if self
+                .upload_json_if_generation(&object_name, job, Some(&generation))
+                .await
+                .context("upload job.json")?
+            {
+                return Ok(true);
+            }
+        }
+    }
+
     async fn list_job_ids(&self) -> anyhow::Result<Vec<String>> {
         #[derive(Debug, serde::Deserialize)]
         struct ObjectItem {
@@ -4,4 +4,5 @@ pub mod job_store;
 pub mod model;
 pub mod preview;
 pub mod queue;
+pub mod recovery;
 pub mod runner;
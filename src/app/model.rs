@@ -12,6 +12,7 @@ pub enum JobStatus {
     Running,
     Done,
     Error,
+    Cancelled,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +46,14 @@ pub struct StartJobRequest {
 
     pub toc_engine: LlmEngine,
     pub render_engine: LlmEngine,
+
+    /// Explicit page list; when non-empty, crawling is skipped and exactly these URLs are fetched.
+    #[serde(default)]
+    pub page_urls: Vec<String>,
+
+    /// Skip `book.epub`; the artifact then carries only `book.md` and `assets/`.
+    #[serde(default)]
+    pub skip_epub: bool,
 }
 
 impl StartJobRequest {
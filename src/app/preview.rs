@@ -32,6 +32,10 @@ pub enum PreviewSource {
 pub struct PreviewChapter {
     pub title: String,
     pub pages: usize,
+    /// Share of `total_characters`: the per-page average times `pages`.
+    pub estimated_characters: u64,
+    pub estimated_input_tokens: u64,
+    pub estimated_output_tokens: u64,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
@@ -109,9 +113,12 @@ struct TokenRange {
 }
 
 pub async fn preview_site(start_url: &Url) -> anyhow::Result<SitePreview> {
-    let client = reqwest::Client::builder()
+    let client = crate::net_guard::apply(reqwest::Client::builder())
         .timeout(Duration::from_secs(10))
-        .redirect(reqwest::redirect::Policy::limited(10))
+        .redirect(crate::net_guard::redirect_policy(10, false))
+        .gzip(true)
+        .brotli(true)
+        .deflate(true)
         .build()
         .context("build preview http client")?;
 
@@ -157,15 +164,18 @@ async fn preview_site_with_client(
 }
 
 #[derive(Debug, Clone)]
-struct FetchedText {
-    text: String,
-    truncated: bool,
+pub struct FetchedText {
+    pub text: String,
+    /// Body was cut at the size limit.
+    pub truncated: bool,
 }
 
-async fn try_fetch_text(
+/// GETs `url` as text, capped at 2 MiB; `None` for a non-2xx status.
+pub async fn try_fetch_text(
     client: &reqwest::Client,
     url: &Url,
 ) -> anyhow::Result<Option<FetchedText>> {
+    crate::net_guard::check_ip_literal(url)?;
     let resp = client
         .get(url.clone())
         .header(reqwest::header::USER_AGENT, "sitebookify/0.1")
@@ -213,7 +223,8 @@ fn with_path(base: &Url, path: &str) -> anyhow::Result<Url> {
     Ok(out)
 }
 
-fn extract_xml_locs(xml: &str) -> Vec<String> {
+/// `<loc>` values of a sitemap (urlset or index) in document order, capped at 20k entries.
+pub fn extract_xml_locs(xml: &str) -> Vec<String> {
     let lower = xml.to_ascii_lowercase();
     let mut locs = Vec::new();
 
@@ -237,19 +248,6 @@ fn extract_xml_locs(xml: &str) -> Vec<String> {
     locs
 }
 
-fn canonical_url(url: &Url) -> Url {
-    let mut canonical = url.clone();
-    canonical.set_fragment(None);
-    canonical.set_query(None);
-
-    let mut path = canonical.path().to_owned();
-    while path.len() > 1 && path.ends_with('/') {
-        path.pop();
-    }
-    canonical.set_path(&path);
-    canonical
-}
-
 fn join_href(base_url: &Url, href: &str) -> Result<Url, url::ParseError> {
     if href.starts_with("http://") || href.starts_with("https://") || href.starts_with('/') {
         return base_url.join(href);
@@ -269,38 +267,6 @@ fn join_href(base_url: &Url, href: &str) -> Result<Url, url::ParseError> {
     adjusted.join(href)
 }
 
-fn chapter_key(start_url: &Url, page_url: &Url) -> String {
-    let base_path = {
-        let p = start_url.path();
-        if p.ends_with('/') {
-            p.to_string()
-        } else {
-            format!("{p}/")
-        }
-    };
-
-    let path = page_url.path();
-    let key = if path.starts_with(&base_path) {
-        let rest = &path[base_path.len()..];
-        rest.split('/')
-            .find(|s| !s.trim().is_empty())
-            .unwrap_or("root")
-            .to_string()
-    } else {
-        path.trim_start_matches('/')
-            .split('/')
-            .find(|s| !s.trim().is_empty())
-            .unwrap_or("root")
-            .to_string()
-    };
-
-    if key.trim().is_empty() {
-        "root".to_string()
-    } else {
-        key
-    }
-}
-
 fn summarize(
     start_url: &Url,
     source: PreviewSource,
@@ -309,13 +275,19 @@ fn summarize(
 ) -> SitePreview {
     let mut by_chapter: BTreeMap<String, usize> = BTreeMap::new();
     for u in pages {
-        let key = chapter_key(start_url, u);
+        let key = crate::url_norm::chapter_key(start_url, u);
         *by_chapter.entry(key).or_insert(0) += 1;
     }
 
     let mut chapters: Vec<PreviewChapter> = by_chapter
         .into_iter()
-        .map(|(title, pages)| PreviewChapter { title, pages })
+        .map(|(title, pages)| PreviewChapter {
+            title,
+            pages,
+            estimated_characters: 0,
+            estimated_input_tokens: 0,
+            estimated_output_tokens: 0,
+        })
         .collect();
     chapters.sort_by(|a, b| b.pages.cmp(&a.pages).then_with(|| a.title.cmp(&b.title)));
 
@@ -360,7 +332,7 @@ fn preview_from_sitemap_urlset(start_url: &Url, host: &str, xml: &str) -> Option
         if u.host_str() != Some(host) {
             continue;
         }
-        let u = canonical_url(&u);
+        let u = crate::url_norm::canonical_url(&u);
         if uniq.insert(u.to_string()) {
             pages.push(u);
         }
@@ -417,7 +389,7 @@ async fn preview_from_sitemap_index(
             if page.host_str() != Some(host) {
                 continue;
             }
-            let page = canonical_url(&page);
+            let page = crate::url_norm::canonical_url(&page);
             if uniq.insert(page.to_string()) {
                 pages.push(page);
             }
@@ -449,7 +421,7 @@ async fn preview_from_links(
     start_url: &Url,
     host: &str,
 ) -> anyhow::Result<SitePreview> {
-    let start_url = canonical_url(start_url);
+    let start_url = crate::url_norm::canonical_url(start_url);
     let mut notes = Vec::new();
     let mut queued: HashSet<String> = HashSet::new();
     let mut queue: VecDeque<(Url, usize)> = VecDeque::new();
@@ -474,7 +446,7 @@ async fn preview_from_links(
         truncated_any |= fetched.truncated;
         pages.push(current_url.clone());
 
-        let hrefs = extract_html_hrefs(&fetched.text);
+        let hrefs = extract_html_hrefs(&fetched.text, MAX_LINK_HREFS);
         if hrefs.len() > MAX_LINKS_PER_PAGE {
             per_page_link_cap_hit = true;
         }
@@ -499,7 +471,13 @@ async fn preview_from_links(
             if next_url.scheme() != "http" && next_url.scheme() != "https" {
                 continue;
             }
-            let next_url = canonical_url(&next_url);
+            if crate::url_norm::has_extension(
+                &next_url,
+                crate::url_norm::DEFAULT_EXCLUDED_EXTENSIONS,
+            ) {
+                continue;
+            }
+            let next_url = crate::url_norm::canonical_url(&next_url);
             if queued.insert(next_url.to_string()) {
                 queue.push_back((next_url, depth + 1));
             }
@@ -616,6 +594,7 @@ async fn enrich_preview_with_estimates(client: &reqwest::Client, preview: &mut S
     };
 
     preview.total_characters = total_characters;
+    estimate_chapters(preview, &pricing);
 
     let input_base = ceil_to_u64(total_characters as f64 * pricing.token_per_char_input);
     let output_base = ceil_to_u64(total_characters as f64 * pricing.token_per_char_output);
@@ -646,6 +625,22 @@ async fn enrich_preview_with_estimates(client: &reqwest::Client, preview: &mut S
     }
 }
 
+/// Spreads `total_characters` over the chapters by page count, with point token estimates.
+fn estimate_chapters(preview: &mut SitePreview, pricing: &PreviewPricingConfig) {
+    if preview.estimated_pages == 0 {
+        return;
+    }
+    let characters_per_page = preview.total_characters as f64 / preview.estimated_pages as f64;
+    for chapter in &mut preview.chapters {
+        let characters = (characters_per_page * chapter.pages as f64).round() as u64;
+        chapter.estimated_characters = characters;
+        chapter.estimated_input_tokens =
+            ceil_to_u64(characters as f64 * pricing.token_per_char_input);
+        chapter.estimated_output_tokens =
+            ceil_to_u64(characters as f64 * pricing.token_per_char_output);
+    }
+}
+
 fn estimate_token_range(base: u64) -> TokenRange {
     if base == 0 {
         return TokenRange { min: 0, max: 0 };
@@ -714,12 +709,13 @@ fn floor_to_u64(value: f64) -> u64 {
     value.floor() as u64
 }
 
-fn extract_html_hrefs(html: &str) -> Vec<String> {
+/// Up to `max` raw `href` values in document order, skipping empty and fragment-only ones.
+pub fn extract_html_hrefs(html: &str, max: usize) -> Vec<String> {
     let lower = html.to_ascii_lowercase();
     let mut hrefs = Vec::new();
 
     let mut pos = 0usize;
-    while hrefs.len() < MAX_LINK_HREFS {
+    while hrefs.len() < max {
         let Some(rel) = lower[pos..].find("href=") else {
             break;
         };
@@ -799,6 +795,7 @@ mod tests {
     <a href="intro">Intro</a>
     <a href="advanced#top">Advanced</a>
     <a href="/outside">Outside</a>
+    <a href="manual.pdf">Manual (PDF)</a>
   </body>
 </html>
 "#
@@ -818,6 +815,7 @@ mod tests {
                         "text/html",
                     ),
                     "/docs/advanced" => (200, "Advanced".to_string(), "text/plain"),
+                    "/docs/manual.pdf" => (200, "%PDF-1.4".to_string(), "application/pdf"),
                     "/docs/guide/part-1" => (
                         200,
                         r#"<!doctype html>
@@ -872,6 +870,41 @@ mod tests {
         let _ = handle.join();
     }
 
+    #[test]
+    fn chapter_estimates_add_up_to_total_characters() {
+        let start_url = Url::parse("https://example.com/docs/").unwrap();
+        let pages = ["guide/a", "guide/b", "guide/c", "api/x", "intro"]
+            .iter()
+            .map(|path| start_url.join(path).unwrap())
+            .collect::<Vec<_>>();
+        let mut preview = summarize(&start_url, PreviewSource::Links, &pages, Vec::new());
+        preview.total_characters = 1_001;
+        let pricing = PreviewPricingConfig {
+            model: "test".to_owned(),
+            input_usd_per_1m: None,
+            output_usd_per_1m: None,
+            token_per_char_input: DEFAULT_TOKEN_PER_CHAR_INPUT,
+            token_per_char_output: DEFAULT_TOKEN_PER_CHAR_OUTPUT,
+        };
+        estimate_chapters(&mut preview, &pricing);
+
+        let guide = &preview.chapters[0];
+        assert_eq!((guide.title.as_str(), guide.pages), ("guide", 3));
+        assert_eq!(guide.estimated_characters, 601);
+        assert_eq!(guide.estimated_input_tokens, 151);
+        assert_eq!(guide.estimated_output_tokens, 76);
+        let sum = preview
+            .chapters
+            .iter()
+            .map(|chapter| chapter.estimated_characters)
+            .sum::<u64>();
+        assert!(
+            sum.abs_diff(preview.total_characters) <= preview.chapters.len() as u64,
+            "{sum} vs {}",
+            preview.total_characters
+        );
+    }
+
     #[tokio::test]
     async fn preview_falls_back_to_link_crawl_when_no_sitemap() {
         let (base_url, shutdown_tx, handle) = spawn_preview_server(false);
@@ -890,6 +923,11 @@ mod tests {
                 .iter()
                 .any(|u| u.ends_with("/docs/guide/part-2"))
         );
+        assert!(
+            !out.sample_urls.iter().any(|u| u.ends_with(".pdf")),
+            "pdf link must not be enqueued: {:?}",
+            out.sample_urls
+        );
         assert!(
             out.notes.iter().any(|n| n.contains("depth limit (2)")),
             "expected depth-limit note, got {:?}",
@@ -1,32 +1,65 @@
 use std::future::Future;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 use tokio::sync::Semaphore;
 
 #[derive(Debug, Clone)]
 pub struct InProcessQueue {
     semaphore: Arc<Semaphore>,
+    permits: u32,
+    closed: Arc<AtomicBool>,
 }
 
 impl InProcessQueue {
     pub fn new(max_concurrency: usize) -> Self {
-        let permits = max_concurrency.max(1);
+        let permits = max_concurrency.clamp(1, u32::MAX as usize);
         Self {
             semaphore: Arc::new(Semaphore::new(permits)),
+            permits: permits as u32,
+            closed: Arc::new(AtomicBool::new(false)),
         }
     }
 
-    pub fn spawn<F>(&self, fut: F)
+    /// Runs `fut` once a slot is free. Fails after [`Self::close`].
+    pub fn spawn<F>(&self, fut: F) -> anyhow::Result<()>
     where
         F: Future<Output = ()> + Send + 'static,
     {
+        if self.is_closed() {
+            anyhow::bail!("in-process queue is shutting down");
+        }
         let semaphore = Arc::clone(&self.semaphore);
+        let closed = Arc::clone(&self.closed);
         tokio::spawn(async move {
             let _permit = semaphore
                 .acquire_owned()
                 .await
                 .expect("in-process queue semaphore is closed");
+            // Closed while waiting for a slot: the job stays queued for startup recovery.
+            if closed.load(Ordering::SeqCst) {
+                return;
+            }
             fut.await;
         });
+        Ok(())
+    }
+
+    /// Stops starting work: new `spawn` calls fail and queued futures are dropped unstarted.
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Waits up to `timeout` for running futures to finish; returns how many are still running.
+    pub async fn drain(&self, timeout: Duration) -> usize {
+        match tokio::time::timeout(timeout, self.semaphore.acquire_many(self.permits)).await {
+            Ok(_) => 0,
+            Err(_) => (self.permits as usize).saturating_sub(self.semaphore.available_permits()),
+        }
     }
 }
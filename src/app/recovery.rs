@@ -0,0 +1,231 @@
+use anyhow::Context as _;
+use chrono::Utc;
+
+use crate::app::dispatcher::JobDispatcher;
+use crate::app::job_store::JobStore;
+use crate::app::model::{Job, JobStatus};
+
+/// What to do with jobs left `Running` by a previous process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryMode {
+    /// Reset interrupted jobs and run them again from scratch.
+    Requeue,
+    /// Mark interrupted jobs as failed.
+    Fail,
+}
+
+impl RecoveryMode {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let raw =
+            std::env::var("SITEBOOKIFY_RECOVER_JOBS").unwrap_or_else(|_| "requeue".to_string());
+        Self::parse(&raw).with_context(|| {
+            format!("invalid SITEBOOKIFY_RECOVER_JOBS={raw:?}. expected one of: requeue, fail")
+        })
+    }
+
+    pub fn parse(raw: &str) -> anyhow::Result<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "" | "requeue" => Ok(Self::Requeue),
+            "fail" => Ok(Self::Fail),
+            other => anyhow::bail!("unsupported recovery mode: {other}"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RecoverySummary {
+    pub requeued: Vec<String>,
+    pub failed: Vec<String>,
+}
+
+pub const INTERRUPTED_MESSAGE: &str = "interrupted by restart";
+
+/// Scans the job store for non-terminal jobs left behind by a previous process.
+///
+/// `Queued` jobs are always dispatched again. `Running` jobs are reset (work dir removed) and
+/// dispatched in `Requeue` mode, or marked as `Error` in `Fail` mode.
+pub async fn recover_jobs(
+    job_store: &dyn JobStore,
+    dispatcher: &dyn JobDispatcher,
+    mode: RecoveryMode,
+) -> anyhow::Result<RecoverySummary> {
+    let mut summary = RecoverySummary::default();
+    for job_id in job_store.list_job_ids().await.context("list jobs")? {
+        let Some(mut job) = job_store
+            .get(&job_id)
+            .await
+            .with_context(|| format!("load job: {job_id}"))?
+        else {
+            continue;
+        };
+
+        match (job.status, mode) {
+            (JobStatus::Done | JobStatus::Error | JobStatus::Cancelled, _) => continue,
+            (JobStatus::Running, RecoveryMode::Fail) => {
+                job.status = JobStatus::Error;
+                job.message = INTERRUPTED_MESSAGE.to_string();
+                job.finished_at = Some(Utc::now());
+                job_store
+                    .put(&job)
+                    .await
+                    .with_context(|| format!("save job: {job_id}"))?;
+                summary.failed.push(job_id);
+                continue;
+            }
+            (JobStatus::Running, RecoveryMode::Requeue) => {
+                reset_for_rerun(&mut job).with_context(|| format!("reset job: {job_id}"))?;
+                job_store
+                    .put(&job)
+                    .await
+                    .with_context(|| format!("save job: {job_id}"))?;
+            }
+            (JobStatus::Queued, _) => {}
+        }
+
+        dispatcher
+            .dispatch(&job_id)
+            .await
+            .with_context(|| format!("dispatch job: {job_id}"))?;
+        summary.requeued.push(job_id);
+    }
+    Ok(summary)
+}
+
+fn reset_for_rerun(job: &mut Job) -> anyhow::Result<()> {
+    // The runner refuses to reuse an existing work dir, so drop the partial output.
+    if job.work_dir.exists() {
+        std::fs::remove_dir_all(&job.work_dir)
+            .with_context(|| format!("remove work dir: {}", job.work_dir.display()))?;
+    }
+    job.status = JobStatus::Queued;
+    job.progress_percent = 0;
+    job.message = "queued".to_string();
+    job.started_at = None;
+    job.finished_at = None;
+    job.artifact_path = None;
+    job.artifact_uri = None;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::app::job_store::LocalFsJobStore;
+    use crate::app::model::StartJobRequest;
+
+    #[derive(Default)]
+    struct RecordingDispatcher {
+        dispatched: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl JobDispatcher for RecordingDispatcher {
+        async fn dispatch(&self, job_id: &str) -> anyhow::Result<()> {
+            self.dispatched.lock().unwrap().push(job_id.to_string());
+            Ok(())
+        }
+    }
+
+    fn job(job_id: &str, status: JobStatus, work_dir: std::path::PathBuf) -> Job {
+        Job {
+            job_id: job_id.to_string(),
+            status,
+            progress_percent: 40,
+            message: "extract".to_string(),
+            created_at: Utc::now(),
+            started_at: Some(Utc::now()),
+            finished_at: None,
+            work_dir,
+            artifact_path: None,
+            artifact_uri: None,
+        }
+    }
+
+    fn request() -> StartJobRequest {
+        StartJobRequest {
+            url: "https://example.com/".to_string(),
+            title: None,
+            max_pages: 1,
+            max_depth: 0,
+            concurrency: 1,
+            delay_ms: 0,
+            language: "日本語".to_string(),
+            tone: "丁寧".to_string(),
+            toc_engine: crate::cli::LlmEngine::Noop,
+            render_engine: crate::cli::LlmEngine::Noop,
+            page_urls: Vec::new(),
+            skip_epub: false,
+        }
+    }
+
+    async fn seed(store: &LocalFsJobStore, base: &std::path::Path) {
+        for (id, status) in [
+            ("a_queued", JobStatus::Queued),
+            ("b_running", JobStatus::Running),
+            ("c_done", JobStatus::Done),
+        ] {
+            let work_dir = base.join(id).join("work");
+            std::fs::create_dir_all(&work_dir).unwrap();
+            store
+                .create(&job(id, status, work_dir), &request())
+                .await
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn parse_modes() {
+        assert_eq!(RecoveryMode::parse("").unwrap(), RecoveryMode::Requeue);
+        assert_eq!(RecoveryMode::parse(" Fail ").unwrap(), RecoveryMode::Fail);
+        assert!(RecoveryMode::parse("skip").is_err());
+    }
+
+    #[tokio::test]
+    async fn requeue_resets_running_jobs() {
+        let temp = tempfile::tempdir().unwrap();
+        let store = LocalFsJobStore::new(temp.path());
+        seed(&store, temp.path()).await;
+        let dispatcher = RecordingDispatcher::default();
+
+        let summary = recover_jobs(&store, &dispatcher, RecoveryMode::Requeue)
+            .await
+            .unwrap();
+
+        assert_eq!(summary.requeued, vec!["a_queued", "b_running"]);
+        assert!(summary.failed.is_empty());
+        assert_eq!(
+            *dispatcher.dispatched.lock().unwrap(),
+            vec!["a_queued", "b_running"]
+        );
+        let running = store.get("b_running").await.unwrap().unwrap();
+        assert_eq!(running.status, JobStatus::Queued);
+        assert_eq!(running.progress_percent, 0);
+        assert!(!running.work_dir.exists());
+    }
+
+    #[tokio::test]
+    async fn fail_marks_running_jobs_as_error() {
+        let temp = tempfile::tempdir().unwrap();
+        let store = LocalFsJobStore::new(temp.path());
+        seed(&store, temp.path()).await;
+        let dispatcher = RecordingDispatcher::default();
+
+        let summary = recover_jobs(&store, &dispatcher, RecoveryMode::Fail)
+            .await
+            .unwrap();
+
+        assert_eq!(summary.requeued, vec!["a_queued"]);
+        assert_eq!(summary.failed, vec!["b_running"]);
+        let running = store.get("b_running").await.unwrap().unwrap();
+        assert_eq!(running.status, JobStatus::Error);
+        assert_eq!(running.message, INTERRUPTED_MESSAGE);
+        assert_eq!(
+            store.get("c_done").await.unwrap().unwrap().status,
+            JobStatus::Done
+        );
+    }
+}
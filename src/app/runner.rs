@@ -1,5 +1,7 @@
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use anyhow::Context as _;
 use chrono::Utc;
@@ -8,8 +10,9 @@ use crate::app::artifact_store::ArtifactStore;
 use crate::app::job_store::JobStore;
 use crate::app::model::{Job, JobStatus, StartJobRequest};
 use crate::cli::{
-    BookBundleArgs, BookInitArgs, BookRenderArgs, CrawlArgs, ExtractArgs, ManifestArgs,
-    TocCreateArgs,
+    AnchorStyle, AssetErrorPolicy, AssetNaming, BookBundleArgs, BookInitArgs, BookRenderArgs,
+    ChapterIdStyle, CrawlArgs, ExternalLinkPolicy, ExtractArgs, ManifestArgs, NoopGrouping,
+    OutputLayout, PageOrder, RewriteGranularity, TitleSource, TocCreateArgs,
 };
 use crate::formats::Toc;
 
@@ -23,10 +26,59 @@ const STAGE_BOOK_RENDER: &str = "book render";
 const STAGE_BOOK_BUNDLE: &str = "book bundle";
 const STAGE_BOOK_EPUB: &str = "book epub";
 const STAGE_DONE: &str = "done";
+/// Message of a job stopped by `CancelOperation`.
+pub const CANCELLED_MESSAGE: &str = "cancelled";
+
+/// Default for `SITEBOOKIFY_JOB_MAX_SECS` (6 hours).
+pub const DEFAULT_JOB_MAX_SECS: u64 = 6 * 60 * 60;
 
 pub struct JobRunner {
     job_store: Arc<dyn JobStore>,
     artifact_store: Arc<dyn ArtifactStore>,
+    max_runtime: Option<Duration>,
+    /// Jobs running in this process.
+    running: Mutex<HashMap<String, RunState>>,
+}
+
+/// What a running job checks at each stage boundary.
+#[derive(Debug, Clone, Copy)]
+struct RunState {
+    cancel_requested: bool,
+    deadline: Option<Instant>,
+}
+
+/// Returned at a stage boundary of a job that must stop; `run_job` then removes the work dir
+/// (no stage is running any more) and records the outcome.
+#[derive(Debug)]
+enum JobStopped {
+    Cancelled,
+    TimedOut(Duration),
+}
+
+impl std::fmt::Display for JobStopped {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cancelled => f.write_str(CANCELLED_MESSAGE),
+            Self::TimedOut(limit) => write!(f, "timed out after {limit:?}"),
+        }
+    }
+}
+
+impl std::error::Error for JobStopped {}
+
+/// Keeps a job in [`JobRunner::running`] while `try_run_job` is alive.
+struct RunningGuard<'a> {
+    running: &'a Mutex<HashMap<String, RunState>>,
+    job_id: &'a str,
+}
+
+impl Drop for RunningGuard<'_> {
+    fn drop(&mut self) {
+        self.running
+            .lock()
+            .expect("running jobs lock")
+            .remove(self.job_id);
+    }
 }
 
 impl JobRunner {
@@ -34,17 +86,90 @@ impl JobRunner {
         Self {
             job_store,
             artifact_store,
+            max_runtime: None,
+            running: Mutex::new(HashMap::new()),
         }
     }
 
+    /// Asks job `job_id` to stop at its next stage boundary. Returns `false` when the job is
+    /// not running in this process (queued, finished, or owned by another worker).
+    pub fn request_cancel(&self, job_id: &str) -> bool {
+        match self
+            .running
+            .lock()
+            .expect("running jobs lock")
+            .get_mut(job_id)
+        {
+            Some(state) => {
+                state.cancel_requested = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Fail jobs that run longer than `max_runtime` (`None` disables the watchdog). The async
+    /// stages (crawl, TOC) are dropped as soon as the limit passes; the synchronous ones cannot
+    /// be interrupted, so the limit is also checked at every stage boundary.
+    pub fn with_max_runtime(mut self, max_runtime: Option<Duration>) -> Self {
+        self.max_runtime = max_runtime;
+        self
+    }
+
     pub async fn run_job(&self, job_id: &str) {
-        if let Err(err) = self.try_run_job(job_id).await {
-            tracing::error!(job_id, ?err, "job failed");
-            let _ = self.mark_error(job_id, format!("{err:#}")).await;
+        let Err(err) = self.try_run_job(job_id).await else {
+            return;
+        };
+        match err.downcast_ref::<JobStopped>() {
+            Some(JobStopped::Cancelled) => {
+                tracing::info!(job_id, "job cancelled");
+                self.cleanup_work_dir(job_id).await;
+                // A cancel that arrived while the zip was written leaves no artifact behind.
+                let artifact_path = self.artifact_store.artifact_path(job_id);
+                if artifact_path.exists()
+                    && let Err(err) = std::fs::remove_file(&artifact_path)
+                {
+                    tracing::warn!(job_id, ?err, "failed to remove artifact of cancelled job");
+                }
+                let _ = self.mark_cancelled(job_id).await;
+            }
+            Some(stopped @ JobStopped::TimedOut(limit)) => {
+                tracing::error!(job_id, limit_secs = limit.as_secs(), "job timed out");
+                self.cleanup_work_dir(job_id).await;
+                let _ = self.mark_error(job_id, stopped.to_string()).await;
+            }
+            None => {
+                tracing::error!(job_id, ?err, "job failed");
+                let _ = self.mark_error(job_id, format!("{err:#}")).await;
+            }
+        }
+    }
+
+    async fn cleanup_work_dir(&self, job_id: &str) {
+        let Ok(Some(job)) = self.job_store.get(job_id).await else {
+            return;
+        };
+        if job.work_dir.exists()
+            && let Err(err) = std::fs::remove_dir_all(&job.work_dir)
+        {
+            tracing::warn!(job_id, ?err, "failed to remove work dir of stopped job");
         }
     }
 
     async fn try_run_job(&self, job_id: &str) -> anyhow::Result<()> {
+        // Registered before the job is loaded, so a cancel arriving while the job starts
+        // either reaches this runner or finds the job still queued.
+        self.running.lock().expect("running jobs lock").insert(
+            job_id.to_string(),
+            RunState {
+                cancel_requested: false,
+                deadline: self.max_runtime.map(|limit| Instant::now() + limit),
+            },
+        );
+        let _running = RunningGuard {
+            running: &self.running,
+            job_id,
+        };
         let mut job = self
             .job_store
             .get(job_id)
@@ -64,6 +189,7 @@ impl JobRunner {
 
         self.mark_running(&mut job).await.context("mark running")?;
         self.run_pipeline(&mut job, &request).await?;
+        self.ensure_not_stopped(job_id).await?;
 
         let artifact_path = self
             .artifact_store
@@ -78,8 +204,7 @@ impl JobRunner {
         job.artifact_path = Some(artifact_path);
         job.artifact_uri = Some(self.artifact_store.artifact_uri(job_id));
 
-        self.job_store.put(&job).await.context("save job")?;
-        Ok(())
+        self.save_job(&job).await
     }
 
     async fn mark_running(&self, job: &mut Job) -> anyhow::Result<()> {
@@ -87,7 +212,21 @@ impl JobRunner {
         job.started_at = Some(Utc::now());
         job.progress_percent = 0;
         job.message = STAGE_STARTING.to_string();
-        self.job_store.put(job).await.context("save job")?;
+        self.save_job(job).await
+    }
+
+    /// Writes the runner's copy of `job`, unless the stored job was cancelled in the meantime
+    /// (by `CancelOperation` in a process that does not run it): a job never leaves `Cancelled`.
+    /// The check and the write are one `put_if_status`, so a cancel cannot land in between.
+    async fn save_job(&self, job: &Job) -> anyhow::Result<()> {
+        let saved = self
+            .job_store
+            .put_if_status(job, &[JobStatus::Queued, JobStatus::Running])
+            .await
+            .context("save job")?;
+        if !saved {
+            return Err(JobStopped::Cancelled.into());
+        }
         Ok(())
     }
 
@@ -95,6 +234,9 @@ impl JobRunner {
         let Some(mut job) = self.job_store.get(job_id).await? else {
             return Ok(());
         };
+        if job.status == JobStatus::Cancelled {
+            return Ok(());
+        }
         job.status = JobStatus::Error;
         job.message = message;
         job.finished_at = Some(Utc::now());
@@ -102,16 +244,77 @@ impl JobRunner {
         Ok(())
     }
 
+    async fn mark_cancelled(&self, job_id: &str) -> anyhow::Result<()> {
+        let Some(mut job) = self.job_store.get(job_id).await? else {
+            return Ok(());
+        };
+        job.status = JobStatus::Cancelled;
+        job.message = CANCELLED_MESSAGE.to_string();
+        job.finished_at = Some(Utc::now());
+        self.job_store.put(&job).await?;
+        Ok(())
+    }
+
+    /// Fails with [`JobStopped`] once `request_cancel` was called for the job, its stored
+    /// status is `Cancelled` (set by a process that does not run it), or `max_runtime` passed.
+    async fn ensure_not_stopped(&self, job_id: &str) -> anyhow::Result<()> {
+        let state = self
+            .running
+            .lock()
+            .expect("running jobs lock")
+            .get(job_id)
+            .copied();
+        let stored = self
+            .job_store
+            .get(job_id)
+            .await
+            .context("load job")?
+            .is_some_and(|job| job.status == JobStatus::Cancelled);
+        if stored || state.is_some_and(|state| state.cancel_requested) {
+            return Err(JobStopped::Cancelled.into());
+        }
+        if let Some(limit) = self.max_runtime
+            && state
+                .and_then(|state| state.deadline)
+                .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(JobStopped::TimedOut(limit).into());
+        }
+        Ok(())
+    }
+
+    /// Runs an async stage, dropping it once the job's deadline passes (a hung fetch never
+    /// returns on its own). `run_job` removes the work dir after the stage is gone.
+    async fn within_deadline<T>(
+        &self,
+        job_id: &str,
+        stage: impl std::future::Future<Output = anyhow::Result<T>>,
+    ) -> anyhow::Result<T> {
+        let deadline = self
+            .running
+            .lock()
+            .expect("running jobs lock")
+            .get(job_id)
+            .and_then(|state| state.deadline);
+        match (self.max_runtime, deadline) {
+            (Some(limit), Some(deadline)) => tokio::time::timeout_at(deadline.into(), stage)
+                .await
+                .map_err(|_| JobStopped::TimedOut(limit))?,
+            _ => stage.await,
+        }
+    }
+
+    /// Records the next stage; also the point where a cancelled or timed-out job stops.
     async fn update_progress(
         &self,
         job: &mut Job,
         percent: u32,
         message: &str,
     ) -> anyhow::Result<()> {
+        self.ensure_not_stopped(&job.job_id).await?;
         job.progress_percent = percent.min(100);
         job.message = message.to_string();
-        self.job_store.put(job).await.context("save job")?;
-        Ok(())
+        self.save_job(job).await
     }
 
     async fn run_pipeline(&self, job: &mut Job, request: &StartJobRequest) -> anyhow::Result<()> {
@@ -128,21 +331,45 @@ impl JobRunner {
         let epub_path = job.work_dir.join("book.epub");
 
         self.update_progress(job, 5, STAGE_CRAWL).await?;
-        crate::crawl::run(CrawlArgs {
+        let crawl = crate::crawl::run(CrawlArgs {
             url: request.url.clone(),
             out: raw_dir.to_string_lossy().to_string(),
+            force: false,
             max_pages: request.max_pages,
             max_depth: request.max_depth,
             concurrency: request.concurrency,
+            concurrency_per_host: None,
             delay_ms: request.delay_ms,
-        })
-        .await
-        .context("crawl")?;
+            page_urls: request.page_urls.clone(),
+            seed_from_sitemap: false,
+            resume: false,
+            host_aliases: Vec::new(),
+            keep_query: false,
+            tracking_params: Vec::new(),
+            fetch_command: None,
+            exclude_extensions: Vec::new(),
+            max_redirects: 10,
+            same_host_redirects_only: false,
+            fail_on_login_wall: false,
+            proxy: None,
+            accept_language: None,
+            ca_cert: None,
+            insecure: false,
+        });
+        self.within_deadline(&job.job_id, async { crawl.await.context("crawl") })
+            .await?;
 
         self.update_progress(job, 25, STAGE_EXTRACT).await?;
         crate::extract::run(ExtractArgs {
             raw: raw_dir.to_string_lossy().to_string(),
             out: extracted_dir.to_string_lossy().to_string(),
+            force: false,
+            output_layout: OutputLayout::Flat,
+            min_content_chars: 0,
+            if_modified: None,
+            title_source: TitleSource::Readability,
+            strip_repeated: false,
+            strip_repeated_threshold: 80,
         })
         .context("extract")?;
 
@@ -154,7 +381,7 @@ impl JobRunner {
         .context("manifest")?;
 
         self.update_progress(job, 55, STAGE_TOC).await?;
-        crate::toc::create(TocCreateArgs {
+        let toc_create = crate::toc::create(TocCreateArgs {
             manifest: manifest_path.to_string_lossy().to_string(),
             out: toc_path.to_string_lossy().to_string(),
             book_title: request.title.clone(),
@@ -162,9 +389,22 @@ impl JobRunner {
             language: request.language.clone(),
             tone: request.tone.clone(),
             engine: request.toc_engine,
+            chapter_ids: ChapterIdStyle::Positional,
+            noop_grouping: NoopGrouping::Flat,
+            page_order: PageOrder::Toc,
+            sitemap: None,
+            dump_prompt: None,
+            openai_organization: None,
+            openai_project: None,
+            openai_api_key_env: None,
+            openai_retry_base_ms: None,
+            openai_retry_max_ms: None,
+            proxy: None,
+        });
+        self.within_deadline(&job.job_id, async {
+            toc_create.await.context("toc create")
         })
-        .await
-        .context("toc create")?;
+        .await?;
 
         let toc_yaml = std::fs::read_to_string(&toc_path)
             .with_context(|| format!("read toc: {}", toc_path.display()))?;
@@ -174,6 +414,8 @@ impl JobRunner {
         crate::book::init(BookInitArgs {
             out: book_dir.to_string_lossy().to_string(),
             title: toc.book_title,
+            template: None,
+            summary_template: None,
         })
         .context("book init")?;
 
@@ -185,6 +427,48 @@ impl JobRunner {
             language: request.language.clone(),
             tone: request.tone.clone(),
             engine: request.render_engine,
+            openai_fallback_model: None,
+            rewrite_granularity: RewriteGranularity::Section,
+            max_section_chars: None,
+            openai_chunk_overlap_chars: 0,
+            tone_example_file: None,
+            instructions_template: None,
+            dump_prompt: None,
+            sample: None,
+            sample_seed: 0,
+            toc_depth: 1,
+            intro: false,
+            toc_title: "Introduction".to_owned(),
+            chapter_summaries: false,
+            chapter_summary_title: "What you learned".to_owned(),
+            chapter_title_format: None,
+            sections_as_chapters: false,
+            emit_redirects: false,
+            anchor_style: AnchorStyle::Html,
+            concurrency: None,
+            force_rerender: false,
+            asset_naming: AssetNaming::Hash,
+            strip_badges: false,
+            skip_image_hosts: Vec::new(),
+            dedupe_assets_by_content: false,
+            asset_retries: 2,
+            max_asset_bytes: 20 * 1024 * 1024,
+            on_asset_error: AssetErrorPolicy::KeepUrl,
+            external_link_policy: ExternalLinkPolicy::Keep,
+            host_aliases: Vec::new(),
+            no_normalize: false,
+            include_excerpts: false,
+            excerpt_chars: 300,
+            openai_organization: None,
+            openai_project: None,
+            openai_api_key_env: None,
+            openai_retry_base_ms: None,
+            openai_retry_max_ms: None,
+            openai_stream: false,
+            proxy: None,
+            accept_language: None,
+            ca_cert: None,
+            insecure: false,
         };
         tokio::task::block_in_place(|| crate::book::render(render_args)).context("book render")?;
 
@@ -193,9 +477,17 @@ impl JobRunner {
             book: book_dir.to_string_lossy().to_string(),
             out: bundled_md_path.to_string_lossy().to_string(),
             force: false,
+            chapter_separator: None,
+            front_matter: false,
+            author: None,
+            date: None,
         })
         .context("book bundle")?;
 
+        if request.skip_epub {
+            return Ok(());
+        }
+
         self.update_progress(job, 95, STAGE_BOOK_EPUB).await?;
         crate::epub::create_from_mdbook(
             &book_dir,
@@ -203,6 +495,9 @@ impl JobRunner {
             &crate::epub::CreateEpubOptions {
                 force: false,
                 lang: crate::epub::guess_lang_tag(&request.language),
+                cover_image: None,
+                nav_depth: None,
+                chapter_page_breaks: false,
             },
         )
         .context("book epub")?;
@@ -1,11 +1,12 @@
+use std::collections::HashMap;
 use std::io::Read as _;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use axum::Router;
 use axum::error_handling::HandleErrorLayer;
-use axum::extract::{Path, Query, State};
+use axum::extract::{ConnectInfo, Path, Query, State};
 use axum::http::HeaderMap;
 use axum::http::HeaderValue;
 use axum::http::StatusCode;
@@ -23,14 +24,19 @@ use tower::ServiceBuilder;
 use tower_http::services::{ServeDir, ServeFile};
 use tower_http::trace::TraceLayer;
 
-use sitebookify::app::artifact_store::{ArtifactStore, GcsArtifactStore, LocalFsArtifactStore};
+use sitebookify::app::artifact_store::{
+    ArtifactKind, ArtifactStore, GcsArtifactStore, LocalFsArtifactStore, probe_writable_dir,
+};
 use sitebookify::app::dispatcher::{
     ExecutionMode, InProcessJobDispatcher, JobDispatcher, WorkerJobDispatcher,
 };
 use sitebookify::app::job_store::{GcsJobStore, JobStore, LocalFsJobStore};
 use sitebookify::app::model::{Job, JobStatus, StartJobRequest};
 use sitebookify::app::queue::InProcessQueue;
-use sitebookify::app::runner::{JobRunner, default_job_work_dir};
+use sitebookify::app::recovery::{RecoveryMode, recover_jobs};
+use sitebookify::app::runner::{
+    CANCELLED_MESSAGE, DEFAULT_JOB_MAX_SECS, JobRunner, default_job_work_dir,
+};
 use sitebookify::cli::LlmEngine;
 use sitebookify::google::longrunning::operations_server::{
     Operations as LongrunningOperations, OperationsServer as LongrunningOperationsServer,
@@ -45,10 +51,19 @@ use sitebookify::grpc::v1::sitebookify_service_server::{
     SitebookifyService, SitebookifyServiceServer,
 };
 use sitebookify::grpc::v1::{
-    CreateJobMetadata, CreateJobRequest, Engine, GenerateJobDownloadUrlRequest,
+    Artifact, CreateJobMetadata, CreateJobRequest, Engine, GenerateJobDownloadUrlRequest,
     GenerateJobDownloadUrlResponse, GetJobRequest, Job as PbJob, JobSpec, ListJobsRequest,
     ListJobsResponse,
 };
+use sitebookify::net_guard::{HostPolicy, check_public_http_url};
+
+const DEFAULT_PREVIEW_MAX_PER_CLIENT: usize = 2;
+/// Cloud Run allows 10 seconds between SIGTERM and SIGKILL.
+const DEFAULT_SHUTDOWN_DRAIN_SECS: u64 = 8;
+/// Longest `WaitOperation` wait (also used when the request sets no timeout), kept well below
+/// typical proxy request timeouts.
+const WAIT_OPERATION_MAX_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+const WAIT_OPERATION_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
 
 #[derive(Debug, Parser)]
 #[command(author, version, about)]
@@ -65,6 +80,10 @@ struct AppArgs {
     /// Static web assets directory (serve if exists).
     #[arg(long, default_value = "web/dist")]
     web_dir: PathBuf,
+
+    /// Log output format (default: `SITEBOOKIFY_LOG_FORMAT`, else `text`).
+    #[arg(long, value_enum)]
+    log_format: Option<sitebookify::cli::LogFormat>,
 }
 
 #[derive(Clone)]
@@ -76,6 +95,85 @@ struct AppState {
     dispatcher: Arc<dyn JobDispatcher>,
     inprocess_dispatcher: Arc<InProcessJobDispatcher>,
     internal_dispatch_token: Option<String>,
+    host_policy: Arc<HostPolicy>,
+    preview_limiter: Arc<PreviewLimiter>,
+}
+
+/// Caps concurrent `/preview` requests per client address.
+#[derive(Debug)]
+struct PreviewLimiter {
+    max_per_client: usize,
+    /// Reverse proxies in front of the app (`SITEBOOKIFY_TRUSTED_PROXY_HOPS`). With `0` the
+    /// client is the TCP peer; otherwise it is read from `X-Forwarded-For`, where each proxy
+    /// appends the address it received the request from.
+    trusted_proxy_hops: usize,
+    in_flight: std::sync::Mutex<HashMap<IpAddr, usize>>,
+}
+
+/// Held for the duration of one preview; releases its slot on drop.
+struct PreviewPermit {
+    limiter: Arc<PreviewLimiter>,
+    client: IpAddr,
+}
+
+impl PreviewLimiter {
+    fn new(max_per_client: usize, trusted_proxy_hops: usize) -> Self {
+        Self {
+            max_per_client,
+            trusted_proxy_hops,
+            in_flight: Default::default(),
+        }
+    }
+
+    /// The `trusted_proxy_hops`-th `X-Forwarded-For` entry from the right: the address the
+    /// outermost trusted proxy saw. Entries left of it can be forged by the client. Falls back
+    /// to `peer` when the header is missing, shorter, or not an IP address.
+    fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if self.trusted_proxy_hops == 0 {
+            return peer;
+        }
+        let forwarded = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .collect::<Vec<_>>();
+        forwarded
+            .len()
+            .checked_sub(self.trusted_proxy_hops)
+            .and_then(|idx| forwarded[idx].parse().ok())
+            .unwrap_or(peer)
+    }
+
+    fn try_acquire(self: &Arc<Self>, client: IpAddr) -> Option<PreviewPermit> {
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        let count = in_flight.entry(client).or_default();
+        if *count >= self.max_per_client {
+            return None;
+        }
+        *count += 1;
+        Some(PreviewPermit {
+            limiter: Arc::clone(self),
+            client,
+        })
+    }
+}
+
+impl Drop for PreviewPermit {
+    fn drop(&mut self) {
+        let mut in_flight = self
+            .limiter
+            .in_flight
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Some(count) = in_flight.get_mut(&self.client) {
+            *count -= 1;
+            if *count == 0 {
+                in_flight.remove(&self.client);
+            }
+        }
+    }
 }
 
 #[tokio::main]
@@ -88,9 +186,8 @@ async fn main() -> std::process::ExitCode {
 }
 
 async fn try_main() -> anyhow::Result<()> {
-    sitebookify::logging::init()?;
-
     let args = AppArgs::parse();
+    sitebookify::logging::init(args.log_format, false)?;
     tracing::info!(?args, "starting sitebookify-app");
     let execution_mode = ExecutionMode::from_env()?;
 
@@ -130,12 +227,30 @@ async fn try_main() -> anyhow::Result<()> {
             Arc::new(LocalFsArtifactStore::new(args.data_dir.clone()))
         }
     };
-    let runner = Arc::new(JobRunner::new(
-        Arc::clone(&job_store),
-        Arc::clone(&artifact_store),
-    ));
+    let job_max_secs = match std::env::var("SITEBOOKIFY_JOB_MAX_SECS") {
+        Ok(raw) => raw
+            .trim()
+            .parse::<u64>()
+            .map_err(|err| anyhow::anyhow!("invalid SITEBOOKIFY_JOB_MAX_SECS={raw:?}: {err}"))?,
+        Err(_) => DEFAULT_JOB_MAX_SECS,
+    };
+    let runner = Arc::new(
+        JobRunner::new(Arc::clone(&job_store), Arc::clone(&artifact_store)).with_max_runtime(
+            (job_max_secs > 0).then(|| std::time::Duration::from_secs(job_max_secs)),
+        ),
+    );
+    let shutdown_drain = match std::env::var("SITEBOOKIFY_SHUTDOWN_DRAIN_SECS") {
+        Ok(raw) => raw.trim().parse::<u64>().map_err(|err| {
+            anyhow::anyhow!("invalid SITEBOOKIFY_SHUTDOWN_DRAIN_SECS={raw:?}: {err}")
+        })?,
+        Err(_) => DEFAULT_SHUTDOWN_DRAIN_SECS,
+    };
+    let shutdown_drain = std::time::Duration::from_secs(shutdown_drain);
     let queue = InProcessQueue::new(args.max_concurrency);
-    let inprocess_dispatcher = Arc::new(InProcessJobDispatcher::new(queue, Arc::clone(&runner)));
+    let inprocess_dispatcher = Arc::new(InProcessJobDispatcher::new(
+        queue.clone(),
+        Arc::clone(&runner),
+    ));
     let dispatcher: Arc<dyn JobDispatcher> = match execution_mode {
         ExecutionMode::InProcess => {
             tracing::info!("execution mode is inprocess");
@@ -146,11 +261,56 @@ async fn try_main() -> anyhow::Result<()> {
             Arc::new(WorkerJobDispatcher::from_env()?)
         }
     };
+    // Before recovery, so requeued jobs build guarded clients too.
+    let host_policy = HostPolicy::from_env();
+    if !host_policy.allow_private_networks {
+        sitebookify::net_guard::enforce_public_addresses();
+    }
+    if matches!(execution_mode, ExecutionMode::InProcess) {
+        let recovery_mode = RecoveryMode::from_env()?;
+        let summary = recover_jobs(
+            job_store.as_ref(),
+            inprocess_dispatcher.as_ref(),
+            recovery_mode,
+        )
+        .await?;
+        tracing::info!(
+            ?recovery_mode,
+            requeued = summary.requeued.len(),
+            failed = summary.failed.len(),
+            "recovered unfinished jobs"
+        );
+    }
+
     let internal_dispatch_token = std::env::var("SITEBOOKIFY_INTERNAL_DISPATCH_TOKEN")
         .ok()
         .map(|v| v.trim().to_string())
         .filter(|v| !v.is_empty());
 
+    let preview_max_per_client = match std::env::var("SITEBOOKIFY_PREVIEW_MAX_PER_CLIENT") {
+        Ok(raw) => raw
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|v| *v >= 1)
+            .ok_or_else(|| {
+                anyhow::anyhow!("invalid SITEBOOKIFY_PREVIEW_MAX_PER_CLIENT={raw:?}: expected >= 1")
+            })?,
+        Err(_) => DEFAULT_PREVIEW_MAX_PER_CLIENT,
+    };
+    let trusted_proxy_hops = match std::env::var("SITEBOOKIFY_TRUSTED_PROXY_HOPS") {
+        Ok(raw) => raw.trim().parse::<usize>().map_err(|_| {
+            anyhow::anyhow!("invalid SITEBOOKIFY_TRUSTED_PROXY_HOPS={raw:?}: expected >= 0")
+        })?,
+        Err(_) => 0,
+    };
+    tracing::info!(
+        ?host_policy,
+        preview_max_per_client,
+        trusted_proxy_hops,
+        "url policy"
+    );
+
     let state = AppState {
         base_dir: args.data_dir,
         job_store,
@@ -159,6 +319,11 @@ async fn try_main() -> anyhow::Result<()> {
         dispatcher,
         inprocess_dispatcher,
         internal_dispatch_token,
+        host_policy: Arc::new(host_policy),
+        preview_limiter: Arc::new(PreviewLimiter::new(
+            preview_max_per_client,
+            trusted_proxy_hops,
+        )),
     };
 
     let grpc_impl = GrpcSitebookifyService {
@@ -215,6 +380,7 @@ async fn try_main() -> anyhow::Result<()> {
 
     let mut app = Router::new()
         .route("/healthz", get(|| async { "ok\n" }))
+        .route("/readyz", get(readyz))
         .route("/preview", get(preview_site_handler))
         .route("/artifacts/:job_id", get(download_artifact))
         .route("/jobs/:job_id/book.md", get(download_book_md))
@@ -249,13 +415,82 @@ async fn try_main() -> anyhow::Result<()> {
         .await
         .map_err(|err| anyhow::anyhow!("bind {}: {err}", args.addr))?;
     tracing::info!(addr = %args.addr, "listening");
-    axum::serve(listener, app).await?;
+    let (stop_serving, mut serving_stopped) = tokio::sync::watch::channel(false);
+    let mut server = tokio::spawn(
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(async move {
+            let _ = serving_stopped.wait_for(|stopped| *stopped).await;
+        })
+        .into_future(),
+    );
+    tokio::select! {
+        served = &mut server => {
+            served.map_err(|err| anyhow::anyhow!("join http server: {err}"))??;
+            return Ok(());
+        }
+        () = shutdown_signal() => {}
+    }
+
+    // Refuse new queue work first, so requests still draining cannot start jobs.
+    tracing::info!(drain_secs = shutdown_drain.as_secs(), "shutting down");
+    queue.close();
+    let _ = stop_serving.send(true);
+    let deadline = tokio::time::Instant::now() + shutdown_drain;
+    match tokio::time::timeout_at(deadline, &mut server).await {
+        Ok(served) => served.map_err(|err| anyhow::anyhow!("join http server: {err}"))??,
+        Err(_) => tracing::warn!("http connections still open at the drain deadline"),
+    }
+    // The job store writes through on every update, so there is nothing to flush: jobs that
+    // do not finish in time stay `Running` and are picked up by startup recovery.
+    let remaining = queue
+        .drain(deadline.saturating_duration_since(tokio::time::Instant::now()))
+        .await;
+    if remaining > 0 {
+        tracing::warn!(
+            running = remaining,
+            "jobs still running at the drain deadline; leaving them for recovery"
+        );
+    } else {
+        tracing::info!("in-flight jobs drained");
+    }
     Ok(())
 }
 
+/// Resolves on Ctrl-C, or SIGTERM on Unix (what Cloud Run and Kubernetes send on rollout).
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            tracing::warn!(?err, "listen for ctrl-c");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(err) => {
+                tracing::warn!(?err, "listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        () = ctrl_c => {}
+        () = terminate => {}
+    }
+}
+
 async fn download_artifact(
     State(state): State<AppState>,
     Path(job_id): Path<String>,
+    headers: HeaderMap,
 ) -> Result<Response, axum::http::StatusCode> {
     if uuid::Uuid::parse_str(job_id.trim()).is_err() {
         return Err(axum::http::StatusCode::BAD_REQUEST);
@@ -281,7 +516,7 @@ async fn download_artifact(
     {
         let url = state
             .artifact_store
-            .generate_download_url(&job_id, state.signed_url_ttl_secs)
+            .generate_download_url(&job_id, ArtifactKind::Zip, state.signed_url_ttl_secs)
             .await
             .map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
 
@@ -302,10 +537,8 @@ async fn download_artifact(
     let file = tokio::fs::File::open(&path)
         .await
         .map_err(|_| axum::http::StatusCode::NOT_FOUND)?;
-    let stream = ReaderStream::new(file);
-    let body = axum::body::Body::from_stream(stream);
 
-    let mut resp = Response::new(body);
+    let mut resp = ranged_response(DownloadBody::File(file), &headers).await?;
     resp.headers_mut().insert(
         header::CONTENT_TYPE,
         HeaderValue::from_static("application/zip"),
@@ -325,7 +558,57 @@ struct PreviewQuery {
     url: String,
 }
 
+async fn readyz(State(state): State<AppState>) -> (StatusCode, Json<serde_json::Value>) {
+    let (status, body) = readiness(&state.base_dir, state.artifact_store.as_ref()).await;
+    (status, Json(body))
+}
+
+/// `/readyz` body: every dependency check by name, plus a `reason` when one fails (503).
+async fn readiness(
+    base_dir: &std::path::Path,
+    artifact_store: &dyn ArtifactStore,
+) -> (StatusCode, serde_json::Value) {
+    let results = [
+        ("data_dir", probe_writable_dir(base_dir).await),
+        ("artifact_store", artifact_store.health_check().await),
+    ];
+
+    let mut checks = serde_json::Map::new();
+    let mut failures = Vec::new();
+    for (name, result) in results {
+        match result {
+            Ok(()) => {
+                checks.insert(name.to_owned(), "ok".into());
+            }
+            Err(err) => {
+                tracing::warn!(check = name, error = %format!("{err:#}"), "readiness check failed");
+                failures.push(format!("{name}: {err:#}"));
+                checks.insert(name.to_owned(), format!("{err:#}").into());
+            }
+        }
+    }
+
+    if failures.is_empty() {
+        (
+            StatusCode::OK,
+            serde_json::json!({ "status": "ready", "checks": checks }),
+        )
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            serde_json::json!({
+                "status": "not_ready",
+                "reason": failures.join("; "),
+                "checks": checks,
+            }),
+        )
+    }
+}
+
 async fn preview_site_handler(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Query(q): Query<PreviewQuery>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     let raw = q.url.trim();
@@ -339,7 +622,28 @@ async fn preview_site_handler(
             format!("invalid url query parameter: {err}"),
         )
     })?;
-    let url = sitebookify::crawl::resolve_start_url_for_crawl(&url).await;
+    check_public_http_url(&url, &state.host_policy)
+        .await
+        .map_err(|reason| (StatusCode::BAD_REQUEST, reason))?;
+    let client = state.preview_limiter.client_ip(peer.ip(), &headers);
+    let Some(_permit) = state.preview_limiter.try_acquire(client) else {
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            "too many concurrent previews from this client".to_string(),
+        ));
+    };
+
+    let url = sitebookify::crawl::resolve_start_url_for_crawl(
+        &url,
+        None,
+        &Default::default(),
+        None,
+        &Default::default(),
+    )
+    .await;
+    check_public_http_url(&url, &state.host_policy)
+        .await
+        .map_err(|reason| (StatusCode::BAD_REQUEST, reason))?;
 
     let preview = sitebookify::app::preview::preview_site(&url)
         .await
@@ -350,6 +654,7 @@ async fn preview_site_handler(
 async fn download_book_md(
     State(state): State<AppState>,
     Path(job_id): Path<String>,
+    headers: HeaderMap,
 ) -> Result<Response, axum::http::StatusCode> {
     if uuid::Uuid::parse_str(job_id.trim()).is_err() {
         return Err(axum::http::StatusCode::BAD_REQUEST);
@@ -370,23 +675,19 @@ async fn download_book_md(
 
     let path = job.work_dir.join("book.md");
     let body = match tokio::fs::File::open(&path).await {
-        Ok(file) => {
-            let stream = ReaderStream::new(file);
-            axum::body::Body::from_stream(stream)
-        }
+        Ok(file) => DownloadBody::File(file),
         Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
             tracing::info!(
                 job_id = %job_id,
                 path = %path.display(),
                 "book.md was not found in work dir, fallback to artifact zip"
             );
-            let bytes = read_book_output_from_artifact(&state, &job, "book.md").await?;
-            axum::body::Body::from(bytes)
+            DownloadBody::Bytes(read_book_output_from_artifact(&state, &job, "book.md").await?)
         }
         Err(_) => return Err(axum::http::StatusCode::INTERNAL_SERVER_ERROR),
     };
 
-    let mut resp = Response::new(body);
+    let mut resp = ranged_response(body, &headers).await?;
     resp.headers_mut().insert(
         header::CONTENT_TYPE,
         HeaderValue::from_static("text/plain; charset=utf-8"),
@@ -401,6 +702,7 @@ async fn download_book_md(
 async fn download_book_epub(
     State(state): State<AppState>,
     Path(job_id): Path<String>,
+    headers: HeaderMap,
 ) -> Result<Response, axum::http::StatusCode> {
     if uuid::Uuid::parse_str(job_id.trim()).is_err() {
         return Err(axum::http::StatusCode::BAD_REQUEST);
@@ -421,23 +723,19 @@ async fn download_book_epub(
 
     let path = job.work_dir.join("book.epub");
     let body = match tokio::fs::File::open(&path).await {
-        Ok(file) => {
-            let stream = ReaderStream::new(file);
-            axum::body::Body::from_stream(stream)
-        }
+        Ok(file) => DownloadBody::File(file),
         Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
             tracing::info!(
                 job_id = %job_id,
                 path = %path.display(),
                 "book.epub was not found in work dir, fallback to artifact zip"
             );
-            let bytes = read_book_output_from_artifact(&state, &job, "book.epub").await?;
-            axum::body::Body::from(bytes)
+            DownloadBody::Bytes(read_book_output_from_artifact(&state, &job, "book.epub").await?)
         }
         Err(_) => return Err(axum::http::StatusCode::INTERNAL_SERVER_ERROR),
     };
 
-    let mut resp = Response::new(body);
+    let mut resp = ranged_response(body, &headers).await?;
     resp.headers_mut().insert(
         header::CONTENT_TYPE,
         HeaderValue::from_static("application/epub+zip"),
@@ -452,6 +750,131 @@ async fn download_book_epub(
     Ok(resp)
 }
 
+/// A download served from the work dir or extracted from the artifact zip.
+enum DownloadBody {
+    File(tokio::fs::File),
+    Bytes(Vec<u8>),
+}
+
+/// Which bytes of a `len`-byte download a `Range` header asks for.
+#[derive(Debug, PartialEq, Eq)]
+enum ByteRange {
+    /// No (usable) `Range` header: send everything with `200`.
+    Full,
+    /// Inclusive `start..=end`, sent with `206`.
+    Partial { start: u64, end: u64 },
+    /// Starts past the end: `416`.
+    Unsatisfiable,
+}
+
+/// Parses a single `bytes=` range (`a-b`, `a-`, `-suffix`). Multiple ranges and malformed
+/// headers are ignored, which RFC 9110 allows.
+fn parse_byte_range(range: Option<&HeaderValue>, len: u64) -> ByteRange {
+    let Some(spec) = range
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().strip_prefix("bytes="))
+        .filter(|spec| !spec.contains(','))
+    else {
+        return ByteRange::Full;
+    };
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return ByteRange::Full;
+    };
+    let (start, end) = (start.trim(), end.trim());
+    let (start, end) = if start.is_empty() {
+        let Ok(suffix) = end.parse::<u64>() else {
+            return ByteRange::Full;
+        };
+        if suffix == 0 {
+            return ByteRange::Unsatisfiable;
+        }
+        (len.saturating_sub(suffix), len.saturating_sub(1))
+    } else {
+        let Ok(start) = start.parse::<u64>() else {
+            return ByteRange::Full;
+        };
+        let end = match end {
+            "" => len.saturating_sub(1),
+            end => match end.parse::<u64>() {
+                Ok(end) if end >= start => end.min(len.saturating_sub(1)),
+                _ => return ByteRange::Full,
+            },
+        };
+        (start, end)
+    };
+    if start >= len {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial { start, end }
+}
+
+/// Streams `body`, honoring the request's `Range` header (`206`/`416`, `Accept-Ranges: bytes`)
+/// so clients can resume large downloads.
+async fn ranged_response(
+    body: DownloadBody,
+    request_headers: &HeaderMap,
+) -> Result<Response, StatusCode> {
+    use tokio::io::{AsyncReadExt as _, AsyncSeekExt as _};
+
+    let len = match &body {
+        DownloadBody::File(file) => file
+            .metadata()
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .len(),
+        DownloadBody::Bytes(bytes) => bytes.len() as u64,
+    };
+    let range = parse_byte_range(request_headers.get(header::RANGE), len);
+    let (status, start, count) = match range {
+        ByteRange::Full => (StatusCode::OK, 0, len),
+        ByteRange::Partial { start, end } => (StatusCode::PARTIAL_CONTENT, start, end - start + 1),
+        ByteRange::Unsatisfiable => {
+            let mut resp = Response::new(axum::body::Body::empty());
+            *resp.status_mut() = StatusCode::RANGE_NOT_SATISFIABLE;
+            resp.headers_mut().insert(
+                header::CONTENT_RANGE,
+                HeaderValue::from_str(&format!("bytes */{len}"))
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+            );
+            resp.headers_mut()
+                .insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+            return Ok(resp);
+        }
+    };
+
+    let body = match body {
+        DownloadBody::File(mut file) => {
+            if start > 0 {
+                file.seek(std::io::SeekFrom::Start(start))
+                    .await
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            }
+            axum::body::Body::from_stream(ReaderStream::new(file.take(count)))
+        }
+        DownloadBody::Bytes(mut bytes) => {
+            bytes.truncate((start + count) as usize);
+            bytes.drain(..start as usize);
+            axum::body::Body::from(bytes)
+        }
+    };
+    let mut resp = Response::new(body);
+    *resp.status_mut() = status;
+    resp.headers_mut()
+        .insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    resp.headers_mut().insert(
+        header::CONTENT_LENGTH,
+        HeaderValue::from_str(&count.to_string()).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+    );
+    if status == StatusCode::PARTIAL_CONTENT {
+        resp.headers_mut().insert(
+            header::CONTENT_RANGE,
+            HeaderValue::from_str(&format!("bytes {start}-{}/{len}", start + count - 1))
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+        );
+    }
+    Ok(resp)
+}
+
 async fn read_book_output_from_artifact(
     state: &AppState,
     job: &Job,
@@ -475,7 +898,7 @@ async fn read_artifact_zip_bytes(state: &AppState, job: &Job) -> Result<Vec<u8>,
     {
         let signed_url = state
             .artifact_store
-            .generate_download_url(&job.job_id, state.signed_url_ttl_secs)
+            .generate_download_url(&job.job_id, ArtifactKind::Zip, state.signed_url_ttl_secs)
             .await
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
         let resp = reqwest::get(&signed_url).await.map_err(|_| {
@@ -609,7 +1032,33 @@ impl SitebookifyService for GrpcSitebookifyService {
                 "job.spec.source_url must be http/https",
             ));
         }
-        let url = sitebookify::crawl::resolve_start_url_for_crawl(&url).await;
+        check_public_http_url(&url, &self.state.host_policy)
+            .await
+            .map_err(|reason| Status::invalid_argument(format!("job.spec.source_url: {reason}")))?;
+        let url = sitebookify::crawl::resolve_start_url_for_crawl(
+            &url,
+            None,
+            &Default::default(),
+            None,
+            &Default::default(),
+        )
+        .await;
+
+        let page_urls = if spec.page_urls.is_empty() {
+            Vec::new()
+        } else {
+            sitebookify::crawl::canonical_page_list(&spec.page_urls, &Default::default()).map_err(
+                |err| Status::invalid_argument(format!("invalid job.spec.page_urls: {err:#}")),
+            )?
+        };
+        for page_url in &page_urls {
+            check_public_http_url(page_url, &self.state.host_policy)
+                .await
+                .map_err(|reason| {
+                    Status::invalid_argument(format!("job.spec.page_urls: {reason}"))
+                })?;
+        }
+        let page_urls = page_urls.into_iter().map(|url| url.to_string()).collect();
 
         let work_dir = default_job_work_dir(&self.state.base_dir, &job_id);
 
@@ -640,6 +1089,8 @@ impl SitebookifyService for GrpcSitebookifyService {
                 .map_err(Status::invalid_argument)?,
             render_engine: engine_or_default(spec.render_engine, StartJobRequest::default_engine())
                 .map_err(Status::invalid_argument)?,
+            page_urls,
+            skip_epub: spec.skip_epub,
         };
 
         let job = Job {
@@ -797,8 +1248,9 @@ impl SitebookifyService for GrpcSitebookifyService {
         &self,
         request: Request<GenerateJobDownloadUrlRequest>,
     ) -> Result<TonicResponse<GenerateJobDownloadUrlResponse>, Status> {
-        let job_id =
-            job_id_from_name(&request.into_inner().name).map_err(Status::invalid_argument)?;
+        let request = request.into_inner();
+        let job_id = job_id_from_name(&request.name).map_err(Status::invalid_argument)?;
+        let artifact = artifact_from_pb(request.artifact).map_err(Status::invalid_argument)?;
         let Some(job) = self
             .state
             .job_store
@@ -812,11 +1264,25 @@ impl SitebookifyService for GrpcSitebookifyService {
         if job.status != JobStatus::Done || job.artifact_path.is_none() {
             return Err(Status::failed_precondition("artifact not ready"));
         }
+        if artifact == ArtifactKind::Epub {
+            let skip_epub = self
+                .state
+                .job_store
+                .get_request(&job_id)
+                .await
+                .map_err(|err| Status::internal(format!("get job request: {err:#}")))?
+                .is_some_and(|request| request.skip_epub);
+            if skip_epub {
+                return Err(Status::failed_precondition(
+                    "job was created with skip_epub; no EPUB artifact",
+                ));
+            }
+        }
 
         let url = self
             .state
             .artifact_store
-            .generate_download_url(&job_id, self.state.signed_url_ttl_secs)
+            .generate_download_url(&job_id, artifact, self.state.signed_url_ttl_secs)
             .await
             .map_err(|err| Status::internal(format!("generate download url: {err:#}")))?;
 
@@ -842,20 +1308,10 @@ struct GrpcOperations {
     state: AppState,
 }
 
-#[tonic::async_trait]
-impl LongrunningOperations for GrpcOperations {
-    async fn list_operations(
-        &self,
-        _request: Request<ListOperationsRequest>,
-    ) -> Result<TonicResponse<ListOperationsResponse>, Status> {
-        Err(Status::unimplemented("ListOperations is not implemented"))
-    }
-
-    async fn get_operation(
-        &self,
-        request: Request<GetOperationRequest>,
-    ) -> Result<TonicResponse<Operation>, Status> {
-        let name = request.into_inner().name;
+impl GrpcOperations {
+    /// The operation for job `name`, with `CreateJobMetadata` and (once finished) the `Job`
+    /// or error as its result.
+    async fn load_operation(&self, name: String) -> Result<Operation, Status> {
         let job_id = job_id_from_operation_name(&name).map_err(Status::invalid_argument)?;
 
         let Some(job) = self
@@ -886,7 +1342,10 @@ impl LongrunningOperations for GrpcOperations {
             message: job.message.clone(),
         };
 
-        let done = matches!(job.status, JobStatus::Done | JobStatus::Error);
+        let done = matches!(
+            job.status,
+            JobStatus::Done | JobStatus::Error | JobStatus::Cancelled
+        );
         let result = match job.status {
             JobStatus::Done => {
                 let pb_job = job_to_pb(&job, &start_request);
@@ -904,10 +1363,17 @@ impl LongrunningOperations for GrpcOperations {
                     details: Vec::new(),
                 },
             )),
+            JobStatus::Cancelled => Some(
+                sitebookify::google::longrunning::operation::Result::Error(RpcStatus {
+                    code: 1, // CANCELLED
+                    message: job.message.clone(),
+                    details: Vec::new(),
+                }),
+            ),
             JobStatus::Queued | JobStatus::Running => None,
         };
 
-        Ok(TonicResponse::new(Operation {
+        Ok(Operation {
             name,
             metadata: Some(pack_any(
                 "type.googleapis.com/sitebookify.v1.CreateJobMetadata",
@@ -915,7 +1381,25 @@ impl LongrunningOperations for GrpcOperations {
             )),
             done,
             result,
-        }))
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl LongrunningOperations for GrpcOperations {
+    async fn list_operations(
+        &self,
+        _request: Request<ListOperationsRequest>,
+    ) -> Result<TonicResponse<ListOperationsResponse>, Status> {
+        Err(Status::unimplemented("ListOperations is not implemented"))
+    }
+
+    async fn get_operation(
+        &self,
+        request: Request<GetOperationRequest>,
+    ) -> Result<TonicResponse<Operation>, Status> {
+        let operation = self.load_operation(request.into_inner().name).await?;
+        Ok(TonicResponse::new(operation))
     }
 
     async fn delete_operation(
@@ -925,18 +1409,85 @@ impl LongrunningOperations for GrpcOperations {
         Err(Status::unimplemented("DeleteOperation is not implemented"))
     }
 
+    /// Stops the job at its next stage boundary (running in this process) or marks it
+    /// cancelled in the job store (queued, or running on a worker that checks the store
+    /// between stages). A cancelled job produces no artifact.
     async fn cancel_operation(
         &self,
-        _request: Request<CancelOperationRequest>,
+        request: Request<CancelOperationRequest>,
     ) -> Result<TonicResponse<()>, Status> {
-        Err(Status::unimplemented("CancelOperation is not implemented"))
+        let name = request.into_inner().name;
+        let job_id = job_id_from_operation_name(&name).map_err(Status::invalid_argument)?;
+
+        let Some(mut job) = self
+            .state
+            .job_store
+            .get(&job_id)
+            .await
+            .map_err(|err| Status::internal(format!("get job: {err:#}")))?
+        else {
+            return Err(Status::not_found("operation not found"));
+        };
+        match job.status {
+            JobStatus::Done | JobStatus::Error => {
+                return Err(Status::failed_precondition("operation is already done"));
+            }
+            JobStatus::Cancelled => {}
+            JobStatus::Queued | JobStatus::Running => {
+                if !self.state.inprocess_dispatcher.request_cancel(&job_id) {
+                    job.status = JobStatus::Cancelled;
+                    job.message = CANCELLED_MESSAGE.to_string();
+                    job.finished_at = Some(chrono::Utc::now());
+                    // The runner may have finished since the read above; never overwrite that.
+                    let cancelled = self
+                        .state
+                        .job_store
+                        .put_if_status(&job, &[JobStatus::Queued, JobStatus::Running])
+                        .await
+                        .map_err(|err| Status::internal(format!("save job: {err:#}")))?;
+                    let finished = !cancelled
+                        && self
+                            .state
+                            .job_store
+                            .get(&job_id)
+                            .await
+                            .map_err(|err| Status::internal(format!("get job: {err:#}")))?
+                            .is_some_and(|job| job.status != JobStatus::Cancelled);
+                    if finished {
+                        return Err(Status::failed_precondition("operation is already done"));
+                    }
+                }
+                tracing::info!(job_id, "cancel requested");
+            }
+        }
+        Ok(TonicResponse::new(()))
     }
 
+    /// Polls the job store until the job is done or `timeout` (capped at
+    /// [`WAIT_OPERATION_MAX_TIMEOUT`], which is also the default) elapses, then returns the
+    /// operation as `GetOperation` would.
     async fn wait_operation(
         &self,
-        _request: Request<sitebookify::google::longrunning::WaitOperationRequest>,
+        request: Request<sitebookify::google::longrunning::WaitOperationRequest>,
     ) -> Result<TonicResponse<Operation>, Status> {
-        Err(Status::unimplemented("WaitOperation is not implemented"))
+        let request = request.into_inner();
+        let timeout = match &request.timeout {
+            Some(timeout) if timeout.seconds < 0 || timeout.nanos < 0 => {
+                return Err(Status::invalid_argument("timeout must be >= 0"));
+            }
+            Some(timeout) => std::time::Duration::new(timeout.seconds as u64, timeout.nanos as u32)
+                .min(WAIT_OPERATION_MAX_TIMEOUT),
+            None => WAIT_OPERATION_MAX_TIMEOUT,
+        };
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let operation = self.load_operation(request.name.clone()).await?;
+            let now = tokio::time::Instant::now();
+            if operation.done || now >= deadline {
+                return Ok(TonicResponse::new(operation));
+            }
+            tokio::time::sleep(WAIT_OPERATION_POLL_INTERVAL.min(deadline - now)).await;
+        }
     }
 }
 
@@ -946,6 +1497,7 @@ fn job_to_pb(job: &Job, start_request: &StartJobRequest) -> PbJob {
         JobStatus::Running => PbJobState::Running as i32,
         JobStatus::Done => PbJobState::Done as i32,
         JobStatus::Error => PbJobState::Error as i32,
+        JobStatus::Cancelled => PbJobState::Cancelled as i32,
     };
 
     let artifact_uri = job
@@ -983,6 +1535,18 @@ fn job_spec_to_pb(start_request: &StartJobRequest) -> JobSpec {
         tone: start_request.tone.clone(),
         toc_engine: engine_to_pb(start_request.toc_engine) as i32,
         render_engine: engine_to_pb(start_request.render_engine) as i32,
+        page_urls: start_request.page_urls.clone(),
+        skip_epub: start_request.skip_epub,
+    }
+}
+
+fn artifact_from_pb(value: i32) -> Result<ArtifactKind, String> {
+    match value {
+        0 => Ok(ArtifactKind::default()),
+        x if x == Artifact::Zip as i32 => Ok(ArtifactKind::Zip),
+        x if x == Artifact::Markdown as i32 => Ok(ArtifactKind::Markdown),
+        x if x == Artifact::Epub as i32 => Ok(ArtifactKind::Epub),
+        other => Err(format!("unknown artifact: {other}")),
     }
 }
 
@@ -1116,6 +1680,85 @@ mod tests {
         zip.finish().expect("finish zip").into_inner()
     }
 
+    #[tokio::test]
+    async fn readiness_reports_failing_checks() {
+        let temp = tempfile::TempDir::new().expect("tempdir");
+        let store = LocalFsArtifactStore::new(temp.path());
+        let (status, body) = readiness(temp.path(), &store).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ready");
+        assert_eq!(body["checks"]["artifact_store"], "ok");
+
+        let not_a_dir = temp.path().join("file");
+        std::fs::write(&not_a_dir, b"x").expect("write file");
+        let (status, body) = readiness(&not_a_dir, &store).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "not_ready");
+        assert_eq!(body["checks"]["artifact_store"], "ok");
+        assert!(
+            body["reason"]
+                .as_str()
+                .unwrap_or_default()
+                .starts_with("data_dir: ")
+        );
+    }
+
+    #[test]
+    fn preview_limiter_caps_in_flight_requests_per_client() {
+        let limiter = Arc::new(PreviewLimiter::new(2, 0));
+        let a: IpAddr = "203.0.113.1".parse().unwrap();
+        let b: IpAddr = "203.0.113.2".parse().unwrap();
+
+        let first = limiter.try_acquire(a).expect("first permit");
+        let _second = limiter.try_acquire(a).expect("second permit");
+        assert!(limiter.try_acquire(a).is_none());
+        assert!(limiter.try_acquire(b).is_some());
+
+        drop(first);
+        assert!(limiter.try_acquire(a).is_some());
+    }
+
+    #[test]
+    fn preview_limiter_keys_on_forwarded_client_behind_trusted_proxies() {
+        let peer: IpAddr = "10.0.0.9".parse().unwrap();
+        let headers = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert("x-forwarded-for", HeaderValue::from_str(value).unwrap());
+            headers
+        };
+
+        let direct = PreviewLimiter::new(2, 0);
+        assert_eq!(direct.client_ip(peer, &headers("203.0.113.1")), peer);
+
+        let one_proxy = PreviewLimiter::new(2, 1);
+        assert_eq!(
+            one_proxy.client_ip(peer, &headers("198.51.100.7, 203.0.113.1")),
+            "203.0.113.1".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(one_proxy.client_ip(peer, &HeaderMap::new()), peer);
+
+        let two_proxies = PreviewLimiter::new(2, 2);
+        assert_eq!(
+            two_proxies.client_ip(peer, &headers("198.51.100.7, 203.0.113.1, 10.0.0.2")),
+            "203.0.113.1".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(two_proxies.client_ip(peer, &headers("10.0.0.2")), peer);
+    }
+
+    #[test]
+    fn artifact_from_pb_defaults_to_zip() {
+        assert_eq!(artifact_from_pb(0), Ok(ArtifactKind::Zip));
+        assert_eq!(
+            artifact_from_pb(Artifact::Markdown as i32),
+            Ok(ArtifactKind::Markdown)
+        );
+        assert_eq!(
+            artifact_from_pb(Artifact::Epub as i32),
+            Ok(ArtifactKind::Epub)
+        );
+        assert!(artifact_from_pb(42).is_err());
+    }
+
     #[test]
     fn extract_zip_entry_reads_target_file() {
         let zip = make_zip(&[("book.md", b"# title\n"), ("book.epub", b"EPUB")]);
@@ -1129,4 +1772,477 @@ mod tests {
         let err = extract_zip_entry(&zip, "book.epub").expect_err("book.epub should not exist");
         assert_eq!(err, StatusCode::NOT_FOUND);
     }
+
+    #[test]
+    fn parse_byte_range_handles_closed_open_and_suffix_ranges() {
+        let range = |raw: &str| parse_byte_range(Some(&HeaderValue::from_str(raw).unwrap()), 100);
+        assert_eq!(parse_byte_range(None, 100), ByteRange::Full);
+        assert_eq!(
+            range("bytes=10-19"),
+            ByteRange::Partial { start: 10, end: 19 }
+        );
+        assert_eq!(
+            range("bytes=90-"),
+            ByteRange::Partial { start: 90, end: 99 }
+        );
+        assert_eq!(
+            range("bytes=95-500"),
+            ByteRange::Partial { start: 95, end: 99 }
+        );
+        assert_eq!(range("bytes=-5"), ByteRange::Partial { start: 95, end: 99 });
+        assert_eq!(range("bytes=100-"), ByteRange::Unsatisfiable);
+        assert_eq!(range("bytes=-0"), ByteRange::Unsatisfiable);
+        for ignored in ["bytes=0-1,5-6", "bytes=5-1", "items=0-1", "bytes=x-"] {
+            assert_eq!(range(ignored), ByteRange::Full, "{ignored}");
+        }
+    }
+
+    async fn ranged(body: DownloadBody, range: Option<&str>) -> (StatusCode, HeaderMap, Vec<u8>) {
+        let mut headers = HeaderMap::new();
+        if let Some(range) = range {
+            headers.insert(header::RANGE, HeaderValue::from_str(range).unwrap());
+        }
+        let resp = ranged_response(body, &headers)
+            .await
+            .expect("ranged response");
+        let (parts, body) = resp.into_parts();
+        let bytes = axum::body::to_bytes(body, usize::MAX)
+            .await
+            .expect("read body");
+        (parts.status, parts.headers, bytes.to_vec())
+    }
+
+    #[tokio::test]
+    async fn ranged_response_serves_the_requested_slice_of_a_file() {
+        let temp = tempfile::TempDir::new().expect("tempdir");
+        let path = temp.path().join("book.epub");
+        let content = (0..100u8).collect::<Vec<_>>();
+        std::fs::write(&path, &content).expect("write file");
+        let open = || async { DownloadBody::File(tokio::fs::File::open(&path).await.unwrap()) };
+
+        let (status, headers, body) = ranged(open().await, Some("bytes=10-19")).await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(body, &content[10..20]);
+        assert_eq!(headers[header::CONTENT_RANGE], "bytes 10-19/100");
+        assert_eq!(headers[header::CONTENT_LENGTH], "10");
+        assert_eq!(headers[header::ACCEPT_RANGES], "bytes");
+
+        let (status, headers, body) = ranged(open().await, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, content);
+        assert_eq!(headers[header::ACCEPT_RANGES], "bytes");
+        assert!(headers.get(header::CONTENT_RANGE).is_none());
+
+        let (status, headers, body) = ranged(open().await, Some("bytes=100-")).await;
+        assert_eq!(status, StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(headers[header::CONTENT_RANGE], "bytes */100");
+        assert!(body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn ranged_response_slices_bytes_extracted_from_the_artifact() {
+        let (status, headers, body) = ranged(
+            DownloadBody::Bytes(b"0123456789".to_vec()),
+            Some("bytes=-3"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(body, b"789");
+        assert_eq!(headers[header::CONTENT_RANGE], "bytes 7-9/10");
+    }
+
+    async fn operations_with_job(
+        base: &std::path::Path,
+        status: JobStatus,
+        url: &str,
+    ) -> (GrpcOperations, Job) {
+        let job_store: Arc<dyn JobStore> = Arc::new(LocalFsJobStore::new(base));
+        let artifact_store: Arc<dyn ArtifactStore> = Arc::new(LocalFsArtifactStore::new(base));
+        let runner = Arc::new(JobRunner::new(
+            Arc::clone(&job_store),
+            Arc::clone(&artifact_store),
+        ));
+        let inprocess_dispatcher =
+            Arc::new(InProcessJobDispatcher::new(InProcessQueue::new(1), runner));
+        let job_id = uuid::Uuid::new_v4().to_string();
+        let job = Job {
+            job_id: job_id.clone(),
+            status,
+            progress_percent: 40,
+            message: "extract".to_string(),
+            created_at: chrono::Utc::now(),
+            started_at: Some(chrono::Utc::now()),
+            finished_at: None,
+            work_dir: default_job_work_dir(base, &job_id),
+            artifact_path: None,
+            artifact_uri: None,
+        };
+        let request = StartJobRequest {
+            url: url.to_string(),
+            title: None,
+            max_pages: 1,
+            max_depth: 0,
+            concurrency: 1,
+            delay_ms: 0,
+            language: "日本語".to_string(),
+            tone: "丁寧".to_string(),
+            toc_engine: LlmEngine::Noop,
+            render_engine: LlmEngine::Noop,
+            page_urls: Vec::new(),
+            skip_epub: false,
+        };
+        job_store.create(&job, &request).await.expect("create job");
+        let state = AppState {
+            base_dir: base.to_path_buf(),
+            job_store,
+            artifact_store,
+            signed_url_ttl_secs: 60,
+            dispatcher: inprocess_dispatcher.clone(),
+            inprocess_dispatcher,
+            internal_dispatch_token: None,
+            host_policy: Arc::new(HostPolicy::default()),
+            preview_limiter: Arc::new(PreviewLimiter::new(1, 0)),
+        };
+        (GrpcOperations { state }, job)
+    }
+
+    fn wait_request(
+        job_id: &str,
+        timeout_ms: u64,
+    ) -> Request<sitebookify::google::longrunning::WaitOperationRequest> {
+        Request::new(sitebookify::google::longrunning::WaitOperationRequest {
+            name: operation_name(job_id),
+            timeout: Some(duration_from_ms(timeout_ms)),
+        })
+    }
+
+    #[tokio::test]
+    async fn wait_operation_returns_once_the_job_finishes() {
+        let temp = tempfile::TempDir::new().expect("tempdir");
+        let (ops, job) =
+            operations_with_job(temp.path(), JobStatus::Running, "https://example.com/").await;
+        let job_store = Arc::clone(&ops.state.job_store);
+        let finisher = tokio::spawn({
+            let mut job = job.clone();
+            async move {
+                tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+                job.status = JobStatus::Done;
+                job.progress_percent = 100;
+                job.finished_at = Some(chrono::Utc::now());
+                job_store.put(&job).await.expect("finish job");
+            }
+        });
+
+        let started = std::time::Instant::now();
+        let operation = ops
+            .wait_operation(wait_request(&job.job_id, 10_000))
+            .await
+            .expect("wait operation")
+            .into_inner();
+        finisher.await.expect("finisher");
+        assert!(operation.done);
+        assert!(matches!(
+            operation.result,
+            Some(sitebookify::google::longrunning::operation::Result::Response(_))
+        ));
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn wait_operation_returns_the_running_operation_on_timeout() {
+        let temp = tempfile::TempDir::new().expect("tempdir");
+        let (ops, job) =
+            operations_with_job(temp.path(), JobStatus::Running, "https://example.com/").await;
+        let started = std::time::Instant::now();
+        let operation = ops
+            .wait_operation(wait_request(&job.job_id, 300))
+            .await
+            .expect("wait operation")
+            .into_inner();
+        assert!(!operation.done);
+        assert!(operation.result.is_none());
+        assert!(started.elapsed() >= std::time::Duration::from_millis(300));
+    }
+
+    /// Serves a one-page site whose responses take `delay`, so a crawl stays in flight.
+    fn spawn_slow_site(delay: std::time::Duration) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+        let addr = listener.local_addr().expect("addr");
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                std::thread::spawn(move || {
+                    let mut buf = [0u8; 4096];
+                    let _ = stream.read(&mut buf);
+                    std::thread::sleep(delay);
+                    let body = "<html><head><title>Slow</title></head><body><h1>Slow</h1><p>Hello.</p></body></html>";
+                    let _ = write!(
+                        stream,
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                        body.len()
+                    );
+                });
+            }
+        });
+        format!("http://{addr}/")
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn job_with_skip_epub_finishes_without_an_epub() {
+        let temp = tempfile::TempDir::new().expect("tempdir");
+        let url = spawn_slow_site(std::time::Duration::ZERO);
+        let (ops, job) = operations_with_job(temp.path(), JobStatus::Queued, &url).await;
+        let mut request = ops
+            .state
+            .job_store
+            .get_request(&job.job_id)
+            .await
+            .expect("get request")
+            .expect("request");
+        request.skip_epub = true;
+        ops.state
+            .job_store
+            .create(&job, &request)
+            .await
+            .expect("rewrite request");
+        let runner = JobRunner::new(
+            Arc::clone(&ops.state.job_store),
+            Arc::clone(&ops.state.artifact_store),
+        );
+
+        runner.run_job(&job.job_id).await;
+
+        let stored = ops
+            .state
+            .job_store
+            .get(&job.job_id)
+            .await
+            .expect("get job")
+            .expect("job");
+        assert_eq!(stored.status, JobStatus::Done, "{}", stored.message);
+        assert!(!job.work_dir.join("book.epub").exists());
+        let artifact = std::fs::File::open(stored.artifact_path.expect("artifact path"))
+            .expect("open artifact");
+        let zip = zip::ZipArchive::new(artifact).expect("read artifact zip");
+        let names = zip.file_names().collect::<Vec<_>>();
+        assert!(names.contains(&"book.md"), "{names:?}");
+        assert!(
+            !names.iter().any(|name| name.ends_with(".epub")),
+            "{names:?}"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn timed_out_job_stops_a_hung_crawl() {
+        let temp = tempfile::TempDir::new().expect("tempdir");
+        let url = spawn_slow_site(std::time::Duration::from_secs(60));
+        let (ops, job) = operations_with_job(temp.path(), JobStatus::Queued, &url).await;
+        let runner = JobRunner::new(
+            Arc::clone(&ops.state.job_store),
+            Arc::clone(&ops.state.artifact_store),
+        )
+        .with_max_runtime(Some(std::time::Duration::from_millis(200)));
+
+        // The fetch hangs far past the limit; the crawl is dropped instead of awaited.
+        tokio::time::timeout(
+            std::time::Duration::from_secs(10),
+            runner.run_job(&job.job_id),
+        )
+        .await
+        .expect("job stops at its deadline");
+
+        let stored = ops
+            .state
+            .job_store
+            .get(&job.job_id)
+            .await
+            .expect("get job")
+            .expect("job");
+        assert_eq!(stored.status, JobStatus::Error);
+        assert_eq!(stored.message, "timed out after 200ms");
+        assert!(stored.artifact_path.is_none());
+        assert!(!job.work_dir.exists());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn job_cancelled_in_the_store_by_another_process_stays_cancelled() {
+        let temp = tempfile::TempDir::new().expect("tempdir");
+        let url = spawn_slow_site(std::time::Duration::from_millis(500));
+        let (ops, job) = operations_with_job(temp.path(), JobStatus::Queued, &url).await;
+        let runner = Arc::new(JobRunner::new(
+            Arc::clone(&ops.state.job_store),
+            Arc::clone(&ops.state.artifact_store),
+        ));
+        let run = tokio::spawn({
+            let runner = Arc::clone(&runner);
+            let job_id = job.job_id.clone();
+            async move { runner.run_job(&job_id).await }
+        });
+
+        let started = std::time::Instant::now();
+        let mut stored = loop {
+            let stored = ops
+                .state
+                .job_store
+                .get(&job.job_id)
+                .await
+                .expect("get job")
+                .expect("job");
+            if stored.status == JobStatus::Running {
+                break stored;
+            }
+            assert!(started.elapsed() < std::time::Duration::from_secs(10));
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        };
+        // What `CancelOperation` writes when the job runs in another worker.
+        stored.status = JobStatus::Cancelled;
+        stored.message = CANCELLED_MESSAGE.to_string();
+        ops.state.job_store.put(&stored).await.expect("put job");
+        run.await.expect("run job");
+
+        let stored = ops
+            .state
+            .job_store
+            .get(&job.job_id)
+            .await
+            .expect("get job")
+            .expect("job");
+        assert_eq!(stored.status, JobStatus::Cancelled);
+        assert_eq!(stored.message, CANCELLED_MESSAGE);
+        assert!(stored.artifact_path.is_none());
+        assert!(!ops.state.artifact_store.artifact_path(&job.job_id).exists());
+        assert!(!job.work_dir.exists());
+    }
+
+    /// Finishes the job right after the first read, as a runner completing between
+    /// `CancelOperation`'s read and its write would.
+    struct FinishAfterFirstRead {
+        inner: Arc<dyn JobStore>,
+        finished: std::sync::atomic::AtomicBool,
+    }
+
+    #[tonic::async_trait]
+    impl JobStore for FinishAfterFirstRead {
+        async fn create(&self, job: &Job, request: &StartJobRequest) -> anyhow::Result<()> {
+            self.inner.create(job, request).await
+        }
+
+        async fn get(&self, job_id: &str) -> anyhow::Result<Option<Job>> {
+            let job = self.inner.get(job_id).await?;
+            if let Some(job) = &job
+                && !self
+                    .finished
+                    .swap(true, std::sync::atomic::Ordering::SeqCst)
+            {
+                let mut done = job.clone();
+                done.status = JobStatus::Done;
+                self.inner.put(&done).await?;
+            }
+            Ok(job)
+        }
+
+        async fn get_request(&self, job_id: &str) -> anyhow::Result<Option<StartJobRequest>> {
+            self.inner.get_request(job_id).await
+        }
+
+        async fn put(&self, job: &Job) -> anyhow::Result<()> {
+            self.inner.put(job).await
+        }
+
+        async fn put_if_status(&self, job: &Job, expected: &[JobStatus]) -> anyhow::Result<bool> {
+            self.inner.put_if_status(job, expected).await
+        }
+
+        async fn list_job_ids(&self) -> anyhow::Result<Vec<String>> {
+            self.inner.list_job_ids().await
+        }
+    }
+
+    #[tokio::test]
+    async fn cancel_operation_keeps_a_job_that_finished_after_it_was_read() {
+        let temp = tempfile::TempDir::new().expect("tempdir");
+        let (mut ops, job) =
+            operations_with_job(temp.path(), JobStatus::Running, "https://example.com/").await;
+        let inner = Arc::clone(&ops.state.job_store);
+        ops.state.job_store = Arc::new(FinishAfterFirstRead {
+            inner: Arc::clone(&inner),
+            finished: std::sync::atomic::AtomicBool::new(false),
+        });
+
+        let err = ops
+            .cancel_operation(Request::new(CancelOperationRequest {
+                name: operation_name(&job.job_id),
+            }))
+            .await
+            .expect_err("the job finished first");
+        assert_eq!(err.code(), tonic::Code::FailedPrecondition);
+
+        let stored = inner.get(&job.job_id).await.expect("get job").expect("job");
+        assert_eq!(stored.status, JobStatus::Done);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cancel_operation_stops_a_running_job_without_an_artifact() {
+        let temp = tempfile::TempDir::new().expect("tempdir");
+        let url = spawn_slow_site(std::time::Duration::from_millis(500));
+        let (ops, job) = operations_with_job(temp.path(), JobStatus::Queued, &url).await;
+        ops.state
+            .inprocess_dispatcher
+            .dispatch(&job.job_id)
+            .await
+            .expect("dispatch");
+
+        let started = std::time::Instant::now();
+        while ops
+            .state
+            .job_store
+            .get(&job.job_id)
+            .await
+            .expect("get job")
+            .expect("job")
+            .status
+            == JobStatus::Queued
+        {
+            assert!(started.elapsed() < std::time::Duration::from_secs(10));
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        ops.cancel_operation(Request::new(CancelOperationRequest {
+            name: operation_name(&job.job_id),
+        }))
+        .await
+        .expect("cancel operation");
+
+        let operation = ops
+            .wait_operation(wait_request(&job.job_id, 30_000))
+            .await
+            .expect("wait operation")
+            .into_inner();
+        assert!(operation.done);
+        let Some(sitebookify::google::longrunning::operation::Result::Error(status)) =
+            operation.result
+        else {
+            panic!("expected an error result: {operation:?}");
+        };
+        assert_eq!(status.code, 1);
+        assert_eq!(status.message, CANCELLED_MESSAGE);
+
+        let stored = ops
+            .state
+            .job_store
+            .get(&job.job_id)
+            .await
+            .expect("get job")
+            .expect("job");
+        assert_eq!(stored.status, JobStatus::Cancelled);
+        assert!(stored.artifact_path.is_none());
+        assert!(!ops.state.artifact_store.artifact_path(&job.job_id).exists());
+        assert!(!job.work_dir.exists());
+
+        let again = ops
+            .cancel_operation(Request::new(CancelOperationRequest {
+                name: operation_name(&job.job_id),
+            }))
+            .await;
+        assert!(again.is_ok(), "{again:?}");
+    }
 }
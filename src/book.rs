@@ -1,7 +1,8 @@
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fs::OpenOptions;
-use std::io::{BufRead as _, BufReader, Write as _};
+use std::io::{BufRead as _, BufReader, Read as _, Write as _};
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -13,12 +14,31 @@ use sha2::Digest as _;
 use sha2::Sha256;
 use url::Url;
 
-use crate::cli::{BookBundleArgs, BookEpubArgs, BookInitArgs, BookRenderArgs, LlmEngine};
-use crate::formats::{ManifestRecord, Toc};
+use crate::cli::{
+    AnchorStyle, AssetErrorPolicy, AssetNaming, BookBundleArgs, BookEpubArgs, BookInitArgs,
+    BookRenderArgs, BookStatsArgs, BookValidateArgs, ExternalLinkPolicy, LlmEngine,
+    RewriteGranularity, StatsFormat,
+};
+use crate::formats::{
+    BookExport, BookExportChapter, BookExportPart, BookExportSection, ManifestRecord, Toc,
+};
 use crate::rewrite;
+use crate::tls::TlsOptions;
+use crate::url_norm::HostAliases;
+
+const DEFAULT_SUMMARY_TEMPLATE: &str = "# Summary\n\n- [Chapter 1](chapters/ch01.md)\n";
+const DEFAULT_CHAPTER_TEMPLATE: &str = "# Chapter 1\n\n## Objectives\nTODO\n\n## Prerequisites\nTODO\n\n## Body\nTODO\n\n## Summary\nTODO\n\n## Sources\n\n";
 
 pub fn init(args: BookInitArgs) -> anyhow::Result<()> {
     let out_dir = PathBuf::from(&args.out);
+
+    // Read templates before creating anything, so a bad path leaves no half-made book.
+    let summary_md =
+        read_init_template(args.summary_template.as_deref(), DEFAULT_SUMMARY_TEMPLATE)?
+            .replace("{{title}}", &args.title);
+    let chapter_md = read_init_template(args.template.as_deref(), DEFAULT_CHAPTER_TEMPLATE)?
+        .replace("{{title}}", &args.title);
+
     std::fs::create_dir_all(out_dir.join("src").join("chapters"))
         .with_context(|| format!("create book dirs: {}", out_dir.display()))?;
 
@@ -37,8 +57,8 @@ pub fn init(args: BookInitArgs) -> anyhow::Result<()> {
         .write(true)
         .open(&summary)
         .with_context(|| format!("create SUMMARY.md: {}", summary.display()))?;
-    writeln!(file, "# Summary\n")?;
-    writeln!(file, "- [Chapter 1](chapters/ch01.md)")?;
+    file.write_all(summary_md.as_bytes())
+        .with_context(|| format!("write SUMMARY.md: {}", summary.display()))?;
 
     let ch01 = out_dir.join("src").join("chapters").join("ch01.md");
     let mut file = OpenOptions::new()
@@ -46,21 +66,75 @@ pub fn init(args: BookInitArgs) -> anyhow::Result<()> {
         .write(true)
         .open(&ch01)
         .with_context(|| format!("create chapter: {}", ch01.display()))?;
-    writeln!(file, "# Chapter 1\n")?;
-    writeln!(file, "## Objectives\nTODO\n")?;
-    writeln!(file, "## Prerequisites\nTODO\n")?;
-    writeln!(file, "## Body\nTODO\n")?;
-    writeln!(file, "## Summary\nTODO\n")?;
-    writeln!(file, "## Sources\n")?;
+    file.write_all(chapter_md.as_bytes())
+        .with_context(|| format!("write chapter: {}", ch01.display()))?;
 
     Ok(())
 }
 
+fn read_init_template(path: Option<&str>, default: &str) -> anyhow::Result<String> {
+    match path {
+        Some(path) => std::fs::read_to_string(path)
+            .with_context(|| format!("read book init template: {path}")),
+        None => Ok(default.to_owned()),
+    }
+}
+
 pub fn render(args: BookRenderArgs) -> anyhow::Result<()> {
+    render_book(args, None)
+}
+
+/// Runs the `book render` pipeline but writes a single `book.json` (see
+/// [`crate::formats::BookExport`]) to `--out`, with images downloaded next to it in `assets/`.
+pub fn export(args: BookRenderArgs) -> anyhow::Result<()> {
+    if args.intro || args.emit_redirects {
+        anyhow::bail!("--intro and --emit-redirects only apply to `book render`");
+    }
+    let json_path = PathBuf::from(&args.out);
+    render_book(args, Some(&json_path))
+}
+
+/// `json_out` switches the output from mdBook chapter files to one `book.json`.
+fn render_book(args: BookRenderArgs, json_out: Option<&Path>) -> anyhow::Result<()> {
     let toc_path = PathBuf::from(&args.toc);
     let toc_yaml = std::fs::read_to_string(&toc_path)
         .with_context(|| format!("read toc: {}", toc_path.display()))?;
-    let toc: Toc = serde_yaml::from_str(&toc_yaml).context("parse toc")?;
+    let mut toc: Toc = serde_yaml::from_str(&toc_yaml).context("parse toc")?;
+    // Split chapters hold a single section, so section-level entries would only repeat them.
+    let toc_depth = if args.sections_as_chapters {
+        split_sections_into_chapters(&mut toc)?;
+        1
+    } else {
+        args.toc_depth
+    };
+    // Before `--sample`, so a sampled chapter keeps the number it has in the full book.
+    if let Some(format) = args.chapter_title_format.as_deref() {
+        decorate_chapter_titles(&mut toc, format).context("apply --chapter-title-format")?;
+    }
+    let sample_notice = match args.sample {
+        Some(sample) => {
+            let (sampled, total) = sample_toc_chapters(&mut toc, sample, args.sample_seed);
+            tracing::warn!(
+                sampled,
+                total,
+                seed = args.sample_seed,
+                "book render: sampled chapters only; output is incomplete"
+            );
+            Some(format!(
+                "**Sample render:** {sampled} of {total} chapters (`--sample-seed {}`). This book is incomplete.",
+                args.sample_seed
+            ))
+        }
+        None => None,
+    };
+    let tone_examples = match args.tone_example_file.as_deref() {
+        Some(path) => rewrite::load_tone_examples(Path::new(path))?,
+        None => Vec::new(),
+    };
+    let instructions_template = match args.instructions_template.as_deref() {
+        Some(path) => Some(rewrite::load_instructions_template(Path::new(path))?),
+        None => None,
+    };
 
     let manifest_path = PathBuf::from(&args.manifest);
     let manifest_file = OpenOptions::new()
@@ -81,42 +155,132 @@ pub fn render(args: BookRenderArgs) -> anyhow::Result<()> {
     }
 
     let dir_index_ids = compute_dir_index_ids(manifest.values());
-    let url_to_location = build_url_to_location(&toc, &manifest);
-
-    let out_dir = PathBuf::from(&args.out);
+    let host_aliases = HostAliases::parse(&args.host_aliases).context("parse --host-alias")?;
+    let url_to_location = build_url_to_location(&toc, &manifest, args.anchor_style, &host_aliases);
+
+    let out_dir = match json_out {
+        Some(json_path) => json_path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."))
+            .to_path_buf(),
+        None => PathBuf::from(&args.out),
+    };
     let chapters_dir = out_dir.join("src").join("chapters");
-    let assets_dir = out_dir.join("src").join("assets");
-    std::fs::create_dir_all(&chapters_dir)
-        .with_context(|| format!("create chapters dir: {}", chapters_dir.display()))?;
+    let assets_dir = match json_out {
+        Some(_) => out_dir.join("assets"),
+        None => out_dir.join("src").join("assets"),
+    };
+    let create_dir = if json_out.is_some() {
+        &out_dir
+    } else {
+        &chapters_dir
+    };
+    std::fs::create_dir_all(create_dir)
+        .with_context(|| format!("create output dir: {}", create_dir.display()))?;
 
-    let assets = AssetDownloader::new(assets_dir).context("initialize book asset downloader")?;
+    let tls = TlsOptions::from_args(args.ca_cert.as_deref(), args.insecure)?;
+    let assets = AssetDownloader::new(assets_dir.clone(), &tls, &args)
+        .context("initialize book asset downloader")?;
 
-    let summary_md = render_summary_md(&toc);
-    std::fs::write(out_dir.join("src").join("SUMMARY.md"), summary_md)
-        .with_context(|| format!("write SUMMARY.md: {}", out_dir.display()))?;
+    let intro_title = args.intro.then_some(args.toc_title.trim());
+    if intro_title.is_some() {
+        if toc
+            .parts
+            .iter()
+            .flat_map(|part| part.chapters.iter())
+            .any(|chapter| chapter.id == INTRO_CHAPTER_ID)
+        {
+            anyhow::bail!(
+                "toc already contains a chapter with id {INTRO_CHAPTER_ID}; cannot add intro"
+            );
+        }
+        let intro_path = chapters_dir.join(format!("{INTRO_CHAPTER_ID}.md"));
+        std::fs::write(&intro_path, render_intro_md(&toc, args.toc_title.trim()))
+            .with_context(|| format!("write intro chapter: {}", intro_path.display()))?;
+    }
+
+    if args.emit_redirects {
+        let redirects_path = out_dir.join("redirects.json");
+        let redirects = render_redirects_json(&manifest, &url_to_location)?;
+        std::fs::write(&redirects_path, redirects)
+            .with_context(|| format!("write redirects map: {}", redirects_path.display()))?;
+    }
+
+    if json_out.is_none() {
+        let summary_md = render_summary_md(&toc, toc_depth, intro_title);
+        std::fs::write(out_dir.join("src").join("SUMMARY.md"), summary_md)
+            .with_context(|| format!("write SUMMARY.md: {}", out_dir.display()))?;
+    }
 
     let chapters_in_order = toc
         .parts
         .iter()
         .flat_map(|part| part.chapters.iter())
         .collect::<Vec<_>>();
-    if chapters_in_order.is_empty() {
+    if chapters_in_order.is_empty() && json_out.is_none() {
         return Ok(());
     }
-    let worker_count = std::thread::available_parallelism()
-        .map(|n| n.get())
-        .unwrap_or(1)
-        .min(chapters_in_order.len());
+    let worker_count = args
+        .concurrency
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        })
+        .min(chapters_in_order.len())
+        .max(1);
 
     let engine = args.engine;
     let language = args.language.as_str();
     let tone = args.tone.as_str();
+    let sections_as_chapters = args.sections_as_chapters;
+    let anchor_style = args.anchor_style;
+    let external_link_policy = args.external_link_policy;
+    let normalize_whitespace = !args.no_normalize;
+    let excerpt_chars = args.include_excerpts.then_some(args.excerpt_chars);
+    let rewrite_options = &rewrite::RewriteOptions {
+        fallback_model: args.openai_fallback_model.clone(),
+        granularity: args.rewrite_granularity,
+        max_section_chars: args.max_section_chars,
+        openai_organization: args.openai_organization.clone(),
+        openai_project: args.openai_project.clone(),
+        openai_api_key_env: args.openai_api_key_env.clone(),
+        openai_retry_base_ms: args.openai_retry_base_ms,
+        openai_retry_max_ms: args.openai_retry_max_ms,
+        openai_stream: args.openai_stream,
+        proxy: args.proxy.clone(),
+        tone_examples,
+        instructions_template,
+        dump_prompt_dir: args.dump_prompt.as_ref().map(PathBuf::from),
+        chunk_overlap_chars: args.openai_chunk_overlap_chars,
+    };
+    if let Some(dir) = &rewrite_options.dump_prompt_dir {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("create prompt dump dir: {}", dir.display()))?;
+    }
     let manifest = &manifest;
     let url_to_location = &url_to_location;
     let dir_index_ids = &dir_index_ids;
     let assets = &assets;
-
+    let sample_notice = sample_notice.as_deref();
+    let chapter_summary_title = args
+        .chapter_summaries
+        .then_some(args.chapter_summary_title.trim());
+    let force_rerender = args.force_rerender;
+    // Outside `src/`, so mdBook and `book bundle` never see the sidecars.
+    let render_hash_dir = &out_dir.join(RENDER_HASH_DIR);
+
+    // Only filled (by chapter index) when exporting JSON.
+    let exported = &Mutex::new(
+        std::iter::repeat_with(|| None)
+            .take(chapters_in_order.len())
+            .collect::<Vec<Option<RenderedChapter>>>(),
+    );
     let next_idx = Arc::new(AtomicUsize::new(0));
+    let skipped = &AtomicUsize::new(0);
+    let progress = &crate::logging::Progress::new("book render", chapters_in_order.len())?;
+    tracing::info!(chapters = chapters_in_order.len(), "book render: start");
 
     std::thread::scope(|scope| -> anyhow::Result<()> {
         let chapters_in_order = &chapters_in_order;
@@ -137,16 +301,61 @@ pub fn render(args: BookRenderArgs) -> anyhow::Result<()> {
                         engine,
                         language,
                         tone,
+                        toc_depth,
+                        sections_as_chapters,
+                        anchor_style,
+                        external_link_policy,
+                        normalize_whitespace,
+                        excerpt_chars,
+                        rewrite_options,
+                        sample_notice,
+                        chapter_summary_title,
                         manifest,
                         url_to_location,
                         dir_index_ids,
                         assets,
                     };
 
-                    let chapter_md = render_chapter_md(chapter, &ctx)
+                    // The JSON export is always rendered in full.
+                    let chapter_path = chapters_dir.join(format!("{chapter_id}.md"));
+                    let hash_path = render_hash_dir.join(&chapter_id);
+                    let render_hash = match json_out {
+                        Some(_) => None,
+                        None => chapter_render_hash(chapter, &ctx),
+                    };
+                    if !force_rerender
+                        && chapter_path.is_file()
+                        && let Some(hash) = &render_hash
+                        && std::fs::read_to_string(&hash_path)
+                            .is_ok_and(|previous| previous.trim() == hash)
+                    {
+                        skipped.fetch_add(1, Ordering::Relaxed);
+                        progress.inc();
+                        continue;
+                    }
+
+                    let rendered = render_chapter(chapter, &ctx)
                         .with_context(|| format!("render chapter: {}", chapter_id))?;
-                    std::fs::write(chapters_dir.join(format!("{}.md", chapter_id)), chapter_md)
-                        .with_context(|| format!("write chapter: {}", chapter_id))?;
+                    if json_out.is_some() {
+                        exported.lock().expect("export lock poisoned")[idx] = Some(rendered);
+                    } else {
+                        std::fs::write(&chapter_path, rendered.markdown)
+                            .with_context(|| format!("write chapter: {}", chapter_id))?;
+                        // A chapter whose images failed is never up to date.
+                        match render_hash.as_ref().filter(|_| !rendered.asset_failures) {
+                            Some(hash) => std::fs::create_dir_all(render_hash_dir)
+                                .and_then(|()| std::fs::write(&hash_path, format!("{hash}\n"))),
+                            None => std::fs::remove_file(&hash_path).or_else(|err| {
+                                if err.kind() == std::io::ErrorKind::NotFound {
+                                    Ok(())
+                                } else {
+                                    Err(err)
+                                }
+                            }),
+                        }
+                        .with_context(|| format!("write render hash: {}", hash_path.display()))?;
+                    }
+                    progress.inc();
                 }
 
                 Ok(())
@@ -162,6 +371,88 @@ pub fn render(args: BookRenderArgs) -> anyhow::Result<()> {
         Ok(())
     })?;
 
+    let asset_renames = if args.dedupe_assets_by_content {
+        dedupe_assets_by_content(&assets_dir)?
+    } else {
+        Vec::new()
+    };
+
+    let Some(json_path) = json_out else {
+        let mut chapter_paths = chapters_in_order
+            .iter()
+            .map(|chapter| chapters_dir.join(format!("{}.md", chapter.id)))
+            .collect::<Vec<_>>();
+        if intro_title.is_some() {
+            chapter_paths.push(chapters_dir.join(format!("{INTRO_CHAPTER_ID}.md")));
+        }
+        for chapter_path in &chapter_paths {
+            let chapter_md = std::fs::read_to_string(chapter_path)
+                .with_context(|| format!("read chapter: {}", chapter_path.display()))?;
+            let deduped = rename_asset_references(&chapter_md, &asset_renames, "../assets/");
+            if deduped != chapter_md {
+                std::fs::write(chapter_path, deduped)
+                    .with_context(|| format!("write chapter: {}", chapter_path.display()))?;
+            }
+        }
+        remove_duplicate_assets(&assets_dir, &asset_renames)?;
+        tracing::info!(
+            chapters = progress.done(),
+            unchanged = skipped.load(Ordering::Relaxed),
+            "book render: done"
+        );
+        return Ok(());
+    };
+
+    // Chapter Markdown points at `../assets/` (from `src/chapters/`); in the export the
+    // assets sit next to `book.json`.
+    let local_assets = |markdown: &str| {
+        rename_asset_references(markdown, &asset_renames, "../assets/")
+            .replace("../assets/", "assets/")
+    };
+    let mut exported = exported
+        .lock()
+        .expect("export lock poisoned")
+        .drain(..)
+        .collect::<Vec<_>>()
+        .into_iter();
+    let mut book = BookExport {
+        book_title: toc.book_title.clone(),
+        parts: Vec::new(),
+    };
+    for part in &toc.parts {
+        let mut chapters = Vec::new();
+        for chapter in &part.chapters {
+            let rendered = exported
+                .next()
+                .flatten()
+                .ok_or_else(|| anyhow::anyhow!("chapter not rendered: {}", chapter.id))?;
+            chapters.push(BookExportChapter {
+                id: chapter.id.clone(),
+                title: chapter.title.clone(),
+                intent: chapter.intent.clone(),
+                reader_gains: chapter.reader_gains.clone(),
+                sections: rendered
+                    .sections
+                    .into_iter()
+                    .map(|section| BookExportSection {
+                        markdown: local_assets(&section.markdown),
+                        ..section
+                    })
+                    .collect(),
+                markdown: local_assets(&rendered.markdown),
+            });
+        }
+        book.parts.push(BookExportPart {
+            title: part.title.clone(),
+            chapters,
+        });
+    }
+    let json = serde_json::to_string_pretty(&book).context("serialize book.json")?;
+    std::fs::write(json_path, format!("{json}\n"))
+        .with_context(|| format!("write book json: {}", json_path.display()))?;
+    remove_duplicate_assets(&assets_dir, &asset_renames)?;
+    tracing::info!(chapters = progress.done(), "book render: done");
+
     Ok(())
 }
 
@@ -191,8 +482,35 @@ pub fn bundle(args: BookBundleArgs) -> anyhow::Result<()> {
             .with_context(|| format!("create bundle parent dir: {}", parent.display()))?;
     }
 
+    let chapter_ids = chapter_rel_paths
+        .iter()
+        .filter_map(|rel| Path::new(rel).file_stem().and_then(|s| s.to_str()))
+        .map(ToOwned::to_owned)
+        .collect::<HashSet<_>>();
+
+    let chapter_separator = args
+        .chapter_separator
+        .as_deref()
+        .map(unescape_chapter_separator)
+        .filter(|sep| !sep.is_empty());
+
+    let metadata = read_book_metadata(&book_dir)?;
+    let front_matter = if args.front_matter {
+        let author = args
+            .author
+            .clone()
+            .or_else(|| (!metadata.authors.is_empty()).then(|| metadata.authors.join(", ")));
+        let date = args
+            .date
+            .clone()
+            .unwrap_or_else(|| chrono::Utc::now().format("%Y-%m-%d").to_string());
+        render_bundle_front_matter(metadata.title.as_deref(), author.as_deref(), &date)?
+    } else {
+        String::new()
+    };
+
     let mut bundled = String::new();
-    if let Some(title) = read_book_title(&book_dir)? {
+    if let Some(title) = &metadata.title {
         bundled.push_str(&format!("# {title}\n\n"));
     }
 
@@ -208,11 +526,31 @@ pub fn bundle(args: BookBundleArgs) -> anyhow::Result<()> {
             bundled.push('\n');
         }
 
-        bundled.push_str(chapter_md.trim_end());
-        bundled.push('\n');
+        let Some(sep) = &chapter_separator else {
+            bundled.push_str(chapter_md.trim_end());
+            bundled.push('\n');
+            continue;
+        };
+        if idx != 0 {
+            bundled.push_str(sep);
+            bundled.push_str("\n\n");
+        }
+        // Close fences and rewrite per chapter so the separator is never parsed as chapter
+        // content.
+        let mut chapter_md = chapter_md.trim_end().to_owned();
+        chapter_md.push('\n');
+        if let Some(marker) = unclosed_fence_marker(&chapter_md) {
+            chapter_md.push_str(&marker);
+            chapter_md.push('\n');
+        }
+        bundled.push_str(&rewrite_bundled_internal_links(&chapter_md, &chapter_ids));
+    }
+    if chapter_separator.is_none() {
+        bundled = rewrite_bundled_internal_links(&bundled, &chapter_ids);
     }
 
-    let bundled = rewrite_bundled_internal_links(&bundled);
+    // Front matter is prepended after link rewriting so it is never treated as body content.
+    let bundled = format!("{front_matter}{bundled}");
     copy_assets_for_bundle(&src_dir.join("assets"), &out_path, args.force)
         .context("copy assets for bundle")?;
 
@@ -244,11 +582,390 @@ pub fn epub(args: BookEpubArgs) -> anyhow::Result<()> {
         &crate::epub::CreateEpubOptions {
             force: args.force,
             lang: args.lang,
+            cover_image: args.cover.map(PathBuf::from),
+            nav_depth: args.nav_depth,
+            chapter_page_breaks: args.chapter_page_breaks,
         },
     )
     .context("create epub from mdBook")
 }
 
+/// Words per minute for space-separated text in the reading-time estimate.
+const READING_WORDS_PER_MINUTE: f64 = 200.0;
+/// Characters per minute for CJK text (no word separators) in the reading-time estimate.
+const READING_CJK_CHARS_PER_MINUTE: f64 = 500.0;
+
+#[derive(Debug, Default, Clone, serde::Serialize)]
+struct ChapterStats {
+    path: String,
+    title: String,
+    words: usize,
+    chars: usize,
+    headings: usize,
+    images: usize,
+    reading_minutes: f64,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct BookStats {
+    chapters: Vec<ChapterStats>,
+    total: ChapterStats,
+}
+
+pub fn stats(args: BookStatsArgs) -> anyhow::Result<()> {
+    let src_dir = PathBuf::from(&args.book).join("src");
+    let summary_path = src_dir.join("SUMMARY.md");
+    let summary_md = std::fs::read_to_string(&summary_path)
+        .with_context(|| format!("read SUMMARY.md: {}", summary_path.display()))?;
+
+    let mut chapters = Vec::new();
+    for rel in parse_summary_chapter_paths(&summary_md) {
+        let path = src_dir.join(&rel);
+        let markdown = std::fs::read_to_string(&path)
+            .with_context(|| format!("read chapter: {}", path.display()))?;
+        chapters.push(chapter_stats(&rel, &markdown));
+    }
+
+    let mut total = ChapterStats {
+        path: "total".to_owned(),
+        ..ChapterStats::default()
+    };
+    for chapter in &chapters {
+        total.words += chapter.words;
+        total.chars += chapter.chars;
+        total.headings += chapter.headings;
+        total.images += chapter.images;
+        total.reading_minutes += chapter.reading_minutes;
+    }
+    total.reading_minutes = (total.reading_minutes * 10.0).round() / 10.0;
+    let stats = BookStats { chapters, total };
+
+    match args.format {
+        StatsFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&stats).context("serialize book stats")?
+        ),
+        StatsFormat::Text => print!("{}", render_stats_text(&stats)),
+    }
+    Ok(())
+}
+
+/// Read-only publishability check: builds the EPUB in memory (XHTML well-formedness), and
+/// checks that every relative link in SUMMARY.md and the chapters points at an existing
+/// chapter, anchor or file. Prints each problem and fails when there is any.
+pub fn validate(args: BookValidateArgs) -> anyhow::Result<()> {
+    let book_dir = PathBuf::from(&args.book);
+    let src_dir = book_dir.join("src");
+    let summary_path = src_dir.join("SUMMARY.md");
+    let summary_md = std::fs::read_to_string(&summary_path)
+        .with_context(|| format!("read SUMMARY.md: {}", summary_path.display()))?;
+
+    let mut problems = Vec::new();
+    let mut anchors_by_chapter: HashMap<PathBuf, HashSet<String>> = HashMap::new();
+    let mut links = Vec::new();
+    for rel in parse_summary_chapter_paths(&summary_md) {
+        let path = src_dir.join(&rel);
+        let markdown = match std::fs::read_to_string(&path) {
+            Ok(markdown) => markdown,
+            Err(err) => {
+                problems.push(format!("SUMMARY.md: chapter {rel}: {err}"));
+                continue;
+            }
+        };
+        let (anchors, destinations) = chapter_anchors_and_destinations(&markdown);
+        anchors_by_chapter.insert(PathBuf::from(&rel), anchors);
+        links.extend(destinations.into_iter().map(|dest| (rel.clone(), dest)));
+    }
+    let chapter_count = anchors_by_chapter.len();
+    for line in summary_md.lines() {
+        if let Some(target) = parse_markdown_link_target(line) {
+            links.push(("SUMMARY.md".to_owned(), target.trim().to_owned()));
+        }
+    }
+
+    let mut checked_links = 0usize;
+    for (from, dest) in &links {
+        if dest.is_empty() || Url::parse(dest).is_ok() || dest.starts_with("//") {
+            continue;
+        }
+        checked_links += 1;
+        let (path, anchor) = match dest.split_once('#') {
+            Some((path, anchor)) => (path, Some(anchor)),
+            None => (dest.as_str(), None),
+        };
+        let target = if path.is_empty() {
+            Some(PathBuf::from(from))
+        } else {
+            let from_dir = Path::new(from).parent().unwrap_or(Path::new(""));
+            normalize_book_rel_path(&from_dir.join(path))
+        };
+        let Some(target) = target else {
+            problems.push(format!(
+                "{from}: link {dest}: leaves the book's src/ directory"
+            ));
+            continue;
+        };
+        if let Some(anchors) = anchors_by_chapter.get(&target) {
+            if let Some(anchor) = anchor.filter(|anchor| !anchor.is_empty())
+                && !anchors.contains(anchor)
+            {
+                problems.push(format!(
+                    "{from}: link {dest}: no anchor #{anchor} in {}",
+                    target.display()
+                ));
+            }
+        } else if !src_dir.join(&target).is_file() {
+            problems.push(format!(
+                "{from}: link {dest}: {} does not exist",
+                target.display()
+            ));
+        }
+    }
+
+    match crate::epub::validate_mdbook(&book_dir, &args.lang) {
+        Ok(epub_problems) => problems.extend(
+            epub_problems
+                .into_iter()
+                .map(|problem| format!("epub {problem}")),
+        ),
+        Err(err) => problems.push(format!("epub: {err:#}")),
+    }
+
+    for problem in &problems {
+        println!("FAIL {problem}");
+    }
+    println!(
+        "{}: {chapter_count} chapters, {checked_links} relative links, {} problems",
+        if problems.is_empty() { "ok" } else { "failed" },
+        problems.len()
+    );
+    if !problems.is_empty() {
+        anyhow::bail!("book is not publishable ({} problems)", problems.len());
+    }
+    Ok(())
+}
+
+/// Ids a link can target in a chapter (`{#id}` heading attributes, mdBook's generated
+/// heading ids, HTML `id` attributes) and every link, image and HTML `src` destination.
+fn chapter_anchors_and_destinations(markdown: &str) -> (HashSet<String>, Vec<String>) {
+    use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_FOOTNOTES);
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_HEADING_ATTRIBUTES);
+
+    let mut anchors = HashSet::new();
+    let mut destinations = Vec::new();
+    let mut heading_text: Option<String> = None;
+    for event in Parser::new_ext(markdown, options) {
+        match event {
+            Event::Start(Tag::Heading { id: Some(id), .. }) => {
+                anchors.insert(id.to_string());
+            }
+            Event::Start(Tag::Heading { id: None, .. }) => heading_text = Some(String::new()),
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some(text) = heading_text.take() {
+                    let slug = mdbook_heading_id(&text);
+                    let mut id = slug.clone();
+                    let mut n = 1;
+                    while anchors.contains(&id) {
+                        id = format!("{slug}-{n}");
+                        n += 1;
+                    }
+                    anchors.insert(id);
+                }
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(heading) = heading_text.as_mut() {
+                    heading.push_str(&text);
+                }
+            }
+            Event::Start(Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. }) => {
+                destinations.push(dest_url.to_string());
+            }
+            Event::Html(html) | Event::InlineHtml(html) => {
+                anchors.extend(html_attribute_values(&html, "id"));
+                destinations.extend(html_attribute_values(&html, "src"));
+            }
+            _ => {}
+        }
+    }
+    (anchors, destinations)
+}
+
+/// The id mdBook generates for a heading without `{#id}`.
+fn mdbook_heading_id(text: &str) -> String {
+    text.trim()
+        .chars()
+        .filter_map(|ch| {
+            if ch.is_alphanumeric() || ch == '_' || ch == '-' {
+                Some(ch.to_ascii_lowercase())
+            } else if ch.is_whitespace() {
+                Some('-')
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn html_attribute_values(html: &str, attr: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    for quote in ['"', '\''] {
+        let needle = format!("{attr}={quote}");
+        let mut rest = html;
+        while let Some(start) = rest.find(&needle) {
+            let preceded_by_space = rest[..start]
+                .chars()
+                .next_back()
+                .is_some_and(char::is_whitespace);
+            rest = &rest[start + needle.len()..];
+            let Some(end) = rest.find(quote) else {
+                break;
+            };
+            if preceded_by_space {
+                values.push(rest[..end].to_owned());
+            }
+            rest = &rest[end..];
+        }
+    }
+    values
+}
+
+/// Lexically resolves `.` and `..`; `None` when the path climbs above its root.
+fn normalize_book_rel_path(path: &Path) -> Option<PathBuf> {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                if !out.pop() {
+                    return None;
+                }
+            }
+            std::path::Component::Normal(part) => out.push(part),
+            std::path::Component::RootDir | std::path::Component::Prefix(_) => return None,
+        }
+    }
+    Some(out)
+}
+
+/// Counts prose in a chapter, ignoring fenced code, HTML tags and link destinations.
+fn chapter_stats(path: &str, markdown: &str) -> ChapterStats {
+    let mut stats = ChapterStats {
+        path: path.to_owned(),
+        ..ChapterStats::default()
+    };
+    let mut in_fence = false;
+    let mut fence_marker = String::new();
+    let mut cjk_chars = 0usize;
+
+    for line in markdown.lines() {
+        if in_fence {
+            if fence_end_marker(line, &fence_marker) {
+                in_fence = false;
+            }
+            continue;
+        }
+        if let Some(marker) = fence_start_marker(line) {
+            in_fence = true;
+            fence_marker = marker.to_owned();
+            continue;
+        }
+
+        stats.images += line.matches("![").count() + line.matches("<img").count();
+        let mut text = line.trim_start();
+        let hashes = text.chars().take_while(|c| *c == '#').count();
+        if (1..=6).contains(&hashes) && text[hashes..].starts_with(' ') {
+            stats.headings += 1;
+            text = text[hashes..].trim();
+            if hashes == 1 && stats.title.is_empty() {
+                stats.title = text.to_owned();
+            }
+        }
+
+        let prose = strip_link_destinations(&strip_html_tags(text));
+        for word in prose.split_whitespace() {
+            let word_cjk = word.chars().filter(|c| is_cjk(*c)).count();
+            cjk_chars += word_cjk;
+            if word_cjk < word.chars().count() {
+                stats.words += 1;
+            }
+            stats.chars += word.chars().count();
+        }
+    }
+
+    let minutes = stats.words as f64 / READING_WORDS_PER_MINUTE
+        + cjk_chars as f64 / READING_CJK_CHARS_PER_MINUTE;
+    stats.reading_minutes = (minutes * 10.0).round() / 10.0;
+    stats
+}
+
+fn strip_html_tags(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut in_tag = false;
+    for ch in line.chars() {
+        match ch {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => out.push(ch),
+            _ => {}
+        }
+    }
+    out
+}
+
+fn strip_link_destinations(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find("](") {
+        out.push_str(&rest[..=start]);
+        match rest[start + 2..].find(')') {
+            Some(end) => rest = &rest[start + 2 + end + 1..],
+            None => {
+                rest = &rest[start + 1..];
+                break;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn is_cjk(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{3040}'..='\u{30ff}' // Hiragana, Katakana
+            | '\u{3400}'..='\u{4dbf}' // CJK Extension A
+            | '\u{4e00}'..='\u{9fff}' // CJK Unified Ideographs
+            | '\u{ac00}'..='\u{d7af}' // Hangul
+            | '\u{f900}'..='\u{faff}' // CJK Compatibility Ideographs
+    )
+}
+
+fn render_stats_text(stats: &BookStats) -> String {
+    let rows = stats.chapters.iter().chain(std::iter::once(&stats.total));
+    let width = rows
+        .clone()
+        .map(|row| row.path.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("chapter".len());
+
+    let mut out = format!(
+        "{:<width$}  {:>8}  {:>8}  {:>8}  {:>6}  {:>7}\n",
+        "chapter", "words", "chars", "headings", "images", "minutes"
+    );
+    for row in rows {
+        out.push_str(&format!(
+            "{:<width$}  {:>8}  {:>8}  {:>8}  {:>6}  {:>7.1}\n",
+            row.path, row.words, row.chars, row.headings, row.images, row.reading_minutes
+        ));
+    }
+    out
+}
+
 fn copy_assets_for_bundle(
     src_assets_dir: &Path,
     out_path: &Path,
@@ -282,17 +999,51 @@ fn copy_assets_for_bundle(
 }
 
 fn copy_dir_recursive_skip_existing(src: &Path, dest: &Path) -> anyhow::Result<()> {
+    copy_dir_within(src, src, dest, &mut HashSet::new())
+}
+
+/// `ancestors` holds the canonical directories being copied above `src`; a symlinked
+/// directory among them is skipped, so links into each other's directories cannot loop.
+fn copy_dir_within(
+    base: &Path,
+    src: &Path,
+    dest: &Path,
+    ancestors: &mut HashSet<PathBuf>,
+) -> anyhow::Result<()> {
+    let canonical = src
+        .canonicalize()
+        .with_context(|| format!("resolve dir: {}", src.display()))?;
+    ancestors.insert(canonical.clone());
     for entry in std::fs::read_dir(src).with_context(|| format!("read dir: {}", src.display()))? {
         let entry = entry.context("read dir entry")?;
-        let src_path = entry.path();
-        let file_type = entry.file_type().context("read file type")?;
+        let mut src_path = entry.path();
+        let mut file_type = entry.file_type().context("read file type")?;
         let name = entry.file_name();
         let dest_path = dest.join(name);
 
+        if file_type.is_symlink() {
+            let Some(target) = symlink_target_within(base, &src_path) else {
+                tracing::warn!(path = %src_path.display(), base = %base.display(), "skipping symlink that leaves the copied directory");
+                continue;
+            };
+            file_type = std::fs::metadata(&target)
+                .with_context(|| format!("stat symlink target: {}", target.display()))?
+                .file_type();
+            src_path = target;
+        }
+
+        if file_type.is_dir()
+            && src_path
+                .canonicalize()
+                .is_ok_and(|dir| ancestors.contains(&dir))
+        {
+            tracing::warn!(path = %src_path.display(), "skipping symlink that loops back to a parent directory");
+            continue;
+        }
         if file_type.is_dir() {
             std::fs::create_dir_all(&dest_path)
                 .with_context(|| format!("create dir: {}", dest_path.display()))?;
-            copy_dir_recursive_skip_existing(&src_path, &dest_path)?;
+            copy_dir_within(base, &src_path, &dest_path, ancestors)?;
             continue;
         }
 
@@ -312,10 +1063,78 @@ fn copy_dir_recursive_skip_existing(src: &Path, dest: &Path) -> anyhow::Result<(
         })?;
     }
 
+    ancestors.remove(&canonical);
     Ok(())
 }
 
-fn rewrite_bundled_internal_links(markdown: &str) -> String {
+/// Resolved target of the symlink `link` found while walking `base`, if following it stays
+/// inside `base`. `None` for links that escape `base`, dangle, or point at a directory that
+/// contains the link (which would loop). Links between sibling directories can still loop,
+/// so walkers also track the directories they are inside.
+pub fn symlink_target_within(base: &Path, link: &Path) -> Option<PathBuf> {
+    let base = base.canonicalize().ok()?;
+    let target = link.canonicalize().ok()?;
+    if !target.starts_with(&base) {
+        return None;
+    }
+    if target.is_dir()
+        && link
+            .parent()
+            .and_then(|parent| parent.canonicalize().ok())
+            .is_some_and(|parent| parent.starts_with(&target))
+    {
+        return None;
+    }
+    Some(target)
+}
+
+/// Expands `\n`, `\t` and `\\` escapes and trims surrounding newlines (blank lines are added
+/// around the separator so e.g. `---` is never parsed as a setext heading underline).
+fn unescape_chapter_separator(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('\\') => out.push('\\'),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out.trim_matches(['\n', '\r']).to_owned()
+}
+
+/// Returns the fence marker if `markdown` ends inside an open code fence.
+fn unclosed_fence_marker(markdown: &str) -> Option<String> {
+    let mut open: Option<String> = None;
+    for line in markdown.lines() {
+        match &open {
+            None => {
+                if let Some(marker) = fence_start_marker(line) {
+                    open = Some(marker.to_owned());
+                }
+            }
+            Some(marker) => {
+                if fence_end_marker(line, marker) {
+                    open = None;
+                }
+            }
+        }
+    }
+    open
+}
+
+/// `chapter_ids` are the chapter file stems listed in SUMMARY.md; only `<id>.md#p_...` links to
+/// those chapters are collapsed to in-document `#p_...` anchors.
+fn rewrite_bundled_internal_links(markdown: &str, chapter_ids: &HashSet<String>) -> String {
     let mut out = String::with_capacity(markdown.len());
     let mut in_fence = false;
     let mut fence_marker = String::new();
@@ -329,7 +1148,7 @@ fn rewrite_bundled_internal_links(markdown: &str) -> String {
                 out.push_str(line);
                 continue;
             }
-            out.push_str(&rewrite_inline_bundled_line(line));
+            out.push_str(&rewrite_inline_bundled_line(line, chapter_ids));
             continue;
         }
 
@@ -342,7 +1161,7 @@ fn rewrite_bundled_internal_links(markdown: &str) -> String {
     out
 }
 
-fn rewrite_inline_bundled_line(input: &str) -> String {
+fn rewrite_inline_bundled_line(input: &str, chapter_ids: &HashSet<String>) -> String {
     let mut out = String::with_capacity(input.len());
     let mut i = 0usize;
     while i < input.len() {
@@ -357,7 +1176,8 @@ fn rewrite_inline_bundled_line(input: &str) -> String {
         }
 
         if rest.starts_with("![")
-            && let Some((consumed, rewritten)) = try_rewrite_bundled_link_like(rest, true)
+            && let Some((consumed, rewritten)) =
+                try_rewrite_bundled_link_like(rest, true, chapter_ids)
         {
             out.push_str(&rewritten);
             i += consumed;
@@ -365,7 +1185,8 @@ fn rewrite_inline_bundled_line(input: &str) -> String {
         }
 
         if rest.starts_with('[')
-            && let Some((consumed, rewritten)) = try_rewrite_bundled_link_like(rest, false)
+            && let Some((consumed, rewritten)) =
+                try_rewrite_bundled_link_like(rest, false, chapter_ids)
         {
             out.push_str(&rewritten);
             i += consumed;
@@ -379,7 +1200,11 @@ fn rewrite_inline_bundled_line(input: &str) -> String {
     out
 }
 
-fn try_rewrite_bundled_link_like(input: &str, is_image: bool) -> Option<(usize, String)> {
+fn try_rewrite_bundled_link_like(
+    input: &str,
+    is_image: bool,
+    chapter_ids: &HashSet<String>,
+) -> Option<(usize, String)> {
     let mut i = if is_image { 2 } else { 1 };
     let mut bracket_depth = 1u32;
 
@@ -458,7 +1283,7 @@ fn try_rewrite_bundled_link_like(input: &str, is_image: bool) -> Option<(usize,
     let paren_close = j;
 
     let dest = &input[paren_open + 1..paren_close];
-    let rewritten_dest = rewrite_bundled_link_destination(dest);
+    let rewritten_dest = rewrite_bundled_link_destination(dest, chapter_ids);
 
     let mut rewritten = String::with_capacity(paren_close + 1);
     rewritten.push_str(&input[..paren_open + 1]);
@@ -468,7 +1293,7 @@ fn try_rewrite_bundled_link_like(input: &str, is_image: bool) -> Option<(usize,
     Some((paren_close + 1, rewritten))
 }
 
-fn rewrite_bundled_link_destination(dest: &str) -> String {
+fn rewrite_bundled_link_destination(dest: &str, chapter_ids: &HashSet<String>) -> String {
     let mut i = 0usize;
     while i < dest.len() {
         let ch = dest[i..].chars().next().unwrap();
@@ -508,10 +1333,11 @@ fn rewrite_bundled_link_destination(dest: &str) -> String {
     let rewritten_inner = if let Some(stripped) = core_inner.strip_prefix("../assets/") {
         format!("assets/{stripped}")
     } else if let Some((chapter_ref, fragment)) = core_inner.split_once(".md#")
-        && chapter_ref.len() == 4
-        && chapter_ref.starts_with("ch")
-        && chapter_ref[2..].chars().all(|c| c.is_ascii_digit())
-        && fragment.starts_with("p_")
+        && chapter_ids.contains(chapter_ref)
+        && (fragment.starts_with("p_")
+            || fragment
+                .strip_prefix(chapter_ref)
+                .is_some_and(|rest| rest.starts_with("-s")))
     {
         format!("#{fragment}")
     } else {
@@ -535,9 +1361,15 @@ fn rewrite_bundled_link_destination(dest: &str) -> String {
     out
 }
 
-fn render_summary_md(toc: &Toc) -> String {
+const INTRO_CHAPTER_ID: &str = "ch00";
+
+fn render_summary_md(toc: &Toc, toc_depth: u8, intro_title: Option<&str>) -> String {
     let mut md = String::new();
     md.push_str("# Summary\n\n");
+    if let Some(title) = intro_title {
+        // mdBook prefix chapter (before the first part).
+        md.push_str(&format!("[{title}](chapters/{INTRO_CHAPTER_ID}.md)\n\n"));
+    }
     for part in &toc.parts {
         md.push_str(&format!("- {}\n", part.title));
         for chapter in &part.chapters {
@@ -545,48 +1377,374 @@ fn render_summary_md(toc: &Toc) -> String {
                 "  - [{}](chapters/{}.md)\n",
                 chapter.title, chapter.id
             ));
+            if toc_depth < 2 {
+                continue;
+            }
+            for (idx, section) in chapter.sections.iter().enumerate() {
+                if section.title.trim().is_empty() {
+                    continue;
+                }
+                md.push_str(&format!(
+                    "    - [{}](chapters/{}.md#{})\n",
+                    section.title.trim(),
+                    chapter.id,
+                    section_anchor_id(&chapter.id, idx)
+                ));
+            }
         }
     }
     md
 }
 
+/// Introduction chapter summarizing the book from the planner's chapter intents/reader gains.
+fn render_intro_md(toc: &Toc, title: &str) -> String {
+    let mut md = String::new();
+    md.push_str(&format!("# {title}\n\n"));
+    for part in &toc.parts {
+        md.push_str(&format!("## {}\n\n", part.title));
+        for chapter in &part.chapters {
+            md.push_str(&format!("### [{}]({}.md)\n\n", chapter.title, chapter.id));
+            if !chapter.intent.trim().is_empty() {
+                md.push_str(&format!("{}\n\n", chapter.intent.trim()));
+            }
+            let gains = chapter_gains(chapter);
+            if !gains.is_empty() {
+                for gain in gains {
+                    md.push_str(&format!("- {gain}\n"));
+                }
+                md.push('\n');
+            }
+        }
+    }
+    md
+}
+
+/// Non-empty `reader_gains` of `chapter`, trimmed.
+fn chapter_gains(chapter: &crate::formats::TocChapter) -> Vec<&str> {
+    chapter
+        .reader_gains
+        .iter()
+        .map(|gain| gain.trim())
+        .filter(|gain| !gain.is_empty())
+        .collect()
+}
+
+/// Stable anchor id for the `idx`-th (0-based) section of a chapter, e.g. `ch01-s01`.
+fn section_anchor_id(chapter_id: &str, idx: usize) -> String {
+    format!("{chapter_id}-s{:02}", idx + 1)
+}
+
 struct ChapterRenderContext<'a> {
     engine: LlmEngine,
     language: &'a str,
     tone: &'a str,
+    toc_depth: u8,
+    /// Chapters are single sections (`--sections-as-chapters`): the H1 replaces the `##` heading.
+    sections_as_chapters: bool,
+    anchor_style: AnchorStyle,
+    external_link_policy: ExternalLinkPolicy,
+    normalize_whitespace: bool,
+    /// `Some(max chars)` when `--include-excerpts` is set.
+    excerpt_chars: Option<usize>,
+    rewrite_options: &'a rewrite::RewriteOptions,
+    /// Banner for chapters of a `--sample` render.
+    sample_notice: Option<&'a str>,
+    /// Heading of the closing `reader_gains` list when `--chapter-summaries` is set.
+    chapter_summary_title: Option<&'a str>,
     manifest: &'a HashMap<String, ManifestRecord>,
     url_to_location: &'a HashMap<String, PageLocation>,
     dir_index_ids: &'a HashSet<String>,
     assets: &'a AssetDownloader,
 }
 
-fn render_chapter_md(
+/// Directory under the book root holding one `<chapter id>` render hash file per chapter.
+const RENDER_HASH_DIR: &str = ".render-hash";
+
+/// Digest of everything `render_chapter` reads for `chapter`: its TOC entry, each source's
+/// manifest record and extracted Markdown, the render and asset options and the link map.
+/// `None` when a source cannot be read, so the chapter is rendered (and the error reported) as
+/// usual.
+fn chapter_render_hash(
     chapter: &crate::formats::TocChapter,
     ctx: &ChapterRenderContext<'_>,
+) -> Option<String> {
+    let mut hasher = Sha256::new();
+    let mut feed = |bytes: &[u8]| {
+        hasher.update((bytes.len() as u64).to_le_bytes());
+        hasher.update(bytes);
+    };
+    feed(b"sitebookify-render-hash-v1");
+    feed(&serde_json::to_vec(chapter).ok()?);
+    for source_id in chapter.sections.iter().flat_map(|section| &section.sources) {
+        let record = ctx.manifest.get(source_id)?;
+        feed(&serde_json::to_vec(record).ok()?);
+        feed(&std::fs::read(&record.extracted_md).ok()?);
+    }
+    let options = format!(
+        "{:?}",
+        (
+            ctx.engine,
+            ctx.language,
+            ctx.tone,
+            ctx.toc_depth,
+            ctx.sections_as_chapters,
+            ctx.anchor_style,
+            ctx.external_link_policy,
+            ctx.normalize_whitespace,
+            ctx.excerpt_chars,
+            ctx.sample_notice,
+            ctx.chapter_summary_title,
+            ctx.rewrite_options,
+        )
+    );
+    feed(options.as_bytes());
+    feed(ctx.assets.fingerprint.as_bytes());
+    // The OpenAI model is picked from the environment (see `OpenAiConfig`).
+    for name in [
+        "SITEBOOKIFY_OPENAI_MODEL",
+        "OPENAI_MODEL",
+        "SITEBOOKIFY_OPENAI_REASONING_EFFORT",
+    ] {
+        feed(std::env::var(name).unwrap_or_default().as_bytes());
+    }
+    // Links into other chapters resolve through these, so moving a page elsewhere counts too.
+    let mut locations = ctx
+        .url_to_location
+        .iter()
+        .map(|(url, location)| {
+            format!(
+                "{url} {} {}",
+                location.chapter_id,
+                location.anchor.as_deref().unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>();
+    locations.sort();
+    let mut dir_index_ids = ctx.dir_index_ids.iter().collect::<Vec<_>>();
+    dir_index_ids.sort();
+    feed(format!("{locations:?} {dir_index_ids:?}").as_bytes());
+    Some(hex::encode(hasher.finalize()))
+}
+
+/// `--sections-as-chapters`: replaces each chapter by one single-section chapter per titled
+/// section, with id `<chapter id>-sNN` (the section's old anchor id). The chapter intent and
+/// reader gains move to its first section. `url_to_location`, SUMMARY.md and anchors are then
+/// derived from the split TOC as for any other.
+fn split_sections_into_chapters(toc: &mut Toc) -> anyhow::Result<()> {
+    let mut seen_ids = HashSet::new();
+    for part in &mut toc.parts {
+        let mut chapters = Vec::new();
+        for chapter in std::mem::take(&mut part.chapters) {
+            if chapter.sections.is_empty() {
+                chapters.push(chapter);
+                continue;
+            }
+            let mut first = true;
+            for (idx, section) in chapter.sections.iter().enumerate() {
+                if section.title.trim().is_empty() {
+                    continue;
+                }
+                chapters.push(crate::formats::TocChapter {
+                    id: section_anchor_id(&chapter.id, idx),
+                    title: section.title.trim().to_owned(),
+                    intent: if first {
+                        chapter.intent.clone()
+                    } else {
+                        String::new()
+                    },
+                    reader_gains: if first {
+                        chapter.reader_gains.clone()
+                    } else {
+                        Vec::new()
+                    },
+                    sections: vec![section.clone()],
+                });
+                first = false;
+            }
+        }
+        for chapter in &chapters {
+            if !seen_ids.insert(chapter.id.clone()) {
+                anyhow::bail!(
+                    "--sections-as-chapters: chapter id {} is used twice; rename the chapter",
+                    chapter.id
+                );
+            }
+        }
+        part.chapters = chapters;
+    }
+    Ok(())
+}
+
+/// Rewrites every chapter title of `toc` through `--chapter-title-format`, so SUMMARY.md, the
+/// chapter H1 (and with it the EPUB nav) and the intro all show the same decorated title.
+fn decorate_chapter_titles(toc: &mut Toc, format: &str) -> anyhow::Result<()> {
+    let chapters = toc
+        .parts
+        .iter_mut()
+        .flat_map(|part| part.chapters.iter_mut());
+    for (idx, chapter) in chapters.enumerate() {
+        chapter.title = format_chapter_title(format, idx + 1, &chapter.id, &chapter.title)?;
+    }
+    Ok(())
+}
+
+/// Expands `{index}`, `{index:0N}`, `{id}` and `{title}`; `{{` and `}}` are literal braces.
+fn format_chapter_title(
+    format: &str,
+    index: usize,
+    id: &str,
+    title: &str,
 ) -> anyhow::Result<String> {
+    let mut out = String::new();
+    let mut rest = format;
+    while let Some(pos) = rest.find(['{', '}']) {
+        out.push_str(&rest[..pos]);
+        let tail = &rest[pos..];
+        if let Some(after) = tail.strip_prefix("{{") {
+            out.push('{');
+            rest = after;
+            continue;
+        }
+        if let Some(after) = tail.strip_prefix("}}") {
+            out.push('}');
+            rest = after;
+            continue;
+        }
+        if tail.starts_with('}') {
+            anyhow::bail!("unmatched `}}` in chapter title format: {format}");
+        }
+        let Some(end) = tail.find('}') else {
+            anyhow::bail!("unclosed `{{` in chapter title format: {format}");
+        };
+        let placeholder = &tail[1..end];
+        match placeholder.split_once(':') {
+            None if placeholder == "index" => out.push_str(&index.to_string()),
+            None if placeholder == "id" => out.push_str(id),
+            None if placeholder == "title" => out.push_str(title),
+            Some(("index", width))
+                if width.len() > 1
+                    && width.starts_with('0')
+                    && width.bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                let width = width.parse::<usize>().context("parse index width")?;
+                out.push_str(&format!("{index:0width$}"));
+            }
+            _ => anyhow::bail!(
+                "unknown placeholder `{{{placeholder}}}` in chapter title format (expected {{index}}, {{index:0N}}, {{id}} or {{title}})"
+            ),
+        }
+        rest = &tail[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Keeps `sample` chapters of `toc` (in TOC order), ranked by a hash of the seed and chapter
+/// id so the pick is stable across runs. Parts left empty are dropped. Returns
+/// `(kept, total)` chapter counts.
+fn sample_toc_chapters(toc: &mut Toc, sample: usize, seed: u64) -> (usize, usize) {
+    let mut ranked = toc
+        .parts
+        .iter()
+        .flat_map(|part| part.chapters.iter())
+        .map(|chapter| {
+            let digest = Sha256::digest(format!("{seed}:{}", chapter.id).as_bytes());
+            (digest, chapter.id.clone())
+        })
+        .collect::<Vec<_>>();
+    let total = ranked.len();
+    ranked.sort();
+    let keep = ranked
+        .into_iter()
+        .take(sample)
+        .map(|(_, id)| id)
+        .collect::<HashSet<_>>();
+
+    for part in &mut toc.parts {
+        part.chapters.retain(|chapter| keep.contains(&chapter.id));
+    }
+    toc.parts.retain(|part| !part.chapters.is_empty());
+    (keep.len(), total)
+}
+
+/// A rendered chapter, plus the body of each of its sections for `export book`.
+struct RenderedChapter {
+    markdown: String,
+    sections: Vec<BookExportSection>,
+    /// Some image could not be downloaded, so the chapter is rendered again next time.
+    asset_failures: bool,
+}
+
+fn render_chapter(
+    chapter: &crate::formats::TocChapter,
+    ctx: &ChapterRenderContext<'_>,
+) -> anyhow::Result<RenderedChapter> {
     let mut md = String::new();
-    md.push_str(&format!("# {}\n\n", chapter.title));
+    let mut sections = Vec::new();
+    if ctx.sections_as_chapters && ctx.anchor_style == AnchorStyle::Heading {
+        md.push_str(&format!(
+            "# {} {{#{}}}\n\n",
+            chapter.title,
+            section_anchor_id(&chapter.id, 0)
+        ));
+    } else {
+        md.push_str(&format!("# {}\n\n", chapter.title));
+    }
+    if let Some(notice) = ctx.sample_notice {
+        md.push_str(&format!("> {notice}\n\n"));
+    }
+    if !chapter.intent.trim().is_empty() {
+        md.push_str(&format!("*{}*\n\n", chapter.intent.trim()));
+    }
 
     let mut chapter_source_ids_in_order = Vec::new();
     let mut chapter_source_ids_seen = HashSet::new();
+    let mut excerpts = HashMap::new();
+    let mut external_links = ExternalLinks::new(ctx.external_link_policy);
+    let assets = ChapterAssets {
+        downloader: ctx.assets,
+        failed: Cell::new(false),
+    };
 
-    for section in &chapter.sections {
+    for (section_idx, section) in chapter.sections.iter().enumerate() {
         if section.title.trim().is_empty() {
             continue;
         }
 
-        md.push_str(&format!("## {}\n\n", section.title.trim()));
+        if ctx.sections_as_chapters {
+            // The chapter H1 already is the section heading.
+        } else if ctx.anchor_style == AnchorStyle::Heading {
+            // The heading id doubles as the target for every source page of this section.
+            md.push_str(&format!(
+                "## {} {{#{}}}\n\n",
+                section.title.trim(),
+                section_anchor_id(&chapter.id, section_idx)
+            ));
+        } else {
+            md.push_str(&format!("## {}\n\n", section.title.trim()));
+            if ctx.toc_depth >= 2 {
+                // Stable target for the section-level entries in SUMMARY.md / EPUB nav.
+                md.push_str(&format!(
+                    "<span id=\"{}\" style=\"display:none\" aria-hidden=\"true\"></span>\n",
+                    section_anchor_id(&chapter.id, section_idx)
+                ));
+            }
+        }
 
         // Insert stable anchors for each referenced source page id (for internal link rewriting).
         for source_id in &section.sources {
             if chapter_source_ids_seen.insert(source_id.clone()) {
                 chapter_source_ids_in_order.push(source_id.clone());
             }
-            md.push_str(&format!(
-                "<span id=\"{source_id}\" style=\"display:none\" aria-hidden=\"true\"></span>\n"
-            ));
+            if ctx.anchor_style == AnchorStyle::Html {
+                md.push_str(&format!(
+                    "<span id=\"{source_id}\" style=\"display:none\" aria-hidden=\"true\"></span>\n"
+                ));
+            }
+        }
+        if ctx.anchor_style == AnchorStyle::Html {
+            md.push('\n');
         }
-        md.push('\n');
 
         let mut source_material_noop = String::new();
         let mut rewrite_units = Vec::new();
@@ -604,13 +1762,19 @@ fn render_chapter_md(
             })?;
             let body = strip_front_matter(&extracted).context("strip front matter")?;
             let body = strip_leading_h1(body);
+            if let Some(max_chars) = ctx.excerpt_chars
+                && !excerpts.contains_key(source_id)
+            {
+                excerpts.insert(source_id.clone(), source_excerpt(body, max_chars));
+            }
+            let base_for_join = page_link_base(record, ctx.dir_index_ids.contains(&record.id))?;
             let body = rewrite_markdown_links_and_images(
                 body,
-                &record.url,
+                &base_for_join,
                 &chapter.id,
                 ctx.url_to_location,
-                ctx.dir_index_ids.contains(&record.id),
-                ctx.assets,
+                &assets,
+                &mut external_links,
             )
             .with_context(|| format!("rewrite links/images for {}", record.url))?;
 
@@ -626,17 +1790,41 @@ fn render_chapter_md(
                     source_material_noop.push_str(body.trim());
                     source_material_noop.push('\n');
                 }
+                LlmEngine::Openai
+                    if ctx.rewrite_options.granularity == RewriteGranularity::Page =>
+                {
+                    if !body.trim().is_empty() {
+                        for part in split_oversized_markdown(
+                            body.trim(),
+                            ctx.rewrite_options.max_section_chars,
+                        ) {
+                            rewrite_units.push(SectionRewriteUnit {
+                                source_id: record.id.clone(),
+                                source_title: record.title.clone(),
+                                source_url: record.url.clone(),
+                                heading: None,
+                                markdown: part,
+                            });
+                        }
+                    }
+                }
                 LlmEngine::Openai => {
                     for chunk in split_markdown_by_heading_levels(&body) {
                         if chunk.markdown.trim().is_empty() {
                             continue;
                         }
-                        rewrite_units.push(SectionRewriteUnit {
-                            source_id: record.id.clone(),
-                            source_title: record.title.clone(),
-                            heading: chunk.heading,
-                            markdown: chunk.markdown.trim().to_owned(),
-                        });
+                        for part in split_oversized_markdown(
+                            chunk.markdown.trim(),
+                            ctx.rewrite_options.max_section_chars,
+                        ) {
+                            rewrite_units.push(SectionRewriteUnit {
+                                source_id: record.id.clone(),
+                                source_title: record.title.clone(),
+                                source_url: record.url.clone(),
+                                heading: chunk.heading.clone(),
+                                markdown: part,
+                            });
+                        }
                     }
                 }
             }
@@ -649,6 +1837,8 @@ fn render_chapter_md(
                 section,
                 ctx.language,
                 ctx.tone,
+                ctx.rewrite_options,
+                section_idx,
                 &rewrite_units,
             )
             .with_context(|| {
@@ -660,6 +1850,40 @@ fn render_chapter_md(
             md.push_str(section_body.trim_end());
             md.push_str("\n\n");
         }
+        let source_urls = section
+            .sources
+            .iter()
+            .filter_map(|source_id| ctx.manifest.get(source_id))
+            .map(|record| record.url.clone())
+            .collect();
+        let section_md = if ctx.normalize_whitespace && !section_body.trim().is_empty() {
+            normalize_whitespace(section_body.trim())
+        } else {
+            section_body.trim().to_owned()
+        };
+        sections.push(BookExportSection {
+            title: section.title.trim().to_owned(),
+            source_urls,
+            markdown: section_md,
+        });
+    }
+
+    if let Some(title) = ctx.chapter_summary_title {
+        let gains = chapter_gains(chapter);
+        if !gains.is_empty() {
+            md.push_str(&format!("## {title}\n\n"));
+            for gain in gains {
+                md.push_str(&format!("- {gain}\n"));
+            }
+            md.push('\n');
+        }
+    }
+
+    for (idx, url) in external_links.footnotes.iter().enumerate() {
+        md.push_str(&format!("[^{}]: <{url}>\n", external_links.label(idx)));
+    }
+    if !external_links.footnotes.is_empty() {
+        md.push('\n');
     }
 
     md.push_str("## Sources\n");
@@ -671,13 +1895,122 @@ fn render_chapter_md(
         md.push_str(&format!("- {}\n", record.url));
     }
 
-    Ok(md)
+    if ctx.excerpt_chars.is_some() {
+        for source_id in &chapter_source_ids_in_order {
+            let (Some(record), Some(excerpt)) =
+                (ctx.manifest.get(source_id), excerpts.get(source_id))
+            else {
+                continue;
+            };
+            if excerpt.is_empty() {
+                continue;
+            }
+            // A single HTML block (no blank lines) so Markdown leaves the excerpt text alone.
+            md.push_str(&format!(
+                "\n<details>\n<summary>Excerpt: {}</summary>\n<blockquote>{}</blockquote>\n</details>\n",
+                crate::epub::xml_escape(&record.url),
+                crate::epub::xml_escape(excerpt)
+            ));
+        }
+    }
+
+    if ctx.normalize_whitespace {
+        md = normalize_whitespace(&md);
+    }
+    Ok(RenderedChapter {
+        markdown: md,
+        sections,
+        asset_failures: assets.failed.get(),
+    })
+}
+
+/// Plain-text opening of a source page: fenced code, HTML tags and link destinations are
+/// dropped, whitespace is collapsed, and the text is cut at `max_chars`.
+fn source_excerpt(body: &str, max_chars: usize) -> String {
+    let mut words = Vec::new();
+    let mut in_fence = false;
+    let mut fence_marker = String::new();
+    for line in body.lines() {
+        if in_fence {
+            if fence_end_marker(line, &fence_marker) {
+                in_fence = false;
+            }
+            continue;
+        }
+        if let Some(marker) = fence_start_marker(line) {
+            in_fence = true;
+            fence_marker = marker.to_owned();
+            continue;
+        }
+        let text = line.trim_start().trim_start_matches(['#', '>']);
+        let text = strip_link_destinations(&strip_html_tags(text))
+            .replace("![", "")
+            .replace(['[', ']', '*', '`'], "");
+        words.extend(text.split_whitespace().map(str::to_owned));
+    }
+
+    let text = words.join(" ");
+    if text.chars().count() <= max_chars {
+        return text;
+    }
+    let mut excerpt = text.chars().take(max_chars).collect::<String>();
+    // Cut at a word boundary when the limit falls mid-word.
+    if !text[excerpt.len()..].starts_with(' ')
+        && let Some(space) = excerpt.rfind(' ')
+    {
+        excerpt.truncate(space);
+    }
+    excerpt.truncate(excerpt.trim_end().len());
+    excerpt.push('…');
+    excerpt
+}
+
+/// Collapses runs of 3+ blank lines to 2, trims trailing whitespace and ends with exactly one
+/// newline. Fenced code blocks are copied verbatim.
+fn normalize_whitespace(markdown: &str) -> String {
+    let mut out = String::with_capacity(markdown.len());
+    let mut in_fence = false;
+    let mut fence_marker = String::new();
+    let mut blank_run = 0usize;
+
+    for line in markdown.lines() {
+        if in_fence {
+            out.push_str(line);
+            out.push('\n');
+            if fence_end_marker(line, &fence_marker) {
+                in_fence = false;
+            }
+            continue;
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            blank_run += 1;
+            if blank_run <= 2 {
+                out.push('\n');
+            }
+            continue;
+        }
+        blank_run = 0;
+        if let Some(marker) = fence_start_marker(line) {
+            in_fence = true;
+            fence_marker = marker.to_owned();
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+
+    let len = out.trim_end_matches('\n').len();
+    out.truncate(len);
+    out.push('\n');
+    out
 }
 
 #[derive(Debug, Clone)]
 struct SectionRewriteUnit {
     source_id: String,
     source_title: String,
+    source_url: String,
     heading: Option<MarkdownHeading>,
     markdown: String,
 }
@@ -709,23 +2042,36 @@ fn rewrite_section_units_via_openai(
     section: &crate::formats::TocSection,
     language: &str,
     tone: &str,
+    rewrite_options: &rewrite::RewriteOptions,
+    section_idx: usize,
     units: &[SectionRewriteUnit],
 ) -> anyhow::Result<String> {
     if units.is_empty() {
         return Ok(String::new());
     }
 
+    // Chunks of one page run in order, each seeing the end of the previous chunk's rewrite;
+    // different pages still run in parallel.
+    let mut pages: Vec<std::ops::Range<usize>> = Vec::new();
+    for (idx, unit) in units.iter().enumerate() {
+        match pages.last_mut() {
+            Some(page) if units[page.start].source_id == unit.source_id => page.end = idx + 1,
+            _ => pages.push(idx..idx + 1),
+        }
+    }
+    let pages = &pages;
+
     let worker_count = std::thread::available_parallelism()
         .map(|n| n.get())
         .unwrap_or(1)
-        .min(units.len());
-    let next_idx = Arc::new(AtomicUsize::new(0));
+        .min(pages.len());
+    let next_page = Arc::new(AtomicUsize::new(0));
 
     let mut rewritten_chunks = vec![None; units.len()];
     std::thread::scope(|scope| -> anyhow::Result<()> {
         let mut handles = Vec::new();
         for _ in 0..worker_count {
-            let next_idx = Arc::clone(&next_idx);
+            let next_page = Arc::clone(&next_page);
             let chapter_id = chapter.id.clone();
             let chapter_title = chapter.title.clone();
             let section_title = section.title.trim().to_owned();
@@ -733,30 +2079,47 @@ fn rewrite_section_units_via_openai(
             handles.push(
                 scope.spawn(move || -> anyhow::Result<Vec<(usize, String)>> {
                     let mut out = Vec::new();
-                    loop {
-                        let idx = next_idx.fetch_add(1, Ordering::Relaxed);
-                        let Some(unit) = units.get(idx) else {
-                            break;
-                        };
-
-                        let scoped_section_title = unit.scoped_section_title(&section_title);
-                        let rewritten = rewrite::rewrite_section_via_openai(
-                            language,
-                            tone,
-                            &chapter_title,
-                            &scoped_section_title,
-                            unit.markdown.as_str(),
-                        )
-                        .with_context(|| {
-                            format!(
-                                "openai rewrite section chunk: {} / {} / {}",
-                                chapter_id,
-                                section_title,
-                                unit.describe()
+                    while let Some(page) = pages.get(next_page.fetch_add(1, Ordering::Relaxed)) {
+                        let mut previous: Option<String> = None;
+                        for idx in page.clone() {
+                            let unit = &units[idx];
+                            let scoped_section_title = unit.scoped_section_title(&section_title);
+                            let unit_name =
+                                format!("{chapter_id}-s{:02}-u{:03}", section_idx + 1, idx + 1);
+                            let preceding_context = previous.as_deref().and_then(|previous| {
+                                rewrite::chunk_overlap_tail(
+                                    previous,
+                                    rewrite_options.chunk_overlap_chars,
+                                )
+                            });
+                            let rewritten = rewrite::rewrite_section_via_openai(
+                                language,
+                                tone,
+                                &chapter_title,
+                                &scoped_section_title,
+                                &rewrite::RewriteUnit {
+                                    markdown: unit.markdown.as_str(),
+                                    preceding_context,
+                                    name: &unit_name,
+                                    source_title: &unit.source_title,
+                                    source_url: &unit.source_url,
+                                    section_index: section_idx + 1,
+                                    chapter_intent: &chapter.intent,
+                                },
+                                rewrite_options,
                             )
-                        })?;
-
-                        out.push((idx, rewritten));
+                            .with_context(|| {
+                                format!(
+                                    "openai rewrite section chunk: {} / {} / {}",
+                                    chapter_id,
+                                    section_title,
+                                    unit.describe()
+                                )
+                            })?;
+
+                            previous = Some(rewritten.clone());
+                            out.push((idx, rewritten));
+                        }
                     }
                     Ok(out)
                 }),
@@ -795,28 +2158,75 @@ fn rewrite_section_units_via_openai(
 #[derive(Debug, Clone)]
 struct PageLocation {
     chapter_id: String,
-    page_id: String,
+    /// Fragment within the chapter (`None` when anchors are disabled).
+    anchor: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct RedirectTarget<'a> {
+    page_id: &'a str,
+    chapter_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    anchor: Option<&'a str>,
+}
+
+/// `redirects.json`: every source URL placed in the book, mapped to its chapter and anchor.
+fn render_redirects_json(
+    manifest: &HashMap<String, ManifestRecord>,
+    url_to_location: &HashMap<String, PageLocation>,
+) -> anyhow::Result<String> {
+    let redirects = manifest
+        .values()
+        .filter_map(|record| {
+            let location = url_to_location.get(&record.url)?;
+            Some((
+                record.url.as_str(),
+                RedirectTarget {
+                    page_id: &record.id,
+                    chapter_id: &location.chapter_id,
+                    anchor: location.anchor.as_deref(),
+                },
+            ))
+        })
+        .collect::<std::collections::BTreeMap<_, _>>();
+    let mut json = serde_json::to_string_pretty(&redirects).context("serialize redirects map")?;
+    json.push('\n');
+    Ok(json)
 }
 
 fn build_url_to_location(
     toc: &Toc,
     manifest: &HashMap<String, ManifestRecord>,
+    anchor_style: AnchorStyle,
+    host_aliases: &HostAliases,
 ) -> HashMap<String, PageLocation> {
     let mut map = HashMap::new();
     for part in &toc.parts {
         for chapter in &part.chapters {
-            for section in &chapter.sections {
+            for (section_idx, section) in chapter.sections.iter().enumerate() {
                 for source_id in &section.sources {
                     let Some(record) = manifest.get(source_id) else {
                         continue;
                     };
-                    map.insert(
-                        record.url.clone(),
-                        PageLocation {
-                            chapter_id: chapter.id.clone(),
-                            page_id: record.id.clone(),
-                        },
-                    );
+                    let anchor = match anchor_style {
+                        AnchorStyle::Html => Some(record.id.clone()),
+                        AnchorStyle::Heading => Some(section_anchor_id(&chapter.id, section_idx)),
+                        AnchorStyle::None => None,
+                    };
+                    let location = PageLocation {
+                        chapter_id: chapter.id.clone(),
+                        anchor,
+                    };
+                    // Key the page under every aliased host too, so a link to
+                    // `www.example.com` finds the `example.com` record (and vice versa).
+                    if let Ok(url) = Url::parse(&record.url) {
+                        for variant in host_aliases.host_variants(&url) {
+                            if variant.as_str() != record.url {
+                                map.insert(variant.to_string(), location.clone());
+                            }
+                        }
+                    }
+                    map.insert(record.url.clone(), location);
                 }
             }
         }
@@ -847,30 +2257,224 @@ fn compute_dir_index_ids<'a>(
     ids
 }
 
+/// Lowercased basename of `url` without its extension, reduced to `[a-z0-9-]` and at most
+/// 48 characters (`img` when nothing is left).
+fn asset_slug(url: &Url) -> String {
+    let basename = url.path().rsplit('/').next().unwrap_or_default();
+    let stem = basename.rsplit_once('.').map_or(basename, |(stem, _)| stem);
+    let mut slug = String::new();
+    for ch in stem.chars() {
+        if ch.is_ascii_alphanumeric() {
+            slug.push(ch.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.len() >= 48 {
+            break;
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "img".to_owned()
+    } else {
+        slug.to_owned()
+    }
+}
+
+/// Hosts whose images are CI/status badges or tracking pixels; dropped by `--strip-badges`.
+/// Each entry also matches its subdomains.
+pub const DEFAULT_BADGE_IMAGE_HOSTS: &[&str] = &[
+    "shields.io",
+    "badge.fury.io",
+    "badgen.net",
+    "codecov.io",
+    "coveralls.io",
+    "travis-ci.org",
+    "travis-ci.com",
+    "google-analytics.com",
+    "doubleclick.net",
+];
+
+const ASSET_RETRY_BASE_DELAY: Duration = Duration::from_millis(250);
+const ASSET_RETRY_MAX_DELAY: Duration = Duration::from_secs(10);
+const PLACEHOLDER_FILE_NAME: &str = "placeholder.svg";
+const PLACEHOLDER_SVG: &str = "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"320\" height=\"180\" viewBox=\"0 0 320 180\">\
+<rect width=\"320\" height=\"180\" fill=\"#eeeeee\" stroke=\"#bbbbbb\"/>\
+<text x=\"160\" y=\"95\" font-family=\"sans-serif\" font-size=\"16\" fill=\"#777777\" text-anchor=\"middle\">Image unavailable</text>\
+</svg>\n";
+
 struct AssetDownloader {
     client: reqwest::blocking::Client,
     assets_dir: PathBuf,
+    naming: AssetNaming,
+    retries: u32,
+    max_bytes: u64,
+    on_error: AssetErrorPolicy,
+    /// Lowercased hosts whose images are dropped instead of downloaded.
+    skip_image_hosts: Vec<String>,
     cache: Arc<Mutex<HashMap<String, String>>>,
+    /// Every asset option that changes what a chapter's image references become; part of
+    /// the render hash.
+    fingerprint: String,
+}
+
+/// The downloader as seen by one chapter's render, remembering whether any image failed.
+struct ChapterAssets<'a> {
+    downloader: &'a AssetDownloader,
+    failed: Cell<bool>,
 }
 
 impl AssetDownloader {
-    fn new(assets_dir: PathBuf) -> anyhow::Result<Self> {
+    fn new(assets_dir: PathBuf, tls: &TlsOptions, args: &BookRenderArgs) -> anyhow::Result<Self> {
         std::fs::create_dir_all(&assets_dir).with_context(|| {
             format!("create book asset dir: {}", assets_dir.as_path().display())
         })?;
 
-        let client = reqwest::blocking::Client::builder()
-            .timeout(Duration::from_secs(60))
-            .build()
-            .context("build asset download http client")?;
+        let client = crate::proxy::apply_blocking(
+            crate::net_guard::apply_blocking(
+                tls.apply_blocking(reqwest::blocking::Client::builder()),
+            ),
+            args.proxy.as_deref(),
+        )
+        .context("configure asset download proxy")?
+        .timeout(Duration::from_secs(60))
+        .default_headers(
+            crate::crawl::accept_language_headers(args.accept_language.as_deref())
+                .context("validate --accept-language")?,
+        )
+        .redirect(crate::net_guard::redirect_policy(10, false))
+        // Only the transfer encoding is undone; image bytes are stored as received.
+        .gzip(true)
+        .brotli(true)
+        .deflate(true)
+        .build()
+        .context("build asset download http client")?;
+
+        let mut skip_image_hosts = Vec::new();
+        if args.strip_badges {
+            skip_image_hosts.extend(
+                DEFAULT_BADGE_IMAGE_HOSTS
+                    .iter()
+                    .map(|host| host.to_string()),
+            );
+        }
+        for host in &args.skip_image_hosts {
+            let host = host.trim().trim_end_matches('.').to_ascii_lowercase();
+            if host.is_empty() {
+                anyhow::bail!("--skip-image-host must not be empty");
+            }
+            skip_image_hosts.push(host);
+        }
 
         Ok(Self {
             client,
             assets_dir,
+            naming: args.asset_naming,
+            retries: args.asset_retries,
+            max_bytes: args.max_asset_bytes,
+            on_error: args.on_asset_error,
+            fingerprint: format!(
+                "{:?}",
+                (
+                    args.asset_naming,
+                    args.asset_retries,
+                    args.max_asset_bytes,
+                    args.on_asset_error,
+                    &skip_image_hosts,
+                    args.dedupe_assets_by_content,
+                    &args.accept_language,
+                    &args.proxy,
+                    &args.ca_cert,
+                    args.insecure,
+                )
+            ),
+            skip_image_hosts,
             cache: Arc::new(Mutex::new(HashMap::new())),
         })
     }
 
+    /// Whether the image at `url` comes from a `--strip-badges` / `--skip-image-host` host.
+    fn skips_image(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        self.skip_image_hosts.iter().any(|skipped| {
+            host == *skipped
+                || host
+                    .strip_suffix(skipped.as_str())
+                    .is_some_and(|prefix| prefix.ends_with('.'))
+        })
+    }
+
+    /// Chapter reference for the image at `url`: the downloaded copy, or what
+    /// `--on-asset-error` asks for once every attempt failed (which also sets `failed`).
+    fn resolve_image(&self, url: &Url, failed: &Cell<bool>) -> anyhow::Result<String> {
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return Ok(url.to_string());
+        }
+        let err = match self.download_image(url) {
+            Ok(local) => return Ok(local),
+            Err(err) => err,
+        };
+        failed.set(true);
+        match self.on_error {
+            AssetErrorPolicy::KeepUrl => {
+                tracing::warn!(url = %url, ?err, "image download failed; keeping remote URL");
+                Ok(url.to_string())
+            }
+            AssetErrorPolicy::Placeholder => {
+                tracing::warn!(url = %url, ?err, "image download failed; using placeholder");
+                let dest_path = self.assets_dir.join(PLACEHOLDER_FILE_NAME);
+                write_file_if_missing(&dest_path, PLACEHOLDER_SVG.as_bytes())
+                    .with_context(|| format!("write placeholder: {}", dest_path.display()))?;
+                Ok(format!("../assets/{PLACEHOLDER_FILE_NAME}"))
+            }
+            AssetErrorPolicy::Fail => Err(err.context(format!("download image: {url}"))),
+        }
+    }
+
+    /// GET with up to `--asset-retries` retries on send errors and retryable statuses,
+    /// backing off exponentially (or as `Retry-After` says). Returns a 2xx response.
+    fn get_with_retry(&self, url: &Url) -> anyhow::Result<reqwest::blocking::Response> {
+        crate::net_guard::check_ip_literal(url)?;
+        let mut attempt = 0u32;
+        loop {
+            let (err, retry_after) = match self.client.get(url.as_str()).send() {
+                Ok(response) if response.status().is_success() => return Ok(response),
+                Ok(response) => {
+                    let status = response.status();
+                    let err = anyhow::anyhow!("asset download failed ({status})");
+                    if !crate::openai::is_retryable_status(status) {
+                        return Err(err);
+                    }
+                    (err, crate::openai::parse_retry_after(response.headers()))
+                }
+                Err(err) => (anyhow::Error::new(err).context(format!("GET {url}")), None),
+            };
+            if attempt >= self.retries {
+                return Err(err);
+            }
+            let delay = retry_after
+                .unwrap_or_else(|| {
+                    ASSET_RETRY_BASE_DELAY.saturating_mul(2u32.saturating_pow(attempt))
+                })
+                .min(ASSET_RETRY_MAX_DELAY);
+            attempt += 1;
+            tracing::debug!(url = %url, attempt, ?delay, error = %err, "retrying asset download");
+            std::thread::sleep(delay);
+        }
+    }
+
+    /// Local file name for `url`; `hash` is the SHA-256 of its cache key, so both styles stay
+    /// unique per URL (`slug` keeps 8 hex digits after the readable part).
+    fn file_name(&self, url: &Url, hash: &str, ext: &str) -> String {
+        match self.naming {
+            AssetNaming::Hash => format!("img_{hash}.{ext}"),
+            AssetNaming::Slug => format!("{}-{}.{ext}", asset_slug(url), &hash[..8]),
+        }
+    }
+
     fn download_image(&self, url: &Url) -> anyhow::Result<String> {
         let key = normalize_asset_url_key(url);
         if let Ok(cache) = self.cache.lock()
@@ -888,7 +2492,7 @@ impl AssetDownloader {
 
         let hash = sha256_hex(&key);
         if let Some(ext) = image_extension_from_path(url) {
-            let file_name = format!("img_{hash}.{ext}");
+            let file_name = self.file_name(url, &hash, ext);
             let local = format!("../assets/{file_name}");
             let dest_path = self.assets_dir.join(&file_name);
             if dest_path.exists() {
@@ -905,15 +2509,7 @@ impl AssetDownloader {
             return Ok(local);
         }
 
-        let response = self
-            .client
-            .get(url.as_str())
-            .send()
-            .with_context(|| format!("GET {url}"))?;
-        let status = response.status();
-        if !status.is_success() {
-            anyhow::bail!("asset download failed ({status})");
-        }
+        let response = self.get_with_retry(url)?;
 
         let content_type = response
             .headers()
@@ -923,7 +2519,7 @@ impl AssetDownloader {
             .and_then(image_extension_from_content_type)
             .unwrap_or("bin");
 
-        let file_name = format!("img_{hash}.{ext}");
+        let file_name = self.file_name(url, &hash, ext);
         let local = format!("../assets/{file_name}");
         let dest_path = self.assets_dir.join(&file_name);
         if dest_path.exists() {
@@ -933,7 +2529,8 @@ impl AssetDownloader {
             return Ok(local);
         }
 
-        let bytes = response.bytes().context("read asset response body")?;
+        let bytes = self.read_body(response)?;
+        check_image_bytes(ext, &bytes)?;
         write_file_if_missing(&dest_path, &bytes)
             .with_context(|| format!("write asset: {}", dest_path.display()))?;
         if let Ok(mut cache) = self.cache.lock() {
@@ -943,32 +2540,36 @@ impl AssetDownloader {
     }
 
     fn download_to(&self, key: &str, url: &Url, dest_path: &Path) -> anyhow::Result<()> {
-        tracing::info!(url = %url, path = %dest_path.display(), "download asset");
+        tracing::info!(
+            target: crate::logging::PROGRESS_TARGET,
+            url = %url,
+            path = %dest_path.display(),
+            "download asset"
+        );
 
         if dest_path.exists() {
             return Ok(());
         }
 
-        let response = self
-            .client
-            .get(url.as_str())
-            .send()
-            .with_context(|| format!("GET {url}"))?;
-        let status = response.status();
-        if !status.is_success() {
-            anyhow::bail!("asset download failed ({status})");
-        }
+        let response = self.get_with_retry(url)?;
 
-        let bytes = response.bytes().context("read asset response body")?;
+        let bytes = self.read_body(response)?;
         if bytes.is_empty() {
             anyhow::bail!("asset download returned empty body");
         }
+        if let Some(ext) = dest_path.extension().and_then(|ext| ext.to_str()) {
+            check_image_bytes(ext, &bytes)?;
+        }
 
         let expected_hash = sha256_hex(key);
+        let expected_hash = match self.naming {
+            AssetNaming::Hash => expected_hash.as_str(),
+            AssetNaming::Slug => &expected_hash[..8],
+        };
         if !dest_path
             .file_name()
             .and_then(|n| n.to_str())
-            .map(|n| n.contains(&expected_hash))
+            .map(|n| n.contains(expected_hash))
             .unwrap_or(false)
         {
             anyhow::bail!("refusing to write asset with unexpected name");
@@ -978,6 +2579,114 @@ impl AssetDownloader {
             .with_context(|| format!("write asset: {}", dest_path.display()))?;
         Ok(())
     }
+
+    /// Streams the body, giving up as soon as it exceeds `--max-asset-bytes` (or when
+    /// `Content-Length` already says it will).
+    fn read_body(&self, response: reqwest::blocking::Response) -> anyhow::Result<Vec<u8>> {
+        if let Some(len) = response.content_length()
+            && len > self.max_bytes
+        {
+            anyhow::bail!(
+                "asset is {len} bytes, over --max-asset-bytes {}",
+                self.max_bytes
+            );
+        }
+        let mut bytes = Vec::new();
+        response
+            .take(self.max_bytes.saturating_add(1))
+            .read_to_end(&mut bytes)
+            .context("read asset response body")?;
+        if bytes.len() as u64 > self.max_bytes {
+            anyhow::bail!("asset is over --max-asset-bytes {} bytes", self.max_bytes);
+        }
+        Ok(bytes)
+    }
+}
+
+/// Image type named by the leading magic bytes, for the formats that have a reliable one.
+fn sniff_image_extension(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("png")
+    } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+        Some("jpg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("gif")
+    } else if bytes.len() >= 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP" {
+        Some("webp")
+    } else {
+        None
+    }
+}
+
+/// Rejects a png/jpg/gif/webp body whose magic bytes do not match, e.g. an HTML error page
+/// served with an image URL or content type. Other types are not sniffed.
+fn check_image_bytes(ext: &str, bytes: &[u8]) -> anyhow::Result<()> {
+    if !matches!(ext, "png" | "jpg" | "gif" | "webp") {
+        return Ok(());
+    }
+    let sniffed = sniff_image_extension(bytes);
+    if sniffed != Some(ext) {
+        anyhow::bail!(
+            "asset body does not look like a {ext} image (sniffed: {})",
+            sniffed.unwrap_or("unknown")
+        );
+    }
+    Ok(())
+}
+
+/// `--dedupe-assets-by-content`: finds asset files with identical bytes and returns
+/// `(duplicate, canonical)` names, the first name in sort order being canonical. It runs once
+/// every chapter is written, so the outcome does not depend on which render thread downloaded
+/// what first. Files are left in place (see [`remove_duplicate_assets`]) until every
+/// reference has been rewritten. `placeholder.svg` is left out: failed images always point
+/// at it (`--on-asset-error placeholder`), even when a downloaded image has the same bytes.
+fn dedupe_assets_by_content(assets_dir: &Path) -> anyhow::Result<Vec<(String, String)>> {
+    let mut names = std::fs::read_dir(assets_dir)
+        .with_context(|| format!("read book asset dir: {}", assets_dir.display()))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_file()))
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| name != PLACEHOLDER_FILE_NAME)
+        .collect::<Vec<_>>();
+    names.sort();
+
+    let mut canonical_by_hash: HashMap<String, String> = HashMap::new();
+    let mut renames = Vec::new();
+    for name in names {
+        let path = assets_dir.join(&name);
+        let bytes =
+            std::fs::read(&path).with_context(|| format!("read asset: {}", path.display()))?;
+        match canonical_by_hash.entry(hex::encode(Sha256::digest(&bytes))) {
+            std::collections::hash_map::Entry::Occupied(canonical) => {
+                renames.push((name, canonical.get().clone()));
+            }
+            std::collections::hash_map::Entry::Vacant(slot) => {
+                slot.insert(name);
+            }
+        }
+    }
+    Ok(renames)
+}
+
+fn rename_asset_references(markdown: &str, renames: &[(String, String)], prefix: &str) -> String {
+    let mut out = markdown.to_owned();
+    for (duplicate, canonical) in renames {
+        out = out.replace(
+            &format!("{prefix}{duplicate}"),
+            &format!("{prefix}{canonical}"),
+        );
+    }
+    out
+}
+
+fn remove_duplicate_assets(assets_dir: &Path, renames: &[(String, String)]) -> anyhow::Result<()> {
+    for (duplicate, canonical) in renames {
+        tracing::debug!(duplicate, canonical, "removing duplicate asset");
+        let path = assets_dir.join(duplicate);
+        std::fs::remove_file(&path)
+            .with_context(|| format!("remove duplicate asset: {}", path.display()))?;
+    }
+    Ok(())
 }
 
 fn normalize_asset_url_key(url: &Url) -> String {
@@ -1048,21 +2757,30 @@ fn write_file_if_missing(path: &Path, bytes: &[u8]) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// URL that relative links on a page resolve against.
+///
+/// A `<base href>` replaces the page URL, exactly as in a browser; directory index pages
+/// otherwise resolve as if their URL ended in `/`.
+fn page_link_base(record: &ManifestRecord, page_is_dir_index: bool) -> anyhow::Result<Url> {
+    if let Some(base_url) = record.base_url.as_deref() {
+        return Url::parse(base_url).context("parse page base url");
+    }
+    let page_url = Url::parse(&record.url).context("parse page url")?;
+    Ok(if page_is_dir_index {
+        url_with_trailing_slash(&page_url)
+    } else {
+        page_url
+    })
+}
+
 fn rewrite_markdown_links_and_images(
     body: &str,
-    page_url: &str,
+    base_for_join: &Url,
     chapter_id: &str,
     url_to_location: &HashMap<String, PageLocation>,
-    page_is_dir_index: bool,
-    assets: &AssetDownloader,
+    assets: &ChapterAssets<'_>,
+    external_links: &mut ExternalLinks,
 ) -> anyhow::Result<String> {
-    let base_url = Url::parse(page_url).context("parse page url")?;
-    let base_for_join = if page_is_dir_index {
-        url_with_trailing_slash(&base_url)
-    } else {
-        base_url.clone()
-    };
-
     let mut out = String::with_capacity(body.len());
     let mut in_fence = false;
     let mut fence_marker = String::new();
@@ -1078,10 +2796,11 @@ fn rewrite_markdown_links_and_images(
             }
             out.push_str(&rewrite_inline_markdown(
                 line,
-                &base_for_join,
+                base_for_join,
                 chapter_id,
                 url_to_location,
                 assets,
+                external_links,
             )?);
             continue;
         }
@@ -1100,7 +2819,8 @@ fn rewrite_inline_markdown(
     base_url: &Url,
     current_chapter_id: &str,
     url_to_location: &HashMap<String, PageLocation>,
-    assets: &AssetDownloader,
+    assets: &ChapterAssets<'_>,
+    external_links: &mut ExternalLinks,
 ) -> anyhow::Result<String> {
     let mut out = String::with_capacity(input.len());
     let mut i = 0usize;
@@ -1123,6 +2843,7 @@ fn rewrite_inline_markdown(
                 current_chapter_id,
                 url_to_location,
                 assets,
+                external_links,
             )?
         {
             out.push_str(&rewritten);
@@ -1138,6 +2859,7 @@ fn rewrite_inline_markdown(
                 current_chapter_id,
                 url_to_location,
                 assets,
+                external_links,
             )?
         {
             out.push_str(&rewritten);
@@ -1170,7 +2892,8 @@ fn try_rewrite_link_like(
     base_url: &Url,
     current_chapter_id: &str,
     url_to_location: &HashMap<String, PageLocation>,
-    assets: &AssetDownloader,
+    assets: &ChapterAssets<'_>,
+    external_links: &mut ExternalLinks,
 ) -> anyhow::Result<Option<(usize, String)>> {
     let mut i = if is_image { 2 } else { 1 };
     let mut bracket_depth = 1u32;
@@ -1250,31 +2973,70 @@ fn try_rewrite_link_like(
     let paren_close = j;
 
     let dest = &input[paren_open + 1..paren_close];
-    let rewritten_dest = rewrite_link_destination(
+    let Some((rewritten_dest, external)) = rewrite_link_destination(
         dest,
         is_image,
         base_url,
         current_chapter_id,
         url_to_location,
         assets,
-    )?;
+    )?
+    else {
+        // A dropped image leaves its alt text behind.
+        return Ok(Some((paren_close + 1, input[2..close_bracket].to_owned())));
+    };
+
+    // Images nested in link text (`[![CI](badge.svg)](ci)`) get the same treatment as
+    // standalone ones.
+    let text_start = if is_image { 2 } else { 1 };
+    let raw_text = &input[text_start..close_bracket];
+    let text = if is_image || !raw_text.contains("![") {
+        raw_text.to_owned()
+    } else {
+        rewrite_inline_markdown(
+            raw_text,
+            base_url,
+            current_chapter_id,
+            url_to_location,
+            assets,
+            external_links,
+        )?
+    };
+    if !is_image && text.trim().is_empty() && !raw_text.trim().is_empty() {
+        // Nothing left to click once its only image was dropped.
+        return Ok(Some((paren_close + 1, String::new())));
+    }
+
+    if let Some(url) = external {
+        match external_links.policy {
+            ExternalLinkPolicy::Keep => {}
+            ExternalLinkPolicy::Strip => return Ok(Some((paren_close + 1, text))),
+            ExternalLinkPolicy::Footnote => {
+                let label = external_links.footnote_label(url);
+                return Ok(Some((paren_close + 1, format!("{text}[^{label}]"))));
+            }
+        }
+    }
 
     let mut rewritten = String::with_capacity(paren_close + 1);
-    rewritten.push_str(&input[..paren_open + 1]);
+    rewritten.push_str(&input[..text_start]);
+    rewritten.push_str(&text);
+    rewritten.push_str(&input[close_bracket..paren_open + 1]);
     rewritten.push_str(&rewritten_dest);
     rewritten.push_str(&input[paren_close..=paren_close]);
 
     Ok(Some((paren_close + 1, rewritten)))
 }
 
+/// `None` when the destination is an image from a skipped host and should be dropped.
 fn rewrite_link_destination(
     dest: &str,
     is_image: bool,
     base_url: &Url,
     current_chapter_id: &str,
     url_to_location: &HashMap<String, PageLocation>,
-    assets: &AssetDownloader,
-) -> anyhow::Result<String> {
+    assets: &ChapterAssets<'_>,
+) -> anyhow::Result<Option<(String, Option<String>)>> {
     let mut i = 0usize;
     while i < dest.len() {
         let ch = dest[i..].chars().next().unwrap();
@@ -1288,7 +3050,7 @@ fn rewrite_link_destination(
         let core_start = i + 1;
         let after = &dest[core_start..];
         let Some(rel_end) = after.find('>') else {
-            return Ok(dest.to_owned());
+            return Ok(Some((dest.to_owned(), None)));
         };
         (core_start, core_start + rel_end)
     } else {
@@ -1304,26 +3066,72 @@ fn rewrite_link_destination(
     };
 
     let core = &dest[core_start..core_end];
+    let mut external = None;
     let rewritten = if is_image {
         match resolve_url_for_output(base_url, core) {
-            Some(resolved) => match assets.download_image(&resolved) {
-                Ok(local) => local,
-                Err(err) => {
-                    tracing::debug!(url = %resolved, ?err, "image download failed; using URL");
-                    resolved.to_string()
-                }
-            },
+            Some(resolved) if assets.downloader.skips_image(&resolved) => {
+                tracing::debug!(url = %resolved, "dropping image from skipped host");
+                return Ok(None);
+            }
+            Some(resolved) => assets.downloader.resolve_image(&resolved, &assets.failed)?,
             None => core.to_owned(),
         }
     } else {
-        rewrite_page_link(base_url, core, current_chapter_id, url_to_location)?
+        match rewrite_page_link(base_url, core, current_chapter_id, url_to_location)? {
+            PageLink::Internal(target) | PageLink::Verbatim(target) => target,
+            PageLink::External(url) => {
+                external = Some(url.clone());
+                url
+            }
+        }
     };
 
     let mut out = String::with_capacity(dest.len() + 16);
     out.push_str(&dest[..core_start]);
     out.push_str(&rewritten);
     out.push_str(&dest[core_end..]);
-    Ok(out)
+    Ok(Some((out, external)))
+}
+
+/// Outcome of resolving a page link against the pages in the book.
+enum PageLink {
+    /// Points into the book (`chNN.md#...` or `#...`).
+    Internal(String),
+    /// Left exactly as written (fragments, `mailto:`, `javascript:`, unparsable).
+    Verbatim(String),
+    /// Absolute URL of a page that is not part of the book.
+    External(String),
+}
+
+/// Applies `--external-link-policy` and collects footnote URLs for one chapter.
+struct ExternalLinks {
+    policy: ExternalLinkPolicy,
+    footnotes: Vec<String>,
+}
+
+impl ExternalLinks {
+    fn new(policy: ExternalLinkPolicy) -> Self {
+        Self {
+            policy,
+            footnotes: Vec::new(),
+        }
+    }
+
+    fn label(&self, idx: usize) -> String {
+        format!("ext-{}", idx + 1)
+    }
+
+    /// Returns the footnote label for `url`, reusing it when the URL was already cited.
+    fn footnote_label(&mut self, url: String) -> String {
+        let idx = match self.footnotes.iter().position(|existing| *existing == url) {
+            Some(idx) => idx,
+            None => {
+                self.footnotes.push(url);
+                self.footnotes.len() - 1
+            }
+        };
+        self.label(idx)
+    }
 }
 
 fn rewrite_page_link(
@@ -1331,26 +3139,33 @@ fn rewrite_page_link(
     raw: &str,
     current_chapter_id: &str,
     url_to_location: &HashMap<String, PageLocation>,
-) -> anyhow::Result<String> {
+) -> anyhow::Result<PageLink> {
     if raw.is_empty() || raw.starts_with('#') {
-        return Ok(raw.to_owned());
+        return Ok(PageLink::Verbatim(raw.to_owned()));
     }
     if raw.starts_with("mailto:") || raw.starts_with("javascript:") {
-        return Ok(raw.to_owned());
+        return Ok(PageLink::Verbatim(raw.to_owned()));
     }
 
     let Some(resolved) = resolve_url_for_output(base_url, raw) else {
-        return Ok(raw.to_owned());
+        return Ok(PageLink::Verbatim(raw.to_owned()));
     };
-    let canonical = canonicalize_url_for_lookup(&resolved);
-    if let Some(loc) = url_to_location.get(canonical.as_str()) {
-        if loc.chapter_id == current_chapter_id {
-            return Ok(format!("#{}", loc.page_id));
-        }
-        return Ok(format!("{}.md#{}", loc.chapter_id, loc.page_id));
-    }
-
-    Ok(resolved.to_string())
+    // Pages crawled with `--keep-query` are keyed with their query; others without.
+    let location = [
+        crate::url_norm::QueryPolicy::keeping_query().canonical_url(&resolved),
+        crate::url_norm::canonical_url(&resolved),
+    ]
+    .iter()
+    .find_map(|canonical| url_to_location.get(canonical.as_str()));
+    if let Some(loc) = location {
+        return Ok(PageLink::Internal(match &loc.anchor {
+            Some(anchor) if loc.chapter_id == current_chapter_id => format!("#{anchor}"),
+            Some(anchor) => format!("{}.md#{anchor}", loc.chapter_id),
+            None => format!("{}.md", loc.chapter_id),
+        }));
+    }
+
+    Ok(PageLink::External(resolved.to_string()))
 }
 
 fn resolve_url_for_output(base_url: &Url, raw: &str) -> Option<Url> {
@@ -1365,19 +3180,6 @@ fn resolve_url_for_output(base_url: &Url, raw: &str) -> Option<Url> {
     base_url.join(raw).ok()
 }
 
-fn canonicalize_url_for_lookup(url: &Url) -> String {
-    let mut canonical = url.clone();
-    canonical.set_fragment(None);
-    canonical.set_query(None);
-
-    let mut path = canonical.path().to_owned();
-    while path.len() > 1 && path.ends_with('/') {
-        path.pop();
-    }
-    canonical.set_path(&path);
-    canonical.to_string()
-}
-
 fn url_with_trailing_slash(url: &Url) -> Url {
     let mut out = url.clone();
     let path = out.path();
@@ -1492,6 +3294,118 @@ fn split_markdown_by_heading_levels(body: &str) -> Vec<MarkdownChunk> {
     chunks
 }
 
+/// Splits `markdown` into parts of at most `max_chars` characters so each rewrite request
+/// sees whole paragraphs. Parts break at blank lines outside fenced code; a paragraph that is
+/// too long on its own breaks at sentence ends, and a single overlong sentence (or code
+/// block) is kept whole.
+fn split_oversized_markdown(markdown: &str, max_chars: Option<usize>) -> Vec<String> {
+    let Some(max_chars) = max_chars else {
+        return vec![markdown.to_owned()];
+    };
+    if markdown.chars().count() <= max_chars {
+        return vec![markdown.to_owned()];
+    }
+
+    // `(text, separator)`: the separator joins the piece to the previous one in the same part.
+    let mut pieces: Vec<(&str, &str)> = Vec::new();
+    for paragraph in markdown_paragraphs(markdown) {
+        if paragraph.chars().count() > max_chars
+            && !paragraph
+                .lines()
+                .any(|line| fence_start_marker(line).is_some())
+        {
+            let mut sentences = split_sentences(paragraph).into_iter();
+            if let Some(first) = sentences.next() {
+                pieces.push((first, "\n\n"));
+            }
+            pieces.extend(sentences.map(|sentence| (sentence, "")));
+        } else {
+            pieces.push((paragraph, "\n\n"));
+        }
+    }
+
+    let mut parts: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut current_chars = 0usize;
+    for (piece, separator) in pieces {
+        let piece_chars = piece.chars().count();
+        if !current.is_empty() && current_chars + separator.len() + piece_chars > max_chars {
+            parts.push(std::mem::take(&mut current));
+            current_chars = 0;
+        }
+        if current.is_empty() {
+            let piece = piece.trim_start();
+            current.push_str(piece);
+            current_chars = piece.chars().count();
+        } else {
+            current.push_str(separator);
+            current.push_str(piece);
+            current_chars += separator.len() + piece_chars;
+        }
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    parts
+}
+
+/// Blank-line separated blocks, keeping fenced code blocks intact.
+fn markdown_paragraphs(markdown: &str) -> Vec<&str> {
+    let mut paragraphs = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut end = 0usize;
+    let mut in_fence = false;
+    let mut fence_marker = String::new();
+    let mut offset = 0usize;
+    for line in markdown.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        if in_fence {
+            if fence_end_marker(line, &fence_marker) {
+                in_fence = false;
+            }
+        } else if let Some(marker) = fence_start_marker(line) {
+            in_fence = true;
+            fence_marker = marker.to_owned();
+        } else if line.trim().is_empty() {
+            if let Some(start) = start.take() {
+                paragraphs.push(&markdown[start..end]);
+            }
+            continue;
+        }
+        start.get_or_insert(line_start);
+        end = line_start + line.trim_end().len();
+    }
+    if let Some(start) = start {
+        paragraphs.push(&markdown[start..end]);
+    }
+    paragraphs
+}
+
+/// Splits a paragraph after sentence-ending punctuation (`.`, `!`, `?` followed by a space,
+/// or `。`, `！`, `？`). Concatenating the pieces gives back the paragraph.
+fn split_sentences(paragraph: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0usize;
+    let mut chars = paragraph.char_indices().peekable();
+    while let Some((idx, ch)) = chars.next() {
+        let end = idx + ch.len_utf8();
+        let boundary = match ch {
+            '。' | '！' | '？' => true,
+            '.' | '!' | '?' => chars.peek().is_some_and(|(_, next)| *next == ' '),
+            _ => false,
+        };
+        if boundary && end < paragraph.len() {
+            sentences.push(&paragraph[start..end]);
+            start = end;
+        }
+    }
+    if start < paragraph.len() {
+        sentences.push(&paragraph[start..]);
+    }
+    sentences
+}
+
 fn push_markdown_chunk(
     chunks: &mut Vec<MarkdownChunk>,
     current_heading: &mut Option<MarkdownHeading>,
@@ -1529,7 +3443,7 @@ fn parse_atx_heading_line(line: &str) -> Option<(usize, String)> {
 }
 
 fn parse_summary_chapter_paths(summary_md: &str) -> Vec<String> {
-    let mut paths = Vec::new();
+    let mut paths: Vec<String> = Vec::new();
     for line in summary_md.lines() {
         let Some(target) = parse_markdown_link_target(line) else {
             continue;
@@ -1545,6 +3459,10 @@ fn parse_summary_chapter_paths(summary_md: &str) -> Vec<String> {
         if !path.ends_with(".md") {
             continue;
         }
+        // Section entries (`chNN.md#...`) point into an already listed chapter.
+        if paths.iter().any(|p| p == path) {
+            continue;
+        }
         paths.push(path.to_owned());
     }
     paths
@@ -1557,25 +3475,72 @@ fn parse_markdown_link_target(line: &str) -> Option<String> {
     Some(after[..link_end].to_owned())
 }
 
-fn read_book_title(book_dir: &std::path::Path) -> anyhow::Result<Option<String>> {
+#[derive(Debug, Default)]
+struct BookMetadata {
+    title: Option<String>,
+    authors: Vec<String>,
+}
+
+fn read_book_metadata(book_dir: &std::path::Path) -> anyhow::Result<BookMetadata> {
     let book_toml_path = book_dir.join("book.toml");
+    let mut metadata = BookMetadata::default();
     if !book_toml_path.exists() {
-        return Ok(None);
+        return Ok(metadata);
     }
     let contents = std::fs::read_to_string(&book_toml_path)
         .with_context(|| format!("read book.toml: {}", book_toml_path.display()))?;
 
     for line in contents.lines() {
         let line = line.trim();
-        if line.starts_with("title") {
-            let Some((_, rhs)) = line.split_once('=') else {
-                continue;
-            };
-            let rhs = rhs.trim();
-            if let Some(stripped) = rhs.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
-                return Ok(Some(stripped.to_owned()));
+        let Some((key, rhs)) = line.split_once('=') else {
+            continue;
+        };
+        let rhs = rhs.trim();
+        match key.trim() {
+            "title" if metadata.title.is_none() => {
+                if let Some(stripped) = rhs.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+                    metadata.title = Some(stripped.to_owned());
+                }
+            }
+            "authors" if metadata.authors.is_empty() => {
+                if let Some(list) = rhs.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+                    metadata.authors = list
+                        .split(',')
+                        .filter_map(|item| {
+                            item.trim()
+                                .strip_prefix('"')
+                                .and_then(|s| s.strip_suffix('"'))
+                        })
+                        .filter(|s| !s.is_empty())
+                        .map(ToOwned::to_owned)
+                        .collect();
+                }
             }
+            _ => {}
         }
     }
-    Ok(None)
+    Ok(metadata)
+}
+
+fn render_bundle_front_matter(
+    title: Option<&str>,
+    author: Option<&str>,
+    date: &str,
+) -> anyhow::Result<String> {
+    #[derive(serde::Serialize)]
+    struct FrontMatter<'a> {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<&'a str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        author: Option<&'a str>,
+        date: &'a str,
+    }
+
+    let yaml = serde_yaml::to_string(&FrontMatter {
+        title,
+        author,
+        date,
+    })
+    .context("serialize bundle front matter")?;
+    Ok(format!("---\n{yaml}---\n\n"))
 }
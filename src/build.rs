@@ -3,7 +3,8 @@ use std::path::PathBuf;
 use anyhow::Context as _;
 
 use crate::cli::{
-    BookBundleArgs, BookInitArgs, BookRenderArgs, BuildArgs, CrawlArgs, ExtractArgs, ManifestArgs,
+    AnchorStyle, AssetErrorPolicy, AssetNaming, BookBundleArgs, BookInitArgs, BookRenderArgs,
+    BuildArgs, CrawlArgs, ExternalLinkPolicy, ExtractArgs, ManifestArgs, OutputLayout, TitleSource,
     TocCreateArgs,
 };
 use crate::formats::Toc;
@@ -27,14 +28,36 @@ pub async fn run(args: BuildArgs) -> anyhow::Result<()> {
     let bundled_md_path = workspace_dir.join("book.md");
     let epub_path = workspace_dir.join("book.epub");
 
+    let accept_language = args
+        .accept_language
+        .clone()
+        .or_else(|| crate::crawl::language_tag(&args.language));
+
     tracing::info!(url = %args.url, out = %workspace_dir.display(), "build: crawl");
     crate::crawl::run(CrawlArgs {
         url: args.url.clone(),
         out: raw_dir.to_string_lossy().to_string(),
+        force: false,
         max_pages: args.max_pages,
         max_depth: args.max_depth,
         concurrency: args.concurrency,
+        concurrency_per_host: None,
         delay_ms: args.delay_ms,
+        page_urls: args.page_urls.clone(),
+        seed_from_sitemap: false,
+        resume: false,
+        host_aliases: args.host_aliases.clone(),
+        keep_query: false,
+        tracking_params: Vec::new(),
+        fetch_command: None,
+        exclude_extensions: Vec::new(),
+        max_redirects: 10,
+        same_host_redirects_only: false,
+        fail_on_login_wall: false,
+        proxy: args.proxy.clone(),
+        accept_language: accept_language.clone(),
+        ca_cert: args.ca_cert.clone(),
+        insecure: args.insecure,
     })
     .await
     .context("crawl")?;
@@ -43,6 +66,13 @@ pub async fn run(args: BuildArgs) -> anyhow::Result<()> {
     crate::extract::run(ExtractArgs {
         raw: raw_dir.to_string_lossy().to_string(),
         out: extracted_dir.to_string_lossy().to_string(),
+        force: false,
+        output_layout: OutputLayout::Flat,
+        min_content_chars: 0,
+        if_modified: None,
+        title_source: TitleSource::Readability,
+        strip_repeated: false,
+        strip_repeated_threshold: 80,
     })
     .context("extract")?;
 
@@ -62,6 +92,17 @@ pub async fn run(args: BuildArgs) -> anyhow::Result<()> {
         language: args.language.clone(),
         tone: args.tone.clone(),
         engine: args.toc_engine,
+        chapter_ids: args.chapter_ids,
+        noop_grouping: args.noop_grouping,
+        page_order: args.page_order,
+        sitemap: args.sitemap.clone(),
+        dump_prompt: None,
+        openai_organization: args.openai_organization.clone(),
+        openai_project: args.openai_project.clone(),
+        openai_api_key_env: args.openai_api_key_env.clone(),
+        openai_retry_base_ms: args.openai_retry_base_ms,
+        openai_retry_max_ms: args.openai_retry_max_ms,
+        proxy: args.proxy.clone(),
     })
     .await
     .context("toc create")?;
@@ -74,6 +115,8 @@ pub async fn run(args: BuildArgs) -> anyhow::Result<()> {
     crate::book::init(BookInitArgs {
         out: book_dir.to_string_lossy().to_string(),
         title: toc.book_title,
+        template: None,
+        summary_template: None,
     })
     .context("book init")?;
 
@@ -85,6 +128,48 @@ pub async fn run(args: BuildArgs) -> anyhow::Result<()> {
         language: args.language.clone(),
         tone: args.tone.clone(),
         engine: args.render_engine,
+        openai_fallback_model: args.openai_fallback_model.clone(),
+        rewrite_granularity: args.rewrite_granularity,
+        max_section_chars: None,
+        openai_chunk_overlap_chars: 0,
+        tone_example_file: None,
+        instructions_template: None,
+        dump_prompt: None,
+        sample: args.sample,
+        sample_seed: args.sample_seed,
+        toc_depth: args.toc_depth,
+        intro: false,
+        toc_title: "Introduction".to_owned(),
+        chapter_summaries: false,
+        chapter_summary_title: "What you learned".to_owned(),
+        chapter_title_format: None,
+        sections_as_chapters: false,
+        emit_redirects: false,
+        anchor_style: AnchorStyle::Html,
+        concurrency: None,
+        force_rerender: false,
+        asset_naming: AssetNaming::Hash,
+        strip_badges: false,
+        skip_image_hosts: Vec::new(),
+        dedupe_assets_by_content: false,
+        asset_retries: 2,
+        max_asset_bytes: 20 * 1024 * 1024,
+        on_asset_error: AssetErrorPolicy::KeepUrl,
+        external_link_policy: ExternalLinkPolicy::Keep,
+        host_aliases: args.host_aliases.clone(),
+        no_normalize: false,
+        include_excerpts: false,
+        excerpt_chars: 300,
+        openai_organization: args.openai_organization.clone(),
+        openai_project: args.openai_project.clone(),
+        openai_api_key_env: args.openai_api_key_env.clone(),
+        openai_retry_base_ms: args.openai_retry_base_ms,
+        openai_retry_max_ms: args.openai_retry_max_ms,
+        openai_stream: args.openai_stream,
+        proxy: args.proxy.clone(),
+        accept_language,
+        ca_cert: args.ca_cert.clone(),
+        insecure: args.insecure,
     };
     tokio::task::block_in_place(|| crate::book::render(render_args)).context("book render")?;
 
@@ -93,6 +178,10 @@ pub async fn run(args: BuildArgs) -> anyhow::Result<()> {
         book: book_dir.to_string_lossy().to_string(),
         out: bundled_md_path.to_string_lossy().to_string(),
         force: false,
+        chapter_separator: None,
+        front_matter: false,
+        author: None,
+        date: None,
     })
     .context("book bundle")?;
 
@@ -103,6 +192,9 @@ pub async fn run(args: BuildArgs) -> anyhow::Result<()> {
         &crate::epub::CreateEpubOptions {
             force: false,
             lang: crate::epub::guess_lang_tag(&args.language),
+            cover_image: None,
+            nav_depth: None,
+            chapter_page_breaks: false,
         },
     )
     .context("book epub")?;
@@ -3,6 +3,14 @@ use clap::{Args, Parser, Subcommand};
 #[derive(Debug, Parser)]
 #[command(author, version, about)]
 pub struct Cli {
+    /// Log output format (default: `SITEBOOKIFY_LOG_FORMAT`, else `text`).
+    #[arg(long, global = true, value_enum)]
+    pub log_format: Option<LogFormat>,
+
+    /// Hide periodic progress and per-request logs (start/end summaries, warnings and errors stay).
+    #[arg(long, global = true, default_value_t = false)]
+    pub quiet: bool,
+
     #[command(subcommand)]
     pub command: Command,
 }
@@ -10,9 +18,24 @@ pub struct Cli {
 #[derive(Debug, Subcommand)]
 pub enum Command {
     Build(BuildArgs),
-    Crawl(CrawlArgs),
+    #[command(args_conflicts_with_subcommands = true)]
+    Crawl {
+        #[command(subcommand)]
+        command: Option<CrawlCommand>,
+
+        #[command(flatten)]
+        args: Option<CrawlArgs>,
+    },
     Extract(ExtractArgs),
-    Manifest(ManifestArgs),
+    Ingest(IngestArgs),
+    #[command(args_conflicts_with_subcommands = true)]
+    Manifest {
+        #[command(subcommand)]
+        command: Option<ManifestCommand>,
+
+        #[command(flatten)]
+        args: Option<ManifestArgs>,
+    },
     Toc {
         #[command(subcommand)]
         command: TocCommand,
@@ -21,6 +44,23 @@ pub enum Command {
         #[command(subcommand)]
         command: BookCommand,
     },
+    Export {
+        #[command(subcommand)]
+        command: ExportCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CrawlCommand {
+    /// Verify that a Raw snapshot is complete and uncorrupted.
+    Verify(CrawlVerifyArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct CrawlVerifyArgs {
+    /// Raw snapshot directory (created by `crawl`).
+    #[arg(long)]
+    pub raw: String,
 }
 
 #[derive(Debug, Args)]
@@ -33,6 +73,15 @@ pub struct CrawlArgs {
     #[arg(long)]
     pub out: String,
 
+    /// Replace an existing Raw snapshot at `--out` (it must contain `crawl.jsonl`, or be empty).
+    #[arg(long, default_value_t = false)]
+    pub force: bool,
+
+    /// Continue an earlier crawl into the existing `--out`: pages already in its `crawl.jsonl`
+    /// are kept and not fetched again, and links found in them are crawled next.
+    #[arg(long, default_value_t = false, conflicts_with = "force")]
+    pub resume: bool,
+
     /// Maximum pages to retrieve.
     #[arg(long, default_value_t = 200)]
     pub max_pages: usize,
@@ -45,9 +94,78 @@ pub struct CrawlArgs {
     #[arg(long, default_value_t = 4)]
     pub concurrency: usize,
 
+    /// Maximum concurrent HTTP requests to a single host (default: `--concurrency`).
+    #[arg(long, alias = "per-host-concurrency")]
+    pub concurrency_per_host: Option<usize>,
+
     /// Delay before each request (politeness).
     #[arg(long, default_value_t = 200)]
     pub delay_ms: u64,
+
+    /// Fetch exactly these URLs instead of discovering pages by following links (repeatable).
+    #[arg(long = "page-url")]
+    pub page_urls: Vec<String>,
+
+    /// Start from every in-scope URL in the site's `/sitemap.xml` (one level of sitemap index
+    /// is followed) as well as the start page; falls back to link following without a sitemap.
+    #[arg(long, default_value_t = false, conflicts_with = "page_urls")]
+    pub seed_from_sitemap: bool,
+
+    /// Host that serves the same site as another, as `FROM=TO` (e.g.
+    /// `www.example.com=example.com`; repeatable). Links to `FROM` are crawled as `TO`.
+    #[arg(long = "host-alias")]
+    pub host_aliases: Vec<String>,
+
+    /// Treat URLs that differ in their query (`?page=2`, `?page=3`) as separate pages. Tracking
+    /// parameters (`utm_*`, `fbclid`, `gclid`, ...) are still dropped; by default the whole
+    /// query is.
+    #[arg(long, default_value_t = false)]
+    pub keep_query: bool,
+
+    /// Query parameter dropped from page URLs like the built-in tracking ones (repeatable; a
+    /// trailing `*` matches by prefix). Needs `--keep-query`.
+    #[arg(long = "tracking-param", requires = "keep_query")]
+    pub tracking_params: Vec<String>,
+
+    /// Fetch each `--page-url` by running this command (split like a shell would, but not run
+    /// through one) with the URL as its last argument; its stdout is used as the page HTML
+    /// (e.g. a headless browser for JS-rendered sites). Needs `--page-url`.
+    #[arg(long)]
+    pub fetch_command: Option<String>,
+
+    /// Extra file extensions whose links are never followed (comma-separated, e.g. `iso,dmg`),
+    /// on top of the built-in archive/image/media/office list.
+    #[arg(long, value_delimiter = ',')]
+    pub exclude_extensions: Vec<String>,
+
+    /// Maximum redirects to follow per request.
+    #[arg(long, default_value_t = 10)]
+    pub max_redirects: usize,
+
+    /// Skip pages that redirect to a different host.
+    #[arg(long, default_value_t = false)]
+    pub same_host_redirects_only: bool,
+
+    /// Fail the crawl when pages look like a login wall (login redirects or many identical
+    /// short bodies) instead of only warning.
+    #[arg(long, default_value_t = false)]
+    pub fail_on_login_wall: bool,
+
+    /// HTTP(S) or SOCKS5 proxy for all requests (default: `HTTPS_PROXY` / `HTTP_PROXY` from the env).
+    #[arg(long)]
+    pub proxy: Option<String>,
+
+    /// `Accept-Language` sent with every page request (e.g. `ja` or `en-US,en;q=0.8`; default: none).
+    #[arg(long)]
+    pub accept_language: Option<String>,
+
+    /// PEM file with extra root certificates to trust (e.g. a private CA).
+    #[arg(long)]
+    pub ca_cert: Option<String>,
+
+    /// Disable TLS certificate verification (dangerous; logs a warning on every run).
+    #[arg(long, default_value_t = false)]
+    pub insecure: bool,
 }
 
 #[derive(Debug, Args)]
@@ -80,6 +198,15 @@ pub struct BuildArgs {
     #[arg(long, default_value_t = 200)]
     pub delay_ms: u64,
 
+    /// Fetch exactly these URLs instead of discovering pages by following links (repeatable).
+    #[arg(long = "page-url")]
+    pub page_urls: Vec<String>,
+
+    /// Host that serves the same site as another, as `FROM=TO` (repeatable); used by both the
+    /// crawl and the book's link rewriting.
+    #[arg(long = "host-alias")]
+    pub host_aliases: Vec<String>,
+
     /// Language for TOC creation and book rendering.
     ///
     /// Examples: "日本語", "English"
@@ -99,6 +226,85 @@ pub struct BuildArgs {
     /// Book rendering engine (default: openai).
     #[arg(long, value_enum, default_value_t = LlmEngine::Openai)]
     pub render_engine: LlmEngine,
+
+    /// OpenAI model to retry a chunk with when the rewrite drops placeholder tokens.
+    #[arg(long)]
+    pub openai_fallback_model: Option<String>,
+
+    /// Rewrite unit for the openai engine: per heading chunk, or the whole page at once.
+    #[arg(long, value_enum, default_value_t = RewriteGranularity::Section)]
+    pub rewrite_granularity: RewriteGranularity,
+
+    /// Render only this many chapters, picked deterministically by `--sample-seed` (for quick
+    /// dry runs; sampled chapters are marked as such).
+    #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub sample: Option<usize>,
+
+    /// Seed for `--sample`; the same seed picks the same chapters.
+    #[arg(long, default_value_t = 0, requires = "sample")]
+    pub sample_seed: u64,
+
+    /// Table of contents depth for SUMMARY.md and EPUB nav (1 = chapters, 2 = + sections).
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=2))]
+    pub toc_depth: u8,
+
+    /// OpenAI organization id sent as `OpenAI-Organization` (default: `OPENAI_ORG_ID`).
+    #[arg(long)]
+    pub openai_organization: Option<String>,
+
+    /// OpenAI project id sent as `OpenAI-Project` (default: `OPENAI_PROJECT_ID`).
+    #[arg(long)]
+    pub openai_project: Option<String>,
+
+    /// Env var holding the OpenAI API key (default: `SITEBOOKIFY_OPENAI_API_KEY` / `OPENAI_API_KEY`).
+    #[arg(long)]
+    pub openai_api_key_env: Option<String>,
+
+    /// Initial delay (ms) before retrying a transient OpenAI failure; doubles per attempt (default: 1000).
+    #[arg(long)]
+    pub openai_retry_base_ms: Option<u64>,
+
+    /// Longest delay (ms) between OpenAI retries, including `Retry-After` waits (default: 60000).
+    #[arg(long)]
+    pub openai_retry_max_ms: Option<u64>,
+
+    /// Stream rewrite responses, so the request timeout bounds the silence between chunks rather
+    /// than the whole response (openai engine).
+    #[arg(long, default_value_t = false)]
+    pub openai_stream: bool,
+
+    /// HTTP(S) or SOCKS5 proxy for all requests (default: `HTTPS_PROXY` / `HTTP_PROXY` from the env).
+    #[arg(long)]
+    pub proxy: Option<String>,
+
+    /// `Accept-Language` for page and asset requests (default: `--language` when it is a tag like `ja`).
+    #[arg(long)]
+    pub accept_language: Option<String>,
+
+    /// PEM file with extra root certificates to trust (e.g. a private CA).
+    #[arg(long)]
+    pub ca_cert: Option<String>,
+
+    /// Disable TLS certificate verification (dangerous; logs a warning on every run).
+    #[arg(long, default_value_t = false)]
+    pub insecure: bool,
+
+    /// Chapter id scheme: positional (`ch01`) or derived from the title (`ch-getting-started`).
+    #[arg(long, value_enum, default_value_t = ChapterIdStyle::Positional)]
+    pub chapter_ids: ChapterIdStyle,
+
+    /// Chapter grouping for the noop TOC engine: one chapter, or one per first path segment.
+    #[arg(long, value_enum, default_value_t = NoopGrouping::Flat)]
+    pub noop_grouping: NoopGrouping,
+
+    /// Page order the TOC is planned from: manifest path order, crawl discovery order, or
+    /// `--sitemap` order.
+    #[arg(long, value_enum, default_value_t = PageOrder::Toc)]
+    pub page_order: PageOrder,
+
+    /// Local `sitemap.xml` giving the page order for `--page-order sitemap`.
+    #[arg(long, required_if_eq("page_order", "sitemap"))]
+    pub sitemap: Option<String>,
 }
 
 #[derive(Debug, Args)]
@@ -110,6 +316,53 @@ pub struct ExtractArgs {
     /// Output directory for Extracted Pages snapshot.
     #[arg(long)]
     pub out: String,
+
+    /// Replace an existing Extracted Pages snapshot at `--out` (it must contain `pages/`, or be empty).
+    #[arg(long, default_value_t = false)]
+    pub force: bool,
+
+    /// File layout under `pages/`: hash-based names, or mirror the URL path.
+    #[arg(long, value_enum, default_value_t = OutputLayout::Flat)]
+    pub output_layout: OutputLayout,
+
+    /// Skip pages whose extracted Markdown has fewer characters than this (0 = keep all).
+    #[arg(long, default_value_t = 0)]
+    pub min_content_chars: usize,
+
+    /// Prior Extracted Pages snapshot: pages whose Raw HTML is unchanged are copied from it.
+    #[arg(long)]
+    pub if_modified: Option<String>,
+
+    /// Where page titles come from; empty titles fall back to the other sources.
+    #[arg(long, value_enum, default_value_t = TitleSource::Readability)]
+    pub title_source: TitleSource,
+    /// Remove blocks repeated verbatim across pages (sidebars, footer nav) after extraction.
+    #[arg(long)]
+    pub strip_repeated: bool,
+
+    /// With `--strip-repeated`: strip blocks found on more than this percentage of pages.
+    #[arg(
+        long,
+        default_value_t = 80,
+        requires = "strip_repeated",
+        value_parser = clap::builder::RangedU64ValueParser::<u8>::new().range(1..100)
+    )]
+    pub strip_repeated_threshold: u8,
+}
+
+#[derive(Debug, Args)]
+pub struct IngestArgs {
+    /// Directory of local Markdown files (`.md` / `.markdown`).
+    #[arg(long)]
+    pub dir: String,
+
+    /// Output directory for Extracted Pages snapshot.
+    #[arg(long)]
+    pub out: String,
+
+    /// Output file path for `manifest.jsonl`.
+    #[arg(long)]
+    pub manifest: String,
 }
 
 #[derive(Debug, Args)]
@@ -123,9 +376,95 @@ pub struct ManifestArgs {
     pub out: String,
 }
 
+#[derive(Debug, Subcommand)]
+pub enum ManifestCommand {
+    /// Print manifest records for inspection (the JSONL file is not modified).
+    Show(ManifestShowArgs),
+    /// Combine manifests from several runs into one JSONL file.
+    Merge(ManifestMergeArgs),
+    /// Partition a manifest into one JSONL file per key (e.g. per host).
+    Split(ManifestSplitArgs),
+    /// Cross-check a manifest against a TOC before rendering.
+    Validate(ManifestValidateArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ManifestShowArgs {
+    /// Input path to `manifest.jsonl`.
+    #[arg(long)]
+    pub manifest: String,
+
+    /// Output format.
+    #[arg(long, value_enum, default_value_t = ManifestShowFormat::Jsonl)]
+    pub format: ManifestShowFormat,
+
+    /// Pretty-print JSON output.
+    #[arg(long, default_value_t = false, conflicts_with = "compact")]
+    pub pretty: bool,
+
+    /// Print JSON on a single line (default).
+    #[arg(long, default_value_t = false)]
+    pub compact: bool,
+
+    /// Comma-separated fields to keep (e.g. `id,url,title`; default: all, or `id,url,title` for tables).
+    #[arg(long, value_delimiter = ',')]
+    pub fields: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ManifestMergeArgs {
+    /// Input `manifest.jsonl` files, in priority order.
+    #[arg(long, num_args = 1.., required = true)]
+    pub inputs: Vec<String>,
+
+    /// Output file path for the merged `manifest.jsonl`.
+    #[arg(long)]
+    pub out: String,
+
+    /// Also drop records whose canonical URL was already seen under another id.
+    #[arg(long, default_value_t = false)]
+    pub dedupe_by_url: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ManifestSplitArgs {
+    /// Input path to `manifest.jsonl`.
+    #[arg(long)]
+    pub manifest: String,
+
+    /// Record field to partition by.
+    #[arg(long, value_enum, default_value_t = ManifestSplitKey::Host)]
+    pub by: ManifestSplitKey,
+
+    /// Output directory for `manifest.<key>.jsonl` files (created if missing).
+    #[arg(long)]
+    pub out_dir: String,
+}
+
+#[derive(Debug, Args)]
+pub struct ManifestValidateArgs {
+    /// Input path to `manifest.jsonl`.
+    #[arg(long)]
+    pub manifest: String,
+
+    /// Input path to `toc.yaml`.
+    #[arg(long)]
+    pub toc: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ManifestSplitKey {
+    /// Host of the record URL (without the port).
+    #[default]
+    Host,
+}
+
 #[derive(Debug, Subcommand)]
 pub enum TocCommand {
-    Create(TocCreateArgs),
+    Create(Box<TocCreateArgs>),
+    Split(TocSplitArgs),
+    Merge(TocMergeArgs),
+    Check(TocCheckArgs),
 }
 
 #[derive(Debug, Args)]
@@ -157,14 +496,112 @@ pub struct TocCreateArgs {
     /// TOC creation engine (default: openai).
     #[arg(long, value_enum, default_value_t = LlmEngine::Openai)]
     pub engine: LlmEngine,
+
+    /// Chapter id scheme: positional (`ch01`) or derived from the title (`ch-getting-started`).
+    #[arg(long, value_enum, default_value_t = ChapterIdStyle::Positional)]
+    pub chapter_ids: ChapterIdStyle,
+
+    /// Chapter grouping for the noop TOC engine: one chapter, or one per first path segment.
+    #[arg(long, value_enum, default_value_t = NoopGrouping::Flat)]
+    pub noop_grouping: NoopGrouping,
+
+    /// Page order the TOC is planned from: manifest path order, crawl discovery order, or
+    /// `--sitemap` order.
+    #[arg(long, value_enum, default_value_t = PageOrder::Toc)]
+    pub page_order: PageOrder,
+
+    /// Local `sitemap.xml` giving the page order for `--page-order sitemap`.
+    #[arg(long, required_if_eq("page_order", "sitemap"))]
+    pub sitemap: Option<String>,
+
+    /// Directory to write the TOC prompt to (`toc.prompt.txt`) before it is sent.
+    #[arg(long)]
+    pub dump_prompt: Option<String>,
+
+    /// OpenAI organization id sent as `OpenAI-Organization` (default: `OPENAI_ORG_ID`).
+    #[arg(long)]
+    pub openai_organization: Option<String>,
+
+    /// OpenAI project id sent as `OpenAI-Project` (default: `OPENAI_PROJECT_ID`).
+    #[arg(long)]
+    pub openai_project: Option<String>,
+
+    /// Env var holding the OpenAI API key (default: `SITEBOOKIFY_OPENAI_API_KEY` / `OPENAI_API_KEY`).
+    #[arg(long)]
+    pub openai_api_key_env: Option<String>,
+
+    /// Initial delay (ms) before retrying a transient OpenAI failure; doubles per attempt (default: 1000).
+    #[arg(long)]
+    pub openai_retry_base_ms: Option<u64>,
+
+    /// Longest delay (ms) between OpenAI retries, including `Retry-After` waits (default: 60000).
+    #[arg(long)]
+    pub openai_retry_max_ms: Option<u64>,
+
+    /// HTTP(S) or SOCKS5 proxy for all requests (default: `HTTPS_PROXY` / `HTTP_PROXY` from the env).
+    #[arg(long)]
+    pub proxy: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct TocSplitArgs {
+    /// Path to `toc.yaml` (rewritten in place).
+    #[arg(long)]
+    pub toc: String,
+
+    /// Id of the chapter to split.
+    #[arg(long)]
+    pub chapter: String,
+
+    /// Source id that starts the new chapter (it and every later source move).
+    #[arg(long)]
+    pub at: String,
+
+    /// Title of the new chapter (default: `<chapter title> (continued)`).
+    #[arg(long)]
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct TocMergeArgs {
+    /// Path to `toc.yaml` (rewritten in place).
+    #[arg(long)]
+    pub toc: String,
+
+    /// Id of the chapter that receives the sections.
+    #[arg(long)]
+    pub into: String,
+
+    /// Id of the chapter whose sections are appended to `--into` (removed afterwards).
+    #[arg(long)]
+    pub from: String,
+}
+
+#[derive(Debug, Args)]
+pub struct TocCheckArgs {
+    /// Input path to `toc.yaml`.
+    #[arg(long)]
+    pub toc: String,
+
+    /// Input path to `manifest.jsonl`.
+    #[arg(long)]
+    pub manifest: String,
 }
 
 #[derive(Debug, Subcommand)]
 pub enum BookCommand {
     Init(BookInitArgs),
-    Render(BookRenderArgs),
+    Render(Box<BookRenderArgs>),
     Bundle(BookBundleArgs),
     Epub(BookEpubArgs),
+    Stats(BookStatsArgs),
+    Validate(BookValidateArgs),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ExportCommand {
+    /// Render the book into one structured `book.json` instead of mdBook chapter files.
+    Book(Box<BookRenderArgs>),
 }
 
 #[derive(Debug, Args)]
@@ -176,6 +613,14 @@ pub struct BookInitArgs {
     /// Book title (written to `book.toml`).
     #[arg(long)]
     pub title: String,
+
+    /// Markdown template for `chapters/ch01.md` (`{{title}}` is replaced by `--title`).
+    #[arg(long)]
+    pub template: Option<String>,
+
+    /// Markdown template for `SUMMARY.md` (`{{title}}` is replaced by `--title`).
+    #[arg(long)]
+    pub summary_template: Option<String>,
 }
 
 #[derive(Debug, Args)]
@@ -188,7 +633,8 @@ pub struct BookRenderArgs {
     #[arg(long)]
     pub manifest: String,
 
-    /// Output directory for mdBook project (created by `book init`).
+    /// Output directory for mdBook project (created by `book init`); for `export book`, the
+    /// `book.json` path.
     #[arg(long)]
     pub out: String,
 
@@ -203,6 +649,192 @@ pub struct BookRenderArgs {
     /// Book rendering engine (default: openai).
     #[arg(long, value_enum, default_value_t = LlmEngine::Openai)]
     pub engine: LlmEngine,
+
+    /// OpenAI model to retry a chunk with when the rewrite drops placeholder tokens.
+    #[arg(long)]
+    pub openai_fallback_model: Option<String>,
+
+    /// Rewrite unit for the openai engine: per heading chunk, or the whole page at once.
+    #[arg(long, value_enum, default_value_t = RewriteGranularity::Section)]
+    pub rewrite_granularity: RewriteGranularity,
+
+    /// Split rewrite units longer than this many characters at paragraph boundaries (openai engine).
+    #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub max_section_chars: Option<usize>,
+
+    /// Show the last N characters of the previous chunk of the same page to the model as
+    /// read-only context (openai engine; 0 disables).
+    #[arg(long, default_value_t = 0)]
+    pub openai_chunk_overlap_chars: usize,
+
+    /// YAML list of `{input, output}` Markdown pairs shown to the model as tone examples (openai engine).
+    #[arg(long)]
+    pub tone_example_file: Option<String>,
+
+    /// File replacing the built-in rewrite instructions (openai engine); must contain `{token_rules}`.
+    #[arg(long)]
+    pub instructions_template: Option<String>,
+
+    /// Directory to write each rewrite prompt to (`<chapter>-sNN-uNNN.prompt.txt`) before it is sent.
+    #[arg(long)]
+    pub dump_prompt: Option<String>,
+
+    /// Render only this many chapters, picked deterministically by `--sample-seed` (for quick
+    /// dry runs; sampled chapters are marked as such).
+    #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub sample: Option<usize>,
+
+    /// Seed for `--sample`; the same seed picks the same chapters.
+    #[arg(long, default_value_t = 0, requires = "sample")]
+    pub sample_seed: u64,
+
+    /// Table of contents depth for SUMMARY.md and EPUB nav (1 = chapters, 2 = + sections).
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=2))]
+    pub toc_depth: u8,
+
+    /// Emit an introduction chapter (`ch00.md`) built from each chapter's intent and reader gains.
+    #[arg(long, default_value_t = false)]
+    pub intro: bool,
+
+    /// Heading for the introduction chapter.
+    #[arg(long, default_value = "Introduction", requires = "intro")]
+    pub toc_title: String,
+
+    /// End each chapter with a list of its `reader_gains` from `toc.yaml`.
+    #[arg(long, default_value_t = false)]
+    pub chapter_summaries: bool,
+
+    /// Heading for the chapter summary list.
+    #[arg(
+        long,
+        default_value = "What you learned",
+        requires = "chapter_summaries"
+    )]
+    pub chapter_summary_title: String,
+
+    /// Chapter title template applied to SUMMARY.md, chapter headings and the EPUB nav, with
+    /// `{index}` (1-based; `{index:02}` zero-pads), `{id}` and `{title}` (e.g. `"{index:02}. {title}"`).
+    #[arg(long)]
+    pub chapter_title_format: Option<String>,
+
+    /// Render every TOC section as its own chapter file (`<chapter id>-sNN.md`, section title as
+    /// the `#` heading) instead of one file per chapter.
+    #[arg(long, default_value_t = false)]
+    pub sections_as_chapters: bool,
+
+    /// Write `redirects.json` (source URL -> chapter and anchor) next to `book.toml`.
+    #[arg(long, default_value_t = false)]
+    pub emit_redirects: bool,
+
+    /// How source-page anchors are emitted: hidden HTML spans, heading ids, or not at all.
+    #[arg(long, value_enum, default_value_t = AnchorStyle::Html)]
+    pub anchor_style: AnchorStyle,
+
+    /// Chapters rendered in parallel (default: available CPUs). The output does not depend on it.
+    #[arg(
+        long,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub concurrency: Option<usize>,
+
+    /// Re-render every chapter, even those whose `.render-hash` sidecar matches their inputs.
+    #[arg(long, default_value_t = false)]
+    pub force_rerender: bool,
+
+    /// File names for downloaded images: content-addressed hashes or readable slugs.
+    #[arg(long, value_enum, default_value_t = AssetNaming::Hash)]
+    pub asset_naming: AssetNaming,
+
+    /// Drop images served by well-known badge and tracking hosts (shields.io, badge.fury.io, ...).
+    #[arg(long, default_value_t = false)]
+    pub strip_badges: bool,
+
+    /// Drop images from this host or its subdomains (repeatable; applies with or without
+    /// `--strip-badges`).
+    #[arg(long = "skip-image-host", value_name = "HOST")]
+    pub skip_image_hosts: Vec<String>,
+
+    /// Store byte-identical images from different URLs once and point every reference at it.
+    #[arg(long, default_value_t = false)]
+    pub dedupe_assets_by_content: bool,
+
+    /// Retries per image after a failed or transient (5xx/429/408) download.
+    #[arg(long, default_value_t = 2)]
+    pub asset_retries: u32,
+
+    /// Largest image body to accept, in bytes; bigger downloads count as failures.
+    #[arg(
+        long,
+        default_value_t = 20 * 1024 * 1024,
+        value_parser = clap::builder::RangedU64ValueParser::<u64>::new().range(1..)
+    )]
+    pub max_asset_bytes: u64,
+
+    /// What to do with an image that still fails after the retries.
+    #[arg(long, value_enum, default_value_t = AssetErrorPolicy::KeepUrl)]
+    pub on_asset_error: AssetErrorPolicy,
+
+    /// What to do with links that do not point to a page in the book.
+    #[arg(long, value_enum, default_value_t = ExternalLinkPolicy::Keep)]
+    pub external_link_policy: ExternalLinkPolicy,
+
+    /// Host that serves the same site as another, as `FROM=TO` (repeatable); links to either
+    /// host resolve to the manifest page.
+    #[arg(long = "host-alias")]
+    pub host_aliases: Vec<String>,
+
+    /// Append a collapsible verbatim excerpt of each source under `## Sources`.
+    #[arg(long, default_value_t = false)]
+    pub include_excerpts: bool,
+
+    /// Maximum characters per source excerpt.
+    #[arg(long, default_value_t = 300, requires = "include_excerpts")]
+    pub excerpt_chars: usize,
+
+    /// Keep chapter whitespace as generated (skip blank-line collapsing and trailing-space trimming).
+    #[arg(long, default_value_t = false)]
+    pub no_normalize: bool,
+
+    /// OpenAI organization id sent as `OpenAI-Organization` (default: `OPENAI_ORG_ID`).
+    #[arg(long)]
+    pub openai_organization: Option<String>,
+
+    /// OpenAI project id sent as `OpenAI-Project` (default: `OPENAI_PROJECT_ID`).
+    #[arg(long)]
+    pub openai_project: Option<String>,
+
+    /// Env var holding the OpenAI API key (default: `SITEBOOKIFY_OPENAI_API_KEY` / `OPENAI_API_KEY`).
+    #[arg(long)]
+    pub openai_api_key_env: Option<String>,
+
+    /// Initial delay (ms) before retrying a transient OpenAI failure; doubles per attempt (default: 1000).
+    #[arg(long)]
+    pub openai_retry_base_ms: Option<u64>,
+
+    /// Longest delay (ms) between OpenAI retries, including `Retry-After` waits (default: 60000).
+    #[arg(long)]
+    pub openai_retry_max_ms: Option<u64>,
+
+    /// Stream rewrite responses, so the request timeout bounds the silence between chunks rather
+    /// than the whole response (openai engine).
+    #[arg(long, default_value_t = false)]
+    pub openai_stream: bool,
+
+    /// HTTP(S) or SOCKS5 proxy for all requests (default: `HTTPS_PROXY` / `HTTP_PROXY` from the env).
+    #[arg(long)]
+    pub proxy: Option<String>,
+
+    /// `Accept-Language` sent with asset downloads (default: none).
+    #[arg(long)]
+    pub accept_language: Option<String>,
+
+    /// PEM file with extra root certificates to trust (e.g. a private CA).
+    #[arg(long)]
+    pub ca_cert: Option<String>,
+
+    /// Disable TLS certificate verification (dangerous; logs a warning on every run).
+    #[arg(long, default_value_t = false)]
+    pub insecure: bool,
 }
 
 #[derive(Debug, Args)]
@@ -218,6 +850,22 @@ pub struct BookBundleArgs {
     /// Overwrite output file if it already exists.
     #[arg(long, default_value_t = false)]
     pub force: bool,
+
+    /// Separator written between chapters (e.g. `---` or `<!-- pagebreak -->`; `\n` escapes allowed).
+    #[arg(long)]
+    pub chapter_separator: Option<String>,
+
+    /// Prepend a YAML front matter block (title/author/date) for static-site generators.
+    #[arg(long, default_value_t = false)]
+    pub front_matter: bool,
+
+    /// Author for the front matter (default: `authors` from book.toml).
+    #[arg(long, requires = "front_matter")]
+    pub author: Option<String>,
+
+    /// Date for the front matter (default: today, `YYYY-MM-DD` in UTC).
+    #[arg(long, requires = "front_matter")]
+    pub date: Option<String>,
 }
 
 #[derive(Debug, Args)]
@@ -237,6 +885,41 @@ pub struct BookEpubArgs {
     /// Language tag (BCP-47) for EPUB metadata.
     #[arg(long, default_value = "und")]
     pub lang: String,
+
+    /// Cover image (png, jpg, gif, svg, webp or avif), shown first and as the reader thumbnail.
+    #[arg(long)]
+    pub cover: Option<String>,
+
+    /// Table of contents depth: `1` lists chapters only, `2` adds each chapter's `##` headings
+    /// (default: the section entries in SUMMARY.md).
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=2))]
+    pub nav_depth: Option<u8>,
+
+    /// Start every chapter on a new page (CSS `page-break-before` on the chapter heading).
+    #[arg(long, default_value_t = false)]
+    pub chapter_page_breaks: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct BookStatsArgs {
+    /// Input directory for mdBook project (created by `book init` and `book render`).
+    #[arg(long)]
+    pub book: String,
+
+    /// Report format.
+    #[arg(long, value_enum, default_value_t = StatsFormat::Text)]
+    pub format: StatsFormat,
+}
+
+#[derive(Debug, Args)]
+pub struct BookValidateArgs {
+    /// Input directory for mdBook project (created by `book init` and `book render`).
+    #[arg(long)]
+    pub book: String,
+
+    /// Language tag (BCP-47) for the in-memory EPUB build.
+    #[arg(long, default_value = "und")]
+    pub lang: String,
 }
 
 #[derive(Debug, Clone, Copy, clap::ValueEnum, serde::Deserialize, serde::Serialize)]
@@ -248,3 +931,145 @@ pub enum LlmEngine {
     /// Use OpenAI via Responses API.
     Openai,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputLayout {
+    /// `pages/<id>.md` (hash-based).
+    Flat,
+
+    /// `pages/<url path>.md` (falls back to a hash suffix on collisions).
+    Nested,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    serde::Deserialize,
+    serde::Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TitleSource {
+    /// The title picked by readability (sometimes the site name).
+    #[default]
+    Readability,
+    /// The HTML `<title>` element.
+    HtmlTitle,
+    /// The first `<h1>` heading.
+    H1,
+    /// The `og:title` meta tag.
+    Og,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum RewriteGranularity {
+    /// Rewrite each heading chunk of a page independently.
+    #[default]
+    Section,
+
+    /// Rewrite each page in a single call (keeps cross-section flow).
+    Page,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum NoopGrouping {
+    /// Every page is a section of a single chapter.
+    #[default]
+    Flat,
+    /// One chapter per first URL path segment below the pages' common prefix.
+    ByPathSegment,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum PageOrder {
+    /// Manifest path order (natural sort), as before.
+    #[default]
+    Toc,
+    /// Crawl discovery order (`discovery_index`); pages without one follow in path order.
+    Crawl,
+    /// Position in `--sitemap`; pages not listed there follow in path order.
+    Sitemap,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ChapterIdStyle {
+    /// `ch01`, `ch02`, ... in TOC order.
+    #[default]
+    Positional,
+    /// `ch-<title-slug>`, stable across re-planning as long as titles do not change.
+    Slug,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum AnchorStyle {
+    /// Hidden `<span id="p_...">` per source page.
+    #[default]
+    Html,
+    /// `## Section {#chNN-sNN}` heading ids (pure Markdown); links target the section.
+    Heading,
+    /// No anchors; links point to the chapter.
+    None,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum AssetNaming {
+    /// `img_<sha256>.<ext>` from the asset URL.
+    #[default]
+    Hash,
+    /// Sanitized source basename plus a short hash, e.g. `logo-1a2b3c4d.png`.
+    Slug,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum AssetErrorPolicy {
+    /// Leave the remote image URL in the chapter.
+    #[default]
+    KeepUrl,
+    /// Point the image at a bundled placeholder (`assets/placeholder.svg`).
+    Placeholder,
+    /// Abort the render.
+    Fail,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExternalLinkPolicy {
+    /// Keep the link, resolved to an absolute URL.
+    #[default]
+    Keep,
+    /// Drop the link and keep only its text.
+    Strip,
+    /// Replace the link with its text plus a footnote carrying the URL.
+    Footnote,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum StatsFormat {
+    /// Aligned table for terminals.
+    #[default]
+    Text,
+    /// A single JSON document.
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ManifestShowFormat {
+    /// One record per line (or per block with `--pretty`).
+    #[default]
+    Jsonl,
+    /// A single JSON array.
+    Json,
+    /// Tab-separated columns with a header row.
+    Table,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines.
+    Text,
+    /// One JSON object per event.
+    Json,
+}
@@ -1,16 +1,18 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use std::fs::OpenOptions;
 use std::io::{BufWriter, Write as _};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::Context as _;
-use reqwest::header::{ACCEPT, USER_AGENT};
+use reqwest::header::{ACCEPT, ACCEPT_LANGUAGE, HeaderMap, HeaderValue, USER_AGENT};
 use url::Url;
 
 use crate::cli::CrawlArgs;
 use crate::formats::CrawlRecord;
+use crate::tls::TlsOptions;
+use crate::url_norm::{HostAliases, QueryPolicy};
 
 #[derive(Debug, Clone)]
 struct CrawlScope {
@@ -61,14 +63,45 @@ impl CrawlScope {
     }
 }
 
-pub async fn resolve_start_url_for_crawl(url: &Url) -> Url {
-    let url = normalize_crawl_url(url);
+/// Default headers carrying `--accept-language` (empty when unset).
+pub fn accept_language_headers(accept_language: Option<&str>) -> anyhow::Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    if let Some(raw) = accept_language.map(str::trim).filter(|raw| !raw.is_empty()) {
+        let value = HeaderValue::from_str(raw)
+            .with_context(|| format!("invalid accept-language: {raw:?}"))?;
+        headers.insert(ACCEPT_LANGUAGE, value);
+    }
+    Ok(headers)
+}
+
+/// `language` itself when it already looks like a language tag (`ja`, `en-US`), so
+/// `build --language ja` can ask sites for Japanese pages; names like `日本語` give `None`.
+pub fn language_tag(language: &str) -> Option<String> {
+    let language = language.trim();
+    let mut subtags = language.split('-');
+    let primary = subtags.next()?;
+    let is_tag = (2..=3).contains(&primary.len())
+        && primary.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        });
+    is_tag.then(|| language.to_owned())
+}
+
+pub async fn resolve_start_url_for_crawl(
+    url: &Url,
+    proxy: Option<&str>,
+    tls: &TlsOptions,
+    accept_language: Option<&str>,
+    query_policy: &QueryPolicy,
+) -> Url {
+    let url = query_policy.strip(url);
     if !should_try_trailing_slash(&url) {
         return url;
     }
 
     let with_slash = url_with_trailing_slash(&url);
-    match probe_html_url(&with_slash).await {
+    match probe_html_url(&with_slash, proxy, tls, accept_language, query_policy).await {
         Ok(Some(resolved)) => resolved,
         Ok(None) => url,
         Err(err) => {
@@ -79,28 +112,133 @@ pub async fn resolve_start_url_for_crawl(url: &Url) -> Url {
 }
 
 pub async fn run(args: CrawlArgs) -> anyhow::Result<()> {
+    if let Some(command) = args.fetch_command.as_deref() {
+        if args.page_urls.is_empty() {
+            anyhow::bail!(
+                "--fetch-command needs --page-url: link crawling always fetches with the built-in \
+HTTP client, so list the pages to fetch with --page-url"
+            );
+        }
+        fetch_command_argv(command)?;
+    }
     let out_dir = PathBuf::from(&args.out);
-    crate::raw_store::ensure_raw_snapshot_dir_does_not_exist(&out_dir)
-        .context("check raw snapshot output directory")?;
-    std::fs::create_dir_all(&out_dir)
-        .with_context(|| format!("create raw snapshot dir: {}", out_dir.display()))?;
+    let crawl_jsonl_path = out_dir.join("crawl.jsonl");
+    // `--resume` into a missing `--out` is just a fresh crawl.
+    let resumed_records = if args.resume && out_dir.exists() {
+        Some(read_records_for_resume(&crawl_jsonl_path).context("read crawl log for --resume")?)
+    } else {
+        if args.force {
+            crate::raw_store::remove_snapshot_dir_for_force(&out_dir, "crawl.jsonl")
+                .context("replace raw snapshot output directory")?;
+        }
+        crate::raw_store::ensure_raw_snapshot_dir_does_not_exist(&out_dir)
+            .context("check raw snapshot output directory")?;
+        std::fs::create_dir_all(&out_dir)
+            .with_context(|| format!("create raw snapshot dir: {}", out_dir.display()))?;
+        None
+    };
 
-    let start_url = Url::parse(&args.url).context("parse --url")?;
+    let host_aliases = HostAliases::parse(&args.host_aliases).context("parse --host-alias")?;
+    let query_policy = QueryPolicy {
+        keep_query: args.keep_query,
+        extra_tracking_params: args.tracking_params.clone(),
+    };
+    let start_url = host_aliases.canonicalize(&Url::parse(&args.url).context("parse --url")?);
     if start_url.scheme() != "http" && start_url.scheme() != "https" {
         anyhow::bail!("--url must be http/https: {start_url}");
     }
-    let start_url = resolve_start_url_for_crawl(&start_url).await;
-    let start_url_canonical = canonical_url(&start_url);
-
-    let scope = CrawlScope::new(&start_url_canonical).context("build crawl scope")?;
+    if let Some(proxy) = args.proxy.as_deref() {
+        crate::proxy::parse(proxy).context("validate --proxy")?;
+    }
+    let tls = TlsOptions::from_args(args.ca_cert.as_deref(), args.insecure)?;
+    let accept_language = accept_language_headers(args.accept_language.as_deref())
+        .context("validate --accept-language")?;
 
-    let crawl_jsonl_path = out_dir.join("crawl.jsonl");
-    let crawl_jsonl_file = OpenOptions::new()
-        .create_new(true)
-        .write(true)
-        .open(&crawl_jsonl_path)
-        .with_context(|| format!("create crawl log: {}", crawl_jsonl_path.display()))?;
+    let crawl_jsonl_file = match resumed_records {
+        Some(_) => OpenOptions::new().append(true).open(&crawl_jsonl_path),
+        None => OpenOptions::new()
+            .create_new(true)
+            .write(true)
+            .open(&crawl_jsonl_path),
+    }
+    .with_context(|| format!("open crawl log: {}", crawl_jsonl_path.display()))?;
     let mut crawl_jsonl = BufWriter::new(crawl_jsonl_file);
+    let resumed_urls = resumed_records
+        .iter()
+        .flatten()
+        .flat_map(|record| std::iter::once(&record.normalized_url).chain(&record.redirected_from))
+        .cloned()
+        .collect::<BTreeSet<_>>();
+
+    if !args.page_urls.is_empty() {
+        let page_urls = canonical_page_list(&args.page_urls, &query_policy)
+            .context("validate --page-url")?
+            .iter()
+            .map(|url| host_aliases.canonicalize(url))
+            .filter(|url| !resumed_urls.contains(url.as_str()))
+            .collect::<Vec<_>>();
+        // Aliased hosts may fold two entries together; keep the first.
+        let mut seen = HashSet::new();
+        let page_urls = page_urls
+            .into_iter()
+            .filter(|url| seen.insert(url.clone()))
+            .collect::<Vec<_>>();
+        if page_urls.is_empty() {
+            tracing::info!("crawl: every --page-url is already in the resumed snapshot");
+            return Ok(());
+        }
+        fetch_page_list(
+            &args,
+            &tls,
+            &host_aliases,
+            accept_language,
+            &page_urls,
+            &resumed_urls,
+            &mut crawl_jsonl,
+        )
+        .await?;
+        crawl_jsonl.flush().context("flush crawl log")?;
+        return Ok(());
+    }
+
+    let start_url = resolve_start_url_for_crawl(
+        &start_url,
+        args.proxy.as_deref(),
+        &tls,
+        args.accept_language.as_deref(),
+        &query_policy,
+    )
+    .await;
+    // Link crawling never leaves this host, so checking it covers every spider request.
+    crate::net_guard::check_ip_literal(&start_url)?;
+    let start_url_canonical = query_policy.canonical_url(&start_url);
+
+    let scope = CrawlScope::new(&start_url_canonical).context("build crawl scope")?;
+    // A resumed snapshot without records (the earlier run stopped before writing any) is
+    // crawled from the start page like a fresh one.
+    let resume = resumed_records
+        .filter(|records| !records.is_empty())
+        .map(|records| {
+            ResumeState::new(
+                &records,
+                &out_dir,
+                &start_url,
+                &scope,
+                &host_aliases,
+                &query_policy,
+            )
+        });
+    let max_pages = match &resume {
+        Some(resume) => args.max_pages.saturating_sub(resume.pages),
+        None => args.max_pages,
+    };
+    if max_pages == 0 {
+        tracing::info!(
+            max_pages = args.max_pages,
+            "crawl: the resumed snapshot already holds --max-pages pages"
+        );
+        return Ok(());
+    }
 
     let mut website = spider::website::Website::new(start_url.as_str());
     website.configuration.respect_robots_txt = false;
@@ -109,12 +247,62 @@ pub async fn run(args: CrawlArgs) -> anyhow::Result<()> {
     website.with_block_assets(true);
     website.with_return_page_links(true);
     website.with_delay(args.delay_ms);
-    website.with_concurrency_limit(Some(args.concurrency.max(1)));
-    website.with_limit(args.max_pages.min(u32::MAX as usize) as u32);
+    // Discovery never leaves the start origin (alias hosts are fetched as the start host), so
+    // spider's global limit and delay are per-host already; `HostRateLimiter` is only needed
+    // for a `--page-url` list spanning several hosts.
+    website.with_concurrency_limit(Some(
+        args.concurrency.max(1).min(concurrency_per_host(&args)),
+    ));
+    website.with_limit(max_pages.min(u32::MAX as usize) as u32);
     website.with_depth(args.max_depth as usize);
-    website.with_whitelist_url(Some(vec![build_whitelist_regex(&scope).into()]));
+    // spider drops links to other hosts before our link callback sees them; let it keep the
+    // aliased ones so the callback can move them onto the start host.
+    if !host_aliases.is_empty() {
+        website.with_external_domains(Some(
+            host_aliases.aliases_of(&scope.host).map(str::to_owned),
+        ));
+    }
+    website.with_whitelist_url(Some(vec![
+        build_whitelist_regex(&scope, &host_aliases).into(),
+    ]));
+    let mut blacklist = vec![build_extension_blacklist_regex(&args.exclude_extensions).into()];
+    if let Some(resume) = &resume {
+        blacklist.extend(
+            build_resumed_url_regexes(&resume.visited, &host_aliases, query_policy.keep_query)
+                .into_iter()
+                .map(Into::into),
+        );
+    }
+    website.with_blacklist_url(Some(blacklist));
+    website.with_redirect_limit(args.max_redirects);
+    if args.same_host_redirects_only {
+        website.with_redirect_policy(spider::configuration::RedirectPolicy::Strict);
+    }
+    if let Some(proxy) = args.proxy.as_deref() {
+        website.with_proxies(Some(vec![proxy.trim().to_owned()]));
+    }
+    website.with_danger_accept_invalid_certs(tls.insecure);
+    if !accept_language.is_empty() {
+        website.with_headers(Some(accept_language));
+    }
+    if !tls.ca_certs.is_empty() || crate::net_guard::is_enforced() {
+        // spider has no root-certificate or resolver option: build its client ourselves with
+        // the extra roots and the address guard added (headers first, as spider's own setup
+        // would).
+        website.configure_headers();
+        let mut builder = tls.apply(website.configure_http_client_builder());
+        if crate::net_guard::is_enforced() {
+            builder = crate::net_guard::apply(builder).redirect(crate::net_guard::redirect_policy(
+                args.max_redirects,
+                args.same_host_redirects_only,
+            ));
+        }
+        website.set_http_client(builder.build().context("build crawl http client")?);
+    }
 
     let link_scope = scope.clone();
+    let link_aliases = host_aliases.clone();
+    let link_query_policy = query_policy.clone();
     website.on_link_find_callback = Some(Arc::new(move |url_ci, html| {
         let url_str = url_ci.to_string();
         let Ok(parsed) = Url::parse(&url_str) else {
@@ -124,8 +312,9 @@ pub async fn run(args: CrawlArgs) -> anyhow::Result<()> {
             return (url_ci, html);
         }
 
-        let normalized = normalize_crawl_url(&parsed);
-        let canonical = canonical_url(&normalized);
+        let parsed = unescape_link_query(&parsed);
+        let normalized = link_query_policy.strip(&link_aliases.canonicalize(&parsed));
+        let canonical = link_query_policy.canonical_url(&normalized);
         if !link_scope.is_in_scope(&canonical) {
             return (url_ci, html);
         }
@@ -134,8 +323,72 @@ pub async fn run(args: CrawlArgs) -> anyhow::Result<()> {
         (spider::CaseInsensitiveString::new(&normalized_str), html)
     }));
 
+    // Pages to queue besides (or, when resuming, instead of) the start page.
+    let mut extra_links: Vec<String> = Vec::new();
+    // `None` unless `--seed-from-sitemap`; then the seed count, or why the sitemap was unusable.
+    let sitemap_seeding = if args.seed_from_sitemap {
+        let seeds = sitemap_seed_urls(
+            &args,
+            &tls,
+            &start_url_canonical,
+            &scope,
+            &host_aliases,
+            &query_policy,
+        )
+        .await;
+        Some(match seeds {
+            Ok(seeds) if !seeds.is_empty() => {
+                tracing::info!(urls = seeds.len(), "crawl: seeding from sitemap");
+                extra_links.extend(seeds.iter().map(Url::to_string));
+                Ok(seeds.len())
+            }
+            Ok(_) => Err("it lists no pages in crawl scope".to_owned()),
+            Err(err) => Err(format!("{err:#}")),
+        })
+    } else {
+        None
+    };
+    match &resume {
+        Some(resume) => {
+            extra_links.extend(resume.frontier.iter().cloned());
+            extra_links.retain(|link| {
+                Url::parse(link).is_ok_and(|url| {
+                    !resume
+                        .visited
+                        .contains(query_policy.canonical_url(&url).as_str())
+                })
+            });
+            if extra_links.is_empty() {
+                tracing::info!(
+                    pages = resume.pages,
+                    "crawl: the resumed snapshot links to no unfetched page in scope"
+                );
+                return Ok(());
+            }
+            tracing::info!(
+                pages = resume.pages,
+                frontier = extra_links.len(),
+                "crawl: resuming"
+            );
+        }
+        None if !extra_links.is_empty() => extra_links.insert(0, start_url.to_string()),
+        None => {}
+    }
+    if !extra_links.is_empty() {
+        // spider only keeps extra links on a "persisted" crawl, which then skips its own fetch
+        // of the start page; a fresh crawl queues the start page with the seeds instead.
+        website.set_extra_links(
+            extra_links
+                .iter()
+                .map(|link| spider::CaseInsensitiveString::new(link.as_str()))
+                .collect(),
+        );
+        website.persist_links();
+    }
+
     website.scrape().await;
 
+    let mut login_wall = LoginWallCheck::default();
     let pages = website
         .get_pages()
         .cloned()
@@ -145,16 +398,53 @@ pub async fn run(args: CrawlArgs) -> anyhow::Result<()> {
             let Ok(url) = Url::parse(page.get_url()) else {
                 return None;
             };
-            let normalized = normalize_crawl_url(&url);
-            let canonical = canonical_url(&normalized);
-            if !scope.is_in_scope(&canonical) {
+            let requested = query_policy.canonical_url(&host_aliases.canonicalize(&url));
+            if !scope.is_in_scope(&requested) {
+                return None;
+            }
+            let final_url = Url::parse(page.get_url_final())
+                .map(|url| query_policy.canonical_url(&host_aliases.canonicalize(&url)))
+                .unwrap_or_else(|_| requested.clone());
+            login_wall.observe_redirect(&requested, &final_url);
+            let key = redirect_target(
+                &scope,
+                &requested,
+                &final_url,
+                args.same_host_redirects_only,
+            )?;
+            // Already recorded (e.g. reached through an alias host or a redirect).
+            if resume
+                .as_ref()
+                .is_some_and(|resume| resume.visited.contains(key.as_str()))
+            {
                 return None;
             }
-            Some((canonical.to_string(), page))
+            Some((key.to_string(), requested.to_string(), page))
         })
         .collect::<Vec<_>>();
+    // spider keeps pages in the order it fetched them; a redirect target takes the earliest
+    // position of the URLs that landed on it. Resumed pages continue after the earlier ones.
+    let first_discovery_index = resume
+        .as_ref()
+        .map_or(0, |resume| resume.next_discovery_index);
+    let mut discovery_index: HashMap<String, u64> = HashMap::new();
+    for (key, _, _) in &pages {
+        let position = first_discovery_index + discovery_index.len() as u64;
+        discovery_index.entry(key.clone()).or_insert(position);
+    }
+    let (pages, aliases) = dedupe_redirected_pages(pages);
 
-    let (edges, page_by_url) = build_page_graph(&scope, pages);
+    let (mut edges, page_by_url) = build_page_graph(&scope, pages, &aliases, &query_policy);
+    // Depths stay measured from the start page, through the pages of the earlier run.
+    if let Some(resume) = &resume {
+        // spider does not report `page_links` for every page; read those from the HTML.
+        for (url, links) in edges.iter_mut().filter(|(_, links)| links.is_empty()) {
+            if let (Some(page), Ok(page_url)) = (page_by_url.get(url), Url::parse(url)) {
+                *links = resume.page_links(&page.get_html(), &page_url);
+            }
+        }
+        edges.extend(resume.edges.clone());
+    }
     let depths = compute_depths(start_url_canonical.as_str(), &edges, args.max_depth);
 
     let mut urls = page_by_url.keys().cloned().collect::<Vec<_>>();
@@ -178,16 +468,27 @@ pub async fn run(args: CrawlArgs) -> anyhow::Result<()> {
             content_type: None,
             retrieved_at,
             raw_html_path: None,
+            raw_html_sha256: None,
+            redirected_from: redirected_from_for(&aliases, &normalized_url_str),
+            discovery_index: discovery_index.get(&normalized_url_str).copied(),
         };
 
         if (200..300).contains(&status) {
             let html = page.get_html();
             if should_save_html(&html) {
+                login_wall.observe_body(&html);
                 let raw_html_path = crate::raw_store::raw_html_path(&out_dir, &normalized_url)
                     .context("compute raw html path")?;
+                // Left without a record by an interrupted run; this fetch replaces it.
+                if resume.is_some() && raw_html_path.exists() {
+                    std::fs::remove_file(&raw_html_path).with_context(|| {
+                        format!("remove stale raw html: {}", raw_html_path.display())
+                    })?;
+                }
                 crate::raw_store::write_raw_html(&raw_html_path, &html)
                     .context("write raw html")?;
                 record.raw_html_path = Some(raw_html_path.to_string_lossy().to_string());
+                record.raw_html_sha256 = Some(crate::raw_store::sha256_hex(html.as_bytes()));
             }
         }
 
@@ -198,26 +499,757 @@ pub async fn run(args: CrawlArgs) -> anyhow::Result<()> {
     }
 
     crawl_jsonl.flush().context("flush crawl log")?;
-    Ok(())
+    match sitemap_seeding {
+        Some(Ok(seeded)) => {
+            tracing::info!(
+                pages = page_by_url.len(),
+                seeded,
+                "crawl: done (seeded from sitemap)"
+            );
+        }
+        Some(Err(reason)) => tracing::warn!(
+            pages = page_by_url.len(),
+            "crawl: done; --seed-from-sitemap found no usable sitemap ({reason}), so pages were discovered by following links only"
+        ),
+        None => {}
+    }
+    login_wall.report(args.fail_on_login_wall)
+}
+
+/// Records of the snapshot `--resume` continues. A last line cut short by an interrupted run
+/// is dropped (and truncated away, so appended records start on a fresh line).
+fn read_records_for_resume(crawl_jsonl_path: &Path) -> anyhow::Result<Vec<CrawlRecord>> {
+    let text = std::fs::read_to_string(crawl_jsonl_path)
+        .with_context(|| format!("read crawl log: {}", crawl_jsonl_path.display()))?;
+    let complete_len = if text.ends_with('\n') {
+        text.len()
+    } else {
+        text.rfind('\n').map_or(0, |newline| newline + 1)
+    };
+    if complete_len < text.len() {
+        tracing::warn!(
+            crawl_log = %crawl_jsonl_path.display(),
+            "crawl: dropping an incomplete last record before resuming"
+        );
+        OpenOptions::new()
+            .write(true)
+            .open(crawl_jsonl_path)
+            .and_then(|file| file.set_len(complete_len as u64))
+            .with_context(|| format!("truncate crawl log: {}", crawl_jsonl_path.display()))?;
+    }
+    text[..complete_len]
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| {
+            serde_json::from_str::<CrawlRecord>(line)
+                .with_context(|| format!("parse crawl record at line {}", idx + 1))
+        })
+        .collect()
+}
+
+/// What an earlier run into the same `--out` already crawled.
+struct ResumeState {
+    /// Records in the snapshot.
+    pages: usize,
+    /// Canonical URLs with a record, and the URLs that redirected to them.
+    visited: BTreeSet<String>,
+    /// In-scope links in the saved pages that have no record yet.
+    frontier: BTreeSet<String>,
+    /// Links of the saved pages, keyed like `build_page_graph`'s edges.
+    edges: HashMap<String, Vec<String>>,
+    next_discovery_index: u64,
+    start_url: Url,
+    scope: CrawlScope,
+    host_aliases: HostAliases,
+    query_policy: QueryPolicy,
+    /// Recorded `redirected_from` URL -> the record's URL.
+    redirects: HashMap<String, String>,
+}
+
+impl ResumeState {
+    /// Reads links back from the saved HTML instead of fetching the pages again.
+    fn new(
+        records: &[CrawlRecord],
+        out_dir: &Path,
+        start_url: &Url,
+        scope: &CrawlScope,
+        host_aliases: &HostAliases,
+        query_policy: &QueryPolicy,
+    ) -> Self {
+        let mut state = Self {
+            pages: records.len(),
+            visited: BTreeSet::new(),
+            frontier: BTreeSet::new(),
+            edges: HashMap::new(),
+            next_discovery_index: records
+                .iter()
+                .filter_map(|record| record.discovery_index)
+                .max()
+                .map_or(0, |max| max + 1),
+            start_url: start_url.clone(),
+            scope: scope.clone(),
+            host_aliases: host_aliases.clone(),
+            query_policy: query_policy.clone(),
+            redirects: HashMap::new(),
+        };
+        for record in records {
+            state.visited.insert(record.normalized_url.clone());
+            for from in &record.redirected_from {
+                state.visited.insert(from.clone());
+                state
+                    .redirects
+                    .insert(from.clone(), record.normalized_url.clone());
+            }
+        }
+
+        for record in records {
+            let Ok(page_url) = Url::parse(&record.normalized_url) else {
+                continue;
+            };
+            let saved_path = crate::raw_store::raw_html_path(out_dir, &page_url).ok();
+            let Some(html) = saved_path
+                .filter(|path| record.raw_html_path.is_some() && path.is_file())
+                .and_then(|path| std::fs::read_to_string(path).ok())
+            else {
+                continue;
+            };
+            let links = state.page_links(&html, &page_url);
+            let unfetched = links
+                .iter()
+                .filter(|link| !state.visited.contains(*link))
+                .cloned()
+                .collect::<Vec<_>>();
+            state.frontier.extend(unfetched);
+            state.edges.insert(record.normalized_url.clone(), links);
+        }
+        state
+    }
+
+    /// In-scope links of the page at canonical `page_url`, keyed like `build_page_graph`'s
+    /// edges. Canonical URLs have no trailing slash, so relative links resolve against
+    /// `<base href>` when there is one and against the start URL as fetched for the start page.
+    fn page_links(&self, html: &str, page_url: &Url) -> Vec<String> {
+        let base_url = crate::extract::html_base_href(html, page_url.as_str())
+            .and_then(|base| Url::parse(&base).ok())
+            .unwrap_or_else(|| {
+                if *page_url == self.query_policy.canonical_url(&self.start_url) {
+                    self.start_url.clone()
+                } else {
+                    page_url.clone()
+                }
+            });
+        let mut links = crate::app::preview::extract_html_hrefs(html, usize::MAX)
+            .iter()
+            .filter_map(|href| base_url.join(href).ok())
+            .filter(|url| url.scheme() == "http" || url.scheme() == "https")
+            .map(|url| {
+                self.query_policy
+                    .canonical_url(&self.host_aliases.canonicalize(&unescape_link_query(&url)))
+            })
+            .filter(|url| self.scope.is_in_scope(url))
+            .map(|url| {
+                let link = url.to_string();
+                self.redirects.get(&link).cloned().unwrap_or(link)
+            })
+            .collect::<Vec<_>>();
+        links.sort();
+        links.dedup();
+        links
+    }
+}
+
+/// Blacklist entries matching `visited` URLs (under every host alias) with or without a
+/// trailing slash, query or fragment, so spider does not fetch recorded pages again. With
+/// `keep_query` the query is part of the page, so only the fragment may differ.
+fn build_resumed_url_regexes(
+    visited: &BTreeSet<String>,
+    host_aliases: &HostAliases,
+    keep_query: bool,
+) -> Vec<String> {
+    let suffix = if keep_query {
+        "(?:#.*)?$"
+    } else {
+        "(?:[?#].*)?$"
+    };
+    visited
+        .iter()
+        .filter_map(|url| Url::parse(url).ok())
+        .flat_map(|url| host_aliases.host_variants(&url))
+        .map(|url| {
+            let url = url.as_str();
+            if keep_query && url.contains('?') {
+                format!("^{}{suffix}", regex_escape(url))
+            } else {
+                format!(
+                    "^{}/?{suffix}",
+                    regex_escape(url.strip_suffix('/').unwrap_or(url))
+                )
+            }
+        })
+        .collect()
+}
+
+/// Child sitemaps fetched from a sitemap index by `--seed-from-sitemap`.
+const MAX_CHILD_SITEMAPS: usize = 50;
+
+/// `--seed-from-sitemap`: page URLs from `/sitemap.xml` at the start origin (and its child
+/// sitemaps when it is an index), normalized like discovered links and kept to `scope`.
+async fn sitemap_seed_urls(
+    args: &CrawlArgs,
+    tls: &TlsOptions,
+    start_url: &Url,
+    scope: &CrawlScope,
+    host_aliases: &HostAliases,
+    query_policy: &QueryPolicy,
+) -> anyhow::Result<Vec<Url>> {
+    let client = crate::proxy::apply(
+        crate::net_guard::apply(tls.apply(reqwest::Client::builder())),
+        args.proxy.as_deref(),
+    )?
+    .timeout(Duration::from_secs(30))
+    .default_headers(accept_language_headers(args.accept_language.as_deref())?)
+    .redirect(crate::net_guard::redirect_policy(
+        args.max_redirects,
+        args.same_host_redirects_only,
+    ))
+    .gzip(true)
+    .brotli(true)
+    .deflate(true)
+    .build()
+    .context("build sitemap http client")?;
+
+    let mut sitemap_url = start_url.clone();
+    sitemap_url.set_path("/sitemap.xml");
+    sitemap_url.set_query(None);
+    let sitemap = crate::app::preview::try_fetch_text(&client, &sitemap_url)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("{sitemap_url} was not found"))?;
+
+    let mut locs = crate::app::preview::extract_xml_locs(&sitemap.text);
+    if sitemap.text.to_ascii_lowercase().contains("<sitemapindex") {
+        let child_sitemaps = std::mem::take(&mut locs)
+            .into_iter()
+            .filter_map(|loc| Url::parse(&loc).ok())
+            .filter(|url| url.host_str() == start_url.host_str())
+            .take(MAX_CHILD_SITEMAPS)
+            .collect::<Vec<_>>();
+        for child_url in child_sitemaps {
+            match crate::app::preview::try_fetch_text(&client, &child_url).await {
+                Ok(Some(child)) => locs.extend(crate::app::preview::extract_xml_locs(&child.text)),
+                Ok(None) => tracing::warn!(sitemap = %child_url, "child sitemap not found"),
+                Err(err) => {
+                    tracing::warn!(?err, sitemap = %child_url, "fetch child sitemap failed")
+                }
+            }
+        }
+    }
+
+    // Same form as the link callback produces, so spider dedupes seeds against found links.
+    let seeds = locs
+        .iter()
+        .filter_map(|loc| Url::parse(loc).ok())
+        .filter(|url| url.scheme() == "http" || url.scheme() == "https")
+        .map(|url| query_policy.strip(&host_aliases.canonicalize(&url)))
+        .filter(|url| scope.is_in_scope(&query_policy.canonical_url(url)))
+        .collect::<BTreeSet<_>>();
+    Ok(seeds.into_iter().collect())
+}
+
+/// Validates an explicit page list and returns it canonicalized and deduplicated, in list order.
+///
+/// All URLs must be http/https and share the same scheme.
+pub fn canonical_page_list(
+    page_urls: &[String],
+    query_policy: &QueryPolicy,
+) -> anyhow::Result<Vec<Url>> {
+    let mut scheme: Option<String> = None;
+    let mut urls: Vec<Url> = Vec::new();
+    let mut seen: HashSet<Url> = HashSet::new();
+
+    for raw in page_urls {
+        let raw = raw.trim();
+        if raw.is_empty() {
+            continue;
+        }
+        let url = Url::parse(raw).with_context(|| format!("parse page url: {raw}"))?;
+        if url.scheme() != "http" && url.scheme() != "https" {
+            anyhow::bail!("page url must be http/https: {url}");
+        }
+        if url.host_str().is_none() {
+            anyhow::bail!("page url must have host: {url}");
+        }
+        match scheme.as_deref() {
+            None => scheme = Some(url.scheme().to_owned()),
+            Some(expected) if expected != url.scheme() => {
+                anyhow::bail!("page urls must share the same scheme ({expected}): {url}");
+            }
+            Some(_) => {}
+        }
+        let url = query_policy.canonical_url(&url);
+        if seen.insert(url.clone()) {
+            urls.push(url);
+        }
+    }
+
+    if urls.is_empty() {
+        anyhow::bail!("page url list is empty");
+    }
+    Ok(urls)
+}
+
+/// `--concurrency-per-host`, defaulting to (and never above) the global `--concurrency`.
+fn concurrency_per_host(args: &CrawlArgs) -> usize {
+    let global = args.concurrency.max(1);
+    args.concurrency_per_host
+        .map_or(global, |per_host| per_host.clamp(1, global))
+}
+
+/// Per-host politeness for page list fetches: at most `--concurrency-per-host` requests in
+/// flight to one host, and request starts to that host spaced `--delay-ms` apart.
+struct HostRateLimiter {
+    per_host: usize,
+    delay: Duration,
+    hosts: std::sync::Mutex<HashMap<String, Arc<HostSlot>>>,
+}
+
+struct HostSlot {
+    permits: Arc<tokio::sync::Semaphore>,
+    next_start: tokio::sync::Mutex<tokio::time::Instant>,
+}
+
+impl HostRateLimiter {
+    fn new(per_host: usize, delay: Duration) -> Self {
+        Self {
+            per_host,
+            delay,
+            hosts: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Waits for a slot on `url`'s host and for its turn after the previous request start.
+    /// The permit is held until the returned guard drops.
+    async fn acquire(&self, url: &Url) -> tokio::sync::OwnedSemaphorePermit {
+        let slot = self
+            .hosts
+            .lock()
+            .expect("host rate limiter lock poisoned")
+            .entry(host_key(url))
+            .or_insert_with(|| {
+                Arc::new(HostSlot {
+                    permits: Arc::new(tokio::sync::Semaphore::new(self.per_host)),
+                    next_start: tokio::sync::Mutex::new(tokio::time::Instant::now()),
+                })
+            })
+            .clone();
+        let permit = slot
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("host semaphore is never closed");
+        let start = {
+            let mut next_start = slot.next_start.lock().await;
+            let start = (*next_start).max(tokio::time::Instant::now());
+            *next_start = start + self.delay;
+            start
+        };
+        tokio::time::sleep_until(start).await;
+        permit
+    }
+}
+
+fn host_key(url: &Url) -> String {
+    format!(
+        "{}:{}",
+        url.host_str().unwrap_or_default(),
+        url.port_or_known_default().unwrap_or_default()
+    )
+}
+
+struct FetchedPage {
+    /// Canonical URL after redirects (`None` when the request did not complete).
+    final_url: Option<Url>,
+    status: u16,
+    content_type: Option<String>,
+    html: Option<String>,
+}
+
+async fn fetch_page_list(
+    args: &CrawlArgs,
+    tls: &TlsOptions,
+    host_aliases: &HostAliases,
+    headers: HeaderMap,
+    page_urls: &[Url],
+    resumed_urls: &BTreeSet<String>,
+    crawl_jsonl: &mut BufWriter<std::fs::File>,
+) -> anyhow::Result<()> {
+    let out_dir = Path::new(&args.out);
+    let client = crate::proxy::apply(
+        crate::net_guard::apply(tls.apply(reqwest::Client::builder())),
+        args.proxy.as_deref(),
+    )?
+    .timeout(Duration::from_secs(30))
+    .default_headers(headers)
+    // A refused same-host redirect leaves the 3xx response, which is not saved.
+    .redirect(crate::net_guard::redirect_policy(
+        args.max_redirects,
+        args.same_host_redirects_only,
+    ))
+    // Decode `Content-Encoding` transparently; reqwest also sends the matching
+    // `Accept-Encoding` (setting that header by hand would disable decoding).
+    .gzip(true)
+    .brotli(true)
+    .deflate(true)
+    .build()
+    .context("build page list http client")?;
+
+    let query_policy = QueryPolicy {
+        keep_query: args.keep_query,
+        extra_tracking_params: args.tracking_params.clone(),
+    };
+    let semaphore = Arc::new(tokio::sync::Semaphore::new(args.concurrency.max(1)));
+    let host_limiter = Arc::new(HostRateLimiter::new(
+        concurrency_per_host(args),
+        Duration::from_millis(args.delay_ms),
+    ));
+    let mut tasks = tokio::task::JoinSet::new();
+    for (index, url) in page_urls.iter().enumerate() {
+        let client = client.clone();
+        let semaphore = semaphore.clone();
+        let host_limiter = host_limiter.clone();
+        let url = url.clone();
+        let fetch_command = args.fetch_command.clone();
+        let query_policy = query_policy.clone();
+        tasks.spawn(async move {
+            // Host first, so a busy or delayed host does not tie up global permits while it waits.
+            let _host_permit = host_limiter.acquire(&url).await;
+            let _permit = semaphore.acquire_owned().await;
+            let fetched = match fetch_command {
+                Some(command) => {
+                    let command_url = url.clone();
+                    tokio::task::spawn_blocking(move || {
+                        fetch_page_with_command(&command, &command_url, &query_policy)
+                    })
+                    .await
+                    .context("join fetch command")
+                    .and_then(|result| result)
+                }
+                None => fetch_page(&client, &url, &query_policy).await,
+            };
+            (index, url, fetched)
+        });
+    }
+
+    let progress = crate::logging::Progress::new("crawl", tasks.len())?;
+    tracing::info!(pages = tasks.len(), "crawl: fetching page list");
+    let mut fetched_pages: Vec<(usize, Url, FetchedPage)> = Vec::new();
+    let mut failed = 0usize;
+    while let Some(joined) = tasks.join_next().await {
+        let (index, url, fetched) = joined.context("join page fetch task")?;
+        let page = fetched.unwrap_or_else(|err| {
+            tracing::warn!(?err, url = %url, "page fetch failed");
+            failed += 1;
+            FetchedPage {
+                final_url: None,
+                status: 0,
+                content_type: None,
+                html: None,
+            }
+        });
+        fetched_pages.push((index, url, page));
+        progress.inc();
+    }
+    tracing::info!(
+        pages = fetched_pages.len(),
+        failed,
+        "crawl: fetched page list"
+    );
+    // Records follow the list order, not the order fetches finished in.
+    fetched_pages.sort_by_key(|(index, _, _)| *index);
+
+    // Several requested URLs may land on the same page; keep one record per final URL.
+    let mut by_final_url: Vec<(Url, FetchedPage, Vec<String>)> = Vec::new();
+    let mut login_wall = LoginWallCheck::default();
+    for (_, requested, page) in fetched_pages {
+        let url = page
+            .final_url
+            .as_ref()
+            .map(|url| host_aliases.canonicalize(url))
+            .unwrap_or_else(|| requested.clone());
+        login_wall.observe_redirect(&requested, &url);
+        match by_final_url
+            .iter_mut()
+            .find(|(existing, _, _)| *existing == url)
+        {
+            Some((_, _, redirected_from)) => {
+                if requested != url {
+                    redirected_from.push(requested.to_string());
+                }
+            }
+            None => {
+                let redirected_from = if requested != url {
+                    vec![requested.to_string()]
+                } else {
+                    Vec::new()
+                };
+                by_final_url.push((url, page, redirected_from));
+            }
+        }
+    }
+
+    // Discovery order of a page list is the order of `--page-url`.
+    let mut list_positions: HashMap<String, u64> = HashMap::new();
+    for raw in &args.page_urls {
+        if let Ok(url) = Url::parse(raw.trim()) {
+            let position = list_positions.len() as u64;
+            list_positions
+                .entry(
+                    host_aliases
+                        .canonicalize(&query_policy.canonical_url(&url))
+                        .to_string(),
+                )
+                .or_insert(position);
+        }
+    }
+
+    for (url, page, redirected_from) in by_final_url {
+        // A listed URL that redirected to a page the resumed snapshot already holds.
+        if resumed_urls.contains(url.as_str()) {
+            tracing::info!(url = %url, "crawl: page is already in the resumed snapshot");
+            continue;
+        }
+        let discovery_index = std::iter::once(&url.to_string())
+            .chain(redirected_from.iter())
+            .filter_map(|requested| list_positions.get(requested).copied())
+            .min();
+        let mut record = CrawlRecord {
+            url: url.to_string(),
+            normalized_url: url.to_string(),
+            depth: 0,
+            status: page.status,
+            content_type: page.content_type,
+            retrieved_at: chrono::Utc::now().to_rfc3339(),
+            raw_html_path: None,
+            raw_html_sha256: None,
+            redirected_from,
+            discovery_index,
+        };
+
+        if let Some(html) = page.html
+            && should_save_html(&html)
+        {
+            login_wall.observe_body(&html);
+            let raw_html_path =
+                crate::raw_store::raw_html_path(out_dir, &url).context("compute raw html path")?;
+            // Left without a record by an interrupted run; this fetch replaces it.
+            if args.resume && raw_html_path.exists() {
+                std::fs::remove_file(&raw_html_path).with_context(|| {
+                    format!("remove stale raw html: {}", raw_html_path.display())
+                })?;
+            }
+            crate::raw_store::write_raw_html(&raw_html_path, &html).context("write raw html")?;
+            record.raw_html_path = Some(raw_html_path.to_string_lossy().to_string());
+            record.raw_html_sha256 = Some(crate::raw_store::sha256_hex(html.as_bytes()));
+        }
+
+        serde_json::to_writer(&mut *crawl_jsonl, &record).context("write crawl record json")?;
+        crawl_jsonl
+            .write_all(b"\n")
+            .context("write crawl record newline")?;
+    }
+
+    crawl_jsonl.flush().context("flush crawl log")?;
+    login_wall.report(args.fail_on_login_wall)
+}
+
+async fn fetch_page(
+    client: &reqwest::Client,
+    url: &Url,
+    query_policy: &QueryPolicy,
+) -> anyhow::Result<FetchedPage> {
+    crate::net_guard::check_ip_literal(url)?;
+    let response = client
+        .get(url.clone())
+        .header(USER_AGENT, "sitebookify/0.1")
+        .header(ACCEPT, "text/html,application/xhtml+xml;q=0.9,*/*;q=0.8")
+        .send()
+        .await
+        .with_context(|| format!("GET {url}"))?;
+
+    let status = response.status();
+    let final_url = query_policy.canonical_url(response.url());
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(ToOwned::to_owned);
+
+    let html = if status.is_success() {
+        Some(
+            response
+                .text()
+                .await
+                .with_context(|| format!("read body: {url}"))?,
+        )
+    } else {
+        None
+    };
+
+    Ok(FetchedPage {
+        final_url: Some(final_url),
+        status: status.as_u16(),
+        content_type,
+        html,
+    })
+}
+
+/// Splits `--fetch-command` into a program and its arguments (shell-style quoting, no shell).
+fn fetch_command_argv(command: &str) -> anyhow::Result<Vec<String>> {
+    let argv = shlex::split(command)
+        .with_context(|| format!("parse --fetch-command (unbalanced quotes?): {command}"))?;
+    if argv.is_empty() {
+        anyhow::bail!("--fetch-command must not be empty");
+    }
+    Ok(argv)
+}
+
+/// Runs `--fetch-command` for `url` (passed as its last argument) and treats its stdout as the
+/// page body. A non-zero exit is reported as a failed fetch.
+fn fetch_page_with_command(
+    command: &str,
+    url: &Url,
+    query_policy: &QueryPolicy,
+) -> anyhow::Result<FetchedPage> {
+    let argv = fetch_command_argv(command)?;
+    let output = std::process::Command::new(&argv[0])
+        .args(&argv[1..])
+        .arg(url.as_str())
+        .stdin(std::process::Stdio::null())
+        .output()
+        .with_context(|| format!("spawn fetch command for {url}"))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!(
+            "fetch command failed for {url} ({}): {}",
+            output.status,
+            stderr.trim()
+        );
+    }
+
+    let stdout = String::from_utf8(output.stdout)
+        .with_context(|| format!("fetch command output is not UTF-8: {url}"))?;
+    parse_fetch_command_output(url, &stdout, query_policy)
 }
 
-fn build_whitelist_regex(scope: &CrawlScope) -> String {
+/// A fetch command's stdout: the page HTML, optionally preceded (CGI-style) by `Status:`,
+/// `Content-Type:` and `Location:` (final URL) header lines and a blank line. Without them
+/// the page is a `200` `text/html` response from `url`.
+fn parse_fetch_command_output(
+    url: &Url,
+    stdout: &str,
+    query_policy: &QueryPolicy,
+) -> anyhow::Result<FetchedPage> {
+    let mut page = FetchedPage {
+        final_url: Some(url.clone()),
+        status: 200,
+        content_type: Some("text/html".to_owned()),
+        html: None,
+    };
+    let first_line = stdout.lines().next().unwrap_or_default();
+    let has_headers = first_line.split_once(':').is_some_and(|(name, _)| {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    });
+    let mut body = stdout;
+    if has_headers {
+        let (headers, rest) = stdout
+            .split_once("\r\n\r\n")
+            .or_else(|| stdout.split_once("\n\n"))
+            .unwrap_or((stdout, ""));
+        body = rest;
+        for line in headers.lines() {
+            let Some((name, value)) = line.split_once(':') else {
+                anyhow::bail!("fetch command output has a malformed header line for {url}: {line}");
+            };
+            let value = value.trim();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "status" => {
+                    let code = value.split_whitespace().next().unwrap_or_default();
+                    page.status = code
+                        .parse::<u16>()
+                        .ok()
+                        .filter(|code| (100..=599).contains(code))
+                        .with_context(|| {
+                            format!("fetch command returned an invalid Status for {url}: {value}")
+                        })?;
+                }
+                "content-type" => page.content_type = Some(value.to_owned()),
+                "location" => {
+                    let location = url.join(value).with_context(|| {
+                        format!("fetch command returned an invalid Location for {url}: {value}")
+                    })?;
+                    page.final_url = Some(query_policy.canonical_url(&location));
+                }
+                _ => {}
+            }
+        }
+    }
+    if (200..300).contains(&page.status) {
+        page.html = Some(body.to_owned());
+    }
+    Ok(page)
+}
+
+fn build_whitelist_regex(scope: &CrawlScope, host_aliases: &HostAliases) -> String {
     let port = match scope.port {
         Some(port) => format!(":{port}"),
         None => String::new(),
     };
-    let origin = format!("{}://{}{port}", scope.scheme, scope.host);
-    let prefix = format!("{origin}{}", scope.path_prefix);
+    // Links to an aliased host pass too; the link callback moves them onto the start host.
+    let hosts = std::iter::once(scope.host.as_str())
+        .chain(host_aliases.aliases_of(&scope.host))
+        .map(regex_escape)
+        .collect::<Vec<_>>()
+        .join("|");
+    let origin = format!("{}://(?:{hosts}){port}", regex_escape(&scope.scheme));
+    let path_prefix = regex_escape(&scope.path_prefix);
 
     if scope.path_prefix == "/" {
-        format!("^{}.*$", regex_escape(&origin))
+        format!("^{origin}.*$")
     } else if scope.path_prefix.ends_with('/') {
-        format!("^{}.*$", regex_escape(&prefix))
+        format!("^{origin}{path_prefix}.*$")
     } else {
-        format!("^{}(?:/.*)?$", regex_escape(&prefix))
+        format!("^{origin}{path_prefix}(?:/.*)?$")
     }
 }
 
+/// Matches links to files with a denied extension (built-in list plus `--exclude-extensions`),
+/// so they are skipped before any request is made.
+fn build_extension_blacklist_regex(extra: &[String]) -> String {
+    let mut extensions = crate::url_norm::DEFAULT_EXCLUDED_EXTENSIONS
+        .iter()
+        .map(|extension| (*extension).to_owned())
+        .collect::<BTreeSet<_>>();
+    extensions.extend(
+        extra
+            .iter()
+            .map(|extension| {
+                extension
+                    .trim()
+                    .trim_start_matches('.')
+                    .to_ascii_lowercase()
+            })
+            .filter(|extension| !extension.is_empty()),
+    );
+    let alternatives = extensions
+        .iter()
+        .map(|extension| regex_escape(extension))
+        .collect::<Vec<_>>()
+        .join("|");
+    format!(r"(?i)[^/]\.(?:{alternatives})(?:[?#].*)?$")
+}
+
 fn regex_escape(input: &str) -> String {
     let mut out = String::with_capacity(input.len());
     for ch in input.chars() {
@@ -232,9 +1264,68 @@ fn regex_escape(input: &str) -> String {
     out
 }
 
+/// Picks the URL a crawled page is recorded under.
+///
+/// In-scope redirect targets replace the requested URL. Off-host targets are dropped when
+/// `same_host_only` is set and otherwise keep the requested URL (the target is out of scope).
+fn redirect_target<'a>(
+    scope: &CrawlScope,
+    requested: &'a Url,
+    final_url: &'a Url,
+    same_host_only: bool,
+) -> Option<&'a Url> {
+    if final_url == requested || scope.is_in_scope(final_url) {
+        return Some(final_url);
+    }
+    if same_host_only && final_url.host_str() != requested.host_str() {
+        tracing::info!(%requested, %final_url, "skipping page redirected off-host");
+        return None;
+    }
+    Some(requested)
+}
+
+/// Collapses pages recorded under the same URL and returns `requested -> recorded` aliases.
+///
+/// A page fetched directly wins over one that only reached the URL through a redirect.
+fn dedupe_redirected_pages(
+    pages: Vec<(String, String, spider::page::Page)>,
+) -> (Vec<(String, spider::page::Page)>, HashMap<String, String>) {
+    let mut aliases = HashMap::new();
+    let mut by_key: HashMap<String, (bool, spider::page::Page)> = HashMap::new();
+    for (key, requested, page) in pages {
+        let direct = key == requested;
+        if !direct {
+            aliases.insert(requested, key.clone());
+        }
+        match by_key.get(&key) {
+            Some((existing_direct, _)) if *existing_direct || !direct => {}
+            _ => {
+                by_key.insert(key, (direct, page));
+            }
+        }
+    }
+    let pages = by_key
+        .into_iter()
+        .map(|(key, (_, page))| (key, page))
+        .collect();
+    (pages, aliases)
+}
+
+fn redirected_from_for(aliases: &HashMap<String, String>, url: &str) -> Vec<String> {
+    let mut from = aliases
+        .iter()
+        .filter(|(_, target)| target.as_str() == url)
+        .map(|(requested, _)| requested.clone())
+        .collect::<Vec<_>>();
+    from.sort();
+    from
+}
+
 fn build_page_graph(
     scope: &CrawlScope,
     pages: Vec<(String, spider::page::Page)>,
+    aliases: &HashMap<String, String>,
+    query_policy: &QueryPolicy,
 ) -> (
     HashMap<String, Vec<String>>,
     HashMap<String, spider::page::Page>,
@@ -249,10 +1340,10 @@ fn build_page_graph(
                 let Ok(url) = Url::parse(link.as_ref()) else {
                     continue;
                 };
-                let normalized = normalize_crawl_url(&url);
-                let canonical = canonical_url(&normalized);
+                let canonical = query_policy.canonical_url(&unescape_link_query(&url));
                 if scope.is_in_scope(&canonical) {
-                    links.push(canonical.to_string());
+                    let link = canonical.to_string();
+                    links.push(aliases.get(&link).cloned().unwrap_or(link));
                 }
             }
         }
@@ -298,6 +1389,127 @@ fn compute_depths(
     depths
 }
 
+/// Pages whose visible text is at most this long count towards the identical-body check.
+const LOGIN_WALL_SHORT_TEXT_CHARS: usize = 1_500;
+/// Path segments of a redirect target that mean "sign in first".
+const LOGIN_PATH_SEGMENTS: &[&str] = &[
+    "login", "log-in", "logon", "signin", "sign-in", "sign_in", "sso",
+];
+/// Host prefixes of dedicated identity providers (`accounts.example.com`).
+const LOGIN_HOST_PREFIXES: &[&str] = &["accounts.", "login.", "signin.", "sso.", "auth."];
+
+/// Heuristics for a crawl that only captured a login wall: requests redirected to a login URL,
+/// or most pages sharing one short body (digits ignored, so CSRF tokens do not hide it).
+#[derive(Debug, Default)]
+struct LoginWallCheck {
+    login_redirects: Vec<String>,
+    pages: usize,
+    short_bodies: HashMap<String, usize>,
+}
+
+impl LoginWallCheck {
+    fn observe_redirect(&mut self, requested: &Url, final_url: &Url) {
+        if final_url != requested && is_login_url(final_url) && !is_login_url(requested) {
+            self.login_redirects
+                .push(format!("{requested} -> {final_url}"));
+        }
+    }
+
+    fn observe_body(&mut self, html: &str) {
+        self.pages += 1;
+        let text = visible_text_fingerprint(html);
+        if text.chars().count() <= LOGIN_WALL_SHORT_TEXT_CHARS {
+            *self.short_bodies.entry(text).or_default() += 1;
+        }
+    }
+
+    /// Warns about (or with `fail`, rejects) a crawl that looks like a login wall.
+    fn report(&self, fail: bool) -> anyhow::Result<()> {
+        let mut findings = Vec::new();
+        if !self.login_redirects.is_empty() {
+            findings.push(format!(
+                "{} request(s) redirected to a login page (e.g. {})",
+                self.login_redirects.len(),
+                self.login_redirects[0]
+            ));
+        }
+        if let Some(identical) = self.short_bodies.values().copied().max()
+            && identical >= 3
+            && identical * 2 >= self.pages
+        {
+            findings.push(format!(
+                "{identical} of {} pages have the same short body",
+                self.pages
+            ));
+        }
+        if findings.is_empty() {
+            return Ok(());
+        }
+
+        let summary = findings.join("; ");
+        if fail {
+            anyhow::bail!(
+                "crawl looks like a login wall: {summary} (crawl.jsonl was written; rerun with --force once the site is reachable)"
+            );
+        }
+        tracing::warn!(
+            "crawl: LOGIN WALL SUSPECTED: {summary}; the book will likely contain the sign-in page instead of the docs (pass --fail-on-login-wall to abort)"
+        );
+        Ok(())
+    }
+}
+
+fn is_login_url(url: &Url) -> bool {
+    let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+    if LOGIN_HOST_PREFIXES
+        .iter()
+        .any(|prefix| host.starts_with(prefix))
+    {
+        return true;
+    }
+    url.path_segments().is_some_and(|mut segments| {
+        segments.any(|segment| {
+            LOGIN_PATH_SEGMENTS
+                .iter()
+                .any(|login| segment.eq_ignore_ascii_case(login))
+        })
+    })
+}
+
+/// Lowercased text outside tags, `<script>` and `<style>`, without digits, whitespace collapsed.
+fn visible_text_fingerprint(html: &str) -> String {
+    let lower = html.to_ascii_lowercase();
+    let mut text = String::new();
+    let mut rest = lower.as_str();
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        text.push(' ');
+        let tag = &rest[start..];
+        let skip_until = ["<script", "<style"]
+            .iter()
+            .find(|open| tag.starts_with(**open))
+            .map(|open| format!("</{}", &open[1..]));
+        let end = match skip_until {
+            Some(close) => tag.find(&close).map(|pos| pos + close.len()),
+            None => Some(0),
+        }
+        .and_then(|from| tag[from..].find('>').map(|pos| from + pos + 1));
+        match end {
+            Some(end) => rest = &tag[end..],
+            None => {
+                rest = "";
+                break;
+            }
+        }
+    }
+    text.push_str(rest);
+    text.split_whitespace()
+        .map(|word| word.replace(|ch: char| ch.is_ascii_digit(), ""))
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 fn should_save_html(html: &str) -> bool {
     if html.trim().is_empty() {
         return false;
@@ -308,11 +1520,14 @@ fn should_save_html(html: &str) -> bool {
         || trimmed.contains("<html")
 }
 
-fn normalize_crawl_url(url: &Url) -> Url {
-    let mut normalized = url.clone();
-    normalized.set_fragment(None);
-    normalized.set_query(None);
-    normalized
+/// spider takes `href` values verbatim, so `?a=1&amp;b=2` arrives with the `&amp;` entity
+/// still in its query.
+fn unescape_link_query(url: &Url) -> Url {
+    let mut out = url.clone();
+    if let Some(query) = url.query().filter(|query| query.contains("&amp;")) {
+        out.set_query(Some(&query.replace("&amp;", "&")));
+    }
+    out
 }
 
 fn should_try_trailing_slash(url: &Url) -> bool {
@@ -338,13 +1553,27 @@ fn url_with_trailing_slash(url: &Url) -> Url {
     out
 }
 
-async fn probe_html_url(url: &Url) -> anyhow::Result<Option<Url>> {
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(10))
-        .redirect(reqwest::redirect::Policy::limited(10))
-        .build()
-        .context("build url probe http client")?;
+async fn probe_html_url(
+    url: &Url,
+    proxy: Option<&str>,
+    tls: &TlsOptions,
+    accept_language: Option<&str>,
+    query_policy: &QueryPolicy,
+) -> anyhow::Result<Option<Url>> {
+    let client = crate::proxy::apply(
+        crate::net_guard::apply(tls.apply(reqwest::Client::builder())),
+        proxy,
+    )?
+    .timeout(Duration::from_secs(10))
+    .default_headers(accept_language_headers(accept_language)?)
+    .redirect(crate::net_guard::redirect_policy(10, false))
+    .gzip(true)
+    .brotli(true)
+    .deflate(true)
+    .build()
+    .context("build url probe http client")?;
 
+    crate::net_guard::check_ip_literal(url)?;
     let response = client
         .get(url.clone())
         .header(USER_AGENT, "sitebookify/0.1")
@@ -370,15 +1599,5 @@ async fn probe_html_url(url: &Url) -> anyhow::Result<Option<Url>> {
         }
     }
 
-    Ok(Some(normalize_crawl_url(response.url())))
-}
-
-fn canonical_url(url: &Url) -> Url {
-    let mut canonical = normalize_crawl_url(url);
-    let mut path = canonical.path().to_owned();
-    while path.len() > 1 && path.ends_with('/') {
-        path.pop();
-    }
-    canonical.set_path(&path);
-    canonical
+    Ok(Some(query_policy.strip(response.url())))
 }
@@ -1,10 +1,11 @@
 use std::fs::{self, OpenOptions};
-use std::io::Write as _;
+use std::io::{Read as _, Seek, Write};
 use std::path::{Path, PathBuf};
 
 use anyhow::Context as _;
-use chrono::Utc;
 use pulldown_cmark::{Options, Parser};
+use sha2::Digest as _;
+use sha2::Sha256;
 use zip::write::SimpleFileOptions;
 
 #[derive(Debug, Clone)]
@@ -12,6 +13,13 @@ pub struct CreateEpubOptions {
     pub force: bool,
     /// BCP-47 language tag used for EPUB metadata and XHTML documents.
     pub lang: String,
+    /// Image shown as the book cover (first spine item and reader thumbnail).
+    pub cover_image: Option<PathBuf>,
+    /// Table of contents depth: `1` for chapters only, `2` for chapters and their `##`
+    /// headings. `None` nests the section entries SUMMARY.md lists.
+    pub nav_depth: Option<u8>,
+    /// Start every chapter on a new page (`page-break-before` on the chapter heading).
+    pub chapter_page_breaks: bool,
 }
 
 impl Default for CreateEpubOptions {
@@ -19,6 +27,9 @@ impl Default for CreateEpubOptions {
         Self {
             force: false,
             lang: "und".to_string(),
+            cover_image: None,
+            nav_depth: None,
+            chapter_page_breaks: false,
         }
     }
 }
@@ -69,27 +80,133 @@ pub fn create_from_mdbook(
             .with_context(|| format!("create epub parent dir: {}", parent.display()))?;
     }
 
+    let mut sources = load_sources(book_dir)?;
+    match options.nav_depth {
+        None => {}
+        Some(1) => sources
+            .chapters
+            .iter_mut()
+            .for_each(|chapter| chapter.sections.clear()),
+        Some(2) => sources.chapters.iter_mut().for_each(use_heading_sections),
+        Some(depth) => anyhow::bail!("nav depth must be 1 or 2: {depth}"),
+    }
+    if let Some(path) = &options.cover_image {
+        sources.cover = Some(load_cover(path, &sources.chapters)?);
+    }
+
+    let mut out_options = OpenOptions::new();
+    out_options.write(true);
+    if options.force {
+        out_options.create(true).truncate(true);
+    } else {
+        out_options.create_new(true);
+    }
+    let out_file = out_options
+        .open(out_path)
+        .with_context(|| format!("open epub output: {}", out_path.display()))?;
+
+    write_epub(
+        &sources,
+        &options.lang,
+        options.chapter_page_breaks,
+        out_file,
+    )?;
+    Ok(())
+}
+
+/// Builds the EPUB in memory and checks that every XML document in it (XHTML chapters, nav,
+/// OPF, NCX) is well-formed. Returns one message per problem; `Err` only when the book
+/// cannot be read at all.
+pub fn validate_mdbook(book_dir: &Path, lang: &str) -> anyhow::Result<Vec<String>> {
+    if !book_dir.is_dir() {
+        anyhow::bail!("book directory not found: {}", book_dir.display());
+    }
+    let sources = load_sources(book_dir)?;
+    let epub = write_epub(&sources, lang, false, std::io::Cursor::new(Vec::new()))?;
+
+    let mut archive = zip::ZipArchive::new(epub).context("reopen in-memory epub")?;
+    let mut problems = Vec::new();
+    for idx in 0..archive.len() {
+        let mut entry = archive.by_index(idx).context("read in-memory epub entry")?;
+        let name = entry.name().to_owned();
+        if ![".xhtml", ".opf", ".ncx", ".xml"]
+            .iter()
+            .any(|ext| name.ends_with(ext))
+        {
+            continue;
+        }
+        let mut doc = String::new();
+        entry
+            .read_to_string(&mut doc)
+            .with_context(|| format!("read in-memory epub entry: {name}"))?;
+        if let Err(problem) = check_well_formed_xml(&doc) {
+            problems.push(format!("{name}: {problem}"));
+        }
+    }
+    Ok(problems)
+}
+
+struct EpubSources {
+    title: String,
+    chapters: Vec<ChapterSpec>,
+    assets: Vec<AssetSpec>,
+    cover: Option<CoverSpec>,
+}
+
+/// Checks the `--cover` image and names its `OEBPS/cover.<ext>` entry.
+fn load_cover(path: &Path, chapters: &[ChapterSpec]) -> anyhow::Result<CoverSpec> {
+    if !path.is_file() {
+        anyhow::bail!("cover image not found: {}", path.display());
+    }
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    let file_name = format!("cover.{ext}");
+    let media_type = media_type_for_asset(&file_name);
+    if ext.is_empty() || media_type == "application/octet-stream" {
+        anyhow::bail!(
+            "unsupported cover image type: {} (expected png, jpg, jpeg, gif, svg, webp or avif)",
+            path.display()
+        );
+    }
+    if let Some(chapter) = chapters
+        .iter()
+        .find(|chapter| chapter.stem == COVER_PAGE_ID || chapter.stem == COVER_IMAGE_ID)
+    {
+        anyhow::bail!(
+            "chapter {}.md clashes with the generated cover page; rename it to use --cover",
+            chapter.stem
+        );
+    }
+    Ok(CoverSpec {
+        file_name,
+        media_type,
+        abs_path: path.to_path_buf(),
+    })
+}
+
+fn load_sources(book_dir: &Path) -> anyhow::Result<EpubSources> {
     let title = read_book_title(book_dir)?.unwrap_or_else(|| "Book".to_string());
-    let lang = options.lang.trim();
-    let lang = if lang.is_empty() { "und" } else { lang };
 
     let src_dir = book_dir.join("src");
     let summary_path = src_dir.join("SUMMARY.md");
     let summary_md = fs::read_to_string(&summary_path)
         .with_context(|| format!("read SUMMARY.md: {}", summary_path.display()))?;
 
-    let chapter_rel_paths = parse_summary_chapter_paths(&summary_md);
-    if chapter_rel_paths.is_empty() {
+    let summary_chapters = parse_summary_chapters(&summary_md);
+    if summary_chapters.is_empty() {
         anyhow::bail!(
             "no chapter links found in SUMMARY.md: {}",
             summary_path.display()
         );
     }
 
-    let chapters = chapter_rel_paths
+    let chapters = summary_chapters
         .into_iter()
-        .map(|rel| {
-            let md_path = src_dir.join(&rel);
+        .map(|entry| {
+            let md_path = src_dir.join(&entry.path);
             let stem = md_path
                 .file_stem()
                 .and_then(|s| s.to_str())
@@ -98,7 +215,12 @@ pub fn create_from_mdbook(
             let md = fs::read_to_string(&md_path)
                 .with_context(|| format!("read chapter: {}", md_path.display()))?;
             let title = extract_first_heading(&md).unwrap_or_else(|| stem.clone());
-            anyhow::Ok(ChapterSpec { stem, title, md })
+            anyhow::Ok(ChapterSpec {
+                stem,
+                title,
+                md,
+                sections: entry.sections,
+            })
         })
         .collect::<anyhow::Result<Vec<_>>>()?;
 
@@ -122,32 +244,56 @@ pub fn create_from_mdbook(
         Vec::new()
     };
 
-    let uuid = uuid::Uuid::new_v4();
-    let modified = Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    Ok(EpubSources {
+        title,
+        chapters,
+        assets,
+        cover: None,
+    })
+}
 
-    let container_xml = render_container_xml();
-    let css = default_style_css();
-    let nav_xhtml = render_nav_xhtml(&title, lang, &chapters);
-    let toc_ncx = render_toc_ncx(&title, uuid, &chapters);
-    let content_opf = render_content_opf(&title, lang, uuid, &modified, &chapters, &assets);
+fn write_epub<W: Write + Seek>(
+    sources: &EpubSources,
+    lang: &str,
+    chapter_page_breaks: bool,
+    out: W,
+) -> anyhow::Result<W> {
+    let EpubSources {
+        title,
+        chapters,
+        assets,
+        cover,
+    } = sources;
+    let lang = lang.trim();
+    let lang = if lang.is_empty() { "und" } else { lang };
 
-    let mut out_options = OpenOptions::new();
-    out_options.write(true);
-    if options.force {
-        out_options.create(true).truncate(true);
-    } else {
-        out_options.create_new(true);
+    let container_xml = render_container_xml();
+    let mut css = default_style_css();
+    if chapter_page_breaks {
+        css.push_str("body > h1:first-child { page-break-before: always; break-before: page; }\n");
     }
-    let out_file = out_options
-        .open(out_path)
-        .with_context(|| format!("open epub output: {}", out_path.display()))?;
 
-    let mut zip = zip::ZipWriter::new(out_file);
+    let uuid = content_uuid(sources, lang, &css)?;
+    let modified = EPUB_MODIFIED;
+    let nav_xhtml = render_nav_xhtml(title, lang, chapters);
+    let toc_ncx = render_toc_ncx(title, uuid, chapters);
+    let content_opf = render_content_opf(
+        title,
+        lang,
+        uuid,
+        modified,
+        chapters,
+        assets,
+        cover.as_ref(),
+    );
+
+    let mut zip = zip::ZipWriter::new(out);
 
     // Per EPUB spec, `mimetype` MUST be the first entry and MUST be stored (no compression).
     let mimetype_options = SimpleFileOptions::default()
         .compression_method(zip::CompressionMethod::Stored)
-        .unix_permissions(0o644);
+        .unix_permissions(0o644)
+        .last_modified_time(zip::DateTime::default());
     zip.start_file("mimetype", mimetype_options)
         .context("epub start_file mimetype")?;
     zip.write_all(b"application/epub+zip")
@@ -155,7 +301,8 @@ pub fn create_from_mdbook(
 
     let deflated_options = SimpleFileOptions::default()
         .compression_method(zip::CompressionMethod::Deflated)
-        .unix_permissions(0o644);
+        .unix_permissions(0o644)
+        .last_modified_time(zip::DateTime::default());
 
     zip.start_file("META-INF/container.xml", deflated_options)
         .context("epub start_file container.xml")?;
@@ -182,8 +329,27 @@ pub fn create_from_mdbook(
     zip.write_all(css.as_bytes())
         .context("epub write style.css")?;
 
+    if let Some(cover) = cover {
+        let body = format!(
+            "<div class=\"cover\"><img src=\"{}\" alt=\"{}\" /></div>\n",
+            xml_escape(&cover.file_name),
+            xml_escape(title)
+        );
+        let xhtml = wrap_xhtml_document(title, lang, &body);
+        zip.start_file(format!("OEBPS/{COVER_PAGE_ID}.xhtml"), deflated_options)
+            .context("epub start_file cover.xhtml")?;
+        zip.write_all(xhtml.as_bytes())
+            .context("epub write cover.xhtml")?;
+
+        let mut f = fs::File::open(&cover.abs_path)
+            .with_context(|| format!("open cover image: {}", cover.abs_path.display()))?;
+        zip.start_file(format!("OEBPS/{}", cover.file_name), deflated_options)
+            .context("epub start_file cover image")?;
+        std::io::copy(&mut f, &mut zip).context("epub write cover image")?;
+    }
+
     let chapter_stems = chapters.iter().map(|c| c.stem.as_str()).collect::<Vec<_>>();
-    for chapter in &chapters {
+    for chapter in chapters {
         let html = markdown_to_html_fragment(&chapter.md);
         let html = rewrite_html_for_epub(&html, &chapter_stems);
         let html = ensure_xhtml_void_tags(&html);
@@ -195,7 +361,7 @@ pub fn create_from_mdbook(
             .with_context(|| format!("epub write chapter: {}", chapter.stem))?;
     }
 
-    for asset in &assets {
+    for asset in assets {
         let mut f = fs::File::open(&asset.abs_path)
             .with_context(|| format!("open asset: {}", asset.abs_path.display()))?;
         zip.start_file(format!("OEBPS/assets/{}", asset.rel_path), deflated_options)
@@ -204,8 +370,48 @@ pub fn create_from_mdbook(
             .with_context(|| format!("epub write asset: {}", asset.rel_path))?;
     }
 
-    zip.finish().context("epub finish zip")?;
-    Ok(())
+    zip.finish().context("epub finish zip")
+}
+
+/// `dcterms:modified` of every EPUB. A clock time would make each rebuild of the same book
+/// differ, and the app skips re-uploading an artifact only when its bytes are unchanged.
+const EPUB_MODIFIED: &str = "1980-01-01T00:00:00Z";
+
+/// Book identifier derived from everything written into the EPUB, so the same book always
+/// gets the same identifier and a changed one a new identifier.
+fn content_uuid(sources: &EpubSources, lang: &str, css: &str) -> anyhow::Result<uuid::Uuid> {
+    let mut hasher = Sha256::new();
+    let mut feed = |bytes: &[u8]| {
+        hasher.update((bytes.len() as u64).to_le_bytes());
+        hasher.update(bytes);
+    };
+    feed(sources.title.as_bytes());
+    feed(lang.as_bytes());
+    feed(css.as_bytes());
+    for chapter in &sources.chapters {
+        feed(chapter.stem.as_bytes());
+        feed(chapter.title.as_bytes());
+        feed(chapter.md.as_bytes());
+        feed(format!("{:?}", chapter.sections).as_bytes());
+    }
+    if let Some(cover) = &sources.cover {
+        feed(cover.file_name.as_bytes());
+        feed(
+            &fs::read(&cover.abs_path)
+                .with_context(|| format!("read cover image: {}", cover.abs_path.display()))?,
+        );
+    }
+    for asset in &sources.assets {
+        feed(asset.rel_path.as_bytes());
+        feed(
+            &fs::read(&asset.abs_path)
+                .with_context(|| format!("read asset: {}", asset.abs_path.display()))?,
+        );
+    }
+    let digest = hasher.finalize();
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    Ok(uuid::Builder::from_random_bytes(bytes).into_uuid())
 }
 
 #[derive(Debug)]
@@ -213,6 +419,14 @@ struct ChapterSpec {
     stem: String,
     title: String,
     md: String,
+    /// Section-level SUMMARY.md entries (`chNN.md#anchor`), in order.
+    sections: Vec<SectionSpec>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SectionSpec {
+    title: String,
+    anchor: String,
 }
 
 #[derive(Debug)]
@@ -221,6 +435,77 @@ struct AssetSpec {
     abs_path: PathBuf,
 }
 
+/// Manifest ids of the generated cover page and the cover image.
+const COVER_PAGE_ID: &str = "cover";
+const COVER_IMAGE_ID: &str = "cover-image";
+
+#[derive(Debug)]
+struct CoverSpec {
+    /// `cover.<ext>`, next to the chapters in `OEBPS/`.
+    file_name: String,
+    media_type: &'static str,
+    abs_path: PathBuf,
+}
+
+/// Makes the chapter's `##` headings its sections, giving headings without an `{#id}`
+/// attribute a `<stem>-hNN` id (written into the Markdown, so the XHTML carries it too).
+fn use_heading_sections(chapter: &mut ChapterSpec) {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_HEADING_ATTRIBUTES);
+    let mut sections = Vec::new();
+    // (byte offset, id) of each `{#id}` to insert.
+    let mut insertions: Vec<(usize, String)> = Vec::new();
+    let mut current: Option<(Option<String>, usize, String)> = None;
+    for (event, range) in Parser::new_ext(&chapter.md, options).into_offset_iter() {
+        match event {
+            pulldown_cmark::Event::Start(pulldown_cmark::Tag::Heading {
+                level: pulldown_cmark::HeadingLevel::H2,
+                id,
+                ..
+            }) => {
+                current = Some((id.map(|id| id.to_string()), range.end, String::new()));
+            }
+            pulldown_cmark::Event::Text(text) | pulldown_cmark::Event::Code(text) => {
+                if let Some((_, _, title)) = &mut current {
+                    title.push_str(&text);
+                }
+            }
+            pulldown_cmark::Event::End(pulldown_cmark::TagEnd::Heading(
+                pulldown_cmark::HeadingLevel::H2,
+            )) => {
+                let Some((id, end, title)) = current.take() else {
+                    continue;
+                };
+                let anchor = match id {
+                    Some(id) => id,
+                    // Only ATX headings (`## Title`) can take the attribute on their line.
+                    None if chapter.md[..end]
+                        .rsplit('\n')
+                        .find(|line| !line.trim().is_empty())
+                        .is_some_and(|line| line.trim_start().starts_with("##")) =>
+                    {
+                        let anchor = format!("{}-h{:02}", chapter.stem, sections.len() + 1);
+                        insertions.push((end, anchor.clone()));
+                        anchor
+                    }
+                    None => continue,
+                };
+                sections.push(SectionSpec {
+                    title: title.trim().to_owned(),
+                    anchor,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    for (end, anchor) in insertions.into_iter().rev() {
+        let line_end = chapter.md[..end].trim_end().len();
+        chapter.md.insert_str(line_end, &format!(" {{#{anchor}}}"));
+    }
+    chapter.sections = sections;
+}
+
 fn render_container_xml() -> String {
     r#"<?xml version="1.0" encoding="UTF-8"?>
 <container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
@@ -241,6 +526,8 @@ img { max-width: 100%; height: auto; }
 pre, code { font-family: ui-monospace, Menlo, Consolas, monospace; }
 pre { overflow-x: auto; padding: 0.75em; background: #f6f8fa; border-radius: 6px; }
 blockquote { margin: 1em 0; padding: 0 1em; border-left: 4px solid #ddd; color: #333; }
+.cover { margin: 0; text-align: center; }
+.cover img { max-width: 100%; max-height: 100vh; }
 "#
     .to_string()
 }
@@ -264,11 +551,30 @@ fn render_nav_xhtml(title: &str, lang: &str, chapters: &[ChapterSpec]) -> String
     out.push_str("  <nav epub:type=\"toc\" id=\"toc\">\n");
     out.push_str("    <ol>\n");
     for ch in chapters {
+        if ch.sections.is_empty() {
+            out.push_str(&format!(
+                "      <li><a href=\"{}.xhtml\">{}</a></li>\n",
+                xml_escape(&ch.stem),
+                xml_escape(&ch.title)
+            ));
+            continue;
+        }
         out.push_str(&format!(
-            "      <li><a href=\"{}.xhtml\">{}</a></li>\n",
+            "      <li><a href=\"{}.xhtml\">{}</a>\n",
             xml_escape(&ch.stem),
             xml_escape(&ch.title)
         ));
+        out.push_str("        <ol>\n");
+        for section in &ch.sections {
+            out.push_str(&format!(
+                "          <li><a href=\"{}.xhtml#{}\">{}</a></li>\n",
+                xml_escape(&ch.stem),
+                xml_escape(&section.anchor),
+                xml_escape(&section.title)
+            ));
+        }
+        out.push_str("        </ol>\n");
+        out.push_str("      </li>\n");
     }
     out.push_str("    </ol>\n");
     out.push_str("  </nav>\n");
@@ -289,7 +595,14 @@ fn render_toc_ncx(title: &str, uuid: uuid::Uuid, chapters: &[ChapterSpec]) -> St
         "    <meta name=\"dtb:uid\" content=\"urn:uuid:{}\" />\n",
         xml_escape(&uuid.to_string())
     ));
-    out.push_str("    <meta name=\"dtb:depth\" content=\"1\" />\n");
+    let depth = if chapters.iter().any(|ch| !ch.sections.is_empty()) {
+        2
+    } else {
+        1
+    };
+    out.push_str(&format!(
+        "    <meta name=\"dtb:depth\" content=\"{depth}\" />\n"
+    ));
     out.push_str("    <meta name=\"dtb:totalPageCount\" content=\"0\" />\n");
     out.push_str("    <meta name=\"dtb:maxPageNumber\" content=\"0\" />\n");
     out.push_str("  </head>\n");
@@ -297,8 +610,9 @@ fn render_toc_ncx(title: &str, uuid: uuid::Uuid, chapters: &[ChapterSpec]) -> St
     out.push_str(&xml_escape(title));
     out.push_str("</text></docTitle>\n");
     out.push_str("  <navMap>\n");
-    for (idx, ch) in chapters.iter().enumerate() {
-        let play = idx + 1;
+    let mut play = 0;
+    for ch in chapters {
+        play += 1;
         out.push_str(&format!(
             "    <navPoint id=\"navPoint-{}\" playOrder=\"{}\">\n",
             play, play
@@ -310,6 +624,22 @@ fn render_toc_ncx(title: &str, uuid: uuid::Uuid, chapters: &[ChapterSpec]) -> St
             "      <content src=\"{}.xhtml\" />\n",
             xml_escape(&ch.stem)
         ));
+        for section in &ch.sections {
+            play += 1;
+            out.push_str(&format!(
+                "      <navPoint id=\"navPoint-{}\" playOrder=\"{}\">\n",
+                play, play
+            ));
+            out.push_str("        <navLabel><text>");
+            out.push_str(&xml_escape(&section.title));
+            out.push_str("</text></navLabel>\n");
+            out.push_str(&format!(
+                "        <content src=\"{}.xhtml#{}\" />\n",
+                xml_escape(&ch.stem),
+                xml_escape(&section.anchor)
+            ));
+            out.push_str("      </navPoint>\n");
+        }
         out.push_str("    </navPoint>\n");
     }
     out.push_str("  </navMap>\n");
@@ -324,6 +654,7 @@ fn render_content_opf(
     modified: &str,
     chapters: &[ChapterSpec],
     assets: &[AssetSpec],
+    cover: Option<&CoverSpec>,
 ) -> String {
     let mut out = String::new();
     out.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
@@ -345,6 +676,12 @@ fn render_content_opf(
         "    <meta property=\"dcterms:modified\">{}</meta>\n",
         xml_escape(modified)
     ));
+    if cover.is_some() {
+        // EPUB 2 readers find the cover through this; EPUB 3 uses `properties="cover-image"`.
+        out.push_str(&format!(
+            "    <meta name=\"cover\" content=\"{COVER_IMAGE_ID}\" />\n"
+        ));
+    }
     out.push_str("  </metadata>\n");
     out.push_str("  <manifest>\n");
     out.push_str(
@@ -354,6 +691,16 @@ fn render_content_opf(
         "    <item id=\"ncx\" href=\"toc.ncx\" media-type=\"application/x-dtbncx+xml\" />\n",
     );
     out.push_str("    <item id=\"css\" href=\"style.css\" media-type=\"text/css\" />\n");
+    if let Some(cover) = cover {
+        out.push_str(&format!(
+            "    <item id=\"{COVER_IMAGE_ID}\" href=\"{}\" media-type=\"{}\" properties=\"cover-image\" />\n",
+            xml_escape(&cover.file_name),
+            xml_escape(cover.media_type)
+        ));
+        out.push_str(&format!(
+            "    <item id=\"{COVER_PAGE_ID}\" href=\"{COVER_PAGE_ID}.xhtml\" media-type=\"application/xhtml+xml\" />\n"
+        ));
+    }
 
     for ch in chapters {
         out.push_str(&format!(
@@ -375,6 +722,9 @@ fn render_content_opf(
 
     out.push_str("  </manifest>\n");
     out.push_str("  <spine toc=\"ncx\">\n");
+    if cover.is_some() {
+        out.push_str(&format!("    <itemref idref=\"{COVER_PAGE_ID}\" />\n"));
+    }
     for ch in chapters {
         out.push_str(&format!(
             "    <itemref idref=\"{}\" />\n",
@@ -434,6 +784,8 @@ fn markdown_to_html_fragment(md: &str) -> String {
     options.insert(Options::ENABLE_STRIKETHROUGH);
     options.insert(Options::ENABLE_TABLES);
     options.insert(Options::ENABLE_TASKLISTS);
+    // `book render --anchor-style heading` emits `## Title {#id}` (also understood by mdBook).
+    options.insert(Options::ENABLE_HEADING_ATTRIBUTES);
 
     let parser = Parser::new_ext(md, options);
     let mut html = String::new();
@@ -567,15 +919,22 @@ fn ensure_xhtml_void_tags(html: &str) -> String {
     out
 }
 
-fn parse_summary_chapter_paths(summary_md: &str) -> Vec<String> {
-    let mut paths = Vec::new();
+#[derive(Debug)]
+struct SummaryChapter {
+    path: String,
+    sections: Vec<SectionSpec>,
+}
+
+/// Collects chapter files from SUMMARY.md; `chNN.md#anchor` entries become sections of `chNN.md`.
+fn parse_summary_chapters(summary_md: &str) -> Vec<SummaryChapter> {
+    let mut chapters: Vec<SummaryChapter> = Vec::new();
     for line in summary_md.lines() {
         let Some(target) = parse_markdown_link_target(line) else {
             continue;
         };
-        let path = match target.split_once('#') {
-            Some((path, _)) => path,
-            None => target.as_str(),
+        let (path, fragment) = match target.split_once('#') {
+            Some((path, fragment)) => (path, Some(fragment.trim())),
+            None => (target.as_str(), None),
         };
         let path = path.trim();
         if path.starts_with("http://") || path.starts_with("https://") {
@@ -584,9 +943,26 @@ fn parse_summary_chapter_paths(summary_md: &str) -> Vec<String> {
         if !path.ends_with(".md") {
             continue;
         }
-        paths.push(path.to_owned());
+
+        let idx = match chapters.iter().position(|c| c.path == path) {
+            Some(idx) => idx,
+            None => {
+                chapters.push(SummaryChapter {
+                    path: path.to_owned(),
+                    sections: Vec::new(),
+                });
+                chapters.len() - 1
+            }
+        };
+        if let Some(anchor) = fragment.filter(|f| !f.is_empty()) {
+            let title = parse_markdown_link_text(line).unwrap_or_else(|| anchor.to_owned());
+            chapters[idx].sections.push(SectionSpec {
+                title,
+                anchor: anchor.to_owned(),
+            });
+        }
     }
-    paths
+    chapters
 }
 
 fn parse_markdown_link_target(line: &str) -> Option<String> {
@@ -596,6 +972,12 @@ fn parse_markdown_link_target(line: &str) -> Option<String> {
     Some(after[..link_end].to_owned())
 }
 
+fn parse_markdown_link_text(line: &str) -> Option<String> {
+    let link_start = line.find("](")?;
+    let text_start = line[..link_start].find('[')?;
+    Some(line[text_start + 1..link_start].trim().to_owned())
+}
+
 fn read_book_title(book_dir: &Path) -> anyhow::Result<Option<String>> {
     let book_toml_path = book_dir.join("book.toml");
     if !book_toml_path.exists() {
@@ -666,7 +1048,131 @@ fn list_files_recursively_sorted(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
     Ok(files)
 }
 
-fn xml_escape(input: &str) -> String {
+/// Minimal XML well-formedness check: balanced and properly nested tags, terminated
+/// comments/PIs/CDATA, quoted attributes and only XML's predefined or numeric entities (HTML
+/// names such as `&nbsp;` are not defined in EPUB XHTML). Reports the first problem with its
+/// line number.
+fn check_well_formed_xml(doc: &str) -> Result<(), String> {
+    let line_of = |offset: usize| doc[..offset].matches('\n').count() + 1;
+    let mut open: Vec<(&str, usize)> = Vec::new();
+    let mut i = 0usize;
+
+    while i < doc.len() {
+        let rest = &doc[i..];
+        if rest.starts_with('&') {
+            let end = rest
+                .find(';')
+                .filter(|end| {
+                    *end > 1
+                        && rest[1..*end]
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '#')
+                })
+                .ok_or_else(|| format!("line {}: bare `&` (escape it as `&amp;`)", line_of(i)))?;
+            let entity = &rest[1..end];
+            let known = matches!(entity, "amp" | "lt" | "gt" | "quot" | "apos")
+                || entity
+                    .strip_prefix("#x")
+                    .map(|hex| !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()))
+                    .or_else(|| {
+                        entity
+                            .strip_prefix('#')
+                            .map(|dec| !dec.is_empty() && dec.chars().all(|c| c.is_ascii_digit()))
+                    })
+                    .unwrap_or(false);
+            if !known {
+                return Err(format!(
+                    "line {}: undefined entity `&{entity};`",
+                    line_of(i)
+                ));
+            }
+            i += end + 1;
+            continue;
+        }
+        if !rest.starts_with('<') {
+            i += rest.chars().next().map_or(1, char::len_utf8);
+            continue;
+        }
+
+        let skip_to = |open_marker: &str, close: &str, what: &str| -> Result<usize, String> {
+            rest[open_marker.len()..]
+                .find(close)
+                .map(|end| open_marker.len() + end + close.len())
+                .ok_or_else(|| format!("line {}: unterminated {what}", line_of(i)))
+        };
+        if rest.starts_with("<!--") {
+            i += skip_to("<!--", "-->", "comment")?;
+            continue;
+        }
+        if rest.starts_with("<![CDATA[") {
+            i += skip_to("<![CDATA[", "]]>", "CDATA section")?;
+            continue;
+        }
+        if rest.starts_with("<?") {
+            i += skip_to("<?", "?>", "processing instruction")?;
+            continue;
+        }
+        if rest.starts_with("<!") {
+            i += skip_to("<!", ">", "declaration")?;
+            continue;
+        }
+
+        // Find the end of the tag, skipping quoted attribute values.
+        let mut quote = None;
+        let mut end = None;
+        for (offset, ch) in rest.char_indices().skip(1) {
+            match (quote, ch) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'') => quote = Some(ch),
+                (None, '<') => break,
+                (None, '>') => {
+                    end = Some(offset);
+                    break;
+                }
+                (None, _) => {}
+            }
+        }
+        let end = end.ok_or_else(|| format!("line {}: unterminated tag", line_of(i)))?;
+        let tag = &rest[1..end];
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim();
+            match open.pop() {
+                Some((expected, _)) if expected == name => {}
+                Some((expected, opened_at)) => {
+                    return Err(format!(
+                        "line {}: `</{name}>` closes `<{expected}>` opened on line {}",
+                        line_of(i),
+                        line_of(opened_at)
+                    ));
+                }
+                None => return Err(format!("line {}: unexpected `</{name}>`", line_of(i))),
+            }
+        } else {
+            let name = tag
+                .split(|c: char| c.is_whitespace() || c == '/')
+                .next()
+                .unwrap_or_default();
+            if name.is_empty() {
+                return Err(format!("line {}: tag without a name", line_of(i)));
+            }
+            if !tag.ends_with('/') {
+                open.push((name, i));
+            }
+        }
+        i += end + 1;
+    }
+
+    match open.pop() {
+        Some((name, opened_at)) => Err(format!(
+            "line {}: `<{name}>` is never closed",
+            line_of(opened_at)
+        )),
+        None => Ok(()),
+    }
+}
+
+pub fn xml_escape(input: &str) -> String {
     input
         .replace('&', "&amp;")
         .replace('<', "&lt;")
@@ -679,6 +1185,33 @@ fn xml_escape(input: &str) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn check_well_formed_xml_reports_first_structure_error() {
+        assert_eq!(
+            check_well_formed_xml(
+                "<?xml version=\"1.0\"?>\n<!DOCTYPE html>\n<html><body><!-- <x> -->\
+<p a=\"1 > 2\">x &amp; &#169; &#x2014;<br /></p></body></html>"
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            check_well_formed_xml("<div>\n<p>text</div>\n</p>"),
+            Err("line 2: `</div>` closes `<p>` opened on line 2".to_owned())
+        );
+        assert_eq!(
+            check_well_formed_xml("<p>a&nbsp;b</p>"),
+            Err("line 1: undefined entity `&nbsp;`".to_owned())
+        );
+        assert_eq!(
+            check_well_formed_xml("<p>fish & chips</p>"),
+            Err("line 1: bare `&` (escape it as `&amp;`)".to_owned())
+        );
+        assert_eq!(
+            check_well_formed_xml("<body>\n<p>"),
+            Err("line 2: `<p>` is never closed".to_owned())
+        );
+    }
+
     #[test]
     fn ensure_xhtml_void_tags_preserves_utf8_text() {
         let input = "<p>日本語のテスト</p><img src=\"x.png\">";
@@ -686,4 +1219,195 @@ mod tests {
         assert!(out.contains("日本語のテスト"));
         assert!(out.contains("<img src=\"x.png\" />"));
     }
+
+    #[test]
+    fn parse_summary_chapters_groups_section_entries() {
+        let summary = "# Summary\n\n- Part\n  - [Intro](chapters/ch01.md)\n    - [Setup](chapters/ch01.md#ch01-s01)\n    - [Usage](chapters/ch01.md#ch01-s02)\n  - [Next](chapters/ch02.md)\n";
+        let chapters = parse_summary_chapters(summary);
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].path, "chapters/ch01.md");
+        assert_eq!(
+            chapters[0].sections,
+            vec![
+                SectionSpec {
+                    title: "Setup".to_string(),
+                    anchor: "ch01-s01".to_string(),
+                },
+                SectionSpec {
+                    title: "Usage".to_string(),
+                    anchor: "ch01-s02".to_string(),
+                },
+            ]
+        );
+        assert!(chapters[1].sections.is_empty());
+    }
+
+    #[test]
+    fn render_nav_xhtml_nests_sections() {
+        let chapters = vec![ChapterSpec {
+            stem: "ch01".to_string(),
+            title: "Intro".to_string(),
+            md: String::new(),
+            sections: vec![SectionSpec {
+                title: "Setup".to_string(),
+                anchor: "ch01-s01".to_string(),
+            }],
+        }];
+        let nav = render_nav_xhtml("Book", "en", &chapters);
+        assert!(nav.contains("<li><a href=\"ch01.xhtml\">Intro</a>\n        <ol>"));
+        assert!(nav.contains("<li><a href=\"ch01.xhtml#ch01-s01\">Setup</a></li>"));
+    }
+
+    #[test]
+    fn use_heading_sections_ids_atx_headings_outside_code() {
+        let mut chapter = ChapterSpec {
+            stem: "ch02".to_string(),
+            title: "Usage".to_string(),
+            md: "# Usage\n\n## Install `cli`\n\n```sh\n## not a heading\n```\n\n\
+## Configure {#config}\n\n### Detail\n\nSetext\n------\n"
+                .to_string(),
+            sections: vec![SectionSpec {
+                title: "Old".to_string(),
+                anchor: "old".to_string(),
+            }],
+        };
+        use_heading_sections(&mut chapter);
+        assert_eq!(
+            chapter.sections,
+            vec![
+                SectionSpec {
+                    title: "Install cli".to_string(),
+                    anchor: "ch02-h01".to_string(),
+                },
+                SectionSpec {
+                    title: "Configure".to_string(),
+                    anchor: "config".to_string(),
+                },
+            ]
+        );
+        let html = markdown_to_html_fragment(&chapter.md);
+        assert!(
+            html.contains("<h2 id=\"ch02-h01\">Install <code>cli</code></h2>"),
+            "{html}"
+        );
+        assert!(html.contains("<h2 id=\"config\">Configure</h2>"), "{html}");
+        assert!(html.contains("## not a heading"), "{html}");
+    }
+
+    #[test]
+    fn write_epub_with_cover_keeps_every_document_well_formed() {
+        let temp = tempfile::TempDir::new().expect("tempdir");
+        let image = temp.path().join("cover.jpg");
+        fs::write(&image, b"jpeg").expect("write cover");
+        let chapters = vec![ChapterSpec {
+            stem: "ch01".to_string(),
+            title: "Intro".to_string(),
+            md: "# Intro\n".to_string(),
+            sections: Vec::new(),
+        }];
+        let cover = load_cover(&image, &chapters).expect("load cover");
+        let sources = EpubSources {
+            title: "R&D <Notes>".to_string(),
+            chapters,
+            assets: Vec::new(),
+            cover: Some(cover),
+        };
+        let epub =
+            write_epub(&sources, "en", false, std::io::Cursor::new(Vec::new())).expect("epub");
+        let mut archive = zip::ZipArchive::new(epub).expect("reopen epub");
+        for name in ["OEBPS/content.opf", "OEBPS/cover.xhtml"] {
+            let mut doc = String::new();
+            archive
+                .by_name(name)
+                .expect("entry")
+                .read_to_string(&mut doc)
+                .expect("read entry");
+            assert_eq!(check_well_formed_xml(&doc), Ok(()), "{name}: {doc}");
+        }
+        assert!(archive.by_name("OEBPS/cover.jpg").is_ok());
+
+        let clashing = vec![ChapterSpec {
+            stem: "cover".to_string(),
+            title: "Cover".to_string(),
+            md: String::new(),
+            sections: Vec::new(),
+        }];
+        assert!(load_cover(&image, &clashing).is_err());
+    }
+
+    #[test]
+    fn write_epub_adds_chapter_page_breaks_only_when_asked() {
+        let sources = EpubSources {
+            title: "Book".to_string(),
+            chapters: vec![ChapterSpec {
+                stem: "ch01".to_string(),
+                title: "Intro".to_string(),
+                md: "# Intro\n".to_string(),
+                sections: Vec::new(),
+            }],
+            assets: Vec::new(),
+            cover: None,
+        };
+        for chapter_page_breaks in [false, true] {
+            let epub = write_epub(
+                &sources,
+                "en",
+                chapter_page_breaks,
+                std::io::Cursor::new(Vec::new()),
+            )
+            .expect("epub");
+            let mut css = String::new();
+            zip::ZipArchive::new(epub)
+                .expect("reopen epub")
+                .by_name("OEBPS/style.css")
+                .expect("style.css")
+                .read_to_string(&mut css)
+                .expect("read style.css");
+            assert_eq!(css.contains("page-break-before"), chapter_page_breaks);
+        }
+    }
+
+    #[test]
+    fn write_epub_is_byte_identical_for_the_same_book() {
+        let mut sources = EpubSources {
+            title: "Book".to_string(),
+            chapters: vec![ChapterSpec {
+                stem: "ch01".to_string(),
+                title: "Intro".to_string(),
+                md: "# Intro\n".to_string(),
+                sections: Vec::new(),
+            }],
+            assets: Vec::new(),
+            cover: None,
+        };
+        let build = |sources: &EpubSources| {
+            write_epub(sources, "en", false, std::io::Cursor::new(Vec::new()))
+                .expect("epub")
+                .into_inner()
+        };
+        let first = build(&sources);
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        assert_eq!(first, build(&sources));
+
+        let opf = |epub: Vec<u8>| {
+            let mut opf = String::new();
+            zip::ZipArchive::new(std::io::Cursor::new(epub))
+                .expect("reopen epub")
+                .by_name("OEBPS/content.opf")
+                .expect("content.opf")
+                .read_to_string(&mut opf)
+                .expect("read content.opf");
+            opf
+        };
+        let identifier = |opf: &str| {
+            opf.split("urn:uuid:")
+                .nth(1)
+                .and_then(|rest| rest.split('<').next())
+                .expect("identifier")
+                .to_owned()
+        };
+        let first_id = identifier(&opf(first));
+        sources.chapters[0].md = "# Intro\n\nChanged.\n".to_string();
+        assert_ne!(first_id, identifier(&opf(build(&sources))));
+    }
 }
@@ -1,17 +1,41 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::OpenOptions;
 use std::io::{BufRead as _, BufReader, Write as _};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::Context as _;
 use readability_js::{Readability, ReadabilityError, ReadabilityOptions};
 
-use crate::cli::ExtractArgs;
+use crate::cli::{ExtractArgs, OutputLayout, TitleSource};
 use crate::formats::{CrawlRecord, ExtractedFrontMatter};
 
+/// Whether `path` is `dir` or lies below it (compared after resolving both).
+fn is_same_or_inside(path: &Path, dir: &Path) -> bool {
+    match (path.canonicalize(), dir.canonicalize()) {
+        (Ok(path), Ok(dir)) => path.starts_with(dir),
+        _ => false,
+    }
+}
+
 pub fn run(args: ExtractArgs) -> anyhow::Result<()> {
     let raw_dir = PathBuf::from(&args.raw);
     let out_dir = PathBuf::from(&args.out);
 
+    if args.force && out_dir.exists() {
+        for (flag, input) in [
+            ("--raw", Some(args.raw.as_str())),
+            ("--if-modified", args.if_modified.as_deref()),
+        ] {
+            let Some(input) = input else {
+                continue;
+            };
+            if is_same_or_inside(Path::new(input), &out_dir) {
+                anyhow::bail!("--force would remove {flag} {input}, which is inside --out");
+            }
+        }
+        crate::raw_store::remove_snapshot_dir_for_force(&out_dir, "pages")
+            .context("replace extracted snapshot output directory")?;
+    }
     if out_dir.exists() {
         anyhow::bail!(
             "extracted snapshot output directory already exists: {}",
@@ -32,6 +56,17 @@ pub fn run(args: ExtractArgs) -> anyhow::Result<()> {
     std::fs::create_dir_all(&pages_dir)
         .with_context(|| format!("create extracted pages dir: {}", pages_dir.display()))?;
 
+    let prior_pages = match args.if_modified.as_deref() {
+        Some(prior_dir) => load_prior_pages(Path::new(prior_dir))
+            .with_context(|| format!("load --if-modified snapshot: {prior_dir}"))?,
+        None => HashMap::new(),
+    };
+    let (mut reused, mut extracted) = (0usize, 0usize);
+    let strip_repeated = args.strip_repeated.then_some(args.strip_repeated_threshold);
+
+    // `--strip-repeated` compares every page, so only then are pages held until the end.
+    let mut pending = Vec::new();
+    let mut used_paths: HashSet<PathBuf> = HashSet::new();
     for line in reader.lines() {
         let line = line.context("read crawl jsonl line")?;
         if line.trim().is_empty() {
@@ -46,63 +81,454 @@ pub fn run(args: ExtractArgs) -> anyhow::Result<()> {
         let html = std::fs::read_to_string(raw_html_path)
             .with_context(|| format!("read raw html: {raw_html_path}"))?;
 
-        let extracted = extract_with_readability(&readability, &html, &record.normalized_url);
-        let (mut title, mut body_md) = match extracted {
-            Ok(content) => (content.title, content.body_md),
-            Err(err) => {
-                tracing::debug!(
-                    url = %record.normalized_url,
-                    ?err,
-                    "readability extraction failed; writing placeholder"
-                );
+        let id = page_id_from_normalized_url(&record.normalized_url);
+        let raw_html_sha256 = record
+            .raw_html_sha256
+            .clone()
+            .unwrap_or_else(|| crate::raw_store::sha256_hex(html.as_bytes()));
+
+        let (title, body_md, extra) = match prior_pages.get(&id).filter(|prior| {
+            prior.raw_html_sha256 == raw_html_sha256
+                && prior.title_source == args.title_source
+                && prior.strip_repeated == strip_repeated
+        }) {
+            Some(prior) => {
+                reused += 1;
                 (
-                    record.normalized_url.clone(),
-                    format!("Extraction failed for {}\n", record.normalized_url),
+                    prior.title.clone(),
+                    prior.body_md.clone(),
+                    prior.extra.clone(),
                 )
             }
+            None => {
+                extracted += 1;
+                let (title, body_md) = extract_page_markdown(
+                    &readability,
+                    &html,
+                    &record.normalized_url,
+                    args.title_source,
+                );
+                (title, body_md, BTreeMap::new())
+            }
         };
-        if title.trim().is_empty() {
-            title = record.normalized_url.clone();
-        }
-
-        let id = page_id_from_normalized_url(&record.normalized_url);
 
         let front_matter = ExtractedFrontMatter {
-            id: id.clone(),
+            id,
             url: record.normalized_url.clone(),
             retrieved_at: record.retrieved_at.clone(),
             raw_html_path: raw_html_path.to_owned(),
-            title: title.clone(),
+            title,
+            raw_html_sha256: Some(raw_html_sha256),
+            base_url: html_base_href(&html, &record.normalized_url),
+            discovery_index: record.discovery_index,
+            title_source: (args.title_source != TitleSource::Readability)
+                .then_some(args.title_source),
+            strip_repeated,
+            extra,
         };
 
-        body_md = body_md.trim().to_owned();
-        if !body_md.trim_start().starts_with('#') {
-            body_md = format!("# {}\n\n{body_md}", front_matter.title);
+        if args.strip_repeated {
+            pending.push((front_matter, body_md));
+        } else {
+            write_extracted_page(&args, &pages_dir, &mut used_paths, &front_matter, &body_md)?;
         }
+    }
 
-        body_md = strip_known_boilerplate_sections(&body_md);
-        body_md = body_md.trim().to_owned();
-        if !body_md.trim_start().starts_with('#') {
-            body_md = format!("# {}\n\n{body_md}", front_matter.title);
+    if args.if_modified.is_some() {
+        tracing::info!(reused, extracted, "extract: reused unchanged pages");
+    }
+    if args.strip_repeated {
+        strip_repeated_blocks(&mut pending, args.strip_repeated_threshold);
+        for (front_matter, body_md) in &pending {
+            write_extracted_page(&args, &pages_dir, &mut used_paths, front_matter, body_md)?;
         }
+    }
 
-        let yaml =
-            serde_yaml::to_string(&front_matter).context("serialize extracted front matter")?;
-        let markdown = format!("---\n{yaml}---\n\n{body_md}\n");
+    Ok(())
+}
 
-        let out_path = pages_dir.join(format!("{id}.md"));
-        let mut file = OpenOptions::new()
-            .create_new(true)
-            .write(true)
-            .open(&out_path)
-            .with_context(|| format!("create extracted page: {}", out_path.display()))?;
-        file.write_all(markdown.as_bytes())
-            .with_context(|| format!("write extracted page: {}", out_path.display()))?;
+/// Writes one page under `pages_dir` unless it is shorter than `--min-content-chars`.
+fn write_extracted_page(
+    args: &ExtractArgs,
+    pages_dir: &Path,
+    used_paths: &mut HashSet<PathBuf>,
+    front_matter: &ExtractedFrontMatter,
+    body_md: &str,
+) -> anyhow::Result<()> {
+    let content_chars = body_md.chars().count();
+    if content_chars < args.min_content_chars {
+        tracing::info!(
+            url = %front_matter.url,
+            content_chars,
+            min_content_chars = args.min_content_chars,
+            "skip page: extracted content is too short"
+        );
+        return Ok(());
     }
 
+    let yaml = serde_yaml::to_string(front_matter).context("serialize extracted front matter")?;
+    let markdown = format!("---\n{yaml}---\n\n{body_md}\n");
+
+    let relative_path = match args.output_layout {
+        OutputLayout::Flat => PathBuf::from(format!("{}.md", front_matter.id)),
+        OutputLayout::Nested => nested_page_path(&front_matter.url, &front_matter.id, used_paths),
+    };
+    used_paths.insert(relative_path.clone());
+    let out_path = pages_dir.join(&relative_path);
+    if let Some(parent) = out_path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("create extracted page dir: {}", parent.display()))?;
+    }
+    let mut file = OpenOptions::new()
+        .create_new(true)
+        .write(true)
+        .open(&out_path)
+        .with_context(|| format!("create extracted page: {}", out_path.display()))?;
+    file.write_all(markdown.as_bytes())
+        .with_context(|| format!("write extracted page: {}", out_path.display()))?;
     Ok(())
 }
 
+/// Runs readability on one page and normalizes the result to `(title, body)`.
+fn extract_page_markdown(
+    readability: &Readability,
+    html: &str,
+    normalized_url: &str,
+    title_source: TitleSource,
+) -> (String, String) {
+    let (mut title, mut body_md) = match extract_with_readability(readability, html, normalized_url)
+    {
+        Ok(content) => (
+            select_title(title_source, &content.title, html),
+            content.body_md,
+        ),
+        Err(err) => {
+            tracing::debug!(
+                url = %normalized_url,
+                ?err,
+                "readability extraction failed; writing placeholder"
+            );
+            (
+                normalized_url.to_owned(),
+                format!("Extraction failed for {normalized_url}\n"),
+            )
+        }
+    };
+    if title.trim().is_empty() {
+        title = normalized_url.to_owned();
+    }
+
+    body_md = body_md.trim().to_owned();
+    if !body_md.trim_start().starts_with('#') {
+        body_md = format!("# {title}\n\n{body_md}");
+    }
+
+    body_md = strip_known_boilerplate_sections(&body_md);
+    body_md = body_md.trim().to_owned();
+    if !body_md.trim_start().starts_with('#') {
+        body_md = format!("# {title}\n\n{body_md}");
+    }
+    (title, body_md)
+}
+
+/// Picks the title from `source`, falling back through the other sources (readability,
+/// `<title>`, `<h1>`, `og:title`) when it is empty.
+fn select_title(source: TitleSource, readability_title: &str, html: &str) -> String {
+    let order = [
+        source,
+        TitleSource::Readability,
+        TitleSource::HtmlTitle,
+        TitleSource::H1,
+        TitleSource::Og,
+    ];
+    order
+        .into_iter()
+        .filter_map(|source| match source {
+            TitleSource::Readability => Some(collapse_whitespace(readability_title)),
+            TitleSource::HtmlTitle => element_text(html, "title"),
+            TitleSource::H1 => element_text(html, "h1"),
+            TitleSource::Og => meta_property_content(html, "og:title"),
+        })
+        .find(|title| !title.is_empty())
+        .unwrap_or_default()
+}
+
+/// Text of the first `<tag>` element, with nested tags removed and entities decoded.
+fn element_text(html: &str, tag: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let open = format!("<{tag}");
+    let mut pos = 0usize;
+    while let Some(rel) = lower[pos..].find(&open) {
+        let start = pos + rel + open.len();
+        pos = start;
+        // `<h1>` must not match `<h10>`-style prefixes (or `<title` match `<titles`).
+        match lower.as_bytes().get(start) {
+            Some(b'>' | b' ' | b'\t' | b'\n' | b'\r' | b'/') => {}
+            _ => continue,
+        }
+        let content_start = start + lower[start..].find('>')? + 1;
+        let content_end = content_start + lower[content_start..].find(&format!("</{tag}"))?;
+        let text = collapse_whitespace(&decode_html_entities(&strip_tags(
+            &html[content_start..content_end],
+        )));
+        return Some(text);
+    }
+    None
+}
+
+/// `<base href>` of the page resolved against its URL, when it declares one.
+pub fn html_base_href(html: &str, page_url: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let mut pos = 0usize;
+    while let Some(rel) = lower[pos..].find("<base") {
+        let start = pos + rel;
+        pos = start + "<base".len();
+        if !lower
+            .as_bytes()
+            .get(pos)
+            .is_some_and(u8::is_ascii_whitespace)
+        {
+            continue;
+        }
+        let end = start + lower[start..].find('>')?;
+        let href = html_attribute(&html[start..end], "href")?;
+        let base = url::Url::parse(page_url)
+            .ok()?
+            .join(&decode_html_entities(href.trim()))
+            .ok()?;
+        return matches!(base.scheme(), "http" | "https").then(|| base.to_string());
+    }
+    None
+}
+
+/// `content` of the first `<meta property="{property}">` (or `name=`) tag.
+fn meta_property_content(html: &str, property: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let mut pos = 0usize;
+    while let Some(rel) = lower[pos..].find("<meta") {
+        let start = pos + rel;
+        let end = start + lower[start..].find('>')?;
+        pos = end;
+        let tag = &html[start..end];
+        let matches = ["property", "name"].iter().any(|attr| {
+            html_attribute(tag, attr).is_some_and(|value| value.eq_ignore_ascii_case(property))
+        });
+        if matches {
+            return html_attribute(tag, "content")
+                .map(|value| collapse_whitespace(&decode_html_entities(value)));
+        }
+    }
+    None
+}
+
+fn html_attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let lower = tag.to_ascii_lowercase();
+    let needle = format!("{name}=");
+    let mut pos = 0usize;
+    while let Some(rel) = lower[pos..].find(&needle) {
+        let start = pos + rel;
+        pos = start + needle.len();
+        if start > 0 && !lower.as_bytes()[start - 1].is_ascii_whitespace() {
+            continue;
+        }
+        let quote = *tag.as_bytes().get(pos)?;
+        if quote != b'"' && quote != b'\'' {
+            continue;
+        }
+        let value_start = pos + 1;
+        let value_end = value_start + tag[value_start..].find(quote as char)?;
+        return Some(&tag[value_start..value_end]);
+    }
+    None
+}
+
+fn strip_tags(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for ch in html.chars() {
+        match ch {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => out.push(ch),
+            _ => {}
+        }
+    }
+    out
+}
+
+fn decode_html_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+struct PriorPage {
+    raw_html_sha256: String,
+    /// Reused only with the same `--title-source` and `--strip-repeated` settings, since the
+    /// title and body depend on them.
+    title_source: TitleSource,
+    strip_repeated: Option<u8>,
+    title: String,
+    body_md: String,
+    /// Front matter fields added after extraction (carried over with the reused body).
+    extra: BTreeMap<String, serde_yaml::Value>,
+}
+
+/// Loads pages of a prior snapshot that recorded their Raw HTML hash, keyed by page id.
+fn load_prior_pages(extracted_dir: &Path) -> anyhow::Result<HashMap<String, PriorPage>> {
+    let mut pages = HashMap::new();
+    for path in crate::manifest::collect_markdown_files(&extracted_dir.join("pages"))? {
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("read prior extracted page: {}", path.display()))?;
+        let front = crate::manifest::parse_front_matter(&contents)
+            .with_context(|| format!("parse prior extracted page: {}", path.display()))?;
+        let Some(raw_html_sha256) = front.raw_html_sha256 else {
+            continue;
+        };
+        let Some(body_md) = contents
+            .strip_prefix("---\n")
+            .and_then(|rest| rest.split_once("\n---\n"))
+            .map(|(_, body)| body.trim().to_owned())
+        else {
+            continue;
+        };
+        pages.insert(
+            front.id,
+            PriorPage {
+                raw_html_sha256,
+                title_source: front.title_source.unwrap_or_default(),
+                strip_repeated: front.strip_repeated,
+                title: front.title,
+                body_md,
+                extra: front.extra,
+            },
+        );
+    }
+    Ok(pages)
+}
+
+/// With fewer pages every shared paragraph would look like site chrome.
+const MIN_PAGES_FOR_REPEATED_BLOCKS: usize = 3;
+
+/// Removes blocks (blank-line separated) that appear verbatim on more than `threshold_percent`
+/// of the pages, such as "In this article" sidebars or footer nav left over by Readability.
+///
+/// Fenced code blocks and each page's first heading (often a title every page shares) are
+/// never removed. Each stripped block is logged once with the number of pages it was removed
+/// from.
+fn strip_repeated_blocks(pages: &mut [(ExtractedFrontMatter, String)], threshold_percent: u8) {
+    if pages.len() < MIN_PAGES_FOR_REPEATED_BLOCKS {
+        return;
+    }
+
+    let mut page_counts: HashMap<String, usize> = HashMap::new();
+    for (_, body_md) in pages.iter() {
+        let hashes = strippable_blocks(markdown_blocks(body_md))
+            .filter(|(_, strippable)| *strippable)
+            .map(|(block, _)| block)
+            .map(|block| crate::raw_store::sha256_hex(block.text.as_bytes()))
+            .collect::<HashSet<_>>();
+        for hash in hashes {
+            *page_counts.entry(hash).or_default() += 1;
+        }
+    }
+
+    let total_pages = pages.len();
+    let mut removed: BTreeMap<String, usize> = BTreeMap::new();
+    for (_, body_md) in pages.iter_mut() {
+        let (stripped, kept): (Vec<_>, Vec<_>) = strippable_blocks(markdown_blocks(body_md))
+            .partition(|(block, strippable)| {
+                if !strippable {
+                    return false;
+                }
+                let count = page_counts[&crate::raw_store::sha256_hex(block.text.as_bytes())];
+                count >= 2 && count * 100 > total_pages * usize::from(threshold_percent)
+            });
+        if stripped.is_empty() {
+            continue;
+        }
+        for (block, _) in stripped {
+            *removed.entry(block.text).or_default() += 1;
+        }
+        *body_md = kept
+            .into_iter()
+            .map(|(block, _)| block.text)
+            .collect::<Vec<_>>()
+            .join("\n\n");
+    }
+
+    for (block, pages) in removed {
+        let first_line = block.lines().next().unwrap_or_default();
+        let preview = first_line.chars().take(80).collect::<String>();
+        tracing::info!(pages, total_pages, block = %preview, "extract: stripped repeated block");
+    }
+}
+
+/// Pairs each block with whether `--strip-repeated` may remove it: not code, and not the
+/// page's first heading.
+fn strippable_blocks(blocks: Vec<MarkdownBlock>) -> impl Iterator<Item = (MarkdownBlock, bool)> {
+    let first_heading = blocks
+        .iter()
+        .position(|block| !block.is_code && block.text.starts_with('#'));
+    blocks.into_iter().enumerate().map(move |(idx, block)| {
+        let strippable = !block.is_code && Some(idx) != first_heading;
+        (block, strippable)
+    })
+}
+
+struct MarkdownBlock {
+    text: String,
+    is_code: bool,
+}
+
+/// Splits Markdown at blank lines, keeping each fenced code block (blank lines included) whole.
+fn markdown_blocks(markdown: &str) -> Vec<MarkdownBlock> {
+    let mut blocks = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    let mut is_code = false;
+    let mut fence: Option<&str> = None;
+    for line in markdown.lines() {
+        if let Some(marker) = fence {
+            current.push(line);
+            if fence_end_marker(line, marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                blocks.push(MarkdownBlock {
+                    text: current.join("\n"),
+                    is_code,
+                });
+                current.clear();
+                is_code = false;
+            }
+            continue;
+        }
+        if let Some(marker) = fence_start_marker(line) {
+            fence = Some(marker);
+            is_code = true;
+        }
+        current.push(line);
+    }
+    if !current.is_empty() {
+        blocks.push(MarkdownBlock {
+            text: current.join("\n"),
+            is_code,
+        });
+    }
+    blocks
+}
+
 fn strip_known_boilerplate_sections(markdown: &str) -> String {
     strip_mdbook_keyboard_shortcuts_help(markdown)
 }
@@ -360,6 +786,65 @@ fn fence_end_marker(line: &str, marker: &str) -> bool {
     trimmed.starts_with(marker)
 }
 
+/// Returns a `pages/`-relative path that mirrors the URL path (e.g. `docs/intro.md`).
+///
+/// Falls back to a hash-suffixed file name when the path is already taken.
+fn nested_page_path(normalized_url: &str, id: &str, used_paths: &HashSet<PathBuf>) -> PathBuf {
+    let segments = url::Url::parse(normalized_url)
+        .map(|url| {
+            url.path()
+                .split('/')
+                .filter(|s| !s.is_empty() && *s != "." && *s != "..")
+                .map(sanitize_path_segment)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let mut path = PathBuf::new();
+    let stem = match segments.split_last() {
+        None => "index".to_owned(),
+        Some((last, dirs)) => {
+            for dir in dirs {
+                path.push(dir);
+            }
+            let last = last
+                .strip_suffix(".html")
+                .or_else(|| last.strip_suffix(".htm"))
+                .unwrap_or(last);
+            if last.is_empty() {
+                "index".to_owned()
+            } else {
+                last.to_owned()
+            }
+        }
+    };
+
+    let candidate = path.join(format!("{stem}.md"));
+    if !used_paths.contains(&candidate) {
+        return candidate;
+    }
+    // The short suffix can itself be taken (a literal `intro-<hex>` page, or two ids
+    // sharing a prefix), so widen it to the full id and then number it until free.
+    let full_id = id.trim_start_matches("p_");
+    let short_id = &full_id[..full_id.len().min(12)];
+    [short_id, full_id]
+        .into_iter()
+        .map(|suffix| path.join(format!("{stem}-{suffix}.md")))
+        .chain((2..).map(|n| path.join(format!("{stem}-{full_id}-{n}.md"))))
+        .find(|candidate| !used_paths.contains(candidate))
+        .expect("unbounded candidates")
+}
+
+fn sanitize_path_segment(segment: &str) -> String {
+    segment
+        .chars()
+        .map(|c| match c {
+            '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            _ => c,
+        })
+        .collect()
+}
+
 fn page_id_from_normalized_url(normalized_url: &str) -> String {
     let mut hasher = sha2::Sha256::new();
     use sha2::Digest as _;
@@ -402,30 +887,337 @@ fn extract_with_readability(
     html: &str,
     url: &str,
 ) -> Result<ExtractedContent, ReadabilityError> {
-    match readability.parse_with_url(html, url) {
+    // Readability drops `class` attributes, so code block languages ride along as text markers.
+    let html = mark_code_block_languages(html);
+    match readability.parse_with_url(&html, url) {
         Ok(article) => Ok(ExtractedContent {
             title: article.title,
-            body_md: html2md::parse_html(&article.content),
+            body_md: apply_code_block_languages(&html2md::parse_html(&article.content)),
         }),
         Err(ReadabilityError::ReadabilityCheckFailed) => {
             let options = ReadabilityOptions::new()
                 .char_threshold(0)
                 .nb_top_candidates(10)
                 .link_density_modifier(2.0);
-            let article = readability.parse_with_options(html, Some(url), Some(options))?;
+            let article = readability.parse_with_options(&html, Some(url), Some(options))?;
             Ok(ExtractedContent {
                 title: article.title,
-                body_md: html2md::parse_html(&article.content),
+                body_md: apply_code_block_languages(&html2md::parse_html(&article.content)),
             })
         }
         Err(err) => Err(err),
     }
 }
 
+const CODE_LANGUAGE_MARKER: &str = "sitebookifycodelanguage:";
+
+/// Inserts a [`CODE_LANGUAGE_MARKER`] line at the start of every `<pre>` whose language is
+/// known from its own, its inner `<code>`'s or a wrapping element's attributes.
+fn mark_code_block_languages(html: &str) -> String {
+    let lower = html.to_ascii_lowercase();
+    let mut out = String::with_capacity(html.len());
+    // Language announced by a wrapper (Rouge `div.language-ruby`), until text intervenes.
+    let mut pending: Option<String> = None;
+    let mut copied = 0usize;
+    let mut pos = 0usize;
+    while let Some(rel) = lower[pos..].find('<') {
+        let start = pos + rel;
+        if !strip_tags(&html[pos..start]).trim().is_empty() {
+            pending = None;
+        }
+        let Some(end) = lower[start..].find('>').map(|rel| start + rel + 1) else {
+            break;
+        };
+        pos = end;
+        let tag = &html[start..end];
+        match tag_name(&lower[start..end]) {
+            "pre" => {
+                let mut language = code_language_from_tag(tag);
+                let mut insert_at = end;
+                let rest = &lower[end..];
+                let code_start = end + (rest.len() - rest.trim_start().len());
+                if tag_name(&lower[code_start..]) == "code"
+                    && let Some(code_end) = lower[code_start..].find('>')
+                {
+                    let code_end = code_start + code_end + 1;
+                    language =
+                        language.or_else(|| code_language_from_tag(&html[code_start..code_end]));
+                    insert_at = code_end;
+                }
+                if let Some(language) = language.or(pending.take()) {
+                    out.push_str(&html[copied..insert_at]);
+                    out.push_str(CODE_LANGUAGE_MARKER);
+                    out.push_str(&language);
+                    out.push('\n');
+                    copied = insert_at;
+                    pos = insert_at;
+                }
+                pending = None;
+            }
+            "div" | "figure" | "section" => {
+                if let Some(language) = code_language_from_tag(tag) {
+                    pending = Some(language);
+                }
+            }
+            _ => {}
+        }
+    }
+    out.push_str(&html[copied..]);
+    out
+}
+
+fn tag_name(tag_lower: &str) -> &str {
+    let name = tag_lower.strip_prefix('<').unwrap_or("");
+    let len = name
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(name.len());
+    &name[..len]
+}
+
+/// Language named by `data-lang`/`data-language` or by a Prism (`language-rust`),
+/// Highlight.js (`lang-rust`, `hljs rust`), Rouge/Sphinx (`highlight-rust`,
+/// `highlight-source-rust`) or Pandoc (`sourceCode rust`) class.
+fn code_language_from_tag(tag: &str) -> Option<String> {
+    let explicit = ["data-lang", "data-language"]
+        .iter()
+        .find_map(|name| html_attribute(tag, name))
+        .map(str::to_owned);
+    let from_class = || {
+        let classes = html_attribute(tag, "class")?
+            .split_ascii_whitespace()
+            .collect::<Vec<_>>();
+        let prefixed = classes.iter().find_map(|class| {
+            ["language-", "lang-", "highlight-source-", "highlight-"]
+                .iter()
+                .find_map(|prefix| class.strip_prefix(prefix))
+        });
+        let bare = || {
+            if !classes
+                .iter()
+                .any(|class| matches!(*class, "hljs" | "sourceCode"))
+            {
+                return None;
+            }
+            classes.iter().copied().find(|class| {
+                !matches!(*class, "hljs" | "sourceCode" | "numberSource")
+                    && !class.starts_with("hljs-")
+            })
+        };
+        prefixed.or_else(bare).map(str::to_owned)
+    };
+    let language = explicit.or_else(from_class)?.to_ascii_lowercase();
+    let valid = !language.is_empty()
+        && language
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '#' | '-' | '.' | '_'));
+    (valid && !matches!(language.as_str(), "none" | "nohighlight")).then_some(language)
+}
+
+/// Moves [`CODE_LANGUAGE_MARKER`] lines onto the bare opening fence right above them as the
+/// info string; markers that did not end up at the top of a fence are dropped.
+fn apply_code_block_languages(markdown: &str) -> String {
+    if !markdown.contains(CODE_LANGUAGE_MARKER) {
+        return markdown.to_owned();
+    }
+    let mut out: Vec<String> = Vec::new();
+    let mut open_fence: Option<String> = None;
+    for line in markdown.lines() {
+        let marker_language = line.trim().strip_prefix(CODE_LANGUAGE_MARKER);
+        if let Some(language) = marker_language {
+            if let Some(previous) = out.last_mut()
+                && open_fence.is_some()
+                && previous.trim() == open_fence.as_deref().unwrap_or_default()
+            {
+                previous.push_str(language);
+            }
+            continue;
+        }
+        match &open_fence {
+            Some(marker) => {
+                if fence_end_marker(line, marker) {
+                    open_fence = None;
+                }
+            }
+            None => open_fence = fence_start_marker(line).map(str::to_owned),
+        }
+        out.push(line.replace(CODE_LANGUAGE_MARKER, ""));
+    }
+    let mut joined = out.join("\n");
+    if markdown.ends_with('\n') {
+        joined.push('\n');
+    }
+    joined
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn strip_repeated_blocks_removes_site_chrome_but_keeps_code() {
+        let footer = "Footer nav: [Home](/) | [Blog](/blog)";
+        let mut pages = (0..4)
+            .map(|n| {
+                let front_matter = ExtractedFrontMatter {
+                    id: format!("p_{n}"),
+                    url: format!("https://example.com/{n}"),
+                    retrieved_at: "2026-01-01T00:00:00Z".to_owned(),
+                    raw_html_path: format!("raw/{n}.html"),
+                    title: format!("Page {n}"),
+                    raw_html_sha256: None,
+                    base_url: None,
+                    discovery_index: None,
+                    title_source: None,
+            strip_repeated: None,
+                    extra: BTreeMap::new(),
+                };
+                let shared_note = if n < 2 { "\n\nShared by half." } else { "" };
+                let body = format!(
+                    "# Page {n}\n\nBody {n}.{shared_note}\n\n```sh\necho hi\n\necho bye\n```\n\n{footer}"
+                );
+                (front_matter, body)
+            })
+            .collect::<Vec<_>>();
+
+        strip_repeated_blocks(&mut pages, 80);
+
+        assert_eq!(
+            pages[0].1,
+            "# Page 0\n\nBody 0.\n\nShared by half.\n\n```sh\necho hi\n\necho bye\n```"
+        );
+        assert_eq!(
+            pages[3].1,
+            "# Page 3\n\nBody 3.\n\n```sh\necho hi\n\necho bye\n```"
+        );
+
+        let mut two_pages = pages[..2].to_vec();
+        two_pages[0].1.push_str("\n\nOnly two.");
+        two_pages[1].1.push_str("\n\nOnly two.");
+        let before = two_pages.clone();
+        strip_repeated_blocks(&mut two_pages, 80);
+        assert_eq!(
+            two_pages.iter().map(|(_, body)| body).collect::<Vec<_>>(),
+            before.iter().map(|(_, body)| body).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn strip_repeated_blocks_keeps_a_shared_first_heading() {
+        let mut pages = (0..3)
+            .map(|n| {
+                let front_matter = ExtractedFrontMatter {
+                    id: format!("p_{n}"),
+                    url: format!("https://example.com/{n}"),
+                    retrieved_at: "2026-01-01T00:00:00Z".to_owned(),
+                    raw_html_path: format!("raw/{n}.html"),
+                    title: "Example Docs".to_owned(),
+                    raw_html_sha256: None,
+                    base_url: None,
+                    discovery_index: None,
+                    title_source: None,
+                    strip_repeated: None,
+                    extra: BTreeMap::new(),
+                };
+                let body = format!("# Example Docs\n\nBody {n}.\n\n## See also\n\nFooter.");
+                (front_matter, body)
+            })
+            .collect::<Vec<_>>();
+
+        strip_repeated_blocks(&mut pages, 80);
+
+        for (n, (_, body)) in pages.iter().enumerate() {
+            assert_eq!(*body, format!("# Example Docs\n\nBody {n}."));
+        }
+    }
+
+    #[test]
+    fn select_title_reads_requested_source_and_falls_back() {
+        let html = "<html><head><title>Intro | Example Docs</title>\
+<meta property=\"og:title\" content=\"Intro &amp; Setup\"></head>\
+<body><h10>skip</h10><h1 class=\"title\">Getting <em>started</em></h1></body></html>";
+
+        assert_eq!(
+            select_title(TitleSource::Readability, "Example Docs", html),
+            "Example Docs"
+        );
+        assert_eq!(
+            select_title(TitleSource::HtmlTitle, "Example Docs", html),
+            "Intro | Example Docs"
+        );
+        assert_eq!(
+            select_title(TitleSource::H1, "Example Docs", html),
+            "Getting started"
+        );
+        assert_eq!(
+            select_title(TitleSource::Og, "Example Docs", html),
+            "Intro & Setup"
+        );
+
+        let bare = "<html><head><title>Only title</title></head><body></body></html>";
+        assert_eq!(select_title(TitleSource::H1, "  ", bare), "Only title");
+        assert_eq!(select_title(TitleSource::Og, "", "<p>none</p>"), "");
+    }
+
+    #[test]
+    fn html_base_href_resolves_against_page_url() {
+        let page = "https://example.com/docs/intro";
+        assert_eq!(
+            html_base_href(
+                "<head><basefont><base target=\"_top\" href=\"../v2/\"></head>",
+                page
+            )
+            .as_deref(),
+            Some("https://example.com/v2/")
+        );
+        assert_eq!(html_base_href("<head><title>x</title></head>", page), None);
+        assert_eq!(
+            html_base_href("<base href=\"javascript:void(0)\">", page),
+            None
+        );
+    }
+
+    #[test]
+    fn nested_page_path_mirrors_url_path() {
+        let mut used = HashSet::new();
+        let root = nested_page_path("https://example.com/", "p_abc", &used);
+        assert_eq!(root, PathBuf::from("index.md"));
+        used.insert(root);
+
+        let intro = nested_page_path("https://example.com/docs/intro", "p_abc", &used);
+        assert_eq!(intro, PathBuf::from("docs/intro.md"));
+        used.insert(intro);
+
+        let html = nested_page_path("https://example.com/docs/guide.html", "p_abc", &used);
+        assert_eq!(html, PathBuf::from("docs/guide.md"));
+
+        let collision = nested_page_path(
+            "https://other.example.com/docs/intro",
+            "p_0123456789abcdef",
+            &used,
+        );
+        assert_eq!(collision, PathBuf::from("docs/intro-0123456789ab.md"));
+        used.insert(collision);
+
+        // Another id with the same 12-character prefix gets the full id instead.
+        let prefix_collision = nested_page_path(
+            "https://third.example.com/docs/intro",
+            "p_0123456789abffff",
+            &used,
+        );
+        assert_eq!(
+            prefix_collision,
+            PathBuf::from("docs/intro-0123456789abffff.md")
+        );
+        used.insert(prefix_collision);
+
+        let repeat = nested_page_path(
+            "https://fourth.example.com/docs/intro",
+            "p_0123456789abffff",
+            &used,
+        );
+        assert_eq!(repeat, PathBuf::from("docs/intro-0123456789abffff-2.md"));
+    }
+
     #[test]
     fn strip_mdbook_keyboard_shortcuts_help_japanese() {
         let input = "\
@@ -476,4 +1268,44 @@ Keep.
         assert!(out.contains("## Next"));
         assert!(out.contains("Keep."));
     }
+
+    #[test]
+    fn extract_with_readability_keeps_code_block_languages() -> anyhow::Result<()> {
+        let readability = Readability::new().context("initialize readability-js")?;
+        let paragraph = "<p>This paragraph explains the examples below in enough words for readability to keep the article body around.</p>";
+        let html = format!(
+            "<html><head><title>Code</title></head><body><article><h1>Code</h1>{paragraph}\
+<pre><code class=\"language-python\">print(1)\n</code></pre>{paragraph}\
+<div class=\"language-ruby highlighter-rouge\"><div class=\"highlight\"><pre class=\"highlight\"><code>puts 1\n</code></pre></div></div>{paragraph}\
+<pre><code class=\"hljs bash\">ls -la\n</code></pre>{paragraph}\
+<pre><code>no language\n</code></pre>{paragraph}</article></body></html>"
+        );
+
+        let extracted = extract_with_readability(&readability, &html, "https://example.com/code")
+            .map_err(|err| anyhow::anyhow!("readability: {err}"))?;
+        let md = extracted.body_md;
+        assert!(md.contains("```python\nprint(1)"), "{md}");
+        assert!(md.contains("```ruby\nputs 1"), "{md}");
+        assert!(md.contains("```bash\nls -la"), "{md}");
+        assert!(md.contains("```\nno language"), "{md}");
+        assert!(!md.contains(CODE_LANGUAGE_MARKER), "{md}");
+        Ok(())
+    }
+
+    #[test]
+    fn code_language_from_tag_recognizes_highlighter_classes() {
+        for (tag, language) in [
+            ("<code class=\"language-rust\">", Some("rust")),
+            ("<pre class=\"lang-go prettyprint\">", Some("go")),
+            ("<div class=\"highlight-source-shell\">", Some("shell")),
+            ("<pre class=\"sourceCode haskell\">", Some("haskell")),
+            ("<pre data-lang=\"TOML\">", Some("toml")),
+            ("<code class=\"hljs\">", None),
+            ("<div class=\"highlight\">", None),
+            ("<code class=\"language-none\">", None),
+            ("<pre>", None),
+        ] {
+            assert_eq!(code_language_from_tag(tag).as_deref(), language, "{tag}");
+        }
+    }
 }
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,6 +13,14 @@ pub struct CrawlRecord {
     pub retrieved_at: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub raw_html_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_html_sha256: Option<String>,
+    /// Requested URLs that redirected to `normalized_url`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redirected_from: Vec<String>,
+    /// Position of the page in crawl discovery order (see `toc create --page-order crawl`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discovery_index: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +30,24 @@ pub struct ExtractedFrontMatter {
     pub retrieved_at: String,
     pub raw_html_path: String,
     pub title: String,
+    /// sha256 of the Raw HTML the page was extracted from (used by `extract --if-modified`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_html_sha256: Option<String>,
+    /// Absolute `<base href>` of the page; relative links resolve against it instead of `url`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// Crawl discovery position carried over from the `CrawlRecord`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discovery_index: Option<u64>,
+    /// `extract --title-source` the title was taken from; absent for the default (readability).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title_source: Option<crate::cli::TitleSource>,
+    /// `extract --strip-repeated-threshold` the body was stripped with; absent when not stripped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strip_repeated: Option<u8>,
+    /// Fields this version does not know about, kept so they survive re-serialization.
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_yaml::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,6 +57,12 @@ pub struct ManifestRecord {
     pub title: String,
     pub path: String,
     pub extracted_md: String,
+    /// `<base href>` carried over from the extracted page (see `ExtractedFrontMatter`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// Crawl discovery position carried over from the extracted page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discovery_index: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,3 +91,35 @@ pub struct TocSection {
     pub title: String,
     pub sources: Vec<String>,
 }
+
+/// `book.json` written by `export book`: the TOC tree with each chapter's rendered Markdown.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookExport {
+    pub book_title: String,
+    pub parts: Vec<BookExportPart>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookExportPart {
+    pub title: String,
+    pub chapters: Vec<BookExportChapter>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookExportChapter {
+    pub id: String,
+    pub title: String,
+    pub intent: String,
+    pub reader_gains: Vec<String>,
+    pub sections: Vec<BookExportSection>,
+    /// The whole chapter as `book render` would write it (headings, footnotes, `## Sources`).
+    pub markdown: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookExportSection {
+    pub title: String,
+    pub source_urls: Vec<String>,
+    /// Rendered body of the section, without its `##` heading.
+    pub markdown: String,
+}
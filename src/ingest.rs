@@ -0,0 +1,185 @@
+use std::fs::OpenOptions;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use sha2::{Digest as _, Sha256};
+use url::Url;
+
+use crate::cli::{IngestArgs, ManifestArgs};
+use crate::formats::ExtractedFrontMatter;
+
+pub fn run(args: IngestArgs) -> anyhow::Result<()> {
+    let source_dir = PathBuf::from(&args.dir);
+    let out_dir = PathBuf::from(&args.out);
+    let manifest_path = PathBuf::from(&args.manifest);
+
+    if out_dir.exists() {
+        anyhow::bail!(
+            "extracted snapshot output directory already exists: {}",
+            out_dir.display()
+        );
+    }
+    if manifest_path.exists() {
+        anyhow::bail!(
+            "manifest output already exists: {}",
+            manifest_path.display()
+        );
+    }
+
+    let source_dir = std::fs::canonicalize(&source_dir)
+        .with_context(|| format!("resolve ingest dir: {}", source_dir.display()))?;
+    let markdown_files = collect_markdown_files(&source_dir)?;
+    if markdown_files.is_empty() {
+        anyhow::bail!("no Markdown files found under: {}", source_dir.display());
+    }
+
+    let pages_dir = out_dir.join("pages");
+    std::fs::create_dir_all(&pages_dir)
+        .with_context(|| format!("create extracted pages dir: {}", pages_dir.display()))?;
+
+    for path in markdown_files {
+        let relative = path
+            .strip_prefix(&source_dir)
+            .context("strip ingest dir prefix")?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+            .join("/");
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("read markdown file: {}", path.display()))?;
+        let body = strip_front_matter(&contents).trim();
+
+        let title = first_heading_title(body).unwrap_or_else(|| {
+            path.file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| relative.clone())
+        });
+        let url = Url::from_file_path(&path)
+            .map_err(|()| anyhow::anyhow!("build file url: {}", path.display()))?;
+        let retrieved_at = std::fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339())
+            .unwrap_or_else(|_| chrono::Utc::now().to_rfc3339());
+
+        let id = page_id_from_relative_path(&relative);
+        let front_matter = ExtractedFrontMatter {
+            id: id.clone(),
+            url: url.to_string(),
+            retrieved_at,
+            raw_html_path: path.to_string_lossy().to_string(),
+            title,
+            raw_html_sha256: None,
+            base_url: None,
+            discovery_index: None,
+            title_source: None,
+            strip_repeated: None,
+            extra: Default::default(),
+        };
+
+        let body_md = if body.starts_with('#') {
+            body.to_owned()
+        } else {
+            format!("# {}\n\n{body}", front_matter.title)
+        };
+
+        let yaml =
+            serde_yaml::to_string(&front_matter).context("serialize extracted front matter")?;
+        let markdown = format!("---\n{yaml}---\n\n{body_md}\n");
+
+        let out_path = pages_dir.join(format!("{id}.md"));
+        let mut file = OpenOptions::new()
+            .create_new(true)
+            .write(true)
+            .open(&out_path)
+            .with_context(|| format!("create extracted page: {}", out_path.display()))?;
+        file.write_all(markdown.as_bytes())
+            .with_context(|| format!("write extracted page: {}", out_path.display()))?;
+    }
+
+    crate::manifest::run(ManifestArgs {
+        extracted: out_dir.to_string_lossy().to_string(),
+        out: manifest_path.to_string_lossy().to_string(),
+    })
+    .context("manifest")?;
+
+    Ok(())
+}
+
+fn collect_markdown_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(current) = stack.pop() {
+        for entry in std::fs::read_dir(&current)
+            .with_context(|| format!("read ingest dir: {}", current.display()))?
+        {
+            let path = entry?.path();
+            let hidden = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with('.'));
+            if hidden {
+                continue;
+            }
+            if path.is_dir() {
+                stack.push(path);
+                continue;
+            }
+            let is_markdown = path.extension().and_then(|e| e.to_str()).is_some_and(|e| {
+                e.eq_ignore_ascii_case("md") || e.eq_ignore_ascii_case("markdown")
+            });
+            if is_markdown {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn strip_front_matter(contents: &str) -> &str {
+    let Some(rest) = contents
+        .strip_prefix("---\n")
+        .or_else(|| contents.strip_prefix("---\r\n"))
+    else {
+        return contents;
+    };
+    let mut offset = 0usize;
+    for line in rest.split_inclusive('\n') {
+        offset += line.len();
+        if line.trim_end() == "---" {
+            return &rest[offset..];
+        }
+    }
+    contents
+}
+
+fn first_heading_title(body: &str) -> Option<String> {
+    let mut in_fence = false;
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence || !trimmed.starts_with('#') {
+            continue;
+        }
+        let level = trimmed.chars().take_while(|c| *c == '#').count();
+        let rest = &trimmed[level..];
+        if level > 6 || !(rest.is_empty() || rest.starts_with(' ')) {
+            continue;
+        }
+        let title = rest.trim().trim_end_matches('#').trim();
+        if !title.is_empty() {
+            return Some(title.to_owned());
+        }
+    }
+    None
+}
+
+fn page_id_from_relative_path(relative: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(relative.as_bytes());
+    format!("p_{}", hex::encode(hasher.finalize()))
+}
@@ -10,9 +10,14 @@ pub mod extract;
 pub mod formats;
 pub mod google;
 pub mod grpc;
+pub mod ingest;
 pub mod logging;
 pub mod manifest;
+pub mod net_guard;
 pub mod openai;
+pub mod proxy;
 pub mod raw_store;
 pub mod rewrite;
+pub mod tls;
 pub mod toc;
+pub mod url_norm;
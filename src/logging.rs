@@ -1,16 +1,208 @@
+use std::fmt;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
 use anyhow::Context as _;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::cli::LogFormat;
+
+/// Target of periodic progress and per-request logs; `--quiet` turns it off.
+pub const PROGRESS_TARGET: &str = "sitebookify::progress";
+
+const DEFAULT_PROGRESS_INTERVAL: Duration = Duration::from_secs(2);
 
-pub fn init() -> anyhow::Result<()> {
-    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+/// Installs the stderr subscriber. `format` (from `--log-format`) wins over
+/// `SITEBOOKIFY_LOG_FORMAT`; the default is human-readable text. `quiet` drops
+/// [`PROGRESS_TARGET`] events and keeps everything else.
+pub fn init(format: Option<LogFormat>, quiet: bool) -> anyhow::Result<()> {
+    let mut filter = tracing_subscriber::EnvFilter::try_from_default_env()
         .or_else(|_| tracing_subscriber::EnvFilter::try_new("info"))
         .context("build log filter")?;
+    if quiet {
+        filter = filter.add_directive(
+            format!("{PROGRESS_TARGET}=off")
+                .parse()
+                .context("build quiet log directive")?,
+        );
+    }
 
-    tracing_subscriber::fmt()
+    let format = match format {
+        Some(format) => format,
+        None => log_format_from_env()?,
+    };
+
+    let builder = tracing_subscriber::fmt()
         .with_env_filter(filter)
         .with_target(false)
-        .with_writer(std::io::stderr)
-        .try_init()
-        .map_err(|err| anyhow::anyhow!("initialize tracing subscriber: {err}"))?;
+        .with_writer(std::io::stderr);
+    let result = match format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder.event_format(JsonFormat).try_init(),
+    };
+    result.map_err(|err| anyhow::anyhow!("initialize tracing subscriber: {err}"))?;
 
     Ok(())
 }
+
+fn log_format_from_env() -> anyhow::Result<LogFormat> {
+    let Ok(raw) = std::env::var("SITEBOOKIFY_LOG_FORMAT") else {
+        return Ok(LogFormat::Text);
+    };
+    match raw.trim().to_ascii_lowercase().as_str() {
+        "" | "text" => Ok(LogFormat::Text),
+        "json" => Ok(LogFormat::Json),
+        other => anyhow::bail!("invalid SITEBOOKIFY_LOG_FORMAT: {other} (expected text or json)"),
+    }
+}
+
+/// Counts finished steps of a long stage and logs `done`/`total` under [`PROGRESS_TARGET`]
+/// at most once per `SITEBOOKIFY_PROGRESS_INTERVAL_SECS` (default 2; `0` logs every step).
+pub struct Progress {
+    label: &'static str,
+    total: usize,
+    done: AtomicUsize,
+    interval: Duration,
+    last_logged: Mutex<Instant>,
+}
+
+impl Progress {
+    pub fn new(label: &'static str, total: usize) -> anyhow::Result<Self> {
+        Ok(Self {
+            label,
+            total,
+            done: AtomicUsize::new(0),
+            interval: progress_interval_from_env()?,
+            last_logged: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// Records one finished step.
+    pub fn inc(&self) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        let Ok(mut last_logged) = self.last_logged.lock() else {
+            return;
+        };
+        if last_logged.elapsed() < self.interval {
+            return;
+        }
+        *last_logged = Instant::now();
+        tracing::info!(
+            target: PROGRESS_TARGET,
+            done,
+            total = self.total,
+            "{}: progress",
+            self.label
+        );
+    }
+
+    pub fn done(&self) -> usize {
+        self.done.load(Ordering::Relaxed)
+    }
+}
+
+fn progress_interval_from_env() -> anyhow::Result<Duration> {
+    let Ok(raw) = std::env::var("SITEBOOKIFY_PROGRESS_INTERVAL_SECS") else {
+        return Ok(DEFAULT_PROGRESS_INTERVAL);
+    };
+    let secs = raw.trim().parse::<u64>().with_context(|| {
+        format!("invalid SITEBOOKIFY_PROGRESS_INTERVAL_SECS: {raw} (expected whole seconds)")
+    })?;
+    Ok(Duration::from_secs(secs))
+}
+
+/// One JSON object per line: `timestamp` (RFC 3339, UTC), `level`, `target`, `message`,
+/// `fields` and the enclosing `spans` (outermost first).
+struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let metadata = event.metadata();
+        let mut visitor = JsonFieldVisitor::default();
+        event.record(&mut visitor);
+
+        let mut line = serde_json::Map::new();
+        line.insert(
+            "timestamp".to_owned(),
+            chrono::Utc::now()
+                .to_rfc3339_opts(chrono::SecondsFormat::Micros, true)
+                .into(),
+        );
+        line.insert("level".to_owned(), metadata.level().as_str().into());
+        line.insert("target".to_owned(), metadata.target().into());
+        if let Some(message) = visitor.message {
+            line.insert("message".to_owned(), message.into());
+        }
+        if !visitor.fields.is_empty() {
+            line.insert("fields".to_owned(), visitor.fields.into());
+        }
+        if let Some(scope) = ctx.event_scope() {
+            let spans = scope
+                .from_root()
+                .map(|span| serde_json::Value::from(span.name()))
+                .collect::<Vec<_>>();
+            line.insert("spans".to_owned(), spans.into());
+        }
+
+        writeln!(writer, "{}", serde_json::Value::Object(line))
+    }
+}
+
+#[derive(Default)]
+struct JsonFieldVisitor {
+    message: Option<String>,
+    fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl JsonFieldVisitor {
+    fn insert(&mut self, field: &Field, value: serde_json::Value) {
+        if field.name() == "message" {
+            self.message = Some(match value {
+                serde_json::Value::String(message) => message,
+                other => other.to_string(),
+            });
+        } else {
+            self.fields.insert(field.name().to_owned(), value);
+        }
+    }
+}
+
+impl Visit for JsonFieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, format!("{value:?}").into());
+    }
+}
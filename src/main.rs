@@ -14,28 +14,95 @@ async fn main() -> ExitCode {
 }
 
 async fn try_main() -> anyhow::Result<()> {
-    sitebookify::logging::init().context("init logging")?;
-
     let cli = sitebookify::cli::Cli::parse();
+    sitebookify::logging::init(cli.log_format, cli.quiet).context("init logging")?;
     tracing::debug!(?cli, "parsed cli");
 
     match cli.command {
         sitebookify::cli::Command::Build(args) => {
             sitebookify::build::run(args).await.context("build")?;
         }
-        sitebookify::cli::Command::Crawl(args) => {
+        sitebookify::cli::Command::Crawl {
+            command: Some(sitebookify::cli::CrawlCommand::Verify(args)),
+            ..
+        } => {
+            sitebookify::raw_store::verify(args).context("crawl verify")?;
+        }
+        sitebookify::cli::Command::Crawl {
+            command: None,
+            args: Some(args),
+        } => {
             sitebookify::crawl::run(args).await.context("crawl")?;
         }
+        sitebookify::cli::Command::Crawl {
+            command: None,
+            args: None,
+        } => {
+            anyhow::bail!("crawl requires --url and --out (or a subcommand)");
+        }
         sitebookify::cli::Command::Extract(args) => {
             sitebookify::extract::run(args).context("extract")?;
         }
-        sitebookify::cli::Command::Manifest(args) => {
+        sitebookify::cli::Command::Ingest(args) => {
+            sitebookify::ingest::run(args).context("ingest")?;
+        }
+        sitebookify::cli::Command::Manifest {
+            command: Some(sitebookify::cli::ManifestCommand::Show(args)),
+            ..
+        } => {
+            sitebookify::manifest::show(args).context("manifest show")?;
+        }
+        sitebookify::cli::Command::Manifest {
+            command: Some(sitebookify::cli::ManifestCommand::Merge(args)),
+            ..
+        } => {
+            sitebookify::manifest::merge(args).context("manifest merge")?;
+        }
+        sitebookify::cli::Command::Manifest {
+            command: Some(sitebookify::cli::ManifestCommand::Split(args)),
+            ..
+        } => {
+            sitebookify::manifest::split(args).context("manifest split")?;
+        }
+        sitebookify::cli::Command::Manifest {
+            command: Some(sitebookify::cli::ManifestCommand::Validate(args)),
+            ..
+        } => {
+            sitebookify::manifest::validate(args).context("manifest validate")?;
+        }
+        sitebookify::cli::Command::Manifest {
+            command: None,
+            args: Some(args),
+        } => {
             sitebookify::manifest::run(args).context("manifest")?;
         }
+        sitebookify::cli::Command::Manifest {
+            command: None,
+            args: None,
+        } => {
+            anyhow::bail!("manifest requires --extracted and --out (or a subcommand)");
+        }
         sitebookify::cli::Command::Toc {
             command: sitebookify::cli::TocCommand::Create(args),
         } => {
-            sitebookify::toc::create(args).await.context("toc create")?;
+            sitebookify::toc::create(*args)
+                .await
+                .context("toc create")?;
+        }
+        sitebookify::cli::Command::Toc {
+            command: sitebookify::cli::TocCommand::Split(args),
+        } => {
+            sitebookify::toc::split(args).context("toc split")?;
+        }
+        sitebookify::cli::Command::Toc {
+            command: sitebookify::cli::TocCommand::Merge(args),
+        } => {
+            sitebookify::toc::merge(args).context("toc merge")?;
+        }
+        sitebookify::cli::Command::Toc {
+            command: sitebookify::cli::TocCommand::Check(args),
+        } => {
+            sitebookify::toc::check(args).context("toc check")?;
         }
         sitebookify::cli::Command::Book {
             command: sitebookify::cli::BookCommand::Init(args),
@@ -45,7 +112,7 @@ async fn try_main() -> anyhow::Result<()> {
         sitebookify::cli::Command::Book {
             command: sitebookify::cli::BookCommand::Render(args),
         } => {
-            tokio::task::block_in_place(|| sitebookify::book::render(args))
+            tokio::task::block_in_place(|| sitebookify::book::render(*args))
                 .context("book render")?;
         }
         sitebookify::cli::Command::Book {
@@ -58,6 +125,22 @@ async fn try_main() -> anyhow::Result<()> {
         } => {
             sitebookify::book::epub(args).context("book epub")?;
         }
+        sitebookify::cli::Command::Book {
+            command: sitebookify::cli::BookCommand::Stats(args),
+        } => {
+            sitebookify::book::stats(args).context("book stats")?;
+        }
+        sitebookify::cli::Command::Book {
+            command: sitebookify::cli::BookCommand::Validate(args),
+        } => {
+            sitebookify::book::validate(args).context("book validate")?;
+        }
+        sitebookify::cli::Command::Export {
+            command: sitebookify::cli::ExportCommand::Book(args),
+        } => {
+            tokio::task::block_in_place(|| sitebookify::book::export(*args))
+                .context("export book")?;
+        }
     }
 
     Ok(())
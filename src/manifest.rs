@@ -1,13 +1,48 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::OpenOptions;
-use std::io::Write as _;
-use std::path::PathBuf;
+use std::io::{BufRead as _, BufReader, Write as _};
+use std::path::{Path, PathBuf};
 
 use anyhow::Context as _;
 use url::Url;
 
-use crate::cli::ManifestArgs;
+use crate::cli::{
+    ManifestArgs, ManifestMergeArgs, ManifestShowArgs, ManifestShowFormat, ManifestSplitArgs,
+    ManifestSplitKey, ManifestValidateArgs,
+};
 use crate::formats::{ExtractedFrontMatter, ManifestRecord};
 
+/// Orders paths the way people read them: digit runs compare by value, so `chapter-2`
+/// sorts before `chapter-10`. Ties (e.g. `01` vs `1`) fall back to byte order.
+pub fn natural_path_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    let (mut a_rest, mut b_rest) = (a, b);
+    while let (Some(a_ch), Some(b_ch)) = (a_rest.chars().next(), b_rest.chars().next()) {
+        if a_ch.is_ascii_digit() && b_ch.is_ascii_digit() {
+            let a_len = a_rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(a_rest.len());
+            let b_len = b_rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(b_rest.len());
+            let a_num = a_rest[..a_len].trim_start_matches('0');
+            let b_num = b_rest[..b_len].trim_start_matches('0');
+            let ordering = a_num.len().cmp(&b_num.len()).then_with(|| a_num.cmp(b_num));
+            if ordering.is_ne() {
+                return ordering;
+            }
+            a_rest = &a_rest[a_len..];
+            b_rest = &b_rest[b_len..];
+        } else {
+            if a_ch != b_ch {
+                return a_ch.cmp(&b_ch);
+            }
+            a_rest = &a_rest[a_ch.len_utf8()..];
+            b_rest = &b_rest[b_ch.len_utf8()..];
+        }
+    }
+    a_rest.len().cmp(&b_rest.len()).then_with(|| a.cmp(b))
+}
+
 pub fn run(args: ManifestArgs) -> anyhow::Result<()> {
     let extracted_dir = PathBuf::from(&args.extracted);
     let out_path = PathBuf::from(&args.out);
@@ -19,15 +54,7 @@ pub fn run(args: ManifestArgs) -> anyhow::Result<()> {
     let pages_dir = extracted_dir.join("pages");
     let mut records = Vec::new();
 
-    for entry in std::fs::read_dir(&pages_dir)
-        .with_context(|| format!("read extracted pages dir: {}", pages_dir.display()))?
-    {
-        let entry = entry?;
-        let path = entry.path();
-        if path.extension().and_then(|e| e.to_str()) != Some("md") {
-            continue;
-        }
-
+    for path in collect_markdown_files(&pages_dir)? {
         let contents = std::fs::read_to_string(&path)
             .with_context(|| format!("read extracted page: {}", path.display()))?;
         let front = parse_front_matter(&contents)
@@ -40,11 +67,13 @@ pub fn run(args: ManifestArgs) -> anyhow::Result<()> {
             title: front.title,
             path: url.path().to_owned(),
             extracted_md: path.to_string_lossy().to_string(),
+            base_url: front.base_url,
+            discovery_index: front.discovery_index,
         };
         records.push(record);
     }
 
-    records.sort_by(|a, b| a.path.cmp(&b.path));
+    records.sort_by(|a, b| natural_path_cmp(&a.path, &b.path));
 
     let mut out = OpenOptions::new()
         .create_new(true)
@@ -60,7 +89,332 @@ pub fn run(args: ManifestArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn parse_front_matter(contents: &str) -> anyhow::Result<ExtractedFrontMatter> {
+/// Concatenates manifests in input order. Records repeating an earlier id are dropped when
+/// identical and rejected when they differ; `--dedupe-by-url` also drops later records whose
+/// canonical URL was already seen.
+pub fn merge(args: ManifestMergeArgs) -> anyhow::Result<()> {
+    let out_path = PathBuf::from(&args.out);
+    if out_path.exists() {
+        anyhow::bail!("manifest output already exists: {}", out_path.display());
+    }
+
+    let mut records: Vec<ManifestRecord> = Vec::new();
+    let mut by_id: HashMap<String, (usize, String)> = HashMap::new();
+    let mut seen_urls: HashMap<String, String> = HashMap::new();
+    for input in &args.inputs {
+        let input_path = PathBuf::from(input);
+        let file = std::fs::File::open(&input_path)
+            .with_context(|| format!("open manifest: {}", input_path.display()))?;
+        for (idx, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.context("read manifest line")?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: ManifestRecord = serde_json::from_str(&line).with_context(|| {
+                format!(
+                    "parse manifest record ({}:{})",
+                    input_path.display(),
+                    idx + 1
+                )
+            })?;
+
+            if let Some((existing, source)) = by_id.get(&record.id) {
+                let existing = &records[*existing];
+                if serde_json::to_value(existing)? != serde_json::to_value(&record)? {
+                    anyhow::bail!(
+                        "conflicting manifest records for id {}: {source} and {}:{}",
+                        record.id,
+                        input_path.display(),
+                        idx + 1
+                    );
+                }
+                continue;
+            }
+
+            if args.dedupe_by_url {
+                let url = Url::parse(&record.url)
+                    .with_context(|| format!("parse manifest url: {}", record.url))?;
+                // Records of a `--keep-query` crawl differ only in their query.
+                let canonical = crate::url_norm::QueryPolicy::keeping_query()
+                    .canonical_url(&url)
+                    .to_string();
+                if let Some(kept_id) = seen_urls.get(&canonical) {
+                    tracing::info!(
+                        id = %record.id,
+                        kept = %kept_id,
+                        url = %record.url,
+                        "manifest merge: dropping duplicate url"
+                    );
+                    continue;
+                }
+                seen_urls.insert(canonical, record.id.clone());
+            }
+
+            by_id.insert(
+                record.id.clone(),
+                (
+                    records.len(),
+                    format!("{}:{}", input_path.display(), idx + 1),
+                ),
+            );
+            records.push(record);
+        }
+    }
+
+    let mut out = OpenOptions::new()
+        .create_new(true)
+        .write(true)
+        .open(&out_path)
+        .with_context(|| format!("create manifest: {}", out_path.display()))?;
+    for record in &records {
+        serde_json::to_writer(&mut out, record).context("serialize manifest record")?;
+        out.write_all(b"\n").context("write manifest newline")?;
+    }
+    out.flush().context("flush manifest")?;
+
+    Ok(())
+}
+
+pub fn split(args: ManifestSplitArgs) -> anyhow::Result<()> {
+    let manifest_path = PathBuf::from(&args.manifest);
+    let file = std::fs::File::open(&manifest_path)
+        .with_context(|| format!("open manifest: {}", manifest_path.display()))?;
+
+    let mut groups: BTreeMap<String, Vec<ManifestRecord>> = BTreeMap::new();
+    for (idx, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.context("read manifest line")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: ManifestRecord = serde_json::from_str(&line).with_context(|| {
+            format!(
+                "parse manifest record ({}:{})",
+                manifest_path.display(),
+                idx + 1
+            )
+        })?;
+        let key = match args.by {
+            ManifestSplitKey::Host => Url::parse(&record.url)
+                .ok()
+                .and_then(|url| url.host_str().map(|host| host.to_ascii_lowercase())),
+        };
+        let Some(key) = key else {
+            tracing::warn!(
+                id = %record.id,
+                url = %record.url,
+                "manifest split: skipping record without a parseable url host"
+            );
+            continue;
+        };
+        groups.entry(key).or_default().push(record);
+    }
+
+    let out_dir = PathBuf::from(&args.out_dir);
+    let out_paths = groups
+        .keys()
+        .map(|key| out_dir.join(format!("manifest.{key}.jsonl")))
+        .collect::<Vec<_>>();
+    // Refuse before writing anything, so a rerun never leaves a mix of old and new files.
+    if let Some(existing) = out_paths.iter().find(|path| path.exists()) {
+        anyhow::bail!("manifest output already exists: {}", existing.display());
+    }
+    std::fs::create_dir_all(&out_dir)
+        .with_context(|| format!("create manifest split dir: {}", out_dir.display()))?;
+
+    for ((key, records), out_path) in groups.iter().zip(&out_paths) {
+        let mut out = OpenOptions::new()
+            .create_new(true)
+            .write(true)
+            .open(out_path)
+            .with_context(|| format!("create manifest: {}", out_path.display()))?;
+        for record in records {
+            serde_json::to_writer(&mut out, record).context("serialize manifest record")?;
+            out.write_all(b"\n").context("write manifest newline")?;
+        }
+        out.flush().context("flush manifest")?;
+        tracing::info!(
+            key = %key,
+            records = records.len(),
+            path = %out_path.display(),
+            "manifest split: wrote manifest"
+        );
+    }
+
+    Ok(())
+}
+
+const MANIFEST_FIELDS: &[&str] = &[
+    "id",
+    "url",
+    "title",
+    "path",
+    "extracted_md",
+    "base_url",
+    "discovery_index",
+];
+const TABLE_DEFAULT_FIELDS: &[&str] = &["id", "url", "title"];
+
+/// Checks what `book render` needs from the manifest: the `toc check` errors and warnings,
+/// plus unique record ids and that every record's `extracted_md` exists.
+pub fn validate(args: ManifestValidateArgs) -> anyhow::Result<()> {
+    let toc = crate::toc::read_toc(&PathBuf::from(&args.toc))?;
+    let records = crate::toc::read_manifest_records(&PathBuf::from(&args.manifest))
+        .context("read manifest")?;
+    let manifest_ids = records
+        .iter()
+        .map(|record| record.id.as_str())
+        .collect::<HashSet<_>>();
+
+    let mut report = crate::toc::check_toc(&toc, Some(&manifest_ids));
+    let mut seen = HashSet::new();
+    for record in &records {
+        if !seen.insert(record.id.as_str()) {
+            report
+                .errors
+                .push(format!("duplicate manifest id: {}", record.id));
+        }
+        if !Path::new(&record.extracted_md).is_file() {
+            report.errors.push(format!(
+                "extracted_md not found for {}: {}",
+                record.id, record.extracted_md
+            ));
+        }
+    }
+    report.print();
+
+    println!(
+        "{} record(s), {} source reference(s): {} error(s), {} warning(s)",
+        records.len(),
+        report.referenced.len(),
+        report.errors.len(),
+        report.orphans.len()
+    );
+    if !report.errors.is_empty() {
+        anyhow::bail!("manifest validate failed: {} error(s)", report.errors.len());
+    }
+    Ok(())
+}
+
+pub fn show(args: ManifestShowArgs) -> anyhow::Result<()> {
+    let manifest_path = PathBuf::from(&args.manifest);
+    let file = std::fs::File::open(&manifest_path)
+        .with_context(|| format!("open manifest: {}", manifest_path.display()))?;
+
+    let mut fields = args
+        .fields
+        .iter()
+        .map(|f| f.trim())
+        .filter(|f| !f.is_empty())
+        .collect::<Vec<_>>();
+    if let Some(unknown) = fields.iter().find(|f| !MANIFEST_FIELDS.contains(f)) {
+        anyhow::bail!(
+            "unknown manifest field: {unknown} (expected: {})",
+            MANIFEST_FIELDS.join(", ")
+        );
+    }
+    // Unset optional fields are only written (as `null`) when asked for by name.
+    let skip_unset = fields.is_empty() && !matches!(args.format, ManifestShowFormat::Table);
+    if fields.is_empty() {
+        fields = match args.format {
+            ManifestShowFormat::Table => TABLE_DEFAULT_FIELDS.to_vec(),
+            ManifestShowFormat::Jsonl | ManifestShowFormat::Json => MANIFEST_FIELDS.to_vec(),
+        };
+    }
+
+    let mut rows = Vec::new();
+    for (idx, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.context("read manifest line")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: ManifestRecord = serde_json::from_str(&line)
+            .with_context(|| format!("parse manifest record (line {})", idx + 1))?;
+        let value = serde_json::to_value(&record).context("convert manifest record")?;
+        rows.push(ProjectedRecord(
+            fields
+                .iter()
+                .map(|f| (*f, value[*f].clone()))
+                .filter(|(_, value)| !(skip_unset && value.is_null()))
+                .collect(),
+        ));
+    }
+
+    let mut out = std::io::stdout().lock();
+    match args.format {
+        ManifestShowFormat::Jsonl => {
+            for row in &rows {
+                let json = to_json(row, args.pretty).context("serialize manifest record")?;
+                writeln!(out, "{json}")?;
+            }
+        }
+        ManifestShowFormat::Json => {
+            let json = to_json(&rows, args.pretty).context("serialize manifest")?;
+            writeln!(out, "{json}")?;
+        }
+        ManifestShowFormat::Table => {
+            writeln!(out, "{}", fields.join("\t"))?;
+            for row in &rows {
+                let cells = row
+                    .0
+                    .iter()
+                    .map(|(_, value)| match value {
+                        serde_json::Value::String(text) => text.replace(['\t', '\n'], " "),
+                        serde_json::Value::Null => String::new(),
+                        other => other.to_string(),
+                    })
+                    .collect::<Vec<_>>();
+                writeln!(out, "{}", cells.join("\t"))?;
+            }
+        }
+    }
+    out.flush().context("flush stdout")?;
+    Ok(())
+}
+
+/// Selected manifest fields, serialized in the order they were requested.
+struct ProjectedRecord<'a>(Vec<(&'a str, serde_json::Value)>);
+
+impl serde::Serialize for ProjectedRecord<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap as _;
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (key, value) in &self.0 {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+fn to_json<T: serde::Serialize>(value: &T, pretty: bool) -> serde_json::Result<String> {
+    if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    }
+}
+
+/// Collects `*.md` files under `pages/`, including nested layouts.
+pub fn collect_markdown_files(pages_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut stack = vec![pages_dir.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        for entry in std::fs::read_dir(&dir)
+            .with_context(|| format!("read extracted pages dir: {}", dir.display()))?
+        {
+            let path = entry?.path();
+            if path.is_dir() {
+                stack.push(path);
+                continue;
+            }
+            if path.extension().and_then(|e| e.to_str()) == Some("md") {
+                files.push(path);
+            }
+        }
+    }
+    Ok(files)
+}
+
+pub fn parse_front_matter(contents: &str) -> anyhow::Result<ExtractedFrontMatter> {
     let mut lines = contents.lines();
     let first = lines
         .next()
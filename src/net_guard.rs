@@ -0,0 +1,435 @@
+//! Keeps server-side fetches of user-supplied URLs away from internal networks.
+//!
+//! [`check_public_http_url`] validates a URL up front. Once [`enforce_public_addresses`] has been
+//! called (the app server does so unless `SITEBOOKIFY_ALLOW_PRIVATE_NETWORKS=1`), clients built
+//! with [`apply`] / [`apply_blocking`] and [`redirect_policy`] also refuse to connect to
+//! non-public addresses: host names are checked after DNS resolution, and IP literals are
+//! checked by [`check_ip_literal`] before each request and on every redirect, so neither DNS
+//! rebinding nor a redirect can reach e.g. `169.254.169.254`. The CLI never enforces this, so
+//! crawling an intranet or a local dev server keeps working.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use url::Url;
+
+static ENFORCE_PUBLIC_ADDRESSES: AtomicBool = AtomicBool::new(false);
+
+/// Turns the connect-time guard on for the rest of the process.
+pub fn enforce_public_addresses() {
+    ENFORCE_PUBLIC_ADDRESSES.store(true, Ordering::SeqCst);
+}
+
+pub fn is_enforced() -> bool {
+    ENFORCE_PUBLIC_ADDRESSES.load(Ordering::SeqCst)
+}
+
+/// Adds the connect-time address check when enforcement is on; otherwise returns `builder`
+/// unchanged. Pair it with [`redirect_policy`].
+pub fn apply(builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+    if !is_enforced() {
+        return builder;
+    }
+    builder.dns_resolver(Arc::new(PublicOnlyResolver))
+}
+
+/// Blocking counterpart of [`apply`].
+pub fn apply_blocking(
+    builder: reqwest::blocking::ClientBuilder,
+) -> reqwest::blocking::ClientBuilder {
+    if !is_enforced() {
+        return builder;
+    }
+    builder.dns_resolver(Arc::new(PublicOnlyResolver))
+}
+
+/// Fails for a non-public IP-literal host when enforcement is on. reqwest connects to IP
+/// literals without asking the resolver added in [`apply`], so call this before each request.
+pub fn check_ip_literal(url: &Url) -> anyhow::Result<()> {
+    ensure_public_ip_literal(url, is_enforced())
+}
+
+fn ensure_public_ip_literal(url: &Url, block_private: bool) -> anyhow::Result<()> {
+    if block_private
+        && let Some(ip) = ip_literal(url)
+        && !is_public_ip(ip)
+    {
+        anyhow::bail!("connection to a non-public address is blocked: {ip}");
+    }
+    Ok(())
+}
+
+fn ip_literal(url: &Url) -> Option<IpAddr> {
+    match url.host() {
+        Some(url::Host::Ipv4(ip)) => Some(IpAddr::V4(ip)),
+        Some(url::Host::Ipv6(ip)) => Some(IpAddr::V6(ip)),
+        _ => None,
+    }
+}
+
+/// Follows up to `max_redirects` hops. With `same_host_only`, a hop off the first URL's host
+/// stops and leaves the 3xx response. When enforcement is on, hops to non-public IP literals
+/// fail (host names are covered by the resolver added in [`apply`]).
+pub fn redirect_policy(max_redirects: usize, same_host_only: bool) -> reqwest::redirect::Policy {
+    build_redirect_policy(max_redirects, same_host_only, is_enforced())
+}
+
+fn build_redirect_policy(
+    max_redirects: usize,
+    same_host_only: bool,
+    block_private: bool,
+) -> reqwest::redirect::Policy {
+    if !same_host_only && !block_private {
+        return reqwest::redirect::Policy::limited(max_redirects);
+    }
+    reqwest::redirect::Policy::custom(move |attempt| {
+        if block_private
+            && let Some(ip) = ip_literal(attempt.url())
+            && !is_public_ip(ip)
+        {
+            return attempt.error(format!("redirect to a non-public address is blocked: {ip}"));
+        }
+        let same_host = attempt
+            .previous()
+            .first()
+            .is_none_or(|first| first.host_str() == attempt.url().host_str());
+        if same_host_only && !same_host {
+            attempt.stop()
+        } else if attempt.previous().len() > max_redirects {
+            attempt.error("too many redirects")
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+/// Resolves with the system resolver and keeps only public addresses.
+#[derive(Debug)]
+struct PublicOnlyResolver;
+
+impl reqwest::dns::Resolve for PublicOnlyResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        Box::pin(async move {
+            let host = name.as_str().to_owned();
+            let addrs = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .collect::<Vec<SocketAddr>>();
+            let public = addrs
+                .iter()
+                .copied()
+                .filter(|addr| is_public_ip(addr.ip()))
+                .collect::<Vec<_>>();
+            if public.is_empty() {
+                let err: Box<dyn std::error::Error + Send + Sync> = format!(
+                    "connection to a non-public address is blocked: {host} ({})",
+                    addrs
+                        .iter()
+                        .map(|addr| addr.ip().to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+                .into();
+                return Err(err);
+            }
+            let addrs: reqwest::dns::Addrs = Box::new(public.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Which user-supplied URLs the server agrees to fetch.
+///
+/// Read from `SITEBOOKIFY_ALLOWED_HOSTS` / `SITEBOOKIFY_DENIED_HOSTS` (comma-separated host
+/// names; an entry also matches its subdomains) and `SITEBOOKIFY_ALLOW_PRIVATE_NETWORKS=1`.
+#[derive(Debug, Clone, Default)]
+pub struct HostPolicy {
+    /// When non-empty, only these hosts are allowed.
+    pub allowed_hosts: Vec<String>,
+    pub denied_hosts: Vec<String>,
+    /// Skip the public-address check (local development).
+    pub allow_private_networks: bool,
+}
+
+impl HostPolicy {
+    pub fn from_env() -> Self {
+        let hosts = |name: &str| {
+            std::env::var(name)
+                .unwrap_or_default()
+                .split(',')
+                .map(|host| host.trim().trim_end_matches('.').to_ascii_lowercase())
+                .filter(|host| !host.is_empty())
+                .collect::<Vec<_>>()
+        };
+        Self {
+            allowed_hosts: hosts("SITEBOOKIFY_ALLOWED_HOSTS"),
+            denied_hosts: hosts("SITEBOOKIFY_DENIED_HOSTS"),
+            allow_private_networks: std::env::var("SITEBOOKIFY_ALLOW_PRIVATE_NETWORKS")
+                .is_ok_and(|v| matches!(v.trim(), "1" | "true")),
+        }
+    }
+}
+
+fn host_matches(patterns: &[String], host: &str) -> bool {
+    patterns.iter().any(|pattern| {
+        host == pattern
+            || host
+                .strip_suffix(pattern.as_str())
+                .is_some_and(|prefix| prefix.ends_with('.'))
+    })
+}
+
+/// Checks that `url` is http(s), passes `policy`'s host lists, and (unless private networks are
+/// allowed) that every address its host resolves to is public.
+///
+/// The error is a reason suitable for a `400` response.
+pub async fn check_public_http_url(url: &Url, policy: &HostPolicy) -> Result<(), String> {
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err("url must be http/https".to_string());
+    }
+    let Some(host) = url.host_str() else {
+        return Err("url has no host".to_string());
+    };
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    if host_matches(&policy.denied_hosts, &host) {
+        return Err(format!("host is denied: {host}"));
+    }
+    if !policy.allowed_hosts.is_empty() && !host_matches(&policy.allowed_hosts, &host) {
+        return Err(format!("host is not in the allowlist: {host}"));
+    }
+    if policy.allow_private_networks {
+        return Ok(());
+    }
+
+    let addrs: Vec<IpAddr> = match url.host() {
+        Some(url::Host::Ipv4(ip)) => vec![IpAddr::V4(ip)],
+        Some(url::Host::Ipv6(ip)) => vec![IpAddr::V6(ip)],
+        _ => {
+            let port = url.port_or_known_default().unwrap_or(80);
+            tokio::net::lookup_host((host.as_str(), port))
+                .await
+                .map_err(|err| format!("failed to resolve host {host}: {err}"))?
+                .map(|addr| addr.ip())
+                .collect()
+        }
+    };
+    if addrs.is_empty() {
+        return Err(format!("host did not resolve: {host}"));
+    }
+    if let Some(ip) = addrs.iter().find(|ip| !is_public_ip(**ip)) {
+        return Err(format!(
+            "host resolves to a non-public address: {host} ({ip})"
+        ));
+    }
+    Ok(())
+}
+
+/// Whether `ip` is a globally routable unicast address (not loopback, private, link-local,
+/// CGNAT, documentation, multicast, or reserved).
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                || a >= 240
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 198 && (18..20).contains(&b))
+                || (a == 192 && b == 0 && ip.octets()[2] == 0))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(v4) = embedded_ipv4(ip) {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let [first, second, ..] = ip.segments();
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || (first & 0xffc0) == 0xfec0
+                || (first == 0x0064 && second == 0xff9b)
+                || (first == 0x2001 && second == 0x0db8))
+        }
+    }
+}
+
+/// The IPv4 address an IPv6 address forwards to: IPv4-mapped (`::ffff:a.b.c.d`),
+/// IPv4-compatible (`::a.b.c.d`), NAT64 (`64:ff9b::a.b.c.d`) and 6to4 (`2002:aabb:ccdd::`).
+fn embedded_ipv4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    if let Some(v4) = ip.to_ipv4() {
+        return Some(v4);
+    }
+    let segments = ip.segments();
+    let octets = ip.octets();
+    match segments {
+        [0x0064, 0xff9b, 0, 0, 0, 0, ..] => Some(Ipv4Addr::new(
+            octets[12], octets[13], octets[14], octets[15],
+        )),
+        [0x2002, ..] => Some(Ipv4Addr::new(octets[2], octets[3], octets[4], octets[5])),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_public_ip_rejects_internal_ranges() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::127.0.0.1",
+            "::10.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+            "64:ff9b:1::808:808",
+            "2002:7f00:1::1",
+            "2002:c0a8:101::",
+            "fec0::1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "8.8.8.8",
+            "93.184.216.34",
+            "2606:4700::1111",
+            "64:ff9b::808:808",
+            "2002:808:808::1",
+            "::ffff:8.8.8.8",
+        ] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[tokio::test]
+    async fn check_public_http_url_applies_policy() {
+        let url = |raw: &str| Url::parse(raw).unwrap();
+        let policy = HostPolicy::default();
+        let err = check_public_http_url(&url("http://169.254.169.254/latest"), &policy)
+            .await
+            .unwrap_err();
+        assert!(err.contains("non-public address"), "{err}");
+        assert!(
+            check_public_http_url(&url("http://[::1]:8080/"), &policy)
+                .await
+                .is_err()
+        );
+        assert!(
+            check_public_http_url(&url("ftp://8.8.8.8/"), &policy)
+                .await
+                .is_err()
+        );
+        assert!(
+            check_public_http_url(&url("https://8.8.8.8/"), &policy)
+                .await
+                .is_ok()
+        );
+
+        let policy = HostPolicy {
+            allowed_hosts: vec!["example.com".to_string()],
+            denied_hosts: vec!["internal.example.com".to_string()],
+            allow_private_networks: true,
+        };
+        assert!(
+            check_public_http_url(&url("https://docs.example.com/"), &policy)
+                .await
+                .is_ok()
+        );
+        let err = check_public_http_url(&url("https://a.internal.example.com/"), &policy)
+            .await
+            .unwrap_err();
+        assert!(err.contains("denied"), "{err}");
+        let err = check_public_http_url(&url("https://notexample.com/"), &policy)
+            .await
+            .unwrap_err();
+        assert!(err.contains("allowlist"), "{err}");
+        assert!(
+            check_public_http_url(
+                &url("http://127.0.0.1:3000/"),
+                &HostPolicy {
+                    allow_private_networks: true,
+                    ..HostPolicy::default()
+                }
+            )
+            .await
+            .is_ok()
+        );
+    }
+
+    fn spawn_redirect_server() -> String {
+        let server = tiny_http::Server::http("127.0.0.1:0").expect("start server");
+        let base_url = format!("http://{}", server.server_addr());
+        let location = format!("{base_url}/next");
+        std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let response = if request.url() == "/" {
+                    tiny_http::Response::from_string("")
+                        .with_status_code(302)
+                        .with_header(
+                            tiny_http::Header::from_bytes(&b"Location"[..], location.as_bytes())
+                                .expect("location header"),
+                        )
+                } else {
+                    tiny_http::Response::from_string("internal")
+                };
+                let _ = request.respond(response);
+            }
+        });
+        base_url
+    }
+
+    #[tokio::test]
+    async fn guarded_client_refuses_private_hosts_and_redirects() -> anyhow::Result<()> {
+        let base_url = spawn_redirect_server();
+        let port = Url::parse(&base_url)?.port().expect("port");
+        let client = reqwest::Client::builder()
+            .dns_resolver(Arc::new(PublicOnlyResolver))
+            .redirect(build_redirect_policy(10, false, true))
+            .build()?;
+
+        let err = client
+            .get(format!("http://localhost:{port}/next"))
+            .send()
+            .await
+            .expect_err("localhost must be blocked");
+        assert!(format!("{err:?}").contains("non-public address"), "{err:?}");
+
+        let err = client
+            .get(&base_url)
+            .send()
+            .await
+            .expect_err("redirect to 127.0.0.1 must be blocked");
+        assert!(err.is_redirect(), "{err:?}");
+
+        // The resolver never sees the IP literal itself; the pre-request check does.
+        let literal = Url::parse(&base_url)?;
+        let err = ensure_public_ip_literal(&literal, true).expect_err("127.0.0.1 must be blocked");
+        assert!(err.to_string().contains("non-public address"), "{err}");
+        assert!(ensure_public_ip_literal(&literal, false).is_ok());
+        assert!(ensure_public_ip_literal(&Url::parse("http://8.8.8.8/")?, true).is_ok());
+        assert!(ensure_public_ip_literal(&Url::parse("http://[::ffff:10.0.0.5]/")?, true).is_err());
+
+        let unguarded = reqwest::Client::new();
+        assert_eq!(
+            unguarded.get(&base_url).send().await?.text().await?,
+            "internal"
+        );
+        Ok(())
+    }
+}
@@ -10,19 +10,43 @@ pub struct OpenAiConfig {
     pub base_url: String,
     pub model: String,
     pub reasoning_effort: Option<String>,
+    /// Retries for transient failures (network errors, 408/429/5xx) in `exec_readonly_with_retry`.
+    pub max_retries: u32,
+    /// First retry delay; doubled per attempt and jittered.
+    pub retry_base_delay: Duration,
+    /// Upper bound for any retry delay, including `Retry-After`.
+    pub retry_max_delay: Duration,
+    /// Request a streamed (SSE) response and accumulate its `response.output_text.delta` events.
+    pub stream: bool,
+    /// Sent as `OpenAI-Organization` when set.
+    pub organization: Option<String>,
+    /// Sent as `OpenAI-Project` when set.
+    pub project: Option<String>,
+    /// Explicit HTTP(S) proxy; `None` keeps reqwest's env-based proxy lookup.
+    pub proxy: Option<String>,
 }
 
 impl OpenAiConfig {
-    pub fn from_env() -> anyhow::Result<Self> {
-        let api_key = std::env::var("SITEBOOKIFY_OPENAI_API_KEY")
-            .or_else(|_| std::env::var("OPENAI_API_KEY"))
-            .context(
-                "missing OpenAI API key: set OPENAI_API_KEY (or SITEBOOKIFY_OPENAI_API_KEY)",
-            )?;
-
+    /// Reads the config from the environment. `api_key_env` names the variable holding the
+    /// key (default: `SITEBOOKIFY_OPENAI_API_KEY`, then `OPENAI_API_KEY`).
+    ///
+    /// The key may be missing when `SITEBOOKIFY_OPENAI_BASE_URL` points at a server other
+    /// than api.openai.com (e.g. a local OpenAI-compatible one); no `Authorization` is sent then.
+    pub fn from_env(api_key_env: Option<&str>) -> anyhow::Result<Self> {
         let base_url = std::env::var("SITEBOOKIFY_OPENAI_BASE_URL")
             .unwrap_or_else(|_| "https://api.openai.com/v1".to_owned());
 
+        let api_key = match api_key_env.map(str::trim).filter(|name| !name.is_empty()) {
+            Some(name) => non_empty_env(&[name])
+                .or_else(|| (!is_openai_base_url(&base_url)).then(String::new))
+                .with_context(|| format!("missing OpenAI API key: set {name}"))?,
+            None => non_empty_env(&["SITEBOOKIFY_OPENAI_API_KEY", "OPENAI_API_KEY"])
+                .or_else(|| (!is_openai_base_url(&base_url)).then(String::new))
+                .context(
+                    "missing OpenAI API key: set OPENAI_API_KEY (or SITEBOOKIFY_OPENAI_API_KEY)",
+                )?,
+        };
+
         let model = std::env::var("SITEBOOKIFY_OPENAI_MODEL")
             .or_else(|_| std::env::var("OPENAI_MODEL"))
             .unwrap_or_else(|_| "gpt-5.2".to_owned());
@@ -32,21 +56,106 @@ impl OpenAiConfig {
             .filter(|effort| !effort.trim().is_empty())
             .or_else(|| Some("high".to_owned()));
 
+        let max_retries = match std::env::var("SITEBOOKIFY_OPENAI_MAX_RETRIES") {
+            Ok(raw) => raw
+                .trim()
+                .parse::<u32>()
+                .with_context(|| format!("invalid SITEBOOKIFY_OPENAI_MAX_RETRIES: {raw}"))?,
+            Err(_) => DEFAULT_MAX_RETRIES,
+        };
+
+        let organization = non_empty_env(&["SITEBOOKIFY_OPENAI_ORGANIZATION", "OPENAI_ORG_ID"]);
+        let project = non_empty_env(&["SITEBOOKIFY_OPENAI_PROJECT", "OPENAI_PROJECT_ID"]);
+
         Ok(Self {
             api_key,
             base_url,
             model,
             reasoning_effort,
+            max_retries,
+            retry_base_delay: RETRY_BASE_DELAY,
+            retry_max_delay: RETRY_MAX_DELAY,
+            stream: false,
+            organization,
+            project,
+            proxy: None,
         })
     }
+
+    /// Overrides the organization/project (e.g. from CLI flags); `None` keeps the env value.
+    pub fn with_account(mut self, organization: Option<&str>, project: Option<&str>) -> Self {
+        if let Some(organization) = organization.map(str::trim).filter(|v| !v.is_empty()) {
+            self.organization = Some(organization.to_owned());
+        }
+        if let Some(project) = project.map(str::trim).filter(|v| !v.is_empty()) {
+            self.project = Some(project.to_owned());
+        }
+        self
+    }
+
+    /// Routes OpenAI requests through `proxy` (e.g. from `--proxy`); `None` keeps the current
+    /// value.
+    pub fn with_proxy(mut self, proxy: Option<&str>) -> Self {
+        if let Some(proxy) = proxy.map(str::trim).filter(|v| !v.is_empty()) {
+            self.proxy = Some(proxy.to_owned());
+        }
+        self
+    }
+
+    /// Overrides the retry backoff (e.g. from `--openai-retry-base-ms` / `--openai-retry-max-ms`);
+    /// `None` keeps the current value.
+    pub fn with_retry_delays(mut self, base_ms: Option<u64>, max_ms: Option<u64>) -> Self {
+        if let Some(base_ms) = base_ms {
+            self.retry_base_delay = Duration::from_millis(base_ms);
+        }
+        if let Some(max_ms) = max_ms {
+            self.retry_max_delay = Duration::from_millis(max_ms);
+        }
+        self
+    }
+
+    /// Switches to streamed responses (e.g. from `--openai-stream`); `false` keeps the current
+    /// value.
+    pub fn with_stream(mut self, stream: bool) -> Self {
+        self.stream |= stream;
+        self
+    }
+}
+
+fn is_openai_base_url(base_url: &str) -> bool {
+    url::Url::parse(base_url)
+        .ok()
+        .and_then(|url| {
+            url.host_str()
+                .map(|host| host.eq_ignore_ascii_case("api.openai.com"))
+        })
+        .unwrap_or(true)
 }
 
+fn non_empty_env(names: &[&str]) -> Option<String> {
+    names.iter().find_map(|name| {
+        std::env::var(name)
+            .ok()
+            .map(|v| v.trim().to_owned())
+            .filter(|v| !v.is_empty())
+    })
+}
+
+const DEFAULT_MAX_RETRIES: u32 = 3;
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(60);
+/// Whole-response timeout; when streaming, reqwest applies it to each read instead, so it
+/// bounds the silence between events.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(180);
+
 #[derive(Debug, Serialize)]
 struct ResponsesRequest<'a> {
     model: &'a str,
     input: &'a str,
     #[serde(skip_serializing_if = "Option::is_none")]
     reasoning: Option<Reasoning<'a>>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -54,15 +163,120 @@ struct Reasoning<'a> {
     effort: &'a str,
 }
 
+/// Writes `prompt` to `<dir>/<name>.prompt.txt` (for `--dump-prompt`), exactly as it is sent.
+pub fn dump_prompt(dir: &std::path::Path, name: &str, prompt: &str) -> anyhow::Result<()> {
+    let path = dir.join(format!("{name}.prompt.txt"));
+    std::fs::write(&path, prompt).with_context(|| format!("write prompt dump: {}", path.display()))
+}
+
+/// Single Responses API call (no retries).
 pub fn exec_readonly(prompt: &str, config: &OpenAiConfig) -> anyhow::Result<String> {
-    let client = reqwest::blocking::Client::builder()
-        .timeout(Duration::from_secs(180))
-        .build()
-        .context("build openai http client")?;
+    send_responses_request(prompt, config).map_err(|failure| failure.error)
+}
+
+/// Like `exec_readonly`, but retries transient failures with jittered exponential backoff,
+/// honoring `Retry-After` when the server sends one.
+pub fn exec_readonly_with_retry(prompt: &str, config: &OpenAiConfig) -> anyhow::Result<String> {
+    let mut attempt = 0u32;
+    loop {
+        match send_responses_request(prompt, config) {
+            Ok(text) => return Ok(text),
+            Err(failure) if failure.retryable && attempt < config.max_retries => {
+                let delay = failure
+                    .retry_after
+                    .unwrap_or_else(|| backoff_delay(config, attempt))
+                    .min(config.retry_max_delay);
+                attempt += 1;
+                tracing::warn!(
+                    attempt,
+                    max_retries = config.max_retries,
+                    delay_ms = delay.as_millis() as u64,
+                    error = %failure.error,
+                    "openai request failed; retrying"
+                );
+                std::thread::sleep(delay);
+            }
+            Err(failure) => return Err(failure.error),
+        }
+    }
+}
+
+struct RequestFailure {
+    error: anyhow::Error,
+    retryable: bool,
+    retry_after: Option<Duration>,
+}
+
+impl RequestFailure {
+    fn fatal(error: anyhow::Error) -> Self {
+        Self {
+            error,
+            retryable: false,
+            retry_after: None,
+        }
+    }
+
+    fn transient(error: anyhow::Error) -> Self {
+        Self {
+            error,
+            retryable: true,
+            retry_after: None,
+        }
+    }
+}
+
+/// `base * 2^attempt` (capped), randomized to between half and all of it so parallel
+/// rewrites that failed together do not retry in lockstep.
+fn backoff_delay(config: &OpenAiConfig, attempt: u32) -> Duration {
+    let delay = config
+        .retry_base_delay
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(config.retry_max_delay);
+    let half = delay / 2;
+    let jitter_nanos = (half.as_nanos() as u64).saturating_add(1);
+    half + Duration::from_nanos(random_u64() % jitter_nanos)
+}
+
+/// Process-random value from std's hash seeds (no RNG dependency needed for jitter).
+fn random_u64() -> u64 {
+    use std::hash::{BuildHasher as _, Hasher as _};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    hasher.finish()
+}
+
+/// Statuses worth retrying (timeouts, rate limits, transient server errors); shared with the
+/// book asset downloader.
+pub fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 408 | 429 | 500 | 502 | 503 | 504)
+}
+
+/// `Retry-After` in seconds (HTTP dates are ignored).
+pub fn parse_retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?;
+    value.trim().parse::<u64>().ok().map(Duration::from_secs)
+}
+
+fn send_responses_request(prompt: &str, config: &OpenAiConfig) -> Result<String, RequestFailure> {
+    let client = crate::proxy::apply_blocking(
+        reqwest::blocking::Client::builder(),
+        config.proxy.as_deref(),
+    )
+    .map_err(RequestFailure::fatal)?
+    .timeout(REQUEST_TIMEOUT)
+    .build()
+    .context("build openai http client")
+    .map_err(RequestFailure::fatal)?;
 
     let url = format!("{}/responses", config.base_url.trim_end_matches('/'));
 
     tracing::info!(
+        target: crate::logging::PROGRESS_TARGET,
         base_url = %config.base_url,
         model = %config.model,
         reasoning_effort = ?config.reasoning_effort,
@@ -76,46 +290,194 @@ pub fn exec_readonly(prompt: &str, config: &OpenAiConfig) -> anyhow::Result<Stri
             .reasoning_effort
             .as_deref()
             .map(|effort| Reasoning { effort }),
+        stream: config.stream,
     };
 
-    let response = client
-        .post(url)
-        .bearer_auth(&config.api_key)
-        .json(&request)
-        .send()
-        .context("POST /responses")?;
+    let mut builder = client.post(url);
+    if !config.api_key.is_empty() {
+        builder = builder.bearer_auth(&config.api_key);
+    }
+    if let Some(organization) = &config.organization {
+        builder = builder.header("OpenAI-Organization", organization);
+    }
+    if let Some(project) = &config.project {
+        builder = builder.header("OpenAI-Project", project);
+    }
+    let response = builder.json(&request).send().map_err(|err| {
+        let retryable = err.is_timeout() || err.is_connect() || err.is_request();
+        RequestFailure {
+            error: anyhow::Error::new(err).context("POST /responses"),
+            retryable,
+            retry_after: None,
+        }
+    })?;
 
     let status = response.status();
-    let body = response.text().context("read openai response body")?;
+    let retry_after = parse_retry_after(response.headers());
+    // Servers that ignore `stream` answer with plain JSON, which is read as usual.
+    let is_event_stream = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/event-stream"));
+    if status.is_success() && is_event_stream {
+        return read_output_text_stream(std::io::BufReader::new(response));
+    }
+    let body = response
+        .text()
+        .context("read openai response body")
+        .map_err(RequestFailure::transient)?;
 
     if !status.is_success() {
-        if let Ok(value) = serde_json::from_str::<Value>(&body)
-            && let Some(message) = value.pointer("/error/message").and_then(|v| v.as_str())
-        {
-            anyhow::bail!("openai responses api failed ({status}): {message}");
+        return Err(RequestFailure {
+            error: anyhow::anyhow!(
+                "openai responses api failed ({status}): {}",
+                describe_api_error(&body)
+            ),
+            retryable: is_retryable_status(status),
+            retry_after,
+        });
+    }
+
+    let value: Value = serde_json::from_str(&body)
+        .context("parse openai responses json")
+        .map_err(RequestFailure::fatal)?;
+    extract_output_text(&value)
+        .context("extract openai output text")
+        .map_err(RequestFailure::fatal)
+}
+
+/// Accumulates the text of a streamed Responses API reply from its server-sent events.
+///
+/// Read errors (including the per-read timeout) and a stream that ends before
+/// `response.completed` are transient; `error` / `response.failed` events are not.
+fn read_output_text_stream(reader: impl std::io::BufRead) -> Result<String, RequestFailure> {
+    let mut text = String::new();
+    let mut data = String::new();
+    for line in reader.lines() {
+        let line = line
+            .context("read openai response stream")
+            .map_err(RequestFailure::transient)?;
+        if let Some(chunk) = line.strip_prefix("data:") {
+            if !data.is_empty() {
+                data.push('\n');
+            }
+            data.push_str(chunk.strip_prefix(' ').unwrap_or(chunk));
+            continue;
+        }
+        if !line.is_empty() || data.is_empty() {
+            // `event:`, `id:` and comment lines; the event type is repeated in the data.
+            continue;
+        }
+
+        let event = std::mem::take(&mut data);
+        if event == "[DONE]" {
+            break;
+        }
+        let value: Value = serde_json::from_str(&event)
+            .context("parse openai stream event")
+            .map_err(RequestFailure::fatal)?;
+        match value.get("type").and_then(|v| v.as_str()).unwrap_or("") {
+            "response.output_text.delta" => {
+                if let Some(delta) = value.get("delta").and_then(|v| v.as_str()) {
+                    text.push_str(delta);
+                }
+            }
+            "response.completed" => {
+                if text.is_empty()
+                    && let Some(response) = value.get("response")
+                {
+                    return extract_output_text(response)
+                        .context("extract openai output text")
+                        .map_err(RequestFailure::fatal);
+                }
+                return Ok(text);
+            }
+            "response.failed" | "response.incomplete" | "error" => {
+                return Err(RequestFailure::fatal(anyhow::anyhow!(
+                    "openai response stream failed: {}",
+                    describe_api_error(&value.get("response").unwrap_or(&value).to_string())
+                )));
+            }
+            _ => {}
+        }
+    }
+    Err(RequestFailure::transient(anyhow::anyhow!(
+        "openai response stream ended before response.completed"
+    )))
+}
+
+const MAX_ERROR_BODY_CHARS: usize = 2000;
+
+/// Formats the `{"error": {...}}` envelope (message, code, type, param) for error contexts,
+/// falling back to the raw body (truncated) when it is not JSON.
+fn describe_api_error(body: &str) -> String {
+    if let Ok(value) = serde_json::from_str::<Value>(body)
+        && let Some(error) = value.get("error")
+    {
+        let field = |name: &str| {
+            error
+                .get(name)
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+        };
+        if let Some(message) = field("message") {
+            let mut details = Vec::new();
+            for name in ["code", "type", "param"] {
+                if let Some(v) = field(name) {
+                    details.push(format!("{name}={v}"));
+                }
+            }
+            let message = truncate_chars(message, MAX_ERROR_BODY_CHARS);
+            if details.is_empty() {
+                return message;
+            }
+            return format!("{message} ({})", details.join(", "));
         }
-        anyhow::bail!("openai responses api failed ({status}): {body}");
     }
 
-    let value: Value = serde_json::from_str(&body).context("parse openai responses json")?;
-    extract_output_text(&value).context("extract openai output text")
+    let body = body.trim();
+    if body.is_empty() {
+        return "<empty body>".to_owned();
+    }
+    truncate_chars(body, MAX_ERROR_BODY_CHARS)
 }
 
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((idx, _)) => format!("{}… (truncated, {} bytes total)", &text[..idx], text.len()),
+        None => text.to_owned(),
+    }
+}
+
+/// Collects the reply text from a Responses API body, or from the `choices[].message` shape
+/// some OpenAI-compatible servers return. Only the text fields are read; reasoning items are
+/// skipped, and anything else in the body is ignored.
 fn extract_output_text(value: &Value) -> anyhow::Result<String> {
-    if let Some(text) = value.get("output_text").and_then(|v| v.as_str()) {
+    if let Some(text) = value.get("output_text").and_then(|v| v.as_str())
+        && !text.is_empty()
+    {
         return Ok(text.to_owned());
     }
 
-    let Some(output) = value.get("output").and_then(|v| v.as_array()) else {
-        anyhow::bail!("missing `output` in openai responses json");
-    };
-
     let mut parts = Vec::new();
-    for item in output {
+    for item in value
+        .get("output")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+    {
+        if item.get("type").and_then(|v| v.as_str()) == Some("reasoning") {
+            continue;
+        }
         let Some(content) = item.get("content").and_then(|v| v.as_array()) else {
             continue;
         };
         for chunk in content {
+            if chunk.get("type").and_then(|v| v.as_str()) == Some("reasoning_text") {
+                continue;
+            }
             if let Some(text) = chunk.get("text").and_then(|v| v.as_str()) {
                 parts.push(text);
                 continue;
@@ -126,8 +488,21 @@ fn extract_output_text(value: &Value) -> anyhow::Result<String> {
             }
         }
     }
+    for choice in value
+        .get("choices")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+    {
+        if let Some(text) = choice.pointer("/message/content").and_then(|v| v.as_str()) {
+            parts.push(text);
+        }
+    }
 
     if parts.is_empty() {
+        if value.get("output").is_none() && value.get("choices").is_none() {
+            anyhow::bail!("missing `output` in openai responses json");
+        }
         anyhow::bail!("missing output text in openai responses json");
     }
 
@@ -0,0 +1,40 @@
+use anyhow::Context as _;
+use url::Url;
+
+/// Parses a `--proxy` value into a proxy for every outbound request. `socks5://` resolves
+/// host names locally, `socks5h://` lets the proxy resolve them.
+///
+/// Without `--proxy`, reqwest still honors `HTTPS_PROXY` / `HTTP_PROXY` / `NO_PROXY`.
+pub fn parse(raw: &str) -> anyhow::Result<reqwest::Proxy> {
+    let url = Url::parse(raw.trim()).with_context(|| format!("parse proxy url: {raw}"))?;
+    match url.scheme() {
+        "http" | "https" | "socks5" | "socks5h" => {}
+        scheme => anyhow::bail!(
+            "unsupported proxy scheme: {scheme} (expected http, https, socks5 or socks5h)"
+        ),
+    }
+    if url.host_str().is_none() {
+        anyhow::bail!("proxy url has no host: {raw}");
+    }
+    reqwest::Proxy::all(url.as_str()).with_context(|| format!("configure proxy: {raw}"))
+}
+
+pub fn apply(
+    builder: reqwest::ClientBuilder,
+    proxy: Option<&str>,
+) -> anyhow::Result<reqwest::ClientBuilder> {
+    Ok(match proxy {
+        Some(raw) => builder.proxy(parse(raw)?),
+        None => builder,
+    })
+}
+
+pub fn apply_blocking(
+    builder: reqwest::blocking::ClientBuilder,
+    proxy: Option<&str>,
+) -> anyhow::Result<reqwest::blocking::ClientBuilder> {
+    Ok(match proxy {
+        Some(raw) => builder.proxy(parse(raw)?),
+        None => builder,
+    })
+}
@@ -1,8 +1,14 @@
+use std::fs::File;
+use std::io::{BufRead as _, BufReader};
 use std::path::{Path, PathBuf};
 
 use anyhow::Context as _;
+use sha2::{Digest as _, Sha256};
 use url::Url;
 
+use crate::cli::CrawlVerifyArgs;
+use crate::formats::CrawlRecord;
+
 pub fn ensure_raw_snapshot_dir_does_not_exist(out_dir: &Path) -> anyhow::Result<()> {
     if out_dir.exists() {
         anyhow::bail!(
@@ -13,6 +19,39 @@ pub fn ensure_raw_snapshot_dir_does_not_exist(out_dir: &Path) -> anyhow::Result<
     Ok(())
 }
 
+/// For `--force`: deletes an existing output directory, but only when it looks like the
+/// snapshot a previous run wrote (it holds `marker`) or is empty, so a mistyped `--out`
+/// never wipes unrelated files.
+pub fn remove_snapshot_dir_for_force(out_dir: &Path, marker: &str) -> anyhow::Result<()> {
+    let metadata = match std::fs::symlink_metadata(out_dir) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("stat output dir: {}", out_dir.display()));
+        }
+    };
+    if !metadata.is_dir() {
+        anyhow::bail!(
+            "--force only replaces directories; output is not one: {}",
+            out_dir.display()
+        );
+    }
+    let is_empty = std::fs::read_dir(out_dir)
+        .with_context(|| format!("read output dir: {}", out_dir.display()))?
+        .next()
+        .is_none();
+    if !is_empty && !out_dir.join(marker).exists() {
+        anyhow::bail!(
+            "refusing to remove {} with --force: it has no {marker}, so it is not a previous snapshot",
+            out_dir.display()
+        );
+    }
+    std::fs::remove_dir_all(out_dir)
+        .with_context(|| format!("remove existing output dir: {}", out_dir.display()))
+}
+
+/// Where the HTML of `url` is saved: one `index.html` per path, or `index-<hash>.html` for a
+/// URL with a query (`crawl --keep-query`), so query variants of a path do not collide.
 pub fn raw_html_path(out_dir: &Path, url: &Url) -> anyhow::Result<PathBuf> {
     let host = url
         .host_str()
@@ -33,7 +72,13 @@ pub fn raw_html_path(out_dir: &Path, url: &Url) -> anyhow::Result<PathBuf> {
         }
         path = path.join(segment);
     }
-    path = path.join("index.html");
+    path = match url.query() {
+        Some(query) => path.join(format!(
+            "index-{}.html",
+            &sha256_hex(query.as_bytes())[..16]
+        )),
+        None => path.join("index.html"),
+    };
 
     Ok(path)
 }
@@ -53,3 +98,65 @@ pub fn write_raw_html(path: &Path, html: &str) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    hex::encode(hasher.finalize())
+}
+
+pub fn verify(args: CrawlVerifyArgs) -> anyhow::Result<()> {
+    let raw_dir = PathBuf::from(&args.raw);
+    let crawl_jsonl_path = raw_dir.join("crawl.jsonl");
+    let crawl_jsonl = File::open(&crawl_jsonl_path)
+        .with_context(|| format!("open crawl log: {}", crawl_jsonl_path.display()))?;
+
+    let mut checked = 0usize;
+    let mut missing = 0usize;
+    let mut empty = 0usize;
+    let mut mismatched = 0usize;
+
+    for (idx, line) in BufReader::new(crawl_jsonl).lines().enumerate() {
+        let line = line.context("read crawl jsonl line")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: CrawlRecord = serde_json::from_str(&line)
+            .with_context(|| format!("parse crawl record (line {})", idx + 1))?;
+        let Some(raw_html_path) = record.raw_html_path.as_deref() else {
+            continue;
+        };
+        checked += 1;
+
+        let bytes = match std::fs::read(raw_html_path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                println!("missing: {raw_html_path} ({})", record.normalized_url);
+                missing += 1;
+                continue;
+            }
+            Err(err) => {
+                return Err(err).with_context(|| format!("read raw html: {raw_html_path}"));
+            }
+        };
+        if bytes.is_empty() {
+            println!("empty: {raw_html_path} ({})", record.normalized_url);
+            empty += 1;
+            continue;
+        }
+        if let Some(expected) = record.raw_html_sha256.as_deref()
+            && sha256_hex(&bytes) != expected
+        {
+            println!("mismatched: {raw_html_path} ({})", record.normalized_url);
+            mismatched += 1;
+        }
+    }
+
+    tracing::info!(checked, missing, empty, mismatched, "crawl verify");
+    if missing + empty + mismatched > 0 {
+        anyhow::bail!(
+            "raw snapshot verification failed: {missing} missing, {empty} empty, {mismatched} mismatched (of {checked})"
+        );
+    }
+    Ok(())
+}
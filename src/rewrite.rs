@@ -1,30 +1,298 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
 use anyhow::Context as _;
+use serde::Deserialize;
+
+use crate::cli::RewriteGranularity;
+use crate::openai::{OpenAiConfig, exec_readonly_with_retry};
+
+#[derive(Debug, Clone, Default)]
+pub struct RewriteOptions {
+    /// Model to retry with when the primary output drops placeholder tokens.
+    pub fallback_model: Option<String>,
+    /// Whether the input is a heading chunk or a whole page.
+    pub granularity: RewriteGranularity,
+    /// Units longer than this are split into paragraph-aligned parts, one request each.
+    pub max_section_chars: Option<usize>,
+    /// Overrides for `OPENAI_ORG_ID` / `OPENAI_PROJECT_ID`.
+    pub openai_organization: Option<String>,
+    pub openai_project: Option<String>,
+    /// Env var to read the API key from instead of the default ones.
+    pub openai_api_key_env: Option<String>,
+    /// Overrides for the retry backoff (milliseconds).
+    pub openai_retry_base_ms: Option<u64>,
+    pub openai_retry_max_ms: Option<u64>,
+    /// Request streamed (SSE) responses.
+    pub openai_stream: bool,
+    /// Overrides the proxy for OpenAI requests.
+    pub proxy: Option<String>,
+    /// Before/after pairs shown to the model as few-shot examples of the wanted tone.
+    pub tone_examples: Vec<ToneExample>,
+    /// Replaces the built-in instructions; see [`load_instructions_template`].
+    pub instructions_template: Option<String>,
+    /// Directory that receives a copy of every prompt before it is sent.
+    pub dump_prompt_dir: Option<PathBuf>,
+    /// How much of the previous chunk's rewrite (same page) to show as read-only context (0: none).
+    pub chunk_overlap_chars: usize,
+}
+
+/// One rewrite request's input.
+#[derive(Debug, Clone, Copy)]
+pub struct RewriteUnit<'a> {
+    /// Markdown to rewrite.
+    pub markdown: &'a str,
+    /// End of the previous chunk's rewrite (same page); shown to the model for continuity but
+    /// never part of the output.
+    pub preceding_context: Option<&'a str>,
+    /// File stem for `--dump-prompt`.
+    pub name: &'a str,
+    /// Source page the Markdown comes from (`{title}` / `{url}` in an instructions template).
+    pub source_title: &'a str,
+    pub source_url: &'a str,
+    /// 1-based position of the section within its chapter (`{section_index}`).
+    pub section_index: usize,
+    /// The chapter's `intent` from the TOC (`{prompt}`).
+    pub chapter_intent: &'a str,
+}
+
+/// The last `max_chars` characters of `markdown`, starting at a word boundary.
+pub fn chunk_overlap_tail(markdown: &str, max_chars: usize) -> Option<&str> {
+    let markdown = markdown.trim_end();
+    if max_chars == 0 || markdown.is_empty() {
+        return None;
+    }
+    let char_count = markdown.chars().count();
+    if char_count <= max_chars {
+        return Some(markdown.trim_start());
+    }
+    let (start, _) = markdown.char_indices().nth(char_count - max_chars)?;
+    let tail = &markdown[start..];
+    // Do not open on half a word.
+    let tail = match tail.find(char::is_whitespace) {
+        Some(space) if !markdown[..start].ends_with(char::is_whitespace) => &tail[space..],
+        _ => tail,
+    };
+    let tail = tail.trim_start();
+    (!tail.is_empty()).then_some(tail)
+}
+
+/// Upper bounds for `--tone-example-file`, so the examples never crowd out the section itself.
+pub const MAX_TONE_EXAMPLES: usize = 5;
+pub const MAX_TONE_EXAMPLE_CHARS: usize = 8_000;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToneExample {
+    /// Source Markdown, as it would appear in a page.
+    pub input: String,
+    /// The same content rewritten in the wanted tone.
+    pub output: String,
+}
+
+/// Reads a YAML list of `{input, output}` Markdown pairs and checks it stays within
+/// [`MAX_TONE_EXAMPLES`] pairs and [`MAX_TONE_EXAMPLE_CHARS`] characters in total.
+pub fn load_tone_examples(path: &Path) -> anyhow::Result<Vec<ToneExample>> {
+    let yaml = std::fs::read_to_string(path)
+        .with_context(|| format!("read tone examples: {}", path.display()))?;
+    let examples: Vec<ToneExample> = serde_yaml::from_str(&yaml).with_context(|| {
+        format!(
+            "parse tone examples (expected a list of input/output pairs): {}",
+            path.display()
+        )
+    })?;
+
+    if examples.is_empty() {
+        anyhow::bail!("tone examples file has no pairs: {}", path.display());
+    }
+    if examples.len() > MAX_TONE_EXAMPLES {
+        anyhow::bail!(
+            "too many tone examples: {} (max {MAX_TONE_EXAMPLES})",
+            examples.len()
+        );
+    }
+    for (idx, example) in examples.iter().enumerate() {
+        if example.input.trim().is_empty() || example.output.trim().is_empty() {
+            anyhow::bail!(
+                "tone example {} has an empty input or output: {}",
+                idx + 1,
+                path.display()
+            );
+        }
+    }
+    let total_chars = examples
+        .iter()
+        .map(|example| example.input.chars().count() + example.output.chars().count())
+        .sum::<usize>();
+    if total_chars > MAX_TONE_EXAMPLE_CHARS {
+        anyhow::bail!(
+            "tone examples are too long: {total_chars} chars (max {MAX_TONE_EXAMPLE_CHARS})"
+        );
+    }
+
+    Ok(examples)
+}
+
+/// Markers substituted in an `--instructions-template` file. Other `{...}` text is kept as is.
+pub const INSTRUCTIONS_TEMPLATE_MARKERS: &[&str] = &[
+    "chapter_title",
+    "section_title",
+    "title",
+    "url",
+    "section_index",
+    "prompt",
+    "language",
+    "tone",
+    "scope",
+    "heading_rules",
+    "token_rules",
+];
+
+/// Reads an instructions template and checks it contains `{token_rules}`, so the placeholder
+/// preservation rules cannot be dropped by accident.
+pub fn load_instructions_template(path: &Path) -> anyhow::Result<String> {
+    let template = std::fs::read_to_string(path)
+        .with_context(|| format!("read instructions template: {}", path.display()))?;
+    if !template.contains("{token_rules}") {
+        anyhow::bail!(
+            "instructions template must contain the {{token_rules}} marker: {}",
+            path.display()
+        );
+    }
+    Ok(template)
+}
 
-use crate::openai::{OpenAiConfig, exec_readonly};
+/// Replaces each `{marker}` of [`INSTRUCTIONS_TEMPLATE_MARKERS`] in one pass, so substituted
+/// values (e.g. a title containing `{tone}`) are never expanded again.
+fn fill_instructions_template(template: &str, value: impl Fn(&str) -> String) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.split_once('}').map(|(name, _)| name) {
+            Some(name) if INSTRUCTIONS_TEMPLATE_MARKERS.contains(&name) => {
+                out.push_str(&value(name));
+                rest = &after[name.len() + 1..];
+            }
+            _ => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
 
 pub fn rewrite_section_via_openai(
     language: &str,
     tone: &str,
     chapter_title: &str,
     section_title: &str,
-    source_markdown: &str,
+    unit: &RewriteUnit<'_>,
+    options: &RewriteOptions,
 ) -> anyhow::Result<String> {
+    let source_markdown = unit.markdown;
     let mut store = TokenStore::new();
     let protected = protect_markdown(source_markdown, &mut store);
 
-    let prompt =
-        build_openai_rewrite_prompt(language, tone, chapter_title, section_title, &protected);
+    let prompt = build_openai_rewrite_prompt(
+        language,
+        tone,
+        chapter_title,
+        section_title,
+        &PromptInput {
+            markdown: &protected,
+            preceding_context: unit.preceding_context,
+            example_token: &store.token(0),
+            unit,
+        },
+        options,
+    );
+    if let Some(dir) = &options.dump_prompt_dir {
+        crate::openai::dump_prompt(dir, unit.name, &prompt)?;
+    }
 
-    let config = OpenAiConfig::from_env().context("load openai config")?;
-    let raw = exec_readonly(&prompt, &config).context("openai exec for rewrite")?;
-    let rewritten = normalize_placeholder_tokens(raw.trim_end());
+    let config = OpenAiConfig::from_env(options.openai_api_key_env.as_deref())
+        .context("load openai config")?
+        .with_account(
+            options.openai_organization.as_deref(),
+            options.openai_project.as_deref(),
+        )
+        .with_proxy(options.proxy.as_deref())
+        .with_retry_delays(options.openai_retry_base_ms, options.openai_retry_max_ms)
+        .with_stream(options.openai_stream);
+    let raw = exec_readonly_with_retry(&prompt, &config).context("openai exec for rewrite")?;
+    let mut rewritten = normalize_placeholder_tokens(raw.trim_end(), &store);
+
+    let missing = missing_tokens(&rewritten, &store);
+    if !missing.is_empty()
+        && let Some(fallback_model) = options.fallback_model.as_deref()
+    {
+        tracing::warn!(
+            section_title,
+            missing = missing.len(),
+            fallback_model,
+            "rewrite output dropped placeholder tokens; retrying with fallback model"
+        );
+        let fallback_config = OpenAiConfig {
+            model: fallback_model.to_owned(),
+            ..config
+        };
+        match exec_readonly_with_retry(&prompt, &fallback_config) {
+            Ok(raw) => {
+                let fallback = normalize_placeholder_tokens(raw.trim_end(), &store);
+                let fallback_missing = missing_tokens(&fallback, &store);
+                if fallback_missing.is_empty() && !fallback.trim().is_empty() {
+                    tracing::info!(section_title, fallback_model, "fallback rewrite succeeded");
+                    rewritten = fallback;
+                } else {
+                    tracing::warn!(
+                        section_title,
+                        missing = fallback_missing.len(),
+                        fallback_model,
+                        "fallback rewrite also dropped placeholder tokens; keeping primary output"
+                    );
+                }
+            }
+            Err(err) => {
+                tracing::warn!(
+                    section_title,
+                    fallback_model,
+                    ?err,
+                    "fallback rewrite failed; keeping primary output"
+                );
+            }
+        }
+    }
 
     if rewritten.trim().is_empty() {
         tracing::warn!("rewrite output is empty; keeping original section");
-        return Ok(unprotect_markdown_fully(source_markdown, &store.tokens));
+        return Ok(source_markdown.to_owned());
+    }
+
+    let rewritten = unprotect_markdown_fully(&rewritten, &store);
+    // The context is only for reading; drop it if the model repeated it anyway.
+    if let Some(context) = unit.preceding_context
+        && let Some(rest) = rewritten.trim_start().strip_prefix(context.trim())
+        && !rest.trim().is_empty()
+    {
+        tracing::debug!(
+            section_title,
+            "dropping echoed chunk context from rewrite output"
+        );
+        return Ok(rest.trim_start().to_owned());
     }
+    Ok(rewritten)
+}
 
-    Ok(unprotect_markdown_fully(&rewritten, &store.tokens))
+struct PromptInput<'a> {
+    /// Protected Markdown (placeholders instead of code, URLs and HTML).
+    markdown: &'a str,
+    preceding_context: Option<&'a str>,
+    example_token: &'a str,
+    unit: &'a RewriteUnit<'a>,
 }
 
 fn build_openai_rewrite_prompt(
@@ -32,12 +300,52 @@ fn build_openai_rewrite_prompt(
     tone: &str,
     chapter_title: &str,
     section_title: &str,
-    input_markdown: &str,
+    input: &PromptInput<'_>,
+    options: &RewriteOptions,
 ) -> String {
-    format!(
-        "You are a book editor and technical writer.\n\
+    let (scope, heading_rules) = match options.granularity {
+        RewriteGranularity::Section => (
+            "a single book section",
+            "- Headings MUST be minimal.\n  \
+- Do NOT output Markdown headings (`#`, `##`, `###`).\n  \
+- The tool will add the section heading.\n",
+        ),
+        RewriteGranularity::Page => (
+            "a whole source page within a book section",
+            "- Headings MUST be minimal.\n  \
+- Do NOT output `#` or `##` headings; the tool will add the section heading.\n  \
+- You MAY keep the input's subheadings as `###` (or deeper) to preserve structure.\n",
+        ),
+    };
+    let token_rules = format!(
+        "- Do NOT change code blocks, inline code, URLs, or HTML tags.\n\
+- You MUST preserve placeholder tokens of the form {example_token} exactly as they appear (do not remove or alter them).\n",
+        example_token = input.example_token,
+    );
+    let instructions = match options.instructions_template.as_deref() {
+        Some(template) => {
+            let mut instructions = fill_instructions_template(template, |marker| match marker {
+                "chapter_title" => chapter_title.to_owned(),
+                "section_title" => section_title.to_owned(),
+                "title" => input.unit.source_title.to_owned(),
+                "url" => input.unit.source_url.to_owned(),
+                "section_index" => input.unit.section_index.to_string(),
+                "prompt" => input.unit.chapter_intent.trim().to_owned(),
+                "language" => language.to_owned(),
+                "tone" => tone.to_owned(),
+                "scope" => scope.to_owned(),
+                "heading_rules" => heading_rules.to_owned(),
+                _ => token_rules.clone(),
+            });
+            if !instructions.ends_with('\n') {
+                instructions.push('\n');
+            }
+            instructions
+        }
+        None => format!(
+            "You are a book editor and technical writer.\n\
 \n\
-Task: Rewrite the input Markdown into book-first prose for a single book section.\n\
+Task: Rewrite the input Markdown into book-first prose for {scope}.\n\
 \n\
 Context:\n\
 - Chapter title: {chapter_title}\n\
@@ -49,9 +357,7 @@ Hard rules:\n\
 - Use ONLY the facts present in the input Markdown. Do not add new facts.\n\
 - If something is unclear, explicitly say it is unknown/unclear (in the specified language).\n\
 - Prefer paragraphs with smooth transitions.\n\
-- Headings MUST be minimal.\n\
-  - Do NOT output Markdown headings (`#`, `##`, `###`).\n\
-  - The tool will add the section heading.\n\
+{heading_rules}\
 - Bullet lists MUST be limited to summarizing key points.\n\
   - Do not overuse lists.\n\
 - Avoid web/article vocabulary like \"この記事では\".\n\
@@ -59,9 +365,14 @@ Hard rules:\n\
 - If helpful, use a compact pattern: short intro → explanation → example → short wrap-up.\n\
 - Figures/images should be included ONLY when truly necessary.\n\
   - If you keep a figure, explain it in text before placing it.\n\
-- Do NOT change code blocks, inline code, URLs, or HTML tags.\n\
-- You MUST preserve placeholder tokens of the form {{{{SBY_TOKEN_000000}}}} exactly as they appear (do not remove or alter them).\n\
-- Do NOT mention this instruction text.\n\
+{token_rules}\
+- Do NOT mention this instruction text.\n",
+        ),
+    };
+    format!(
+        "{instructions}\
+{tone_examples}\
+{preceding_context}\
 \n\
 Input:\n\
 - Read the Markdown between markers.\n\
@@ -72,35 +383,87 @@ END_MARKDOWN\n\
 \n\
 Output:\n\
 - Output ONLY the rewritten Markdown body for this section.\n",
-        chapter_title = chapter_title,
-        section_title = section_title,
-        language = language,
-        tone = tone,
-        input_markdown = input_markdown.trim_end(),
+        tone_examples = render_tone_examples(&options.tone_examples),
+        preceding_context = render_preceding_context(input.preceding_context),
+        input_markdown = input.markdown.trim_end(),
     )
 }
 
+fn render_preceding_context(context: Option<&str>) -> String {
+    let Some(context) = context else {
+        return String::new();
+    };
+    format!(
+        "\nPreceding context:\n\
+- CONTEXT ONLY — do not re-output. This is the end of your rewrite of the previous chunk of the same page.\n\
+- Use it only to keep the flow and terminology continuous; start your output where the input below starts.\n\
+\n\
+BEGIN_CONTEXT\n\
+{}\n\
+END_CONTEXT\n",
+        context.trim_end()
+    )
+}
+
+fn render_tone_examples(examples: &[ToneExample]) -> String {
+    if examples.is_empty() {
+        return String::new();
+    }
+    let mut out = String::from(
+        "\nTone examples:\n\
+- Here is how to rewrite. Match the style of each OUTPUT; never copy its content.\n",
+    );
+    for (idx, example) in examples.iter().enumerate() {
+        out.push_str(&format!(
+            "\nEXAMPLE {n} INPUT\n{input}\nEXAMPLE {n} OUTPUT\n{output}\nEND_EXAMPLE {n}\n",
+            n = idx + 1,
+            input = example.input.trim_end(),
+            output = example.output.trim_end(),
+        ));
+    }
+    out
+}
+
 #[derive(Debug, Default)]
 struct TokenStore {
+    /// Per-run salt so protected text that merely looks like a placeholder is never expanded.
+    salt: String,
     tokens: Vec<String>,
 }
 
 impl TokenStore {
     fn new() -> Self {
-        Self { tokens: Vec::new() }
+        let salt = uuid::Uuid::new_v4().simple().to_string()[..8].to_owned();
+        Self {
+            salt,
+            tokens: Vec::new(),
+        }
+    }
+
+    /// `SBY_<salt>_`, the part every placeholder of this store starts with.
+    fn prefix(&self) -> String {
+        format!("SBY_{}_", self.salt)
+    }
+
+    fn token(&self, idx: usize) -> String {
+        format!("{{{{{}{idx:06}}}}}", self.prefix())
     }
 
     fn insert(&mut self, original: String) -> String {
-        let idx = self.tokens.len();
+        let token = self.token(self.tokens.len());
         self.tokens.push(original);
-        format!("{{{{SBY_TOKEN_{idx:06}}}}}")
+        token
     }
 }
 
 fn protect_markdown(input: &str, store: &mut TokenStore) -> String {
     let text = protect_fenced_code_blocks(input, store);
+    let text = protect_indented_code_blocks(&text, store);
     let text = protect_inline_code_spans(&text, store);
+    let (text, labels) = protect_link_reference_definitions(&text, store);
+    let text = protect_reference_link_labels(&text, &labels, store);
     let text = protect_markdown_link_destinations(&text, store);
+    let text = protect_footnote_references(&text, store);
     protect_autolinks_and_bare_urls(&text, store)
 }
 
@@ -139,6 +502,105 @@ fn protect_fenced_code_blocks(input: &str, store: &mut TokenStore) -> String {
     out
 }
 
+/// Tokenizes CommonMark indented code blocks (4+ columns of indentation after a blank line).
+///
+/// Indented lines inside lists are list-item continuations, not code, so they are left alone.
+fn protect_indented_code_blocks(input: &str, store: &mut TokenStore) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut block = String::new();
+    let mut pending_blank = String::new();
+    let mut prev_blank = true;
+    let mut in_list = false;
+
+    for piece in input.split_inclusive('\n') {
+        let blank = piece.trim().is_empty();
+
+        if !block.is_empty() {
+            if blank {
+                pending_blank.push_str(piece);
+                continue;
+            }
+            if indent_width(piece) >= 4 {
+                block.push_str(&pending_blank);
+                pending_blank.clear();
+                block.push_str(piece);
+                continue;
+            }
+            flush_indented_code_block(&mut out, &mut block, store);
+            out.push_str(&std::mem::take(&mut pending_blank));
+            prev_blank = true;
+        }
+
+        if blank {
+            out.push_str(piece);
+            prev_blank = true;
+            continue;
+        }
+
+        let indent = indent_width(piece);
+        if indent >= 4 && prev_blank && !in_list {
+            block.push_str(piece);
+            continue;
+        }
+
+        if is_list_item_line(piece) {
+            in_list = true;
+        } else if indent == 0 && prev_blank {
+            in_list = false;
+        }
+        prev_blank = false;
+        out.push_str(piece);
+    }
+
+    if !block.is_empty() {
+        flush_indented_code_block(&mut out, &mut block, store);
+        out.push_str(&pending_blank);
+    }
+
+    out
+}
+
+fn flush_indented_code_block(out: &mut String, block: &mut String, store: &mut TokenStore) {
+    // Keep the trailing line break outside the token so the placeholder sits on its own line.
+    let ends_with_newline = block.ends_with('\n');
+    if ends_with_newline {
+        block.pop();
+    }
+    out.push_str(&store.insert(std::mem::take(block)));
+    if ends_with_newline {
+        out.push('\n');
+    }
+}
+
+fn indent_width(line: &str) -> usize {
+    let mut width = 0usize;
+    for ch in line.chars() {
+        match ch {
+            ' ' => width += 1,
+            '\t' => width += 4 - width % 4,
+            _ => break,
+        }
+    }
+    width
+}
+
+fn is_list_item_line(line: &str) -> bool {
+    if indent_width(line) >= 4 {
+        return false;
+    }
+    let trimmed = line.trim_start();
+    if let Some(rest) = trimmed.strip_prefix(['-', '*', '+']) {
+        return rest.is_empty() || rest.starts_with([' ', '\t', '\n', '\r']);
+    }
+    let digits = trimmed.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits == 0 || digits > 9 {
+        return false;
+    }
+    trimmed[digits..]
+        .strip_prefix(['.', ')'])
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t', '\n', '\r']))
+}
+
 fn protect_inline_code_spans(input: &str, store: &mut TokenStore) -> String {
     let mut out = String::with_capacity(input.len());
     let mut cursor = 0usize;
@@ -214,6 +676,120 @@ fn protect_markdown_link_destinations(input: &str, store: &mut TokenStore) -> St
     out
 }
 
+/// Tokenizes whole `[label]: dest "title"` definition lines and returns the normalized labels.
+/// Code blocks are already tokens at this point, so definitions inside them are never matched.
+fn protect_link_reference_definitions(
+    input: &str,
+    store: &mut TokenStore,
+) -> (String, HashSet<String>) {
+    let mut out = String::with_capacity(input.len());
+    let mut labels = HashSet::new();
+
+    for piece in input.split_inclusive('\n') {
+        let line = piece.trim_end_matches(['\n', '\r']);
+        let Some(label) = link_reference_definition_label(line) else {
+            out.push_str(piece);
+            continue;
+        };
+        labels.insert(normalize_reference_label(label));
+        out.push_str(&store.insert(line.to_owned()));
+        out.push_str(&piece[line.len()..]);
+    }
+
+    (out, labels)
+}
+
+fn link_reference_definition_label(line: &str) -> Option<&str> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let rest = line[indent..].strip_prefix('[')?;
+    let close = rest.find(']')?;
+    let label = &rest[..close];
+    if label.trim().is_empty() || label.starts_with('^') || label.contains('[') {
+        return None;
+    }
+    let dest = rest[close + 1..].strip_prefix(':')?;
+    (!dest.trim().is_empty()).then_some(label)
+}
+
+fn normalize_reference_label(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Tokenizes the `[ref]` of full (`[text][ref]`) and collapsed (`[text][]`) references, and
+/// shortcut references (`[ref]`) whose label is defined on the page, so the label survives even
+/// when the link text is rewritten.
+fn protect_reference_link_labels(
+    input: &str,
+    labels: &HashSet<String>,
+    store: &mut TokenStore,
+) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut cursor = 0usize;
+
+    while let Some(rel) = input[cursor..].find('[') {
+        let start = cursor + rel;
+        out.push_str(&input[cursor..start]);
+        let Some(close) = input[start + 1..]
+            .find([']', '[', '\n'])
+            .map(|len| start + 1 + len)
+            .filter(|end| input[*end..].starts_with(']'))
+        else {
+            out.push('[');
+            cursor = start + 1;
+            continue;
+        };
+
+        let end = close + 1;
+        let label = &input[start + 1..close];
+        let is_reference = input[..start].ends_with(']');
+        let is_shortcut = !label.starts_with('^')
+            && labels.contains(&normalize_reference_label(label))
+            && !input[end..].starts_with(['(', '[', ':']);
+        if is_reference || is_shortcut {
+            out.push_str(&store.insert(input[start..end].to_owned()));
+        } else {
+            out.push_str(&input[start..end]);
+        }
+        cursor = end;
+    }
+
+    out.push_str(&input[cursor..]);
+    out
+}
+
+/// Tokenizes `[^label]` footnote references (e.g. from `--external-link-policy footnote`).
+fn protect_footnote_references(input: &str, store: &mut TokenStore) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut cursor = 0usize;
+
+    while let Some(rel) = input[cursor..].find("[^") {
+        let start = cursor + rel;
+        out.push_str(&input[cursor..start]);
+        let label_start = start + 2;
+        let label_len = input[label_start..]
+            .find(|c: char| c == ']' || c.is_whitespace() || c == '[')
+            .filter(|len| *len > 0 && input[label_start + len..].starts_with(']'));
+        let Some(label_len) = label_len else {
+            out.push_str("[^");
+            cursor = label_start;
+            continue;
+        };
+        let end = label_start + label_len + 1;
+        out.push_str(&store.insert(input[start..end].to_owned()));
+        cursor = end;
+    }
+
+    out.push_str(&input[cursor..]);
+    out
+}
+
 fn protect_autolinks_and_bare_urls(input: &str, store: &mut TokenStore) -> String {
     let mut out = String::with_capacity(input.len());
     let mut cursor = 0usize;
@@ -279,11 +855,12 @@ fn fence_end_marker(line: &str, marker: &str) -> bool {
     trimmed.starts_with(marker)
 }
 
-fn normalize_placeholder_tokens(input: &str) -> String {
+fn normalize_placeholder_tokens(input: &str, store: &TokenStore) -> String {
     // A small normalizer for common model mistakes:
-    // - `{SBY_TOKEN_0}` -> `{{SBY_TOKEN_000000}}`
-    // - `SBY_TOKEN_0` -> `{{SBY_TOKEN_000000}}`
-    // - `{{{SBY_TOKEN_000000}}}` -> `{{SBY_TOKEN_000000}}`
+    // - `{SBY_<salt>_0}` -> `{{SBY_<salt>_000000}}`
+    // - `SBY_<salt>_0` -> `{{SBY_<salt>_000000}}`
+    // - `{{{SBY_<salt>_000000}}}` -> `{{SBY_<salt>_000000}}`
+    let prefix = store.prefix();
     let mut out = String::with_capacity(input.len());
     let mut i = 0usize;
 
@@ -291,17 +868,17 @@ fn normalize_placeholder_tokens(input: &str) -> String {
         let rest = &input[i..];
 
         if rest.starts_with('{')
-            && let Some((consumed, token)) = parse_any_braced_placeholder(rest)
+            && let Some((consumed, idx)) = parse_any_braced_placeholder(rest, &prefix)
         {
-            out.push_str(&token);
+            out.push_str(&store.token(idx));
             i += consumed;
             continue;
         }
 
-        if rest.starts_with("SBY_TOKEN_")
-            && let Some((consumed, token)) = parse_bare_placeholder(rest)
+        if rest.starts_with(&prefix)
+            && let Some((consumed, idx)) = parse_bare_placeholder(rest, &prefix)
         {
-            out.push_str(&token);
+            out.push_str(&store.token(idx));
             i += consumed;
             continue;
         }
@@ -314,7 +891,7 @@ fn normalize_placeholder_tokens(input: &str) -> String {
     out
 }
 
-fn parse_any_braced_placeholder(input: &str) -> Option<(usize, String)> {
+fn parse_any_braced_placeholder(input: &str, prefix: &str) -> Option<(usize, usize)> {
     let bytes = input.as_bytes();
     let mut open_count = 0usize;
     while open_count < bytes.len() && bytes[open_count] == b'{' {
@@ -326,11 +903,11 @@ fn parse_any_braced_placeholder(input: &str) -> Option<(usize, String)> {
 
     let mut i = open_count;
     i = skip_ws(input, i);
-    if !input[i..].starts_with("SBY_TOKEN_") {
+    if !input[i..].starts_with(prefix) {
         return None;
     }
 
-    let (consumed_inner, token) = parse_bare_placeholder(&input[i..])?;
+    let (consumed_inner, idx) = parse_bare_placeholder(&input[i..], prefix)?;
     i += consumed_inner;
     i = skip_ws(input, i);
 
@@ -342,23 +919,17 @@ fn parse_any_braced_placeholder(input: &str) -> Option<(usize, String)> {
         return None;
     }
 
-    Some((i + close_count, token))
+    Some((i + close_count, idx))
 }
 
-fn parse_bare_placeholder(input: &str) -> Option<(usize, String)> {
-    if !input.starts_with("SBY_TOKEN_") {
-        return None;
-    }
-    let rest = &input["SBY_TOKEN_".len()..];
+fn parse_bare_placeholder(input: &str, prefix: &str) -> Option<(usize, usize)> {
+    let rest = input.strip_prefix(prefix)?;
     let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
     if digits == 0 {
         return None;
     }
-    let token_digits = &rest[..digits];
-    let idx: usize = token_digits.parse().ok()?;
-    let canonical = format!("SBY_TOKEN_{idx:06}");
-    let consumed = "SBY_TOKEN_".len() + digits;
-    Some((consumed, format!("{{{{{canonical}}}}}")))
+    let idx: usize = rest[..digits].parse().ok()?;
+    Some((prefix.len() + digits, idx))
 }
 
 fn skip_ws(input: &str, mut i: usize) -> usize {
@@ -372,11 +943,50 @@ fn skip_ws(input: &str, mut i: usize) -> usize {
     i
 }
 
-fn unprotect_markdown_fully(input: &str, originals: &[String]) -> String {
-    let mut out = input.to_owned();
-    for (idx, original) in originals.iter().enumerate() {
-        let token = format!("{{{{SBY_TOKEN_{idx:06}}}}}");
-        out = out.replace(&token, original);
+/// Returns the indices of placeholder tokens that do not appear in `rewritten`.
+fn missing_tokens(rewritten: &str, store: &TokenStore) -> Vec<usize> {
+    (0..store.tokens.len())
+        .filter(|idx| !rewritten.contains(&store.token(*idx)))
+        .collect()
+}
+
+/// Replaces every placeholder in a single left-to-right pass.
+///
+/// Originals may embed placeholders from earlier protection stages (e.g. an inline code span
+/// inside a link destination); those are expanded recursively. Expanded text is never rescanned.
+fn unprotect_markdown_fully(input: &str, store: &TokenStore) -> String {
+    let prefix = format!("{{{{{}", store.prefix());
+    let mut out = String::with_capacity(input.len());
+    let mut cursor = 0usize;
+
+    while let Some(rel) = input[cursor..].find(&prefix) {
+        let start = cursor + rel;
+        out.push_str(&input[cursor..start]);
+
+        let digits_start = start + prefix.len();
+        let digits = input[digits_start..]
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .count();
+        let digits_end = digits_start + digits;
+        let original = input[digits_end..]
+            .starts_with("}}")
+            .then(|| input[digits_start..digits_end].parse::<usize>().ok())
+            .flatten()
+            .and_then(|idx| store.tokens.get(idx));
+
+        match original {
+            Some(original) => {
+                out.push_str(&unprotect_markdown_fully(original, store));
+                cursor = digits_end + 2;
+            }
+            None => {
+                out.push_str(&prefix);
+                cursor = digits_start;
+            }
+        }
     }
+
+    out.push_str(&input[cursor..]);
     out
 }
@@ -0,0 +1,52 @@
+use anyhow::Context as _;
+
+/// Certificate verification settings from `--ca-cert` / `--insecure`.
+#[derive(Debug, Clone, Default)]
+pub struct TlsOptions {
+    /// Extra trusted roots, on top of the built-in ones.
+    pub ca_certs: Vec<reqwest::Certificate>,
+    /// Accept any certificate (expired, self-signed, wrong host).
+    pub insecure: bool,
+}
+
+impl TlsOptions {
+    /// Loads `ca_cert` (a PEM file; bundles are fine) and warns when verification is disabled.
+    pub fn from_args(ca_cert: Option<&str>, insecure: bool) -> anyhow::Result<Self> {
+        let ca_certs = match ca_cert {
+            Some(path) => {
+                let pem = std::fs::read(path).with_context(|| format!("read --ca-cert: {path}"))?;
+                let certs = reqwest::Certificate::from_pem_bundle(&pem)
+                    .with_context(|| format!("parse --ca-cert: {path}"))?;
+                if certs.is_empty() {
+                    anyhow::bail!("--ca-cert contains no PEM certificates: {path}");
+                }
+                certs
+            }
+            None => Vec::new(),
+        };
+        if insecure {
+            tracing::warn!(
+                "!!! TLS certificate verification is DISABLED (--insecure): \
+                 any certificate is accepted and connections can be intercepted !!!"
+            );
+        }
+        Ok(Self { ca_certs, insecure })
+    }
+
+    pub fn apply(&self, mut builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        for cert in &self.ca_certs {
+            builder = builder.add_root_certificate(cert.clone());
+        }
+        builder.danger_accept_invalid_certs(self.insecure)
+    }
+
+    pub fn apply_blocking(
+        &self,
+        mut builder: reqwest::blocking::ClientBuilder,
+    ) -> reqwest::blocking::ClientBuilder {
+        for cert in &self.ca_certs {
+            builder = builder.add_root_certificate(cert.clone());
+        }
+        builder.danger_accept_invalid_certs(self.insecure)
+    }
+}
@@ -1,14 +1,19 @@
 use std::collections::{HashMap, HashSet};
 use std::fs::OpenOptions;
 use std::io::{BufRead as _, BufReader, Write as _};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::Context as _;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
 
-use crate::cli::{LlmEngine, TocCreateArgs};
+use crate::cli::{
+    ChapterIdStyle, LlmEngine, NoopGrouping, PageOrder, TocCheckArgs, TocCreateArgs, TocMergeArgs,
+    TocSplitArgs,
+};
 use crate::formats::{ManifestRecord, Toc, TocChapter, TocPart, TocSection};
-use crate::openai::{OpenAiConfig, exec_readonly};
+use crate::openai::{OpenAiConfig, exec_readonly_with_retry};
+use crate::url_norm::{canonical_url, chapter_key};
 
 pub async fn create(args: TocCreateArgs) -> anyhow::Result<()> {
     let manifest_path = PathBuf::from(&args.manifest);
@@ -18,10 +23,12 @@ pub async fn create(args: TocCreateArgs) -> anyhow::Result<()> {
         anyhow::bail!("toc output already exists: {}", out_path.display());
     }
 
-    let records = read_manifest_records(&manifest_path).context("read manifest")?;
+    let mut records = read_manifest_records(&manifest_path).context("read manifest")?;
     if records.is_empty() {
         anyhow::bail!("manifest is empty: {}", manifest_path.display());
     }
+    order_records(&mut records, args.page_order, args.sitemap.as_deref())
+        .context("apply --page-order")?;
 
     let plan = match args.engine {
         LlmEngine::Noop => plan_noop(&args, &records),
@@ -101,23 +108,68 @@ fn plan_noop(args: &TocCreateArgs, records: &[ManifestRecord]) -> TocPlan {
         .clone()
         .unwrap_or_else(|| format!("{chapter_title} Textbook"));
 
+    let chapters = match args.noop_grouping {
+        NoopGrouping::Flat => vec![noop_chapter(chapter_title, records.iter())],
+        NoopGrouping::ByPathSegment => group_by_path_segment(records)
+            .into_iter()
+            .map(|(key, group)| {
+                let title = if key == "root" {
+                    chapter_title.clone()
+                } else {
+                    title_case_segment(&key)
+                };
+                noop_chapter(title, group.into_iter())
+            })
+            .collect(),
+    };
+
     TocPlan {
         book_title,
-        chapters: vec![TocPlanChapter {
-            title: chapter_title,
-            intent: "素材を整理し、本として読める順序に並べる。".to_owned(),
-            reader_gains: vec!["原典ページを参照しながら、全体像をたどれる。".to_owned()],
-            sections: records
-                .iter()
-                .map(|r| TocPlanSection {
-                    title: r.title.clone(),
-                    sources: vec![r.id.clone()],
-                })
-                .collect(),
-        }],
+        chapters,
+    }
+}
+
+fn noop_chapter<'a>(
+    title: String,
+    records: impl Iterator<Item = &'a ManifestRecord>,
+) -> TocPlanChapter {
+    TocPlanChapter {
+        title,
+        intent: "素材を整理し、本として読める順序に並べる。".to_owned(),
+        reader_gains: vec!["原典ページを参照しながら、全体像をたどれる。".to_owned()],
+        sections: records
+            .map(|r| TocPlanSection {
+                title: r.title.clone(),
+                sources: vec![r.id.clone()],
+            })
+            .collect(),
     }
 }
 
+/// Groups records by [`chapter_key`] below their common path prefix, in first-seen (manifest)
+/// order. A page at the prefix itself (the section index) lands in `root`.
+fn group_by_path_segment(records: &[ManifestRecord]) -> Vec<(String, Vec<&ManifestRecord>)> {
+    let common = common_path_segments(records);
+    let prefix = common.join("/");
+
+    let mut groups: Vec<(String, Vec<&ManifestRecord>)> = Vec::new();
+    for record in records {
+        let key = match url::Url::parse(&record.url) {
+            Ok(page_url) if record.path.trim_matches('/') != prefix => {
+                let mut start_url = page_url.clone();
+                start_url.set_path(&format!("/{prefix}"));
+                chapter_key(&start_url, &page_url)
+            }
+            _ => "root".to_owned(),
+        };
+        match groups.iter_mut().find(|(existing, _)| *existing == key) {
+            Some((_, group)) => group.push(record),
+            None => groups.push((key, vec![record])),
+        }
+    }
+    groups
+}
+
 async fn plan_via_openai(
     args: &TocCreateArgs,
     records: &[ManifestRecord],
@@ -188,12 +240,25 @@ Output:\n\
   {{\"book_title\":\"...\",\"chapters\":[{{\"title\":\"...\",\"intent\":\"...\",\"reader_gains\":[\"...\"],\"sections\":[{{\"title\":\"...\",\"sources\":[\"p_...\"]}}]}}]}}\n",
         input_json = input_json.trim_end(),
     );
+    if let Some(dir) = args.dump_prompt.as_deref() {
+        let dir = Path::new(dir);
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("create prompt dump dir: {}", dir.display()))?;
+        crate::openai::dump_prompt(dir, "toc", &prompt)?;
+    }
 
-    let config = OpenAiConfig::from_env().context("load openai config")?;
+    let config = OpenAiConfig::from_env(args.openai_api_key_env.as_deref())
+        .context("load openai config")?
+        .with_account(
+            args.openai_organization.as_deref(),
+            args.openai_project.as_deref(),
+        )
+        .with_proxy(args.proxy.as_deref())
+        .with_retry_delays(args.openai_retry_base_ms, args.openai_retry_max_ms);
     let raw = tokio::task::spawn_blocking({
         let prompt = prompt.clone();
         let config = config.clone();
-        move || exec_readonly(&prompt, &config).context("openai exec for toc")
+        move || exec_readonly_with_retry(&prompt, &config).context("openai exec for toc")
     })
     .await
     .context("join openai task")??;
@@ -337,18 +402,19 @@ fn toc_from_plan(
     if chapters.is_empty() {
         anyhow::bail!("toc plan has no chapters after deduplication");
     }
-    if chapters.len() > 99 {
+    if args.chapter_ids == ChapterIdStyle::Positional && chapters.len() > 99 {
         anyhow::bail!(
             "too many chapters ({}); chapter ids are limited to ch01..ch99",
             chapters.len()
         );
     }
 
+    let mut used_ids = HashSet::new();
     let chapters = chapters
         .into_iter()
         .enumerate()
         .map(|(idx, (ch, gains, sections))| TocChapter {
-            id: format!("ch{:02}", idx + 1),
+            id: chapter_id(args.chapter_ids, idx, &ch.title, &mut used_ids),
             title: ch.title.clone(),
             intent: ch.intent.clone(),
             reader_gains: gains,
@@ -365,7 +431,328 @@ fn toc_from_plan(
     })
 }
 
-fn read_manifest_records(manifest_path: &PathBuf) -> anyhow::Result<Vec<ManifestRecord>> {
+/// Id of the `idx`-th (0-based, TOC order) chapter under `style`.
+fn chapter_id(
+    style: ChapterIdStyle,
+    idx: usize,
+    title: &str,
+    used: &mut HashSet<String>,
+) -> String {
+    match style {
+        ChapterIdStyle::Positional => format!("ch{:02}", idx + 1),
+        ChapterIdStyle::Slug => slug_chapter_id(title, used),
+    }
+}
+
+pub fn split(args: TocSplitArgs) -> anyhow::Result<()> {
+    let toc_path = PathBuf::from(&args.toc);
+    let mut toc = read_toc(&toc_path)?;
+    split_chapter(&mut toc, &args.chapter, &args.at, args.title.as_deref())?;
+    write_toc(&toc_path, &toc)
+}
+
+pub fn merge(args: TocMergeArgs) -> anyhow::Result<()> {
+    let toc_path = PathBuf::from(&args.toc);
+    let mut toc = read_toc(&toc_path)?;
+    merge_chapters(&mut toc, &args.into, &args.from)?;
+    write_toc(&toc_path, &toc)
+}
+
+pub fn read_toc(path: &PathBuf) -> anyhow::Result<Toc> {
+    let yaml =
+        std::fs::read_to_string(path).with_context(|| format!("read toc: {}", path.display()))?;
+    serde_yaml::from_str(&yaml).with_context(|| format!("parse toc: {}", path.display()))
+}
+
+fn write_toc(path: &PathBuf, toc: &Toc) -> anyhow::Result<()> {
+    let yaml = serde_yaml::to_string(toc).context("serialize toc yaml")?;
+    std::fs::write(path, yaml).with_context(|| format!("write toc: {}", path.display()))
+}
+
+/// Moves `at` and every source after it (in TOC order within the chapter) into a new chapter
+/// placed right after `chapter_id`. A section split in the middle keeps its title on both sides.
+fn split_chapter(
+    toc: &mut Toc,
+    chapter_id: &str,
+    at: &str,
+    title: Option<&str>,
+) -> anyhow::Result<()> {
+    let (part_idx, ch_idx) = find_chapter(toc, chapter_id)?;
+    let chapter = &mut toc.parts[part_idx].chapters[ch_idx];
+
+    let Some((sec_idx, src_idx)) =
+        chapter
+            .sections
+            .iter()
+            .enumerate()
+            .find_map(|(sec_idx, section)| {
+                section
+                    .sources
+                    .iter()
+                    .position(|src| src == at)
+                    .map(|src_idx| (sec_idx, src_idx))
+            })
+    else {
+        anyhow::bail!("source id not found in chapter {chapter_id}: {at}");
+    };
+    if sec_idx == 0 && src_idx == 0 {
+        anyhow::bail!("cannot split chapter {chapter_id} at its first source: {at}");
+    }
+
+    let mut moved = chapter.sections.split_off(sec_idx);
+    if src_idx > 0 {
+        let tail = moved[0].sources.split_off(src_idx);
+        chapter.sections.push(TocSection {
+            title: moved[0].title.clone(),
+            sources: std::mem::replace(&mut moved[0].sources, tail),
+        });
+    }
+
+    let new_chapter = TocChapter {
+        id: String::new(),
+        title: title
+            .map(ToOwned::to_owned)
+            .unwrap_or_else(|| format!("{} (continued)", chapter.title)),
+        intent: chapter.intent.clone(),
+        reader_gains: chapter.reader_gains.clone(),
+        sections: moved,
+    };
+    toc.parts[part_idx].chapters.insert(ch_idx + 1, new_chapter);
+
+    renumber_chapter_ids(toc)?;
+    validate_toc(toc)
+}
+
+/// Appends the sections of `from_id` to `into_id` and removes `from_id`.
+fn merge_chapters(toc: &mut Toc, into_id: &str, from_id: &str) -> anyhow::Result<()> {
+    if into_id == from_id {
+        anyhow::bail!("cannot merge chapter {into_id} into itself");
+    }
+    find_chapter(toc, into_id)?;
+    let (from_part, from_idx) = find_chapter(toc, from_id)?;
+    let from = toc.parts[from_part].chapters.remove(from_idx);
+    let (into_part, into_idx) = find_chapter(toc, into_id)?;
+    let into = &mut toc.parts[into_part].chapters[into_idx];
+
+    into.sections.extend(from.sections);
+    for gain in from.reader_gains {
+        if !into.reader_gains.contains(&gain) {
+            into.reader_gains.push(gain);
+        }
+    }
+    toc.parts.retain(|part| !part.chapters.is_empty());
+
+    renumber_chapter_ids(toc)?;
+    validate_toc(toc)
+}
+
+fn find_chapter(toc: &Toc, chapter_id: &str) -> anyhow::Result<(usize, usize)> {
+    toc.parts
+        .iter()
+        .enumerate()
+        .find_map(|(part_idx, part)| {
+            part.chapters
+                .iter()
+                .position(|ch| ch.id == chapter_id)
+                .map(|ch_idx| (part_idx, ch_idx))
+        })
+        .ok_or_else(|| anyhow::anyhow!("chapter not found in toc: {chapter_id}"))
+}
+
+/// Re-assigns chapter ids in TOC order with the scheme the TOC already uses: positional when
+/// every existing id looks like `chNN`, title slugs otherwise.
+fn renumber_chapter_ids(toc: &mut Toc) -> anyhow::Result<()> {
+    let style = if toc
+        .parts
+        .iter()
+        .flat_map(|part| &part.chapters)
+        .filter(|ch| !ch.id.is_empty())
+        .all(|ch| is_positional_chapter_id(&ch.id))
+    {
+        ChapterIdStyle::Positional
+    } else {
+        ChapterIdStyle::Slug
+    };
+    let count = toc
+        .parts
+        .iter()
+        .map(|part| part.chapters.len())
+        .sum::<usize>();
+    if style == ChapterIdStyle::Positional && count > 99 {
+        anyhow::bail!("too many chapters ({count}); chapter ids are limited to ch01..ch99");
+    }
+
+    let mut used = HashSet::new();
+    for (idx, chapter) in toc
+        .parts
+        .iter_mut()
+        .flat_map(|part| &mut part.chapters)
+        .enumerate()
+    {
+        chapter.id = chapter_id(style, idx, &chapter.title, &mut used);
+    }
+    Ok(())
+}
+
+fn is_positional_chapter_id(id: &str) -> bool {
+    id.strip_prefix("ch")
+        .is_some_and(|digits| digits.len() == 2 && digits.chars().all(|c| c.is_ascii_digit()))
+}
+
+pub fn check(args: TocCheckArgs) -> anyhow::Result<()> {
+    let toc = read_toc(&PathBuf::from(&args.toc))?;
+    let records = read_manifest_records(&PathBuf::from(&args.manifest)).context("read manifest")?;
+    let manifest_ids = records
+        .iter()
+        .map(|r| r.id.as_str())
+        .collect::<HashSet<_>>();
+
+    let report = check_toc(&toc, Some(&manifest_ids));
+    report.print();
+
+    tracing::info!(
+        errors = report.errors.len(),
+        orphans = report.orphans.len(),
+        "toc check"
+    );
+    if !report.errors.is_empty() {
+        anyhow::bail!("toc check failed: {} error(s)", report.errors.len());
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct TocCheckReport {
+    pub(crate) errors: Vec<String>,
+    /// Every source id referenced by a section.
+    pub(crate) referenced: HashSet<String>,
+    /// Manifest ids no section references, sorted (empty without `manifest_ids`).
+    pub(crate) orphans: Vec<String>,
+}
+
+impl TocCheckReport {
+    /// Prints one `error:` line per error, then one `warning:` line per orphan.
+    pub(crate) fn print(&self) {
+        for error in &self.errors {
+            println!("error: {error}");
+        }
+        for id in &self.orphans {
+            println!("warning: page not referenced by any section: {id}");
+        }
+    }
+}
+
+/// Checks the invariants `toc_from_plan` guarantees: unique chapter ids, no empty chapters or
+/// sections, every source id used at most once and (given `manifest_ids`) known to the manifest.
+pub(crate) fn check_toc(toc: &Toc, manifest_ids: Option<&HashSet<&str>>) -> TocCheckReport {
+    let mut report = TocCheckReport::default();
+    let mut ids = HashSet::new();
+    if toc.book_title.trim().is_empty() {
+        report.errors.push("book_title is empty".to_owned());
+    }
+    for chapter in toc.parts.iter().flat_map(|part| &part.chapters) {
+        if chapter.id.trim().is_empty() {
+            report
+                .errors
+                .push(format!("chapter id is empty: {}", chapter.title));
+        } else if !ids.insert(chapter.id.as_str()) {
+            report
+                .errors
+                .push(format!("duplicate chapter id: {}", chapter.id));
+        }
+        if chapter.title.trim().is_empty() {
+            report
+                .errors
+                .push(format!("chapter title is empty: {}", chapter.id));
+        }
+        if chapter.sections.is_empty() {
+            report
+                .errors
+                .push(format!("chapter has no sections: {}", chapter.id));
+        }
+        for section in &chapter.sections {
+            if section.title.trim().is_empty() {
+                report
+                    .errors
+                    .push(format!("section title is empty in chapter {}", chapter.id));
+            }
+            if section.sources.is_empty() {
+                report.errors.push(format!(
+                    "section has no sources in chapter {}: {}",
+                    chapter.id, section.title
+                ));
+            }
+            for src in &section.sources {
+                if !report.referenced.insert(src.clone()) {
+                    report.errors.push(format!(
+                        "duplicate source id in chapter {}: {src}",
+                        chapter.id
+                    ));
+                }
+                if let Some(manifest_ids) = manifest_ids
+                    && !manifest_ids.contains(src.as_str())
+                {
+                    report.errors.push(format!(
+                        "unknown source id in chapter {}: {src}",
+                        chapter.id
+                    ));
+                }
+            }
+        }
+    }
+    if let Some(manifest_ids) = manifest_ids {
+        let mut orphans = manifest_ids
+            .iter()
+            .filter(|id| !report.referenced.contains(**id))
+            .map(|id| (*id).to_owned())
+            .collect::<Vec<_>>();
+        orphans.sort();
+        report.orphans = orphans;
+    }
+    report
+}
+
+fn validate_toc(toc: &Toc) -> anyhow::Result<()> {
+    match check_toc(toc, None).errors.into_iter().next() {
+        Some(error) => anyhow::bail!("{error}"),
+        None => Ok(()),
+    }
+}
+
+/// `ch-<slug>` from the ASCII alphanumerics of `title`; titles without any (e.g. Japanese) use a
+/// short hash instead. Collisions get `-2`, `-3`, ... suffixes in TOC order.
+fn slug_chapter_id(title: &str, used: &mut HashSet<String>) -> String {
+    let mut slug = String::new();
+    for c in title.trim().chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    let slug = if slug.is_empty() {
+        let digest = Sha256::digest(title.trim().as_bytes());
+        hex::encode(&digest[..4])
+    } else {
+        slug.chars()
+            .take(48)
+            .collect::<String>()
+            .trim_end_matches('-')
+            .to_owned()
+    };
+
+    let base = format!("ch-{slug}");
+    let mut id = base.clone();
+    let mut n = 2;
+    while !used.insert(id.clone()) {
+        id = format!("{base}-{n}");
+        n += 1;
+    }
+    id
+}
+
+pub fn read_manifest_records(manifest_path: &PathBuf) -> anyhow::Result<Vec<ManifestRecord>> {
     let file = OpenOptions::new()
         .read(true)
         .open(manifest_path)
@@ -382,10 +769,53 @@ fn read_manifest_records(manifest_path: &PathBuf) -> anyhow::Result<Vec<Manifest
             serde_json::from_str(&line).context("parse manifest record")?;
         records.push(record);
     }
-    records.sort_by(|a, b| a.path.cmp(&b.path));
+    records.sort_by(|a, b| crate::manifest::natural_path_cmp(&a.path, &b.path));
     Ok(records)
 }
 
+/// Reorders path-sorted `records` for `--page-order`. The sort is stable, so pages without a
+/// crawl index (or missing from the sitemap) keep their path order after the ordered ones.
+fn order_records(
+    records: &mut [ManifestRecord],
+    page_order: PageOrder,
+    sitemap: Option<&str>,
+) -> anyhow::Result<()> {
+    match page_order {
+        PageOrder::Toc => {}
+        PageOrder::Crawl => {
+            if records
+                .iter()
+                .all(|record| record.discovery_index.is_none())
+            {
+                tracing::warn!("no manifest record has a discovery_index; keeping path order");
+            }
+            records
+                .sort_by_key(|record| (record.discovery_index.is_none(), record.discovery_index));
+        }
+        PageOrder::Sitemap => {
+            let path = sitemap.ok_or_else(|| anyhow::anyhow!("--sitemap is required"))?;
+            let xml =
+                std::fs::read_to_string(path).with_context(|| format!("read sitemap: {path}"))?;
+            let mut positions: HashMap<String, usize> = HashMap::new();
+            for loc in crate::app::preview::extract_xml_locs(&xml) {
+                if let Ok(url) = url::Url::parse(&loc) {
+                    let position = positions.len();
+                    positions
+                        .entry(canonical_url(&url).to_string())
+                        .or_insert(position);
+                }
+            }
+            records.sort_by_key(|record| {
+                url::Url::parse(&record.url)
+                    .ok()
+                    .and_then(|url| positions.get(canonical_url(&url).as_str()).copied())
+                    .map_or((true, 0), |position| (false, position))
+            });
+        }
+    }
+    Ok(())
+}
+
 fn extract_json_object(text: &str) -> anyhow::Result<&str> {
     let start = text
         .find('{')
@@ -426,13 +856,22 @@ fn strip_front_matter(contents: &str) -> &str {
 }
 
 fn derive_chapter_title(records: &[ManifestRecord]) -> String {
+    let Some(first_segment) = common_path_segments(records).first().copied() else {
+        return "Chapter 1".to_owned();
+    };
+
+    title_case_segment(first_segment)
+}
+
+/// Leading path segments shared by every non-root record path.
+fn common_path_segments(records: &[ManifestRecord]) -> Vec<&str> {
     let mut paths = records
         .iter()
         .map(|r| r.path.trim_matches('/'))
         .filter(|p| !p.is_empty());
 
     let Some(first) = paths.next() else {
-        return "Chapter 1".to_owned();
+        return Vec::new();
     };
 
     let mut common = first.split('/').collect::<Vec<_>>();
@@ -451,12 +890,7 @@ fn derive_chapter_title(records: &[ManifestRecord]) -> String {
             break;
         }
     }
-
-    let Some(first_segment) = common.first().copied() else {
-        return "Chapter 1".to_owned();
-    };
-
-    title_case_segment(first_segment)
+    common
 }
 
 fn title_case_segment(segment: &str) -> String {
@@ -480,6 +914,17 @@ mod tests {
             language: "日本語".to_owned(),
             tone: "丁寧".to_owned(),
             engine: LlmEngine::Noop,
+            chapter_ids: ChapterIdStyle::Positional,
+            noop_grouping: NoopGrouping::Flat,
+            page_order: PageOrder::Toc,
+            sitemap: None,
+            dump_prompt: None,
+            openai_organization: None,
+            openai_project: None,
+            openai_api_key_env: None,
+            openai_retry_base_ms: None,
+            openai_retry_max_ms: None,
+            proxy: None,
         }
     }
 
@@ -490,6 +935,8 @@ mod tests {
             title: id.to_owned(),
             path: "/docs".to_owned(),
             extracted_md: "extracted/pages/example.md".to_owned(),
+            base_url: None,
+            discovery_index: None,
         }
     }
 
@@ -562,4 +1009,237 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn toc_from_plan_slug_chapter_ids() -> anyhow::Result<()> {
+        let mut args = test_args();
+        args.chapter_ids = ChapterIdStyle::Slug;
+        let records = vec![record("p1"), record("p2"), record("p3")];
+
+        let chapter = |title: &str, source: &str| TocPlanChapter {
+            title: title.to_owned(),
+            intent: "Intent".to_owned(),
+            reader_gains: vec!["Gain".to_owned()],
+            sections: vec![TocPlanSection {
+                title: "Section".to_owned(),
+                sources: vec![source.to_owned()],
+            }],
+        };
+        let plan = TocPlan {
+            book_title: "Test Book".to_owned(),
+            chapters: vec![
+                chapter("Getting Started!", "p1"),
+                chapter("Getting  started", "p2"),
+                chapter("はじめに", "p3"),
+            ],
+        };
+
+        let toc = toc_from_plan(&args, &records, &plan)?;
+        let ids = toc.parts[0]
+            .chapters
+            .iter()
+            .map(|c| c.id.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(ids[0], "ch-getting-started");
+        assert_eq!(ids[1], "ch-getting-started-2");
+        assert!(ids[2].starts_with("ch-") && ids[2].len() == "ch-".len() + 8);
+
+        Ok(())
+    }
+
+    fn toc_chapter(id: &str, title: &str, sections: &[(&str, &[&str])]) -> TocChapter {
+        TocChapter {
+            id: id.to_owned(),
+            title: title.to_owned(),
+            intent: "Intent".to_owned(),
+            reader_gains: vec![format!("{title} gain")],
+            sections: sections
+                .iter()
+                .map(|(title, sources)| TocSection {
+                    title: (*title).to_owned(),
+                    sources: sources.iter().map(|s| (*s).to_owned()).collect(),
+                })
+                .collect(),
+        }
+    }
+
+    fn toc_of(chapters: Vec<TocChapter>) -> Toc {
+        Toc {
+            book_title: "Test Book".to_owned(),
+            parts: vec![TocPart {
+                title: "Part 1".to_owned(),
+                chapters,
+            }],
+        }
+    }
+
+    #[test]
+    fn split_chapter_moves_tail_into_new_chapter() -> anyhow::Result<()> {
+        let mut toc = toc_of(vec![
+            toc_chapter("ch01", "One", &[("A", &["p1"]), ("B", &["p2", "p3"])]),
+            toc_chapter("ch02", "Two", &[("C", &["p4"])]),
+        ]);
+
+        split_chapter(&mut toc, "ch01", "p3", None)?;
+
+        let chapters = &toc.parts[0].chapters;
+        let ids = chapters.iter().map(|c| c.id.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["ch01", "ch02", "ch03"]);
+        assert_eq!(chapters[0].sections.len(), 2);
+        assert_eq!(chapters[0].sections[1].sources, vec!["p2"]);
+        assert_eq!(chapters[1].title, "One (continued)");
+        assert_eq!(chapters[1].sections[0].title, "B");
+        assert_eq!(chapters[1].sections[0].sources, vec!["p3"]);
+        assert_eq!(chapters[2].title, "Two");
+
+        assert!(split_chapter(&mut toc, "ch01", "p1", None).is_err());
+        assert!(split_chapter(&mut toc, "ch01", "p4", None).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_chapters_appends_sections_and_renumbers() -> anyhow::Result<()> {
+        let mut toc = toc_of(vec![
+            toc_chapter("ch01", "One", &[("A", &["p1"])]),
+            toc_chapter("ch02", "Two", &[("B", &["p2"])]),
+            toc_chapter("ch03", "Three", &[("C", &["p3"])]),
+        ]);
+
+        merge_chapters(&mut toc, "ch03", "ch01")?;
+
+        let chapters = &toc.parts[0].chapters;
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].id, "ch01");
+        assert_eq!(chapters[0].title, "Two");
+        assert_eq!(chapters[1].id, "ch02");
+        assert_eq!(chapters[1].title, "Three");
+        let sources = chapters[1]
+            .sections
+            .iter()
+            .flat_map(|s| s.sources.iter().map(String::as_str))
+            .collect::<Vec<_>>();
+        assert_eq!(sources, vec!["p3", "p1"]);
+        assert_eq!(chapters[1].reader_gains, vec!["Three gain", "One gain"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn toc_edits_keep_slug_ids_and_reject_duplicates() -> anyhow::Result<()> {
+        let mut toc = toc_of(vec![
+            toc_chapter("ch-one", "One", &[("A", &["p1", "p2"])]),
+            toc_chapter("ch-two", "Two", &[("B", &["p3"])]),
+        ]);
+
+        split_chapter(&mut toc, "ch-one", "p2", Some("Later"))?;
+        let ids = toc.parts[0]
+            .chapters
+            .iter()
+            .map(|c| c.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["ch-one", "ch-later", "ch-two"]);
+
+        let mut dup = toc_of(vec![
+            toc_chapter("ch01", "One", &[("A", &["p1"])]),
+            toc_chapter("ch02", "Two", &[("B", &["p1"])]),
+        ]);
+        assert!(merge_chapters(&mut dup, "ch01", "ch02").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn plan_noop_by_path_segment_groups_pages_into_chapters() {
+        let page = |id: &str, path: &str| ManifestRecord {
+            url: format!("https://example.com{path}"),
+            path: path.to_owned(),
+            ..record(id)
+        };
+        let records = vec![
+            page("index", "/docs"),
+            page("guide", "/docs/guide"),
+            page("install", "/docs/guide/install"),
+            page("api-a", "/docs/api/a"),
+            page("api-b", "/docs/api/b"),
+        ];
+        let mut args = test_args();
+
+        let flat = plan_noop(&args, &records);
+        assert_eq!(flat.chapters.len(), 1);
+        assert_eq!(flat.chapters[0].sections.len(), 5);
+
+        args.noop_grouping = NoopGrouping::ByPathSegment;
+        let plan = plan_noop(&args, &records);
+        let chapters = plan
+            .chapters
+            .iter()
+            .map(|chapter| {
+                let sources = chapter
+                    .sections
+                    .iter()
+                    .flat_map(|section| section.sources.iter().map(String::as_str))
+                    .collect::<Vec<_>>();
+                (chapter.title.as_str(), sources)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            chapters,
+            vec![
+                ("Docs", vec!["index"]),
+                ("Guide", vec!["guide", "install"]),
+                ("Api", vec!["api-a", "api-b"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_toc_reports_unknown_duplicate_and_empty_entries() {
+        let mut toc = toc_of(vec![
+            toc_chapter("ch01", "One", &[("A", &["p1", "typo"])]),
+            toc_chapter("ch01", "Two", &[("B", &["p1"]), ("C", &[])]),
+        ]);
+        toc.parts[0]
+            .chapters
+            .push(toc_chapter("ch03", "Three", &[]));
+        let manifest_ids = ["p1", "p2"].into_iter().collect::<HashSet<_>>();
+
+        let report = check_toc(&toc, Some(&manifest_ids));
+
+        assert_eq!(
+            report.errors,
+            vec![
+                "unknown source id in chapter ch01: typo",
+                "duplicate chapter id: ch01",
+                "duplicate source id in chapter ch01: p1",
+                "section has no sources in chapter ch01: C",
+                "chapter has no sections: ch03",
+            ]
+        );
+        assert!(!report.referenced.contains("p2"));
+    }
+
+    #[test]
+    fn read_manifest_records_sorts_numeric_path_segments_naturally() -> anyhow::Result<()> {
+        let temp = tempfile::TempDir::new()?;
+        let manifest_path = temp.path().join("manifest.jsonl");
+        let lines = ["/a/2", "/a/10", "/a/1", "/a", "/a/02"]
+            .into_iter()
+            .map(|path| {
+                let mut record = record(path);
+                record.path = path.to_owned();
+                serde_json::to_string(&record).expect("serialize manifest record")
+            })
+            .collect::<Vec<_>>();
+        std::fs::write(&manifest_path, lines.join("\n"))?;
+
+        let records = read_manifest_records(&manifest_path)?;
+
+        assert_eq!(
+            records.iter().map(|r| r.path.as_str()).collect::<Vec<_>>(),
+            vec!["/a", "/a/1", "/a/02", "/a/2", "/a/10"]
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,421 @@
+use std::collections::BTreeMap;
+
+use url::Url;
+
+/// Query parameters that only track where a visitor came from; they never identify a page.
+///
+/// Entries ending in `*` match by prefix (`utm_*` covers `utm_source`, `utm_medium`, ...).
+pub const DEFAULT_TRACKING_PARAMS: &[&str] = &[
+    "utm_*", "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid", "mc_cid",
+    "mc_eid", "_ga", "_gl", "igshid", "ref_src",
+];
+
+/// File extensions that never hold a crawlable page (archives, images, media, office docs).
+pub const DEFAULT_EXCLUDED_EXTENSIONS: &[&str] = &[
+    "7z", "apk", "avi", "bmp", "bz2", "csv", "deb", "dmg", "doc", "docx", "eot", "epub", "exe",
+    "flac", "gif", "gz", "ico", "iso", "jar", "jpeg", "jpg", "m4a", "mkv", "mov", "mp3", "mp4",
+    "msi", "odp", "ods", "odt", "ogg", "otf", "pdf", "png", "ppt", "pptx", "rar", "rpm", "svg",
+    "tar", "tgz", "tif", "tiff", "ttf", "wav", "webm", "webp", "woff", "woff2", "xls", "xlsx",
+    "xz", "zip",
+];
+
+/// Lowercased extension of the last path segment (`/files/Guide.PDF` -> `pdf`).
+pub fn path_extension(url: &Url) -> Option<String> {
+    let last_segment = url.path().rsplit('/').next()?;
+    let (stem, extension) = last_segment.rsplit_once('.')?;
+    if stem.is_empty() || extension.is_empty() {
+        return None;
+    }
+    Some(extension.to_ascii_lowercase())
+}
+
+/// Whether `url` points at a file with one of `extensions` (compared case-insensitively,
+/// with or without a leading dot).
+pub fn has_extension(url: &Url, extensions: &[&str]) -> bool {
+    path_extension(url).is_some_and(|extension| {
+        extensions.iter().any(|candidate| {
+            candidate
+                .trim_start_matches('.')
+                .eq_ignore_ascii_case(&extension)
+        })
+    })
+}
+
+/// Returns `url` without the query parameters matched by `denylist` (see
+/// [`DEFAULT_TRACKING_PARAMS`]), keeping every other parameter in its original order.
+pub fn strip_tracking_params(url: &Url, denylist: &[&str]) -> Url {
+    let mut out = url.clone();
+    if url.query().is_none() {
+        return out;
+    }
+
+    let kept = url
+        .query_pairs()
+        .filter(|(name, _)| !is_tracking_param(name, denylist))
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect::<Vec<_>>();
+    if kept.is_empty() {
+        out.set_query(None);
+    } else {
+        out.query_pairs_mut().clear().extend_pairs(kept);
+    }
+    out
+}
+
+fn is_tracking_param(name: &str, denylist: &[&str]) -> bool {
+    let name = name.to_ascii_lowercase();
+    denylist
+        .iter()
+        .any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == *pattern,
+        })
+}
+
+/// Page identity shared by the crawler, the link rewriter and the preview estimate.
+///
+/// Drops the fragment, tracking parameters and the remaining query, then trims trailing
+/// slashes so `/docs/` and `/docs` name the same page. `crawl --keep-query` uses
+/// [`QueryPolicy::canonical_url`] instead.
+pub fn canonical_url(url: &Url) -> Url {
+    QueryPolicy::default().canonical_url(url)
+}
+
+/// How the query string takes part in page identity during a crawl.
+#[derive(Debug, Clone, Default)]
+pub struct QueryPolicy {
+    /// `--keep-query`: keep the non-tracking parameters, so `?page=2` and `?page=3` are
+    /// separate pages.
+    pub keep_query: bool,
+    /// `--tracking-param`: parameters stripped besides [`DEFAULT_TRACKING_PARAMS`].
+    pub extra_tracking_params: Vec<String>,
+}
+
+impl QueryPolicy {
+    /// Policy that keeps non-tracking query parameters (default tracking list only).
+    pub fn keeping_query() -> Self {
+        Self {
+            keep_query: true,
+            extra_tracking_params: Vec::new(),
+        }
+    }
+
+    /// `url` without its fragment and tracking parameters, and without the whole query
+    /// unless `keep_query`. The path is left as is.
+    pub fn strip(&self, url: &Url) -> Url {
+        let mut out = if self.keep_query {
+            let denylist = DEFAULT_TRACKING_PARAMS
+                .iter()
+                .copied()
+                .chain(self.extra_tracking_params.iter().map(String::as_str))
+                .collect::<Vec<_>>();
+            strip_tracking_params(url, &denylist)
+        } else {
+            let mut out = url.clone();
+            out.set_query(None);
+            out
+        };
+        out.set_fragment(None);
+        out
+    }
+
+    /// [`Self::strip`] with trailing slashes trimmed from the path.
+    pub fn canonical_url(&self, url: &Url) -> Url {
+        let mut canonical = self.strip(url);
+        let mut path = canonical.path().to_owned();
+        while path.len() > 1 && path.ends_with('/') {
+            path.pop();
+        }
+        canonical.set_path(&path);
+        canonical
+    }
+}
+
+/// `--host-alias FROM=TO` pairs: hosts that serve the same site under another name
+/// (`www.example.com=example.com`).
+#[derive(Debug, Clone, Default)]
+pub struct HostAliases {
+    /// Lowercased alias host -> canonical host.
+    canonical_by_alias: BTreeMap<String, String>,
+}
+
+impl HostAliases {
+    /// Parses `FROM=TO` entries. A canonical host cannot itself be an alias, so lookups never
+    /// chain.
+    pub fn parse(entries: &[String]) -> anyhow::Result<Self> {
+        let mut canonical_by_alias = BTreeMap::new();
+        for entry in entries {
+            let (from, to) = entry
+                .split_once('=')
+                .map(|(from, to)| {
+                    (
+                        from.trim().to_ascii_lowercase(),
+                        to.trim().to_ascii_lowercase(),
+                    )
+                })
+                .filter(|(from, to)| !from.is_empty() && !to.is_empty())
+                .ok_or_else(|| anyhow::anyhow!("host alias must be FROM=TO: {entry}"))?;
+            for host in [&from, &to] {
+                if url::Host::parse(host).is_err() || host.contains([':', '/']) {
+                    anyhow::bail!("invalid host in host alias {entry}: {host}");
+                }
+            }
+            if from == to {
+                anyhow::bail!("host alias maps a host to itself: {entry}");
+            }
+            if let Some(previous) = canonical_by_alias.get(&from)
+                && *previous != to
+            {
+                anyhow::bail!("host {from} is aliased to both {previous} and {to}");
+            }
+            canonical_by_alias.insert(from, to);
+        }
+        if let Some((alias, canonical)) = canonical_by_alias
+            .iter()
+            .find(|(_, canonical)| canonical_by_alias.contains_key(*canonical))
+        {
+            anyhow::bail!("host alias target {canonical} (for {alias}) is itself an alias");
+        }
+        Ok(Self { canonical_by_alias })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.canonical_by_alias.is_empty()
+    }
+
+    /// `url` with an aliased host replaced by its canonical host.
+    pub fn canonicalize(&self, url: &Url) -> Url {
+        let mut out = url.clone();
+        if let Some(canonical) = url
+            .host_str()
+            .and_then(|host| self.canonical_by_alias.get(&host.to_ascii_lowercase()))
+        {
+            // Both hosts were validated as domain names, so this cannot fail for http(s) URLs.
+            let _ = out.set_host(Some(canonical));
+        }
+        out
+    }
+
+    /// `url` under every host of its alias group (canonical host first), for lookups keyed by
+    /// whichever host a link happens to use. Just `url` when its host has no aliases.
+    pub fn host_variants(&self, url: &Url) -> Vec<Url> {
+        let canonical = self.canonicalize(url);
+        let Some(canonical_host) = canonical.host_str().map(str::to_owned) else {
+            return vec![url.clone()];
+        };
+        let mut variants = vec![canonical.clone()];
+        for alias in self.aliases_of(&canonical_host) {
+            let mut variant = canonical.clone();
+            if variant.set_host(Some(alias)).is_ok() {
+                variants.push(variant);
+            }
+        }
+        variants
+    }
+
+    /// Hosts aliased to `canonical_host`.
+    pub fn aliases_of<'a>(&'a self, canonical_host: &'a str) -> impl Iterator<Item = &'a str> {
+        self.canonical_by_alias
+            .iter()
+            .filter(move |(_, target)| target.eq_ignore_ascii_case(canonical_host))
+            .map(|(alias, _)| alias.as_str())
+    }
+}
+
+/// First path segment of `page_url` below `start_url`'s directory (`root` for the start page
+/// itself); used to group pages into chapters by the preview and the noop TOC planner.
+pub fn chapter_key(start_url: &Url, page_url: &Url) -> String {
+    let base_path = {
+        let p = start_url.path();
+        if p.ends_with('/') {
+            p.to_string()
+        } else {
+            format!("{p}/")
+        }
+    };
+
+    let path = page_url.path();
+    let key = if path.starts_with(&base_path) {
+        let rest = &path[base_path.len()..];
+        rest.split('/')
+            .find(|s| !s.trim().is_empty())
+            .unwrap_or("root")
+            .to_string()
+    } else {
+        path.trim_start_matches('/')
+            .split('/')
+            .find(|s| !s.trim().is_empty())
+            .unwrap_or("root")
+            .to_string()
+    };
+
+    if key.trim().is_empty() {
+        "root".to_string()
+    } else {
+        key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(raw: &str) -> Url {
+        Url::parse(raw).expect("parse url")
+    }
+
+    #[test]
+    fn strip_tracking_params_collapses_utm_only_query_to_bare_url() {
+        let stripped = strip_tracking_params(
+            &url("https://example.com/docs/intro?utm_source=x"),
+            DEFAULT_TRACKING_PARAMS,
+        );
+        assert_eq!(stripped.as_str(), "https://example.com/docs/intro");
+    }
+
+    #[test]
+    fn strip_tracking_params_keeps_other_params_in_order() {
+        let stripped = strip_tracking_params(
+            &url("https://example.com/search?q=rust&UTM_Medium=mail&fbclid=abc&page=2&gclid=1"),
+            DEFAULT_TRACKING_PARAMS,
+        );
+        assert_eq!(
+            stripped.as_str(),
+            "https://example.com/search?q=rust&page=2"
+        );
+    }
+
+    #[test]
+    fn strip_tracking_params_honors_custom_denylist() {
+        let stripped = strip_tracking_params(
+            &url("https://example.com/a?session=1&lang=en"),
+            &["session"],
+        );
+        assert_eq!(stripped.as_str(), "https://example.com/a?lang=en");
+    }
+
+    #[test]
+    fn has_extension_checks_last_path_segment() {
+        assert!(has_extension(
+            &url("https://example.com/files/Guide.PDF?download=1"),
+            DEFAULT_EXCLUDED_EXTENSIONS
+        ));
+        assert!(has_extension(&url("https://example.com/a.iso"), &[".iso"]));
+        assert!(!has_extension(
+            &url("https://example.com/docs/v1.2/intro"),
+            DEFAULT_EXCLUDED_EXTENSIONS
+        ));
+        assert!(!has_extension(
+            &url("https://example.com/docs/.zip"),
+            DEFAULT_EXCLUDED_EXTENSIONS
+        ));
+        assert!(!has_extension(
+            &url("https://example.com/guide.html"),
+            DEFAULT_EXCLUDED_EXTENSIONS
+        ));
+    }
+
+    #[test]
+    fn chapter_key_uses_first_segment_below_start_path() {
+        let start = url("https://example.com/docs/");
+        assert_eq!(
+            chapter_key(&start, &url("https://example.com/docs/guide/install")),
+            "guide"
+        );
+        assert_eq!(
+            chapter_key(&start, &url("https://example.com/docs/")),
+            "root"
+        );
+        assert_eq!(
+            chapter_key(&start, &url("https://example.com/blog/post")),
+            "blog"
+        );
+        assert_eq!(
+            chapter_key(&url("https://example.com/"), &url("https://example.com/")),
+            "root"
+        );
+    }
+
+    #[test]
+    fn host_aliases_canonicalize_and_list_variants() -> anyhow::Result<()> {
+        let aliases = HostAliases::parse(&[
+            "WWW.example.com=example.com".to_owned(),
+            "docs.example.com = example.com".to_owned(),
+        ])?;
+        assert_eq!(
+            aliases
+                .canonicalize(&url("https://www.example.com/docs?q=1"))
+                .as_str(),
+            "https://example.com/docs?q=1"
+        );
+        assert_eq!(
+            aliases.canonicalize(&url("https://other.com/")).as_str(),
+            "https://other.com/"
+        );
+        let variants = aliases
+            .host_variants(&url("https://www.example.com/a"))
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            variants,
+            vec![
+                "https://example.com/a",
+                "https://docs.example.com/a",
+                "https://www.example.com/a"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn host_aliases_reject_malformed_and_chained_entries() {
+        for entries in [
+            vec!["example.com"],
+            vec!["=example.com"],
+            vec!["a.com=a.com"],
+            vec!["a.com=b.com/path"],
+            vec!["a.com=b.com", "b.com=c.com"],
+            vec!["a.com=b.com", "a.com=c.com"],
+        ] {
+            let entries = entries.into_iter().map(str::to_owned).collect::<Vec<_>>();
+            assert!(HostAliases::parse(&entries).is_err(), "{entries:?}");
+        }
+    }
+
+    #[test]
+    fn canonical_url_drops_tracking_params_fragment_and_trailing_slash() {
+        assert_eq!(
+            canonical_url(&url("https://example.com/docs/?utm_source=x#top")).as_str(),
+            "https://example.com/docs"
+        );
+        assert_eq!(
+            canonical_url(&url("https://example.com/")).as_str(),
+            "https://example.com/"
+        );
+    }
+
+    #[test]
+    fn query_policy_keeps_non_tracking_params_only_when_asked() {
+        let link = url("https://example.com/list/?utm_source=x&page=2&session=abc#top");
+        assert_eq!(
+            QueryPolicy::keeping_query().canonical_url(&link).as_str(),
+            "https://example.com/list?page=2&session=abc"
+        );
+        let policy = QueryPolicy {
+            keep_query: true,
+            extra_tracking_params: vec!["session".to_owned()],
+        };
+        assert_eq!(
+            policy.canonical_url(&link).as_str(),
+            "https://example.com/list?page=2"
+        );
+        assert_eq!(
+            policy
+                .canonical_url(&url("https://example.com/list?page=2"))
+                .as_str(),
+            "https://example.com/list?page=2"
+        );
+        assert_eq!(canonical_url(&link).as_str(), "https://example.com/list");
+    }
+}
@@ -0,0 +1,83 @@
+#![cfg(unix)]
+
+use std::io::{Read as _, Write as _};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn free_addr() -> anyhow::Result<String> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    Ok(listener.local_addr()?.to_string())
+}
+
+fn healthz(addr: &str) -> bool {
+    let Ok(mut stream) = TcpStream::connect(addr) else {
+        return false;
+    };
+    let request = format!("GET /healthz HTTP/1.1\r\nHost: {addr}\r\nConnection: close\r\n\r\n");
+    if stream.write_all(request.as_bytes()).is_err() {
+        return false;
+    }
+    let mut response = String::new();
+    let _ = stream.read_to_string(&mut response);
+    response.starts_with("HTTP/1.1 200")
+}
+
+#[test]
+fn app_shuts_down_gracefully_on_sigterm() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let addr = free_addr()?;
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sitebookify-app"))
+        .args([
+            "--addr",
+            &addr,
+            "--data-dir",
+            temp.path().join("data").to_str().unwrap(),
+            "--web-dir",
+            temp.path().join("web").to_str().unwrap(),
+        ])
+        .env("SITEBOOKIFY_SHUTDOWN_DRAIN_SECS", "2")
+        .env_remove("SITEBOOKIFY_ARTIFACT_BUCKET")
+        .env_remove("SITEBOOKIFY_EXECUTION_MODE")
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let started = Instant::now();
+    while !healthz(&addr) {
+        if started.elapsed() > Duration::from_secs(20) {
+            let _ = child.kill();
+            anyhow::bail!("sitebookify-app did not become healthy");
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    let status = Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()?;
+    assert!(status.success());
+
+    let stopped = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if stopped.elapsed() > Duration::from_secs(10) {
+            let _ = child.kill();
+            anyhow::bail!("sitebookify-app did not exit after SIGTERM");
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+    let mut stderr = String::new();
+    child
+        .stderr
+        .take()
+        .expect("piped stderr")
+        .read_to_string(&mut stderr)?;
+    assert!(status.success(), "{status}: {stderr}");
+    assert!(stderr.contains("shutting down"), "{stderr}");
+    assert!(stderr.contains("in-flight jobs drained"), "{stderr}");
+    assert!(!healthz(&addr));
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use std::fs;
+
+use sitebookify::formats::{ManifestRecord, Toc, TocChapter, TocPart, TocSection};
+
+#[test]
+fn render_heading_anchor_style_targets_section_ids() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+
+    let pages = [
+        (
+            "p_test_anchor_a000000000000000000000000000000000000000000000000000000000000",
+            "https://example.com/docs/a",
+            "Page A",
+            "Body A.",
+        ),
+        (
+            "p_test_anchor_b000000000000000000000000000000000000000000000000000000000000",
+            "https://example.com/docs/b",
+            "Page B",
+            "See [page A](https://example.com/docs/a).",
+        ),
+    ];
+
+    let mut manifest = String::new();
+    for (idx, (id, url, title, body)) in pages.iter().enumerate() {
+        let extracted_path = temp.path().join(format!("extracted_{idx}.md"));
+        fs::write(
+            &extracted_path,
+            format!(
+                "---\n\
+id: {id}\n\
+url: {url}\n\
+retrieved_at: 2026-01-01T00:00:00Z\n\
+raw_html_path: raw/{idx}.html\n\
+title: {title}\n\
+---\n\
+\n\
+# {title}\n\
+\n\
+{body}\n"
+            ),
+        )?;
+        let record = ManifestRecord {
+            id: (*id).to_owned(),
+            url: (*url).to_owned(),
+            title: (*title).to_owned(),
+            path: url.trim_start_matches("https://example.com").to_owned(),
+            extracted_md: extracted_path.to_string_lossy().to_string(),
+            base_url: None,
+            discovery_index: None,
+        };
+        manifest.push_str(&serde_json::to_string(&record)?);
+        manifest.push('\n');
+    }
+    let manifest_path = temp.path().join("manifest.jsonl");
+    fs::write(&manifest_path, manifest)?;
+
+    let toc_path = temp.path().join("toc.yaml");
+    let toc = Toc {
+        book_title: "Test Book".to_owned(),
+        parts: vec![TocPart {
+            title: "Basics".to_owned(),
+            chapters: vec![TocChapter {
+                id: "ch01".to_owned(),
+                title: "Getting Started".to_owned(),
+                intent: "Set up the tool.".to_owned(),
+                reader_gains: Vec::new(),
+                sections: vec![
+                    TocSection {
+                        title: "First".to_owned(),
+                        sources: vec![pages[0].0.to_owned()],
+                    },
+                    TocSection {
+                        title: "Second".to_owned(),
+                        sources: vec![pages[1].0.to_owned()],
+                    },
+                ],
+            }],
+        }],
+    };
+    fs::write(&toc_path, serde_yaml::to_string(&toc)?)?;
+
+    let book_dir = temp.path().join("book");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "book",
+        "render",
+        "--toc",
+        toc_path.to_str().unwrap(),
+        "--manifest",
+        manifest_path.to_str().unwrap(),
+        "--out",
+        book_dir.to_str().unwrap(),
+        "--engine",
+        "noop",
+        "--anchor-style",
+        "heading",
+        "--emit-redirects",
+    ])
+    .assert()
+    .success();
+
+    let ch01 = fs::read_to_string(book_dir.join("src").join("chapters").join("ch01.md"))?;
+    assert!(ch01.contains("## First {#ch01-s01}\n"));
+    assert!(ch01.contains("## Second {#ch01-s02}\n"));
+    assert!(ch01.contains("](#ch01-s01)"));
+    assert!(!ch01.contains("<span id="));
+
+    let redirects: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(book_dir.join("redirects.json"))?)?;
+    assert_eq!(
+        redirects,
+        serde_json::json!({
+            "https://example.com/docs/a": {
+                "page_id": pages[0].0,
+                "chapter_id": "ch01",
+                "anchor": "ch01-s01",
+            },
+            "https://example.com/docs/b": {
+                "page_id": pages[1].0,
+                "chapter_id": "ch01",
+                "anchor": "ch01-s02",
+            },
+        })
+    );
+
+    Ok(())
+}
@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::Duration;
+
+mod fixture;
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\nnot really a png";
+
+type Hits = Arc<Mutex<HashMap<String, usize>>>;
+
+/// `/flaky.png` answers 503 twice before serving the image, `/error-page.png` is an HTML page,
+/// `/large.png` is a 4 KiB PNG; anything else is 404.
+fn spawn_flaky_server() -> (String, Hits, mpsc::Sender<()>, thread::JoinHandle<()>) {
+    let server = tiny_http::Server::http("127.0.0.1:0").expect("start tiny_http server");
+    let base_url = format!("http://{}", server.server_addr());
+    let hits: Hits = Arc::new(Mutex::new(HashMap::new()));
+    let hits_for_server = Arc::clone(&hits);
+    let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
+    let handle = thread::spawn(move || {
+        while shutdown_rx.try_recv().is_err() {
+            let request = match server.recv_timeout(Duration::from_millis(50)) {
+                Ok(Some(req)) => req,
+                Ok(None) => continue,
+                Err(_) => break,
+            };
+            let path = request.url().to_owned();
+            let count = {
+                let mut hits = hits_for_server.lock().expect("lock hits");
+                let count = hits.entry(path.clone()).or_default();
+                *count += 1;
+                *count
+            };
+            let response = match path.as_str() {
+                "/flaky.png" if count > 2 => tiny_http::Response::from_data(PNG.to_vec())
+                    .with_header(
+                        tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"image/png"[..])
+                            .expect("build header"),
+                    ),
+                "/flaky.png" => tiny_http::Response::from_data(b"busy".to_vec())
+                    .with_status_code(503)
+                    .with_header(
+                        tiny_http::Header::from_bytes(&b"Retry-After"[..], &b"0"[..])
+                            .expect("build header"),
+                    ),
+                "/error-page.png" => {
+                    tiny_http::Response::from_data(b"<!doctype html><title>Oops</title>".to_vec())
+                        .with_header(
+                            tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"image/png"[..])
+                                .expect("build header"),
+                        )
+                }
+                "/large.png" => {
+                    let mut body = PNG.to_vec();
+                    body.resize(4096, 0);
+                    tiny_http::Response::from_data(body)
+                }
+                _ => tiny_http::Response::from_data(b"not found".to_vec()).with_status_code(404),
+            };
+            let _ = request.respond(response);
+        }
+    });
+    (base_url, hits, shutdown_tx, handle)
+}
+
+fn render_cmd(
+    dir: &Path,
+    base_url: &str,
+    body: &str,
+    extra_args: &[&str],
+) -> anyhow::Result<assert_cmd::Command> {
+    let (toc_path, manifest_path) =
+        fixture::write_inputs(dir, &[fixture::Page::new("assets", body).at(base_url)])?;
+    let book_dir = dir.join("book");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.env_remove("HTTP_PROXY")
+        .env_remove("http_proxy")
+        .args([
+            "book",
+            "render",
+            "--toc",
+            &toc_path,
+            "--manifest",
+            &manifest_path,
+            "--out",
+            book_dir.to_str().unwrap(),
+            "--engine",
+            "noop",
+        ])
+        .args(extra_args);
+    Ok(cmd)
+}
+
+fn image(base_url: &str, path: &str) -> String {
+    format!("![Diagram]({base_url}{path})")
+}
+
+fn read_ch01(dir: &Path) -> anyhow::Result<String> {
+    Ok(fs::read_to_string(
+        dir.join("book")
+            .join("src")
+            .join("chapters")
+            .join("ch01.md"),
+    )?)
+}
+
+#[test]
+fn asset_download_retries_transient_errors() -> anyhow::Result<()> {
+    let (base_url, hits, shutdown_tx, handle) = spawn_flaky_server();
+    let temp = tempfile::TempDir::new()?;
+
+    render_cmd(temp.path(), &base_url, &image(&base_url, "/flaky.png"), &[])?
+        .assert()
+        .success();
+    let _ = shutdown_tx.send(());
+    let _ = handle.join();
+
+    let ch01 = read_ch01(temp.path())?;
+    assert!(ch01.contains("](../assets/img_"), "{ch01}");
+    assert_eq!(hits.lock().unwrap().get("/flaky.png"), Some(&3));
+    Ok(())
+}
+
+#[test]
+fn asset_download_gives_up_after_configured_retries() -> anyhow::Result<()> {
+    let (base_url, hits, shutdown_tx, handle) = spawn_flaky_server();
+    let temp = tempfile::TempDir::new()?;
+
+    render_cmd(
+        temp.path(),
+        &base_url,
+        &image(&base_url, "/flaky.png"),
+        &["--asset-retries", "1"],
+    )?
+    .assert()
+    .success();
+    let _ = shutdown_tx.send(());
+    let _ = handle.join();
+
+    let ch01 = read_ch01(temp.path())?;
+    assert!(ch01.contains(&format!("]({base_url}/flaky.png)")), "{ch01}");
+    assert_eq!(hits.lock().unwrap().get("/flaky.png"), Some(&2));
+    Ok(())
+}
+
+#[test]
+fn on_asset_error_placeholder_points_at_bundled_svg() -> anyhow::Result<()> {
+    let (base_url, hits, shutdown_tx, handle) = spawn_flaky_server();
+    let temp = tempfile::TempDir::new()?;
+
+    render_cmd(
+        temp.path(),
+        &base_url,
+        &image(&base_url, "/missing.png"),
+        &["--on-asset-error", "placeholder"],
+    )?
+    .assert()
+    .success();
+    let _ = shutdown_tx.send(());
+    let _ = handle.join();
+
+    let ch01 = read_ch01(temp.path())?;
+    assert!(ch01.contains("](../assets/placeholder.svg)"), "{ch01}");
+    let placeholder = fs::read_to_string(
+        temp.path()
+            .join("book")
+            .join("src")
+            .join("assets")
+            .join("placeholder.svg"),
+    )?;
+    assert!(placeholder.starts_with("<svg"));
+    // 404 is not transient, so it is not retried.
+    assert_eq!(hits.lock().unwrap().get("/missing.png"), Some(&1));
+    Ok(())
+}
+
+#[test]
+fn on_asset_error_fail_aborts_render() -> anyhow::Result<()> {
+    let (base_url, _hits, shutdown_tx, handle) = spawn_flaky_server();
+    let temp = tempfile::TempDir::new()?;
+
+    render_cmd(
+        temp.path(),
+        &base_url,
+        &image(&base_url, "/missing.png"),
+        &["--on-asset-error", "fail"],
+    )?
+    .assert()
+    .failure()
+    .stderr(predicates::str::contains(format!(
+        "download image: {base_url}/missing.png"
+    )));
+    let _ = shutdown_tx.send(());
+    let _ = handle.join();
+    Ok(())
+}
+
+#[test]
+fn asset_body_that_is_not_the_claimed_image_type_is_rejected() -> anyhow::Result<()> {
+    let (base_url, _hits, shutdown_tx, handle) = spawn_flaky_server();
+    let temp = tempfile::TempDir::new()?;
+
+    render_cmd(
+        temp.path(),
+        &base_url,
+        &image(&base_url, "/error-page.png"),
+        &["--on-asset-error", "fail"],
+    )?
+    .assert()
+    .failure()
+    .stderr(predicates::str::contains(
+        "asset body does not look like a png image",
+    ));
+    let _ = shutdown_tx.send(());
+    let _ = handle.join();
+    let assets_dir = temp.path().join("book").join("src").join("assets");
+    assert_eq!(fs::read_dir(assets_dir)?.count(), 0);
+    Ok(())
+}
+
+#[test]
+fn max_asset_bytes_rejects_oversized_images() -> anyhow::Result<()> {
+    let (base_url, _hits, shutdown_tx, handle) = spawn_flaky_server();
+    let temp = tempfile::TempDir::new()?;
+
+    render_cmd(
+        temp.path(),
+        &base_url,
+        &image(&base_url, "/large.png"),
+        &["--max-asset-bytes", "1024"],
+    )?
+    .assert()
+    .success();
+    let ch01 = read_ch01(temp.path())?;
+    assert!(ch01.contains(&format!("]({base_url}/large.png)")), "{ch01}");
+
+    let temp = tempfile::TempDir::new()?;
+    render_cmd(temp.path(), &base_url, &image(&base_url, "/large.png"), &[])?
+        .assert()
+        .success();
+    let _ = shutdown_tx.send(());
+    let _ = handle.join();
+    let ch01 = read_ch01(temp.path())?;
+    assert!(ch01.contains("](../assets/img_"), "{ch01}");
+    Ok(())
+}
+
+#[test]
+fn strip_badges_drops_badge_images_and_keeps_alt_text() -> anyhow::Result<()> {
+    let (base_url, hits, shutdown_tx, handle) = spawn_flaky_server();
+    let temp = tempfile::TempDir::new()?;
+
+    let body = format!(
+        "[![CI passing](https://img.shields.io/badge/ci-passing-green.svg)](https://ci.example.com)\n\n\
+![Diagram]({base_url}/flaky.png) ![Local badge]({base_url}/missing.png)"
+    );
+    render_cmd(
+        temp.path(),
+        &base_url,
+        &body,
+        &[
+            "--strip-badges",
+            "--skip-image-host",
+            "127.0.0.1",
+            "--on-asset-error",
+            "fail",
+        ],
+    )?
+    .assert()
+    .success();
+    let _ = shutdown_tx.send(());
+    let _ = handle.join();
+
+    let ch01 = read_ch01(temp.path())?;
+    assert!(
+        ch01.contains("[CI passing](https://ci.example.com/)"),
+        "{ch01}"
+    );
+    assert!(ch01.contains("Diagram Local badge"), "{ch01}");
+    assert!(!ch01.contains("shields.io"), "{ch01}");
+    assert!(hits.lock().unwrap().is_empty());
+    Ok(())
+}
+
+#[test]
+fn rerender_retries_failed_images_and_follows_asset_options() -> anyhow::Result<()> {
+    let (base_url, hits, shutdown_tx, handle) = spawn_flaky_server();
+    let temp = tempfile::TempDir::new()?;
+    let body = image(&base_url, "/flaky.png");
+    let flaky_hits = || hits.lock().unwrap().get("/flaky.png").copied();
+
+    // Both attempts fail (503), so the remote URL is kept and the chapter stays out of date.
+    render_cmd(temp.path(), &base_url, &body, &["--asset-retries", "1"])?
+        .assert()
+        .success();
+    assert!(read_ch01(temp.path())?.contains(&format!("]({base_url}/flaky.png)")));
+    assert_eq!(flaky_hits(), Some(2));
+
+    render_cmd(temp.path(), &base_url, &body, &["--asset-retries", "1"])?
+        .assert()
+        .success();
+    let ch01 = read_ch01(temp.path())?;
+    assert!(ch01.contains("](../assets/img_"), "{ch01}");
+    assert_eq!(flaky_hits(), Some(3));
+
+    // Unchanged inputs and options: nothing is fetched or rewritten.
+    render_cmd(temp.path(), &base_url, &body, &["--asset-retries", "1"])?
+        .assert()
+        .success();
+    assert_eq!(read_ch01(temp.path())?, ch01);
+
+    // A different asset option renders the chapter again.
+    render_cmd(
+        temp.path(),
+        &base_url,
+        &body,
+        &["--asset-retries", "1", "--asset-naming", "slug"],
+    )?
+    .assert()
+    .success();
+    let _ = shutdown_tx.send(());
+    let _ = handle.join();
+    let ch01 = read_ch01(temp.path())?;
+    assert!(ch01.contains("](../assets/flaky-"), "{ch01}");
+    assert_eq!(flaky_hits(), Some(4));
+    Ok(())
+}
@@ -0,0 +1,216 @@
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use sitebookify::formats::{ManifestRecord, Toc, TocChapter, TocPart, TocSection};
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\nnot really a png";
+
+fn spawn_image_server() -> (String, mpsc::Sender<()>, thread::JoinHandle<()>) {
+    let server = tiny_http::Server::http("127.0.0.1:0").expect("start tiny_http server");
+    let base_url = format!("http://{}", server.server_addr());
+    let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
+    let handle = thread::spawn(move || {
+        while shutdown_rx.try_recv().is_err() {
+            let request = match server.recv_timeout(Duration::from_millis(50)) {
+                Ok(Some(req)) => req,
+                Ok(None) => continue,
+                Err(_) => break,
+            };
+            if request.url().starts_with("/missing") {
+                let _ = request.respond(tiny_http::Response::empty(404));
+                continue;
+            }
+            let _ = request.respond(
+                tiny_http::Response::from_data(PNG.to_vec()).with_header(
+                    tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"image/png"[..])
+                        .expect("build header"),
+                ),
+            );
+        }
+    });
+    (base_url, shutdown_tx, handle)
+}
+
+fn render(
+    dir: &Path,
+    base_url: &str,
+    extra_args: &[&str],
+) -> anyhow::Result<(String, Vec<String>)> {
+    let images = format!(
+        "![One]({base_url}/a/Logo_Final.png)\n\n![Two]({base_url}/b/logo-final.png)\n\n\
+![Again]({base_url}/a/Logo_Final.png)\n"
+    );
+    render_images(dir, base_url, &images, extra_args)
+}
+
+fn render_images(
+    dir: &Path,
+    base_url: &str,
+    images: &str,
+    extra_args: &[&str],
+) -> anyhow::Result<(String, Vec<String>)> {
+    let page_id = "p_test_asset_naming_0000000000000000000000000000000000000000000000000000000";
+    let extracted_path = dir.join("extracted.md");
+    fs::write(
+        &extracted_path,
+        format!(
+            "---\nid: {page_id}\nurl: {base_url}/docs\n\
+retrieved_at: 2026-01-01T00:00:00Z\nraw_html_path: raw/index.html\ntitle: Test Page\n---\n\n\
+# Test Page\n\n{images}"
+        ),
+    )?;
+
+    let manifest_path = dir.join("manifest.jsonl");
+    let record = ManifestRecord {
+        id: page_id.to_owned(),
+        url: format!("{base_url}/docs"),
+        title: "Test Page".to_owned(),
+        path: "/docs".to_owned(),
+        extracted_md: extracted_path.to_string_lossy().to_string(),
+        base_url: None,
+        discovery_index: None,
+    };
+    fs::write(
+        &manifest_path,
+        format!("{}\n", serde_json::to_string(&record)?),
+    )?;
+
+    let toc_path = dir.join("toc.yaml");
+    let toc = Toc {
+        book_title: "Test Book".to_owned(),
+        parts: vec![TocPart {
+            title: "Basics".to_owned(),
+            chapters: vec![TocChapter {
+                id: "ch01".to_owned(),
+                title: "Getting Started".to_owned(),
+                intent: String::new(),
+                reader_gains: Vec::new(),
+                sections: vec![TocSection {
+                    title: "Setup".to_owned(),
+                    sources: vec![page_id.to_owned()],
+                }],
+            }],
+        }],
+    };
+    fs::write(&toc_path, serde_yaml::to_string(&toc)?)?;
+
+    let book_dir = dir.join("book");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.env_remove("HTTP_PROXY")
+        .env_remove("http_proxy")
+        .args([
+            "book",
+            "render",
+            "--toc",
+            toc_path.to_str().unwrap(),
+            "--manifest",
+            manifest_path.to_str().unwrap(),
+            "--out",
+            book_dir.to_str().unwrap(),
+            "--engine",
+            "noop",
+        ])
+        .args(extra_args)
+        .assert()
+        .success();
+
+    let ch01 = fs::read_to_string(book_dir.join("src").join("chapters").join("ch01.md"))?;
+    let mut assets = fs::read_dir(book_dir.join("src").join("assets"))?
+        .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    assets.sort();
+    Ok((ch01, assets))
+}
+
+#[test]
+fn asset_naming_slug_uses_basename_with_unique_suffix() -> anyhow::Result<()> {
+    let (base_url, shutdown_tx, handle) = spawn_image_server();
+    let temp = tempfile::TempDir::new()?;
+
+    let (ch01, assets) = render(temp.path(), &base_url, &["--asset-naming", "slug"])?;
+    let _ = shutdown_tx.send(());
+    let _ = handle.join();
+
+    // Both URLs slug to `logo-final`; the hash suffix keeps them apart.
+    assert_eq!(assets.len(), 2, "{assets:?}");
+    for asset in &assets {
+        let suffix = asset
+            .strip_prefix("logo-final-")
+            .and_then(|rest| rest.strip_suffix(".png"))
+            .unwrap_or_else(|| panic!("unexpected asset name: {asset}"));
+        assert_eq!(suffix.len(), 8);
+        assert!(suffix.chars().all(|c| c.is_ascii_hexdigit()));
+        assert!(ch01.contains(&format!("](../assets/{asset})")));
+    }
+    assert_eq!(ch01.matches("](../assets/logo-final-").count(), 3);
+    Ok(())
+}
+
+#[test]
+fn asset_naming_defaults_to_hash() -> anyhow::Result<()> {
+    let (base_url, shutdown_tx, handle) = spawn_image_server();
+    let temp = tempfile::TempDir::new()?;
+
+    let (ch01, assets) = render(temp.path(), &base_url, &[])?;
+    let _ = shutdown_tx.send(());
+    let _ = handle.join();
+
+    assert_eq!(assets.len(), 2, "{assets:?}");
+    for asset in &assets {
+        assert!(asset.starts_with("img_") && asset.len() == "img_.png".len() + 64);
+        assert!(ch01.contains(&format!("](../assets/{asset})")));
+    }
+    Ok(())
+}
+
+#[test]
+fn dedupe_assets_by_content_stores_identical_images_once() -> anyhow::Result<()> {
+    let (base_url, shutdown_tx, handle) = spawn_image_server();
+    let temp = tempfile::TempDir::new()?;
+
+    // The server returns the same bytes for both image URLs.
+    let (ch01, assets) = render(temp.path(), &base_url, &["--dedupe-assets-by-content"])?;
+    let _ = shutdown_tx.send(());
+    let _ = handle.join();
+
+    assert_eq!(assets.len(), 1, "{assets:?}");
+    assert_eq!(
+        ch01.matches(&format!("](../assets/{})", assets[0])).count(),
+        3,
+        "{ch01}"
+    );
+    Ok(())
+}
+
+#[test]
+fn dedupe_assets_by_content_keeps_the_placeholder() -> anyhow::Result<()> {
+    let (base_url, shutdown_tx, handle) = spawn_image_server();
+    let temp = tempfile::TempDir::new()?;
+    let images = format!("![Gone]({base_url}/missing.png)\n");
+    let args = [
+        "--on-asset-error",
+        "placeholder",
+        "--asset-retries",
+        "0",
+        "--dedupe-assets-by-content",
+    ];
+
+    let (_, assets) = render_images(temp.path(), &base_url, &images, &args)?;
+    assert_eq!(assets, vec!["placeholder.svg"]);
+    // An earlier image with the placeholder's bytes sorts first, but must not replace it.
+    let assets_dir = temp.path().join("book").join("src").join("assets");
+    fs::copy(
+        assets_dir.join("placeholder.svg"),
+        assets_dir.join("img_copy.svg"),
+    )?;
+    let (ch01, assets) = render_images(temp.path(), &base_url, &images, &args)?;
+    let _ = shutdown_tx.send(());
+    let _ = handle.join();
+
+    assert_eq!(assets, vec!["img_copy.svg", "placeholder.svg"]);
+    assert!(ch01.contains("](../assets/placeholder.svg)"), "{ch01}");
+    Ok(())
+}
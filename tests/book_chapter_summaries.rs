@@ -0,0 +1,97 @@
+use std::fs;
+use std::path::Path;
+
+use sitebookify::formats::{ManifestRecord, Toc, TocChapter, TocPart, TocSection};
+
+fn render(dir: &Path, name: &str, extra_args: &[&str]) -> anyhow::Result<String> {
+    let page_id = "p_test_chapter_summary_00000000000000000000000000000000000000000000000000000";
+    let extracted_path = dir.join("extracted.md");
+    fs::write(
+        &extracted_path,
+        format!(
+            "---\nid: {page_id}\nurl: https://example.com/docs\n\
+retrieved_at: 2026-01-01T00:00:00Z\nraw_html_path: raw/index.html\ntitle: Test Page\n---\n\n\
+# Test Page\n\nBody text.\n"
+        ),
+    )?;
+
+    let manifest_path = dir.join("manifest.jsonl");
+    let record = ManifestRecord {
+        id: page_id.to_owned(),
+        url: "https://example.com/docs".to_owned(),
+        title: "Test Page".to_owned(),
+        path: "/docs".to_owned(),
+        extracted_md: extracted_path.to_string_lossy().to_string(),
+        base_url: None,
+        discovery_index: None,
+    };
+    fs::write(
+        &manifest_path,
+        format!("{}\n", serde_json::to_string(&record)?),
+    )?;
+
+    let toc_path = dir.join("toc.yaml");
+    let toc = Toc {
+        book_title: "Test Book".to_owned(),
+        parts: vec![TocPart {
+            title: "Basics".to_owned(),
+            chapters: vec![TocChapter {
+                id: "ch01".to_owned(),
+                title: "Getting Started".to_owned(),
+                intent: "Set up the tool.".to_owned(),
+                reader_gains: vec!["Install it".to_owned(), " ".to_owned(), "Run it".to_owned()],
+                sections: vec![TocSection {
+                    title: "Setup".to_owned(),
+                    sources: vec![page_id.to_owned()],
+                }],
+            }],
+        }],
+    };
+    fs::write(&toc_path, serde_yaml::to_string(&toc)?)?;
+
+    let book_dir = dir.join(name);
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "book",
+        "render",
+        "--toc",
+        toc_path.to_str().unwrap(),
+        "--manifest",
+        manifest_path.to_str().unwrap(),
+        "--out",
+        book_dir.to_str().unwrap(),
+        "--engine",
+        "noop",
+    ])
+    .args(extra_args)
+    .assert()
+    .success();
+
+    Ok(fs::read_to_string(
+        book_dir.join("src").join("chapters").join("ch01.md"),
+    )?)
+}
+
+#[test]
+fn chapter_summaries_list_reader_gains_before_sources() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+
+    let ch01 = render(temp.path(), "book", &["--chapter-summaries"])?;
+    assert!(
+        ch01.contains(
+            "Body text.\n\n## What you learned\n\n- Install it\n- Run it\n\n## Sources\n"
+        ),
+        "{ch01}"
+    );
+
+    let ch01 = render(
+        temp.path(),
+        "book-titled",
+        &["--chapter-summaries", "--chapter-summary-title", "まとめ"],
+    )?;
+    assert!(ch01.contains("## まとめ\n\n- Install it\n"), "{ch01}");
+
+    let ch01 = render(temp.path(), "book-default", &[])?;
+    assert!(!ch01.contains("Install it"), "{ch01}");
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use std::fs;
+use std::io::Read as _;
+use std::path::Path;
+
+use predicates::prelude::*;
+use sitebookify::formats::{TocChapter, TocPart};
+
+mod fixture;
+
+fn render_cmd(dir: &Path, format: &str) -> anyhow::Result<assert_cmd::Command> {
+    let page = fixture::Page::new("title_format", "Body text.");
+    let (toc, manifest) = fixture::write_inputs(dir, std::slice::from_ref(&page))?;
+    let chapter = |id: &str, title: &str| TocChapter {
+        intent: format!("About {title}."),
+        ..fixture::chapter(id, title, &[page.id()])
+    };
+    fixture::write_toc(
+        Path::new(&toc),
+        vec![
+            TocPart {
+                title: "Basics".to_owned(),
+                chapters: vec![chapter("ch01", "Getting Started")],
+            },
+            TocPart {
+                title: "Advanced".to_owned(),
+                chapters: vec![chapter("ch02", "Tuning")],
+            },
+        ],
+    )?;
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "book",
+        "render",
+        "--toc",
+        &toc,
+        "--manifest",
+        &manifest,
+        "--out",
+        dir.join("book").to_str().unwrap(),
+        "--engine",
+        "noop",
+        "--intro",
+        "--chapter-title-format",
+        format,
+    ]);
+    Ok(cmd)
+}
+
+#[test]
+fn chapter_title_format_decorates_summary_headings_intro_and_epub_nav() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    render_cmd(temp.path(), "{index:02}. {title} [{id}] {{v1}}")?
+        .assert()
+        .success();
+
+    let src = temp.path().join("book").join("src");
+    let summary = fs::read_to_string(src.join("SUMMARY.md"))?;
+    assert!(
+        summary.contains("  - [01. Getting Started [ch01] {v1}](chapters/ch01.md)\n"),
+        "{summary}"
+    );
+    assert!(
+        summary.contains("  - [02. Tuning [ch02] {v1}](chapters/ch02.md)\n"),
+        "{summary}"
+    );
+    // The intro chapter itself is not numbered.
+    assert!(
+        summary.contains("[Introduction](chapters/ch00.md)"),
+        "{summary}"
+    );
+
+    let ch02 = fs::read_to_string(src.join("chapters").join("ch02.md"))?;
+    assert!(ch02.starts_with("# 02. Tuning [ch02] {v1}\n"), "{ch02}");
+    let intro = fs::read_to_string(src.join("chapters").join("ch00.md"))?;
+    assert!(
+        intro.contains("### [01. Getting Started [ch01] {v1}](ch01.md)"),
+        "{intro}"
+    );
+
+    let epub_path = temp.path().join("book.epub");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "book",
+        "epub",
+        "--book",
+        temp.path().join("book").to_str().unwrap(),
+        "--out",
+        epub_path.to_str().unwrap(),
+    ])
+    .assert()
+    .success();
+    let mut archive = zip::ZipArchive::new(fs::File::open(&epub_path)?)?;
+    let nav_name = (0..archive.len())
+        .map(|idx| archive.by_index(idx).map(|file| file.name().to_owned()))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .find(|name| name.ends_with("nav.xhtml"))
+        .expect("nav.xhtml in epub");
+    let mut nav = String::new();
+    archive.by_name(&nav_name)?.read_to_string(&mut nav)?;
+    assert!(nav.contains(">01. Getting Started [ch01] {v1}<"), "{nav}");
+    assert!(nav.contains(">02. Tuning [ch02] {v1}<"), "{nav}");
+    Ok(())
+}
+
+#[test]
+fn chapter_title_format_rejects_unknown_placeholders() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    render_cmd(temp.path(), "{number}. {title}")?
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown placeholder `{number}`"));
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use std::fs;
+use std::io::Read as _;
+use std::path::{Path, PathBuf};
+
+use predicates::prelude::*;
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\nnot really a png";
+
+fn write_book(dir: &Path) -> anyhow::Result<PathBuf> {
+    let book_dir = dir.join("book");
+    let chapters = book_dir.join("src").join("chapters");
+    fs::create_dir_all(&chapters)?;
+    fs::write(
+        book_dir.join("book.toml"),
+        "[book]\ntitle = \"Cover Book\"\n",
+    )?;
+    fs::write(
+        book_dir.join("src").join("SUMMARY.md"),
+        "# Summary\n\n- [Intro](chapters/ch01.md)\n",
+    )?;
+    fs::write(chapters.join("ch01.md"), "# Intro\n\nHello.\n")?;
+    Ok(book_dir)
+}
+
+fn epub_cmd(book_dir: &Path, out: &Path, cover: &Path) -> assert_cmd::Command {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "book",
+        "epub",
+        "--book",
+        book_dir.to_str().unwrap(),
+        "--out",
+        out.to_str().unwrap(),
+        "--cover",
+        cover.to_str().unwrap(),
+    ]);
+    cmd
+}
+
+fn read_entry(archive: &mut zip::ZipArchive<fs::File>, name: &str) -> anyhow::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    archive.by_name(name)?.read_to_end(&mut out)?;
+    Ok(out)
+}
+
+#[test]
+fn book_epub_cover_adds_cover_image_and_first_spine_page() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let book_dir = write_book(temp.path())?;
+    let cover = temp.path().join("Cover.PNG");
+    fs::write(&cover, PNG)?;
+    let epub_path = temp.path().join("book.epub");
+
+    epub_cmd(&book_dir, &epub_path, &cover).assert().success();
+
+    let mut archive = zip::ZipArchive::new(fs::File::open(&epub_path)?)?;
+    assert_eq!(read_entry(&mut archive, "OEBPS/cover.png")?, PNG);
+    let cover_page = String::from_utf8(read_entry(&mut archive, "OEBPS/cover.xhtml")?)?;
+    assert!(
+        cover_page.contains("<img src=\"cover.png\" alt=\"Cover Book\" />"),
+        "{cover_page}"
+    );
+
+    let opf = String::from_utf8(read_entry(&mut archive, "OEBPS/content.opf")?)?;
+    assert!(
+        opf.contains("<meta name=\"cover\" content=\"cover-image\" />"),
+        "{opf}"
+    );
+    assert!(
+        opf.contains(
+            "<item id=\"cover-image\" href=\"cover.png\" media-type=\"image/png\" properties=\"cover-image\" />"
+        ),
+        "{opf}"
+    );
+    let spine = opf
+        .split("<spine")
+        .nth(1)
+        .expect("spine in content.opf")
+        .to_owned();
+    let first_itemref = spine
+        .split("<itemref idref=\"")
+        .nth(1)
+        .and_then(|rest| rest.split('"').next());
+    assert_eq!(first_itemref, Some("cover"), "{opf}");
+    Ok(())
+}
+
+#[test]
+fn book_epub_cover_rejects_missing_or_unsupported_images() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let book_dir = write_book(temp.path())?;
+    let epub_path = temp.path().join("book.epub");
+
+    epub_cmd(&book_dir, &epub_path, &temp.path().join("missing.png"))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cover image not found"));
+
+    let text_cover = temp.path().join("cover.txt");
+    fs::write(&text_cover, "not an image")?;
+    epub_cmd(&book_dir, &epub_path, &text_cover)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unsupported cover image type"));
+    assert!(!epub_path.exists());
+    Ok(())
+}
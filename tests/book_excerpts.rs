@@ -0,0 +1,105 @@
+use std::fs;
+
+use sitebookify::formats::{ManifestRecord, Toc, TocChapter, TocPart, TocSection};
+
+#[test]
+fn render_include_excerpts_appends_collapsible_source_quotes() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+
+    let page_id = "p_test_excerpt_a000000000000000000000000000000000000000000000000000000000000";
+    let extracted_path = temp.path().join("extracted.md");
+    fs::write(
+        &extracted_path,
+        format!(
+            "---\n\
+id: {page_id}\n\
+url: https://example.com/docs/a?x=1&y=2\n\
+retrieved_at: 2026-01-01T00:00:00Z\n\
+raw_html_path: raw/a.html\n\
+title: Page A\n\
+---\n\
+\n\
+# Page A\n\
+\n\
+Use **Tom & Jerry** with [the CLI](https://example.com/cli) <b>today</b>.\n\
+\n\
+```sh\n\
+rm -rf build\n\
+```\n\
+\n\
+Then keep reading this rather long second paragraph.\n"
+        ),
+    )?;
+    let record = ManifestRecord {
+        id: page_id.to_owned(),
+        url: "https://example.com/docs/a?x=1&y=2".to_owned(),
+        title: "Page A".to_owned(),
+        path: "/docs/a".to_owned(),
+        extracted_md: extracted_path.to_string_lossy().to_string(),
+        base_url: None,
+        discovery_index: None,
+    };
+    let manifest_path = temp.path().join("manifest.jsonl");
+    fs::write(
+        &manifest_path,
+        format!("{}\n", serde_json::to_string(&record)?),
+    )?;
+
+    let toc_path = temp.path().join("toc.yaml");
+    let toc = Toc {
+        book_title: "Test Book".to_owned(),
+        parts: vec![TocPart {
+            title: "Basics".to_owned(),
+            chapters: vec![TocChapter {
+                id: "ch01".to_owned(),
+                title: "Getting Started".to_owned(),
+                intent: String::new(),
+                reader_gains: Vec::new(),
+                sections: vec![TocSection {
+                    title: "First".to_owned(),
+                    sources: vec![page_id.to_owned()],
+                }],
+            }],
+        }],
+    };
+    fs::write(&toc_path, serde_yaml::to_string(&toc)?)?;
+
+    let render = |out: &str, extra_args: &[&str]| -> anyhow::Result<String> {
+        let book_dir = temp.path().join(out);
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+        cmd.args([
+            "book",
+            "render",
+            "--toc",
+            toc_path.to_str().unwrap(),
+            "--manifest",
+            manifest_path.to_str().unwrap(),
+            "--out",
+            book_dir.to_str().unwrap(),
+            "--engine",
+            "noop",
+        ])
+        .args(extra_args)
+        .assert()
+        .success();
+        Ok(fs::read_to_string(
+            book_dir.join("src").join("chapters").join("ch01.md"),
+        )?)
+    };
+
+    let ch01 = render("book", &["--include-excerpts", "--excerpt-chars", "60"])?;
+    assert!(ch01.ends_with(
+        "## Sources\n\
+- https://example.com/docs/a?x=1&y=2\n\
+\n\
+<details>\n\
+<summary>Excerpt: https://example.com/docs/a?x=1&amp;y=2</summary>\n\
+<blockquote>Use Tom &amp; Jerry with the CLI today. Then keep reading this…</blockquote>\n\
+</details>\n"
+    ));
+
+    let plain = render("book-plain", &[])?;
+    assert!(!plain.contains("<details>"));
+
+    Ok(())
+}
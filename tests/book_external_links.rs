@@ -0,0 +1,116 @@
+use std::fs;
+
+use sitebookify::formats::{ManifestRecord, Toc, TocChapter, TocPart, TocSection};
+
+fn render_with_policy(policy: &str) -> anyhow::Result<String> {
+    let temp = tempfile::TempDir::new()?;
+
+    let page_id = "p_test_external_links_00000000000000000000000000000000000000000000000000000000";
+    let extracted_path = temp.path().join("extracted.md");
+    fs::write(
+        &extracted_path,
+        format!(
+            "---\n\
+id: {page_id}\n\
+url: https://example.com/docs\n\
+retrieved_at: 2026-01-01T00:00:00Z\n\
+raw_html_path: raw/index.html\n\
+title: Test Page\n\
+---\n\
+\n\
+# Test Page\n\
+\n\
+See [the spec](https://other.example/spec), [the guide](/guide) and [the spec again](https://other.example/spec).\n\
+\n\
+Contact [us](mailto:team@example.com) or [run](javascript:void(0)).\n"
+        ),
+    )?;
+
+    let manifest_path = temp.path().join("manifest.jsonl");
+    let record = ManifestRecord {
+        id: page_id.to_owned(),
+        url: "https://example.com/docs".to_owned(),
+        title: "Test Page".to_owned(),
+        path: "/docs".to_owned(),
+        extracted_md: extracted_path.to_string_lossy().to_string(),
+        base_url: None,
+        discovery_index: None,
+    };
+    fs::write(
+        &manifest_path,
+        format!("{}\n", serde_json::to_string(&record)?),
+    )?;
+
+    let toc_path = temp.path().join("toc.yaml");
+    let toc = Toc {
+        book_title: "Test Book".to_owned(),
+        parts: vec![TocPart {
+            title: "Basics".to_owned(),
+            chapters: vec![TocChapter {
+                id: "ch01".to_owned(),
+                title: "Getting Started".to_owned(),
+                intent: String::new(),
+                reader_gains: Vec::new(),
+                sections: vec![TocSection {
+                    title: "Setup".to_owned(),
+                    sources: vec![page_id.to_owned()],
+                }],
+            }],
+        }],
+    };
+    fs::write(&toc_path, serde_yaml::to_string(&toc)?)?;
+
+    let book_dir = temp.path().join("book");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "book",
+        "render",
+        "--toc",
+        toc_path.to_str().unwrap(),
+        "--manifest",
+        manifest_path.to_str().unwrap(),
+        "--out",
+        book_dir.to_str().unwrap(),
+        "--engine",
+        "noop",
+        "--external-link-policy",
+        policy,
+    ])
+    .assert()
+    .success();
+
+    Ok(fs::read_to_string(
+        book_dir.join("src").join("chapters").join("ch01.md"),
+    )?)
+}
+
+const VERBATIM_LINKS: &str = "Contact [us](mailto:team@example.com) or [run](javascript:void(0)).";
+
+#[test]
+fn external_link_policy_keep_resolves_absolute_urls() -> anyhow::Result<()> {
+    let ch01 = render_with_policy("keep")?;
+    assert!(ch01.contains(
+        "See [the spec](https://other.example/spec), [the guide](https://example.com/guide) and [the spec again](https://other.example/spec)."
+    ));
+    assert!(ch01.contains(VERBATIM_LINKS));
+    Ok(())
+}
+
+#[test]
+fn external_link_policy_strip_keeps_link_text() -> anyhow::Result<()> {
+    let ch01 = render_with_policy("strip")?;
+    assert!(ch01.contains("See the spec, the guide and the spec again."));
+    assert!(ch01.contains(VERBATIM_LINKS));
+    Ok(())
+}
+
+#[test]
+fn external_link_policy_footnote_cites_each_url_once() -> anyhow::Result<()> {
+    let ch01 = render_with_policy("footnote")?;
+    assert!(ch01.contains("See the spec[^ext-1], the guide[^ext-2] and the spec again[^ext-1]."));
+    assert!(ch01.contains(VERBATIM_LINKS));
+    assert!(ch01.contains(
+        "[^ext-1]: <https://other.example/spec>\n[^ext-2]: <https://example.com/guide>\n\n## Sources\n"
+    ));
+    Ok(())
+}
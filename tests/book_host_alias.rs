@@ -0,0 +1,137 @@
+use std::fs;
+use std::path::Path;
+
+use predicates::prelude::*;
+use sitebookify::formats::{ManifestRecord, Toc, TocChapter, TocPart, TocSection};
+
+fn page(dir: &Path, slug: &str, title: &str, body: &str) -> anyhow::Result<ManifestRecord> {
+    let page_id = format!("p_test_host_alias_{slug:0<50}");
+    let extracted_path = dir.join(format!("{slug}.md"));
+    fs::write(
+        &extracted_path,
+        format!(
+            "---\nid: {page_id}\nurl: https://example.com/docs/{slug}\n\
+retrieved_at: 2026-01-01T00:00:00Z\nraw_html_path: raw/{slug}.html\ntitle: {title}\n---\n\n\
+# {title}\n\n{body}\n"
+        ),
+    )?;
+    Ok(ManifestRecord {
+        id: page_id,
+        url: format!("https://example.com/docs/{slug}"),
+        title: title.to_owned(),
+        path: format!("/docs/{slug}"),
+        extracted_md: extracted_path.to_string_lossy().to_string(),
+        base_url: None,
+        discovery_index: None,
+    })
+}
+
+fn render_cmd(dir: &Path) -> anyhow::Result<assert_cmd::Command> {
+    let install = page(dir, "install", "Install", "Run the installer.")?;
+    let usage = page(
+        dir,
+        "usage",
+        "Usage",
+        "Read the [install guide](https://www.example.com/docs/install/) first.",
+    )?;
+
+    let manifest_path = dir.join("manifest.jsonl");
+    let manifest = [&install, &usage]
+        .iter()
+        .map(|record| serde_json::to_string(record).map(|line| format!("{line}\n")))
+        .collect::<Result<String, _>>()?;
+    fs::write(&manifest_path, manifest)?;
+
+    let chapter = |id: &str, title: &str, record: &ManifestRecord| TocChapter {
+        id: id.to_owned(),
+        title: title.to_owned(),
+        intent: String::new(),
+        reader_gains: Vec::new(),
+        sections: vec![TocSection {
+            title: title.to_owned(),
+            sources: vec![record.id.clone()],
+        }],
+    };
+    let toc_path = dir.join("toc.yaml");
+    let toc = Toc {
+        book_title: "Test Book".to_owned(),
+        parts: vec![TocPart {
+            title: "Basics".to_owned(),
+            chapters: vec![
+                chapter("ch01", "Install", &install),
+                chapter("ch02", "Usage", &usage),
+            ],
+        }],
+    };
+    fs::write(&toc_path, serde_yaml::to_string(&toc)?)?;
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "book",
+        "render",
+        "--toc",
+        toc_path.to_str().unwrap(),
+        "--manifest",
+        manifest_path.to_str().unwrap(),
+        "--out",
+        dir.join("book").to_str().unwrap(),
+        "--engine",
+        "noop",
+    ]);
+    Ok(cmd)
+}
+
+fn usage_chapter(dir: &Path) -> anyhow::Result<String> {
+    Ok(fs::read_to_string(
+        dir.join("book")
+            .join("src")
+            .join("chapters")
+            .join("ch02.md"),
+    )?)
+}
+
+#[test]
+fn host_alias_resolves_www_links_to_the_manifest_page() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    render_cmd(temp.path())?
+        .args(["--host-alias", "www.example.com=example.com"])
+        .assert()
+        .success();
+
+    let usage = usage_chapter(temp.path())?;
+    assert!(
+        usage.contains("[install guide](ch01.md#p_test_host_alias_install"),
+        "{usage}"
+    );
+    assert!(!usage.contains("www.example.com"), "{usage}");
+    Ok(())
+}
+
+#[test]
+fn without_host_alias_www_links_stay_external() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    render_cmd(temp.path())?.assert().success();
+
+    let usage = usage_chapter(temp.path())?;
+    assert!(
+        usage.contains("[install guide](https://www.example.com/docs/install/)"),
+        "{usage}"
+    );
+    Ok(())
+}
+
+#[test]
+fn host_alias_rejects_chained_aliases() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    render_cmd(temp.path())?
+        .args([
+            "--host-alias",
+            "www.example.com=example.com",
+            "--host-alias",
+            "example.com=example.org",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("is itself an alias"));
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+#[test]
+fn book_init_uses_templates_with_title() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let chapter_template = temp.path().join("chapter.md");
+    fs::write(&chapter_template, "# {{title}}\n\n## 概要\nTODO\n")?;
+    let summary_template = temp.path().join("summary.md");
+    fs::write(
+        &summary_template,
+        "# {{title}}\n\n- [はじめに](chapters/ch01.md)\n",
+    )?;
+
+    let book_dir = temp.path().join("book");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args(["book", "init", "--title", "Team Handbook", "--out"])
+        .arg(&book_dir)
+        .arg("--template")
+        .arg(&chapter_template)
+        .arg("--summary-template")
+        .arg(&summary_template)
+        .assert()
+        .success();
+
+    let src = book_dir.join("src");
+    assert_eq!(
+        fs::read_to_string(src.join("chapters").join("ch01.md"))?,
+        "# Team Handbook\n\n## 概要\nTODO\n"
+    );
+    assert_eq!(
+        fs::read_to_string(src.join("SUMMARY.md"))?,
+        "# Team Handbook\n\n- [はじめに](chapters/ch01.md)\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn book_init_defaults_to_builtin_skeleton() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let book_dir = temp.path().join("book");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args(["book", "init", "--title", "Book", "--out"])
+        .arg(&book_dir)
+        .assert()
+        .success();
+
+    let ch01 = fs::read_to_string(book_dir.join("src").join("chapters").join("ch01.md"))?;
+    assert!(ch01.starts_with("# Chapter 1\n\n## Objectives\nTODO\n"));
+    Ok(())
+}
+
+#[test]
+fn book_init_rejects_missing_template_before_writing() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let book_dir = temp.path().join("book");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args(["book", "init", "--title", "Book", "--out"])
+        .arg(&book_dir)
+        .arg("--template")
+        .arg(temp.path().join("missing.md"))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("read book init template"));
+    assert!(!book_dir.exists());
+    Ok(())
+}
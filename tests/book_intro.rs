@@ -0,0 +1,96 @@
+use std::fs;
+
+use sitebookify::formats::{ManifestRecord, Toc, TocChapter, TocPart, TocSection};
+
+#[test]
+fn render_intro_summarizes_chapter_intents() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+
+    let page_id = "p_test_intro_0000000000000000000000000000000000000000000000000000000000000";
+    let extracted_path = temp.path().join("extracted.md");
+    fs::write(
+        &extracted_path,
+        format!(
+            "---\n\
+id: {page_id}\n\
+url: https://example.com/docs\n\
+retrieved_at: 2026-01-01T00:00:00Z\n\
+raw_html_path: raw/index.html\n\
+title: Test Page\n\
+---\n\
+\n\
+# Test Page\n\
+\n\
+Body text.\n"
+        ),
+    )?;
+
+    let manifest_path = temp.path().join("manifest.jsonl");
+    let record = ManifestRecord {
+        id: page_id.to_owned(),
+        url: "https://example.com/docs".to_owned(),
+        title: "Test Page".to_owned(),
+        path: "/docs".to_owned(),
+        extracted_md: extracted_path.to_string_lossy().to_string(),
+        base_url: None,
+        discovery_index: None,
+    };
+    fs::write(
+        &manifest_path,
+        format!("{}\n", serde_json::to_string(&record)?),
+    )?;
+
+    let toc_path = temp.path().join("toc.yaml");
+    let toc = Toc {
+        book_title: "Test Book".to_owned(),
+        parts: vec![TocPart {
+            title: "Basics".to_owned(),
+            chapters: vec![TocChapter {
+                id: "ch01".to_owned(),
+                title: "Getting Started".to_owned(),
+                intent: "Set up the tool.".to_owned(),
+                reader_gains: vec!["Install it".to_owned(), "Run it".to_owned()],
+                sections: vec![TocSection {
+                    title: "Setup".to_owned(),
+                    sources: vec![page_id.to_owned()],
+                }],
+            }],
+        }],
+    };
+    fs::write(&toc_path, serde_yaml::to_string(&toc)?)?;
+
+    let book_dir = temp.path().join("book");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "book",
+        "render",
+        "--toc",
+        toc_path.to_str().unwrap(),
+        "--manifest",
+        manifest_path.to_str().unwrap(),
+        "--out",
+        book_dir.to_str().unwrap(),
+        "--engine",
+        "noop",
+        "--intro",
+        "--toc-title",
+        "About This Book",
+    ])
+    .assert()
+    .success();
+
+    let chapters_dir = book_dir.join("src").join("chapters");
+    let summary = fs::read_to_string(book_dir.join("src").join("SUMMARY.md"))?;
+    assert!(summary.starts_with("# Summary\n\n[About This Book](chapters/ch00.md)\n\n- Basics\n"));
+
+    let intro = fs::read_to_string(chapters_dir.join("ch00.md"))?;
+    assert_eq!(
+        intro,
+        "# About This Book\n\n## Basics\n\n### [Getting Started](ch01.md)\n\nSet up the tool.\n\n- Install it\n- Run it\n\n"
+    );
+
+    let ch01 = fs::read_to_string(chapters_dir.join("ch01.md"))?;
+    assert!(ch01.starts_with("# Getting Started\n\n*Set up the tool.*\n\n## Setup\n"));
+
+    Ok(())
+}
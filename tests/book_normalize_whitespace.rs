@@ -0,0 +1,91 @@
+use std::fs;
+use std::path::Path;
+
+use sitebookify::formats::{ManifestRecord, Toc, TocChapter, TocPart, TocSection};
+
+const PAGE_ID: &str = "p_test_ws_a0000000000000000000000000000000000000000000000000000000000000";
+
+fn render(temp: &Path, out: &str, extra_args: &[&str]) -> anyhow::Result<String> {
+    let book_dir = temp.join(out);
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "book",
+        "render",
+        "--toc",
+        temp.join("toc.yaml").to_str().unwrap(),
+        "--manifest",
+        temp.join("manifest.jsonl").to_str().unwrap(),
+        "--out",
+        book_dir.to_str().unwrap(),
+        "--engine",
+        "noop",
+    ])
+    .args(extra_args)
+    .assert()
+    .success();
+    Ok(fs::read_to_string(
+        book_dir.join("src").join("chapters").join("ch01.md"),
+    )?)
+}
+
+#[test]
+fn render_normalizes_whitespace_outside_code_fences() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+
+    let extracted_path = temp.path().join("extracted.md");
+    fs::write(
+        &extracted_path,
+        format!(
+            "---\nid: {PAGE_ID}\nurl: https://example.com/docs/a\nretrieved_at: 2026-01-01T00:00:00Z\n\
+raw_html_path: raw/a.html\ntitle: Page A\n---\n\n# Page A\n\n\
+Intro line.   \n\n\n\n\nAfter gap.\t\n\n```text\ncode   \n\n\n\nend\n```\n\n\n\n\nTail.\n"
+        ),
+    )?;
+    let record = ManifestRecord {
+        id: PAGE_ID.to_owned(),
+        url: "https://example.com/docs/a".to_owned(),
+        title: "Page A".to_owned(),
+        path: "/docs/a".to_owned(),
+        extracted_md: extracted_path.to_string_lossy().to_string(),
+        base_url: None,
+        discovery_index: None,
+    };
+    fs::write(
+        temp.path().join("manifest.jsonl"),
+        format!("{}\n", serde_json::to_string(&record)?),
+    )?;
+
+    let toc = Toc {
+        book_title: "Test Book".to_owned(),
+        parts: vec![TocPart {
+            title: "Basics".to_owned(),
+            chapters: vec![TocChapter {
+                id: "ch01".to_owned(),
+                title: "Getting Started".to_owned(),
+                intent: String::new(),
+                reader_gains: Vec::new(),
+                sections: vec![TocSection {
+                    title: "First".to_owned(),
+                    sources: vec![PAGE_ID.to_owned()],
+                }],
+            }],
+        }],
+    };
+    fs::write(temp.path().join("toc.yaml"), serde_yaml::to_string(&toc)?)?;
+
+    let normalized = render(temp.path(), "book", &[])?;
+    assert!(normalized.contains("Intro line.\n\n\nAfter gap.\n\n```text\n"));
+    assert!(normalized.contains("```text\ncode   \n\n\n\nend\n```\n\n\nTail.\n"));
+    assert!(normalized.ends_with("- https://example.com/docs/a\n"));
+    assert!(
+        normalized
+            .lines()
+            .all(|line| line == "code   " || line == line.trim_end())
+    );
+
+    let raw = render(temp.path(), "book-raw", &["--no-normalize"])?;
+    assert!(raw.contains("Intro line.   \n\n\n\n\nAfter gap.\t\n"));
+    assert!(raw.contains("```\n\n\n\n\nTail.\n"));
+
+    Ok(())
+}
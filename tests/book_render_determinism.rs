@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use sitebookify::formats::TocPart;
+
+mod fixture;
+
+const CHAPTERS: usize = 6;
+
+/// Every `/shared/*` image has the same bytes; `/unique/<n>.png` embeds `n`.
+fn spawn_image_server() -> (String, mpsc::Sender<()>, thread::JoinHandle<()>) {
+    let server = tiny_http::Server::http("127.0.0.1:0").expect("start tiny_http server");
+    let base_url = format!("http://{}", server.server_addr());
+    let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
+    let handle = thread::spawn(move || {
+        while shutdown_rx.try_recv().is_err() {
+            let request = match server.recv_timeout(Duration::from_millis(50)) {
+                Ok(Some(req)) => req,
+                Ok(None) => continue,
+                Err(_) => break,
+            };
+            let mut body = b"\x89PNG\r\n\x1a\n".to_vec();
+            if request.url().starts_with("/unique/") {
+                body.extend_from_slice(request.url().as_bytes());
+            } else {
+                body.extend_from_slice(b"shared");
+            }
+            let _ = request.respond(
+                tiny_http::Response::from_data(body).with_header(
+                    tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"image/png"[..])
+                        .expect("build header"),
+                ),
+            );
+        }
+    });
+    (base_url, shutdown_tx, handle)
+}
+
+/// One chapter per page; every page links to the next and embeds a shared and a unique image.
+fn write_inputs(dir: &Path, base_url: &str) -> anyhow::Result<()> {
+    let pages = (0..CHAPTERS)
+        .map(|n| {
+            fixture::Page::titled(
+                &n.to_string(),
+                &format!("Page {n}"),
+                &format!(
+                    "![Shared]({base_url}/shared/{n}.png)\n\n![Unique]({base_url}/unique/{n}.png)\n\n\
+See [the next page]({base_url}/docs/{next}).",
+                    next = (n + 1) % CHAPTERS,
+                ),
+            )
+            .at(base_url)
+        })
+        .collect::<Vec<_>>();
+    let (toc, _) = fixture::write_inputs(dir, &pages)?;
+    let chapters = pages
+        .iter()
+        .enumerate()
+        .map(|(n, page)| {
+            fixture::chapter(
+                &format!("ch{:02}", n + 1),
+                &format!("Chapter {n}"),
+                &[page.id()],
+            )
+        })
+        .collect();
+    fixture::write_toc(
+        Path::new(&toc),
+        vec![TocPart {
+            title: "Everything".to_owned(),
+            chapters,
+        }],
+    )
+}
+
+/// Relative path -> contents of every file under `dir`.
+fn snapshot(dir: &Path) -> anyhow::Result<BTreeMap<String, Vec<u8>>> {
+    let mut files = BTreeMap::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(&current)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                let rel = path.strip_prefix(dir)?.to_string_lossy().into_owned();
+                files.insert(rel, fs::read(&path)?);
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn render(dir: &Path, out: &str, extra_args: &[&str]) -> anyhow::Result<BTreeMap<String, Vec<u8>>> {
+    let book_dir = dir.join(out);
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.env_remove("HTTP_PROXY")
+        .env_remove("http_proxy")
+        .args([
+            "book",
+            "render",
+            "--toc",
+            dir.join("toc.yaml").to_str().unwrap(),
+            "--manifest",
+            dir.join("manifest.jsonl").to_str().unwrap(),
+            "--out",
+            book_dir.to_str().unwrap(),
+            "--engine",
+            "noop",
+        ])
+        .args(extra_args)
+        .assert()
+        .success();
+    snapshot(&book_dir.join("src"))
+}
+
+#[test]
+fn render_output_does_not_depend_on_concurrency() -> anyhow::Result<()> {
+    let (base_url, shutdown_tx, handle) = spawn_image_server();
+    let temp = tempfile::TempDir::new()?;
+    write_inputs(temp.path(), &base_url)?;
+
+    for extra in [&[][..], &["--dedupe-assets-by-content"][..]] {
+        let label = extra.join(" ");
+        let serial = render(
+            temp.path(),
+            &format!("serial{label}"),
+            &[&["--concurrency", "1"], extra].concat(),
+        )?;
+        let parallel = render(
+            temp.path(),
+            &format!("parallel{label}"),
+            &[&["--concurrency", "4"], extra].concat(),
+        )?;
+        assert_eq!(
+            serial.keys().collect::<Vec<_>>(),
+            parallel.keys().collect::<Vec<_>>(),
+            "{label}"
+        );
+        assert!(serial == parallel, "output differs with {label:?}");
+
+        let assets = serial
+            .keys()
+            .filter(|path| path.starts_with("assets/"))
+            .count();
+        let expected = if extra.is_empty() {
+            2 * CHAPTERS
+        } else {
+            CHAPTERS + 1
+        };
+        assert_eq!(assets, expected, "{label}");
+    }
+
+    let _ = shutdown_tx.send(());
+    let _ = handle.join();
+    Ok(())
+}
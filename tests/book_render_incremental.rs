@@ -0,0 +1,77 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use sitebookify::formats::TocPart;
+
+mod fixture;
+
+/// `ch01` holds the `install` page, `ch02` the `usage` page.
+fn write_inputs(dir: &Path) -> anyhow::Result<()> {
+    let install = fixture::Page::new("install", "Run the installer.");
+    let usage = fixture::Page::new("usage", "Call the tool.");
+    let (toc, _) = fixture::write_inputs(dir, &[install.clone(), usage.clone()])?;
+    fixture::write_toc(
+        Path::new(&toc),
+        vec![TocPart {
+            title: "Basics".to_owned(),
+            chapters: vec![
+                fixture::chapter("ch01", "Install", &[install.id()]),
+                fixture::chapter("ch02", "Usage", &[usage.id()]),
+            ],
+        }],
+    )
+}
+
+fn render(dir: &Path, extra_args: &[&str]) {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "book",
+        "render",
+        "--toc",
+        dir.join("toc.yaml").to_str().unwrap(),
+        "--manifest",
+        dir.join("manifest.jsonl").to_str().unwrap(),
+        "--out",
+        dir.join("book").to_str().unwrap(),
+        "--engine",
+        "noop",
+    ]);
+    cmd.args(extra_args).assert().success();
+}
+
+fn chapter_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join("book")
+        .join("src")
+        .join("chapters")
+        .join(format!("{id}.md"))
+}
+
+fn modified(path: &Path) -> anyhow::Result<SystemTime> {
+    Ok(fs::metadata(path)?.modified()?)
+}
+
+#[test]
+fn book_render_skips_chapters_whose_inputs_are_unchanged() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let dir = temp.path();
+    write_inputs(dir)?;
+    render(dir, &[]);
+    assert!(dir.join("book").join(".render-hash").join("ch01").is_file());
+    let ch01_first = modified(&chapter_path(dir, "ch01"))?;
+    let ch02_first = modified(&chapter_path(dir, "ch02"))?;
+
+    thread::sleep(Duration::from_millis(50));
+    fixture::write_page(dir, &fixture::Page::new("usage", "Call the tool twice."))?;
+    render(dir, &[]);
+    assert_eq!(modified(&chapter_path(dir, "ch01"))?, ch01_first);
+    assert_ne!(modified(&chapter_path(dir, "ch02"))?, ch02_first);
+    let ch02 = fs::read_to_string(chapter_path(dir, "ch02"))?;
+    assert!(ch02.contains("Call the tool twice."), "{ch02}");
+
+    thread::sleep(Duration::from_millis(50));
+    render(dir, &["--force-rerender"]);
+    assert_ne!(modified(&chapter_path(dir, "ch01"))?, ch01_first);
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use std::fs;
+use std::path::Path;
+
+use sitebookify::formats::TocPart;
+
+mod fixture;
+
+const CHAPTERS: usize = 6;
+
+/// One chapter per page.
+fn write_inputs(dir: &Path) -> anyhow::Result<(String, String)> {
+    let pages = (1..=CHAPTERS)
+        .map(|n| fixture::Page::titled(&n.to_string(), &format!("Page {n}"), &format!("Body {n}.")))
+        .collect::<Vec<_>>();
+    let (toc, manifest) = fixture::write_inputs(dir, &pages)?;
+    let chapters = pages
+        .iter()
+        .zip(1..)
+        .map(|(page, n)| {
+            fixture::chapter(&format!("ch{n:02}"), &format!("Chapter {n}"), &[page.id()])
+        })
+        .collect();
+    fixture::write_toc(
+        Path::new(&toc),
+        vec![TocPart {
+            title: "Basics".to_owned(),
+            chapters,
+        }],
+    )?;
+    Ok((toc, manifest))
+}
+
+fn render_sample(dir: &Path, name: &str, seed: &str) -> anyhow::Result<Vec<String>> {
+    let (toc_path, manifest_path) = write_inputs(dir)?;
+    let book_dir = dir.join(name);
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "book",
+        "render",
+        "--toc",
+        &toc_path,
+        "--manifest",
+        &manifest_path,
+        "--out",
+        book_dir.to_str().unwrap(),
+        "--engine",
+        "noop",
+        "--sample",
+        "2",
+        "--sample-seed",
+        seed,
+    ])
+    .assert()
+    .success();
+
+    let chapters_dir = book_dir.join("src").join("chapters");
+    let mut rendered = fs::read_dir(&chapters_dir)?
+        .map(|entry| Ok(entry?.file_name().to_string_lossy().to_string()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    rendered.sort();
+
+    let summary = fs::read_to_string(book_dir.join("src").join("SUMMARY.md"))?;
+    assert_eq!(summary.matches("](chapters/").count(), rendered.len());
+    for file in &rendered {
+        assert!(summary.contains(&format!("(chapters/{file})")));
+        let chapter = fs::read_to_string(chapters_dir.join(file))?;
+        assert!(
+            chapter.contains(&format!(
+                "> **Sample render:** 2 of {CHAPTERS} chapters (`--sample-seed {seed}`)."
+            )),
+            "{chapter}"
+        );
+    }
+    Ok(rendered)
+}
+
+#[test]
+fn sample_renders_a_deterministic_marked_subset() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+
+    let first = render_sample(temp.path(), "book-a", "7")?;
+    assert_eq!(first.len(), 2);
+    let again = render_sample(temp.path(), "book-b", "7")?;
+    assert_eq!(first, again);
+
+    let picks = (0..8)
+        .map(|seed| render_sample(temp.path(), &format!("seed-{seed}"), &seed.to_string()))
+        .collect::<anyhow::Result<std::collections::HashSet<_>>>()?;
+    assert!(picks.len() > 1, "seeds should vary the pick: {picks:?}");
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use std::fs;
+use std::path::Path;
+
+use sitebookify::formats::{ManifestRecord, Toc, TocChapter, TocPart, TocSection};
+
+fn page(dir: &Path, slug: &str, title: &str, body: &str) -> anyhow::Result<ManifestRecord> {
+    let page_id = format!("p_test_sections_as_chapters_{slug:0<50}");
+    let extracted_path = dir.join(format!("{slug}.md"));
+    fs::write(
+        &extracted_path,
+        format!(
+            "---\nid: {page_id}\nurl: https://example.com/docs/{slug}\n\
+retrieved_at: 2026-01-01T00:00:00Z\nraw_html_path: raw/{slug}.html\ntitle: {title}\n---\n\n\
+# {title}\n\n{body}\n"
+        ),
+    )?;
+    Ok(ManifestRecord {
+        id: page_id,
+        url: format!("https://example.com/docs/{slug}"),
+        title: title.to_owned(),
+        path: format!("/docs/{slug}"),
+        extracted_md: extracted_path.to_string_lossy().to_string(),
+        base_url: None,
+        discovery_index: None,
+    })
+}
+
+fn render(dir: &Path, name: &str, extra_args: &[&str]) -> anyhow::Result<std::path::PathBuf> {
+    let install = page(dir, "install", "Install", "Run the installer.")?;
+    let configure = page(
+        dir,
+        "configure",
+        "Configure",
+        "Finish the [installation](install) first.",
+    )?;
+    let tune = page(dir, "tune", "Tune", "Start from the [settings](configure).")?;
+
+    let manifest_path = dir.join("manifest.jsonl");
+    let manifest = [&install, &configure, &tune]
+        .iter()
+        .map(|record| serde_json::to_string(record).map(|line| format!("{line}\n")))
+        .collect::<Result<String, _>>()?;
+    fs::write(&manifest_path, manifest)?;
+
+    let section = |title: &str, record: &ManifestRecord| TocSection {
+        title: title.to_owned(),
+        sources: vec![record.id.clone()],
+    };
+    let toc_path = dir.join("toc.yaml");
+    let toc = Toc {
+        book_title: "Test Book".to_owned(),
+        parts: vec![TocPart {
+            title: "Basics".to_owned(),
+            chapters: vec![
+                TocChapter {
+                    id: "ch01".to_owned(),
+                    title: "Getting Started".to_owned(),
+                    intent: "Set up the tool.".to_owned(),
+                    reader_gains: vec!["Install it".to_owned()],
+                    sections: vec![
+                        section("Installation", &install),
+                        section(" ", &install),
+                        section("Configuration", &configure),
+                    ],
+                },
+                TocChapter {
+                    id: "ch02".to_owned(),
+                    title: "Advanced".to_owned(),
+                    intent: String::new(),
+                    reader_gains: Vec::new(),
+                    sections: vec![section("Tuning", &tune)],
+                },
+            ],
+        }],
+    };
+    fs::write(&toc_path, serde_yaml::to_string(&toc)?)?;
+
+    let book_dir = dir.join(name);
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "book",
+        "render",
+        "--toc",
+        toc_path.to_str().unwrap(),
+        "--manifest",
+        manifest_path.to_str().unwrap(),
+        "--out",
+        book_dir.to_str().unwrap(),
+        "--engine",
+        "noop",
+        "--sections-as-chapters",
+    ])
+    .args(extra_args)
+    .assert()
+    .success();
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args(["book", "validate", "--book", book_dir.to_str().unwrap()])
+        .assert()
+        .success();
+    Ok(book_dir)
+}
+
+#[test]
+fn sections_as_chapters_writes_one_file_per_section() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let book_dir = render(temp.path(), "book", &["--toc-depth", "2"])?;
+    let src = book_dir.join("src");
+
+    let summary = fs::read_to_string(src.join("SUMMARY.md"))?;
+    assert_eq!(
+        summary,
+        "# Summary\n\n- Basics\n  - [Installation](chapters/ch01-s01.md)\n  \
+- [Configuration](chapters/ch01-s03.md)\n  - [Tuning](chapters/ch02-s01.md)\n"
+    );
+    let mut files = fs::read_dir(src.join("chapters"))?
+        .map(|entry| entry.map(|entry| entry.file_name().to_string_lossy().into_owned()))
+        .collect::<Result<Vec<_>, _>>()?;
+    files.sort();
+    assert_eq!(files, vec!["ch01-s01.md", "ch01-s03.md", "ch02-s01.md"]);
+
+    let installation = fs::read_to_string(src.join("chapters").join("ch01-s01.md"))?;
+    assert!(
+        installation.starts_with("# Installation\n\n*Set up the tool.*\n\n"),
+        "{installation}"
+    );
+    assert!(
+        !installation.contains("\n## Installation"),
+        "{installation}"
+    );
+
+    let configuration = fs::read_to_string(src.join("chapters").join("ch01-s03.md"))?;
+    assert!(
+        configuration.starts_with("# Configuration\n\n"),
+        "{configuration}"
+    );
+    assert!(
+        configuration.contains("(ch01-s01.md#p_test_sections_as_chapters_install"),
+        "{configuration}"
+    );
+
+    let tuning = fs::read_to_string(src.join("chapters").join("ch02-s01.md"))?;
+    assert!(
+        tuning.contains("(ch01-s03.md#p_test_sections_as_chapters_configure"),
+        "{tuning}"
+    );
+    Ok(())
+}
+
+#[test]
+fn sections_as_chapters_puts_heading_anchors_on_the_chapter_title() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let book_dir = render(temp.path(), "book", &["--anchor-style", "heading"])?;
+    let chapters = book_dir.join("src").join("chapters");
+
+    let configuration = fs::read_to_string(chapters.join("ch01-s03.md"))?;
+    assert!(
+        configuration.starts_with("# Configuration {#ch01-s03-s01}\n\n"),
+        "{configuration}"
+    );
+    let tuning = fs::read_to_string(chapters.join("ch02-s01.md"))?;
+    assert!(tuning.contains("(ch01-s03.md#ch01-s03-s01)"), "{tuning}");
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use std::fs;
+
+#[test]
+fn book_stats_counts_prose_per_chapter() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let src_dir = temp.path().join("book").join("src");
+    fs::create_dir_all(src_dir.join("chapters"))?;
+    fs::write(
+        src_dir.join("SUMMARY.md"),
+        "# Summary\n\n- Part\n  - [One](chapters/ch01.md)\n    - [Setup](chapters/ch01.md#ch01-s01)\n  - [Two](chapters/ch02.md)\n",
+    )?;
+    fs::write(
+        src_dir.join("chapters").join("ch01.md"),
+        "# One\n\n## Setup\n\n<span id=\"p_x\" style=\"display:none\"></span>\n\nRead [the guide](https://example.com/a/very/long/url) now.\n\n```sh\nnot counted at all\n```\n\n![diagram](../assets/a.png)\n",
+    )?;
+    fs::write(
+        src_dir.join("chapters").join("ch02.md"),
+        "# Two\n\n本章では設定を説明します。\n",
+    )?;
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    let output = cmd
+        .args([
+            "book",
+            "stats",
+            "--book",
+            temp.path().join("book").to_str().unwrap(),
+            "--format",
+            "json",
+        ])
+        .output()?;
+    assert!(output.status.success());
+    let stats: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+
+    let chapters = stats["chapters"].as_array().unwrap();
+    assert_eq!(chapters.len(), 2);
+    let ch01 = &chapters[0];
+    assert_eq!(ch01["path"], "chapters/ch01.md");
+    assert_eq!(ch01["title"], "One");
+    // `One`, `Setup`, `Read [the guide] now.`, `![diagram]` (fenced code and tags skipped).
+    assert_eq!(ch01["words"], 7);
+    assert_eq!(ch01["headings"], 2);
+    assert_eq!(ch01["images"], 1);
+    let ch02 = &chapters[1];
+    assert_eq!(ch02["chars"], 16);
+    assert_eq!(stats["total"]["headings"], 3);
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "book",
+        "stats",
+        "--book",
+        temp.path().join("book").to_str().unwrap(),
+    ])
+    .assert()
+    .success()
+    .stdout(predicates::str::contains("chapters/ch02.md"))
+    .stdout(predicates::str::contains("total"));
+
+    Ok(())
+}
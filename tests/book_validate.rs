@@ -0,0 +1,81 @@
+use std::fs;
+use std::path::Path;
+
+use predicates::prelude::*;
+
+fn write_book(dir: &Path, ch02: &str) -> anyhow::Result<()> {
+    let src = dir.join("src");
+    fs::create_dir_all(src.join("chapters"))?;
+    fs::create_dir_all(src.join("assets"))?;
+    fs::write(dir.join("book.toml"), "[book]\ntitle = \"Test Book\"\n")?;
+    fs::write(
+        src.join("SUMMARY.md"),
+        "# Summary\n\n- [One](chapters/ch01.md)\n  - [Setup](chapters/ch01.md#ch01-s01)\n\
+- [Two](chapters/ch02.md)\n",
+    )?;
+    fs::write(
+        src.join("chapters").join("ch01.md"),
+        "# One\n\n## Setup {#ch01-s01}\n\n<span id=\"p_a\" style=\"display:none\" aria-hidden=\"true\"></span>\n\n\
+See [two](ch02.md#getting-started) and [here](#p_a).\n\n![Logo](../assets/logo.png)\n\n\
+Visit [the site](https://example.com/missing.md#nowhere).\n",
+    )?;
+    fs::write(src.join("chapters").join("ch02.md"), ch02)?;
+    fs::write(src.join("assets").join("logo.png"), b"\x89PNG\r\n\x1a\n")?;
+    Ok(())
+}
+
+#[test]
+fn book_validate_passes_a_consistent_book() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let book = temp.path().join("book");
+    write_book(
+        &book,
+        "# Two\n\n## Getting Started\n\nBack to [one](ch01.md#ch01-s01).\n",
+    )?;
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args(["book", "validate", "--book", book.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "ok: 2 chapters, 7 relative links, 0 problems",
+        ));
+
+    // Read-only: nothing is written next to the book.
+    assert_eq!(fs::read_dir(temp.path())?.count(), 1);
+    Ok(())
+}
+
+#[test]
+fn book_validate_reports_broken_links_assets_and_xhtml() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let book = temp.path().join("book");
+    write_book(
+        &book,
+        "# Two\n\n## Usage\n\nBack to [one](ch01.md#nope) and [three](ch03.md).\n\n\
+![Missing](../assets/missing.png)\n\n<div class=\"note\">\n\nnever closed\n",
+    )?;
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args(["book", "validate", "--book", book.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "FAIL chapters/ch01.md: link ch02.md#getting-started: no anchor #getting-started in chapters/ch02.md",
+        ))
+        .stdout(predicate::str::contains(
+            "FAIL chapters/ch02.md: link ch01.md#nope: no anchor #nope in chapters/ch01.md",
+        ))
+        .stdout(predicate::str::contains(
+            "FAIL chapters/ch02.md: link ch03.md: chapters/ch03.md does not exist",
+        ))
+        .stdout(predicate::str::contains(
+            "FAIL chapters/ch02.md: link ../assets/missing.png: assets/missing.png does not exist",
+        ))
+        .stdout(predicate::str::contains(
+            "FAIL epub OEBPS/ch02.xhtml: line",
+        ))
+        .stdout(predicate::str::contains("failed: 2 chapters"))
+        .stderr(predicate::str::contains("book is not publishable (5 problems)"));
+    Ok(())
+}
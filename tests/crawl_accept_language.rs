@@ -0,0 +1,127 @@
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::Duration;
+
+use predicates::prelude::*;
+
+/// `(request path, Accept-Language)` for every request the server saw.
+type SeenRequests = Arc<Mutex<Vec<(String, Option<String>)>>>;
+
+/// Serves a two-page site and records the `Accept-Language` of every request.
+fn spawn_language_server() -> (
+    String,
+    SeenRequests,
+    mpsc::Sender<()>,
+    thread::JoinHandle<()>,
+) {
+    let server = tiny_http::Server::http("127.0.0.1:0").expect("start tiny_http server");
+    let base_url = format!("http://{}", server.server_addr());
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
+    let handle = thread::spawn({
+        let seen = Arc::clone(&seen);
+        move || {
+            let html_header =
+                tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..])
+                    .expect("build header");
+            while shutdown_rx.try_recv().is_err() {
+                let request = match server.recv_timeout(Duration::from_millis(50)) {
+                    Ok(Some(req)) => req,
+                    Ok(None) => continue,
+                    Err(_) => break,
+                };
+                let accept_language = request
+                    .headers()
+                    .iter()
+                    .find(|header| header.field.equiv("Accept-Language"))
+                    .map(|header| header.value.as_str().to_owned());
+                seen.lock()
+                    .unwrap()
+                    .push((request.url().to_owned(), accept_language));
+                let body = match request.url() {
+                    "/docs/" => {
+                        "<html><head><title>Docs</title></head><body><main><h1>Docs</h1>\
+<a href=\"/docs/intro\">Intro</a></main></body></html>"
+                    }
+                    _ => {
+                        "<html><head><title>Intro</title></head><body><main><h1>Intro</h1>\
+<p>Hello.</p></main></body></html>"
+                    }
+                };
+                let _ = request.respond(
+                    tiny_http::Response::from_string(body).with_header(html_header.clone()),
+                );
+            }
+        }
+    });
+    (base_url, seen, shutdown_tx, handle)
+}
+
+#[test]
+fn crawl_sends_accept_language_when_set() -> anyhow::Result<()> {
+    let (base_url, seen, shutdown_tx, handle) = spawn_language_server();
+    let temp = tempfile::TempDir::new()?;
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.env_remove("HTTP_PROXY")
+        .env_remove("http_proxy")
+        .args(["crawl", "--url", &format!("{base_url}/docs/")])
+        .arg("--out")
+        .arg(temp.path().join("raw"))
+        .args(["--delay-ms", "0", "--accept-language", "ja, en;q=0.5"])
+        .assert()
+        .success();
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.env_remove("HTTP_PROXY")
+        .env_remove("http_proxy")
+        .args(["crawl", "--url", &format!("{base_url}/docs/")])
+        .arg("--out")
+        .arg(temp.path().join("raw-list"))
+        .args(["--delay-ms", "0", "--accept-language", "ja"])
+        .args(["--page-url", &format!("{base_url}/docs/intro")])
+        .assert()
+        .success();
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.env_remove("HTTP_PROXY")
+        .env_remove("http_proxy")
+        .args(["crawl", "--url", &format!("{base_url}/docs/")])
+        .arg("--out")
+        .arg(temp.path().join("raw-default"))
+        .args(["--delay-ms", "0"])
+        .assert()
+        .success();
+
+    let _ = shutdown_tx.send(());
+    let _ = handle.join();
+
+    let seen = seen.lock().unwrap().clone();
+    let crawled = seen
+        .iter()
+        .filter(|(_, lang)| lang.as_deref() == Some("ja, en;q=0.5"))
+        .map(|(url, _)| url.as_str())
+        .collect::<Vec<_>>();
+    assert!(crawled.contains(&"/docs/intro"), "{seen:#?}");
+    assert!(
+        seen.iter()
+            .any(|(url, lang)| url == "/docs/intro" && lang.as_deref() == Some("ja")),
+        "{seen:#?}"
+    );
+    assert!(seen.iter().any(|(_, lang)| lang.is_none()), "{seen:#?}");
+    Ok(())
+}
+
+#[test]
+fn crawl_rejects_invalid_accept_language() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args(["crawl", "--url", "http://127.0.0.1:1/docs/"])
+        .arg("--out")
+        .arg(temp.path().join("raw"))
+        .args(["--accept-language", "ja\nen"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("validate --accept-language"));
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use std::fs;
+
+mod fixture;
+mod openai_stub;
+
+#[test]
+fn dump_prompt_writes_toc_and_rewrite_prompts_as_sent() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let page = fixture::Page::new("dump_prompt", "Body text with `code`.");
+    let manifest_path = fixture::write_page_manifest(temp.path(), std::slice::from_ref(&page))?;
+    let dump_dir = temp.path().join("prompts");
+
+    let openai = openai_stub::OpenAiStub::spawn(openai_stub::OpenAiStubConfig {
+        expected_reasoning_effort: Some("high".to_owned()),
+        rewrite_behavior: openai_stub::RewriteBehavior::EchoInput,
+        transient_failures: 0,
+    });
+
+    let toc_path = temp.path().join("toc.yaml");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.env("OPENAI_API_KEY", "test-key")
+        .env("SITEBOOKIFY_OPENAI_BASE_URL", &openai.base_url)
+        .env("SITEBOOKIFY_OPENAI_MODEL", "stub-model")
+        .args([
+            "toc",
+            "create",
+            "--manifest",
+            manifest_path.to_str().unwrap(),
+            "--out",
+            toc_path.to_str().unwrap(),
+            "--dump-prompt",
+            dump_dir.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    let toc_prompt = fs::read_to_string(dump_dir.join("toc.prompt.txt"))?;
+    assert!(toc_prompt.contains("BEGIN_TOC_INPUT_JSON"));
+    assert!(toc_prompt.contains(&page.id()));
+
+    let book_dir = temp.path().join("book");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.env("OPENAI_API_KEY", "test-key")
+        .env("SITEBOOKIFY_OPENAI_BASE_URL", &openai.base_url)
+        .env("SITEBOOKIFY_OPENAI_MODEL", "stub-model")
+        .args([
+            "book",
+            "render",
+            "--toc",
+            toc_path.to_str().unwrap(),
+            "--manifest",
+            manifest_path.to_str().unwrap(),
+            "--out",
+            book_dir.to_str().unwrap(),
+            "--engine",
+            "openai",
+            "--dump-prompt",
+            dump_dir.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    let sent = openai.rewrite_prompts();
+    assert_eq!(sent.len(), 1);
+    let rewrite_prompt = fs::read_to_string(dump_dir.join("ch01-s01-u001.prompt.txt"))?;
+    assert_eq!(rewrite_prompt, sent[0]);
+    // Code stays protected in the dump, exactly as the model sees it.
+    assert!(rewrite_prompt.contains("{{SBY_"));
+    assert!(!rewrite_prompt.contains("`code`"));
+
+    Ok(())
+}
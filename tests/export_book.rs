@@ -0,0 +1,242 @@
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use sitebookify::formats::{BookExport, TocChapter, TocPart, TocSection};
+
+mod fixture;
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\nnot really a png";
+
+/// Serves `/logo.png`; anything else is 404.
+fn spawn_image_server() -> (String, mpsc::Sender<()>, thread::JoinHandle<()>) {
+    let server = tiny_http::Server::http("127.0.0.1:0").expect("start tiny_http server");
+    let base_url = format!("http://{}", server.server_addr());
+    let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
+    let handle = thread::spawn(move || {
+        while shutdown_rx.try_recv().is_err() {
+            let request = match server.recv_timeout(Duration::from_millis(50)) {
+                Ok(Some(req)) => req,
+                Ok(None) => continue,
+                Err(_) => break,
+            };
+            let response = match request.url() {
+                "/logo.png" => tiny_http::Response::from_data(PNG.to_vec()).with_header(
+                    tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"image/png"[..])
+                        .expect("build header"),
+                ),
+                _ => tiny_http::Response::from_data(b"not found".to_vec()).with_status_code(404),
+            };
+            let _ = request.respond(response);
+        }
+    });
+    (base_url, shutdown_tx, handle)
+}
+
+fn write_inputs(dir: &Path, base_url: &str) -> anyhow::Result<(String, String)> {
+    let install = fixture::Page::titled(
+        "install",
+        "Install",
+        &format!("Run the installer.\n\n![Logo]({base_url}/logo.png)"),
+    )
+    .at(base_url);
+    let usage = fixture::Page::titled("usage", "Usage", "Read the [install guide](install) first.")
+        .at(base_url);
+    let faq = fixture::Page::titled("faq", "FAQ", "Ask away.").at(base_url);
+    let (toc, manifest) =
+        fixture::write_inputs(dir, &[install.clone(), usage.clone(), faq.clone()])?;
+
+    fixture::write_toc(
+        Path::new(&toc),
+        vec![
+            TocPart {
+                title: "Basics".to_owned(),
+                chapters: vec![TocChapter {
+                    intent: "Set up the tool.".to_owned(),
+                    reader_gains: vec!["Install it".to_owned()],
+                    sections: vec![
+                        TocSection {
+                            title: "Setup".to_owned(),
+                            sources: vec![install.id()],
+                        },
+                        TocSection {
+                            title: "First run".to_owned(),
+                            sources: vec![usage.id(), faq.id()],
+                        },
+                    ],
+                    ..fixture::chapter("ch01", "Getting Started", &[])
+                }],
+            },
+            TocPart {
+                title: "Reference".to_owned(),
+                chapters: vec![fixture::chapter("ch02", "Questions", &[faq.id()])],
+            },
+        ],
+    )?;
+    Ok((toc, manifest))
+}
+
+fn export_cmd(toc: &str, manifest: &str, out: &Path) -> assert_cmd::Command {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "export",
+        "book",
+        "--toc",
+        toc,
+        "--manifest",
+        manifest,
+        "--out",
+        out.to_str().unwrap(),
+        "--engine",
+        "noop",
+    ]);
+    cmd
+}
+
+/// Checks the `book.json` schema: every level has exactly these keys, with these JSON types.
+fn assert_object_schema(value: &serde_json::Value, fields: &[(&str, &str)], at: &str) {
+    let object = value
+        .as_object()
+        .unwrap_or_else(|| panic!("{at}: expected an object, got {value}"));
+    let mut keys = object.keys().map(String::as_str).collect::<Vec<_>>();
+    keys.sort();
+    let mut expected = fields.iter().map(|(key, _)| *key).collect::<Vec<_>>();
+    expected.sort();
+    assert_eq!(keys, expected, "{at}: keys");
+    for (key, kind) in fields {
+        let field = &object[*key];
+        let ok = match *kind {
+            "string" => field.is_string(),
+            "array" => field.is_array(),
+            "string[]" => field
+                .as_array()
+                .is_some_and(|items| items.iter().all(serde_json::Value::is_string)),
+            other => panic!("unknown schema type: {other}"),
+        };
+        assert!(ok, "{at}.{key}: expected {kind}, got {field}");
+    }
+}
+
+fn assert_book_schema(book: &serde_json::Value) {
+    assert_object_schema(
+        book,
+        &[("book_title", "string"), ("parts", "array")],
+        "book",
+    );
+    for (p, part) in book["parts"].as_array().unwrap().iter().enumerate() {
+        let at = format!("parts[{p}]");
+        assert_object_schema(part, &[("title", "string"), ("chapters", "array")], &at);
+        for (c, chapter) in part["chapters"].as_array().unwrap().iter().enumerate() {
+            let at = format!("{at}.chapters[{c}]");
+            assert_object_schema(
+                chapter,
+                &[
+                    ("id", "string"),
+                    ("title", "string"),
+                    ("intent", "string"),
+                    ("reader_gains", "string[]"),
+                    ("sections", "array"),
+                    ("markdown", "string"),
+                ],
+                &at,
+            );
+            for (s, section) in chapter["sections"].as_array().unwrap().iter().enumerate() {
+                assert_object_schema(
+                    section,
+                    &[
+                        ("title", "string"),
+                        ("source_urls", "string[]"),
+                        ("markdown", "string"),
+                    ],
+                    &format!("{at}.sections[{s}]"),
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn export_book_writes_structured_json_with_local_assets() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let (base_url, shutdown_tx, handle) = spawn_image_server();
+    let (toc, manifest) = write_inputs(temp.path(), &base_url)?;
+    let out_dir = temp.path().join("export");
+    let json_path = out_dir.join("book.json");
+
+    export_cmd(&toc, &manifest, &json_path).assert().success();
+    let _ = shutdown_tx.send(());
+    let _ = handle.join();
+
+    let raw = fs::read_to_string(&json_path)?;
+    let value: serde_json::Value = serde_json::from_str(&raw)?;
+    assert_book_schema(&value);
+    let book: BookExport = serde_json::from_value(value)?;
+
+    assert_eq!(book.book_title, "Test Book");
+    let parts = book
+        .parts
+        .iter()
+        .map(|part| part.title.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(parts, vec!["Basics", "Reference"]);
+
+    let ch01 = &book.parts[0].chapters[0];
+    assert_eq!(ch01.id, "ch01");
+    assert_eq!(ch01.intent, "Set up the tool.");
+    assert_eq!(ch01.reader_gains, vec!["Install it"]);
+    let sections = ch01
+        .sections
+        .iter()
+        .map(|section| section.title.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(sections, vec!["Setup", "First run"]);
+    assert_eq!(
+        ch01.sections[1].source_urls,
+        vec![
+            format!("{base_url}/docs/usage"),
+            format!("{base_url}/docs/faq")
+        ]
+    );
+
+    let setup = &ch01.sections[0].markdown;
+    assert!(setup.contains("Run the installer."), "{setup}");
+    assert!(!setup.contains("## Setup"), "{setup}");
+    let image_path = setup
+        .split("](")
+        .nth(1)
+        .and_then(|rest| rest.split(')').next())
+        .expect("image link in section markdown");
+    assert!(image_path.starts_with("assets/"), "{setup}");
+    assert_eq!(fs::read(out_dir.join(image_path))?, PNG);
+    assert!(
+        ch01.markdown.contains(&format!("]({image_path})")),
+        "{}",
+        ch01.markdown
+    );
+    assert!(ch01.markdown.starts_with("# Getting Started\n"));
+    assert!(ch01.markdown.contains("## Sources\n"));
+
+    let ch02 = &book.parts[1].chapters[0];
+    assert_eq!(ch02.id, "ch02");
+    assert!(ch02.sections[0].markdown.contains("Ask away."));
+
+    assert!(!out_dir.join("src").exists());
+    Ok(())
+}
+
+#[test]
+fn export_book_rejects_mdbook_only_flags() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let (toc, manifest) = write_inputs(temp.path(), "http://127.0.0.1:9")?;
+    let json_path = temp.path().join("book.json");
+
+    export_cmd(&toc, &manifest, &json_path)
+        .arg("--intro")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("only apply to `book render`"));
+    assert!(!json_path.exists());
+    Ok(())
+}
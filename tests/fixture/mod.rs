@@ -0,0 +1,151 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sitebookify::formats::{ManifestRecord, Toc, TocChapter, TocPart, TocSection};
+
+/// One extracted page of the test site.
+#[derive(Debug, Clone)]
+pub struct Page {
+    /// Names the page's id, file and `/docs/{slug}` path.
+    pub slug: String,
+    pub base_url: String,
+    pub title: String,
+    /// Markdown after the front matter.
+    pub markdown: String,
+}
+
+#[allow(dead_code)]
+impl Page {
+    /// `Test Page` at `https://example.com/docs/{slug}`, with `body` under its `# Test Page`
+    /// heading.
+    pub fn new(slug: &str, body: &str) -> Self {
+        Self {
+            slug: slug.to_owned(),
+            base_url: "https://example.com".to_owned(),
+            title: "Test Page".to_owned(),
+            markdown: format!("# Test Page\n\n{body}\n"),
+        }
+    }
+
+    /// Like `new`, but titled `title` (front matter and heading).
+    pub fn titled(slug: &str, title: &str, body: &str) -> Self {
+        Self {
+            title: title.to_owned(),
+            markdown: format!("# {title}\n\n{body}\n"),
+            ..Self::new(slug, body)
+        }
+    }
+
+    pub fn at(self, base_url: &str) -> Self {
+        Self {
+            base_url: base_url.to_owned(),
+            ..self
+        }
+    }
+
+    /// `p_test_{slug}`.
+    pub fn id(&self) -> String {
+        format!("p_test_{}", self.slug)
+    }
+
+    pub fn url(&self) -> String {
+        format!("{}/docs/{}", self.base_url, self.slug)
+    }
+}
+
+/// Writes `page` to `dir/{slug}.md` and returns its manifest record.
+#[allow(dead_code)]
+pub fn write_page(dir: &Path, page: &Page) -> anyhow::Result<ManifestRecord> {
+    let extracted_path = dir.join(format!("{}.md", page.slug));
+    fs::write(
+        &extracted_path,
+        format!(
+            "---\nid: {}\nurl: {}\nretrieved_at: 2026-01-01T00:00:00Z\n\
+raw_html_path: raw/{}.html\ntitle: {}\n---\n\n{}",
+            page.id(),
+            page.url(),
+            page.slug,
+            page.title,
+            page.markdown
+        ),
+    )?;
+    Ok(ManifestRecord {
+        id: page.id(),
+        url: page.url(),
+        title: page.title.clone(),
+        path: format!("/docs/{}", page.slug),
+        extracted_md: extracted_path.to_string_lossy().to_string(),
+        base_url: None,
+        discovery_index: None,
+    })
+}
+
+#[allow(dead_code)]
+pub fn write_manifest(path: &Path, records: &[ManifestRecord]) -> anyhow::Result<()> {
+    let mut jsonl = String::new();
+    for record in records {
+        jsonl.push_str(&serde_json::to_string(record)?);
+        jsonl.push('\n');
+    }
+    fs::write(path, jsonl)?;
+    Ok(())
+}
+
+/// Writes `pages` and a `dir/manifest.jsonl` listing them; returns the manifest path.
+#[allow(dead_code)]
+pub fn write_page_manifest(dir: &Path, pages: &[Page]) -> anyhow::Result<PathBuf> {
+    let records = pages
+        .iter()
+        .map(|page| write_page(dir, page))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let manifest_path = dir.join("manifest.jsonl");
+    write_manifest(&manifest_path, &records)?;
+    Ok(manifest_path)
+}
+
+/// A chapter with one `Section` listing `sources`, and no intent or reader gains.
+#[allow(dead_code)]
+pub fn chapter(id: &str, title: &str, sources: &[String]) -> TocChapter {
+    TocChapter {
+        id: id.to_owned(),
+        title: title.to_owned(),
+        intent: String::new(),
+        reader_gains: Vec::new(),
+        sections: vec![TocSection {
+            title: "Section".to_owned(),
+            sources: sources.to_vec(),
+        }],
+    }
+}
+
+/// Writes `Test Book` with `parts` to `path`.
+#[allow(dead_code)]
+pub fn write_toc(path: &Path, parts: Vec<TocPart>) -> anyhow::Result<()> {
+    let toc = Toc {
+        book_title: "Test Book".to_owned(),
+        parts,
+    };
+    fs::write(path, serde_yaml::to_string(&toc)?)?;
+    Ok(())
+}
+
+/// `write_page_manifest` plus a `dir/toc.yaml` whose one chapter, `ch01`, lists every page;
+/// returns the TOC and manifest paths. Tests that need another layout overwrite the TOC with
+/// `write_toc`.
+#[allow(dead_code)]
+pub fn write_inputs(dir: &Path, pages: &[Page]) -> anyhow::Result<(String, String)> {
+    let manifest_path = write_page_manifest(dir, pages)?;
+    let toc_path = dir.join("toc.yaml");
+    let sources = pages.iter().map(Page::id).collect::<Vec<_>>();
+    write_toc(
+        &toc_path,
+        vec![TocPart {
+            title: "Part".to_owned(),
+            chapters: vec![chapter("ch01", "Chapter", &sources)],
+        }],
+    )?;
+    Ok((
+        toc_path.to_string_lossy().to_string(),
+        manifest_path.to_string_lossy().to_string(),
+    ))
+}
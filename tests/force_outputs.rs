@@ -0,0 +1,84 @@
+use std::fs;
+use std::path::Path;
+
+use predicates::prelude::*;
+
+fn crawl(out: &Path, extra_args: &[&str]) -> assert_cmd::Command {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.env_remove("HTTP_PROXY")
+        .env_remove("http_proxy")
+        .args(["crawl", "--url", "http://127.0.0.1:1/docs/", "--out"])
+        .arg(out)
+        .args([
+            "--delay-ms",
+            "0",
+            "--page-url",
+            "http://127.0.0.1:1/docs/intro",
+        ])
+        .args(extra_args);
+    cmd
+}
+
+fn extract(raw: &Path, out: &Path, extra_args: &[&str]) -> assert_cmd::Command {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.arg("extract")
+        .arg("--raw")
+        .arg(raw)
+        .arg("--out")
+        .arg(out)
+        .args(extra_args);
+    cmd
+}
+
+#[test]
+fn crawl_force_replaces_previous_snapshot_only() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let raw = temp.path().join("raw");
+
+    crawl(&raw, &[]).assert().success();
+    fs::write(raw.join("stale.txt"), "old run")?;
+    crawl(&raw, &[])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already exists"));
+    crawl(&raw, &["--force"]).assert().success();
+    assert!(raw.join("crawl.jsonl").exists());
+    assert!(!raw.join("stale.txt").exists());
+
+    let unrelated = temp.path().join("notes");
+    fs::create_dir(&unrelated)?;
+    fs::write(unrelated.join("keep.txt"), "mine")?;
+    crawl(&unrelated, &["--force"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("not a previous snapshot"));
+    assert!(unrelated.join("keep.txt").exists());
+
+    Ok(())
+}
+
+#[test]
+fn extract_force_replaces_previous_snapshot() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let raw = temp.path().join("raw");
+    let extracted = temp.path().join("extracted");
+    crawl(&raw, &[]).assert().success();
+
+    extract(&raw, &extracted, &[]).assert().success();
+    fs::write(extracted.join("pages").join("stale.md"), "old run")?;
+    extract(&raw, &extracted, &[])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already exists"));
+    extract(&raw, &extracted, &["--force"]).assert().success();
+    assert!(!extracted.join("pages").join("stale.md").exists());
+
+    // Never delete the input.
+    extract(&raw, &raw, &["--force"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("inside --out"));
+    assert!(raw.join("crawl.jsonl").exists());
+
+    Ok(())
+}
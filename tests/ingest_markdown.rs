@@ -0,0 +1,107 @@
+use std::fs;
+
+use sitebookify::formats::ManifestRecord;
+
+#[test]
+fn ingest_builds_book_from_local_markdown() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+
+    let docs_dir = temp.path().join("docs");
+    fs::create_dir_all(docs_dir.join("guide"))?;
+    fs::write(
+        docs_dir.join("index.md"),
+        "# Welcome\n\nStart with the [guide](guide/usage.markdown).\n",
+    )?;
+    fs::write(
+        docs_dir.join("guide").join("usage.markdown"),
+        "---\nauthor: someone\n---\n\n## Usage\n\nRun the tool.\n",
+    )?;
+    fs::write(docs_dir.join("notes.txt"), "not markdown")?;
+
+    let extracted_dir = temp.path().join("extracted");
+    let manifest_path = temp.path().join("manifest.jsonl");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "ingest",
+        "--dir",
+        docs_dir.to_str().unwrap(),
+        "--out",
+        extracted_dir.to_str().unwrap(),
+        "--manifest",
+        manifest_path.to_str().unwrap(),
+    ])
+    .assert()
+    .success();
+
+    let records: Vec<ManifestRecord> = fs::read_to_string(&manifest_path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).expect("parse manifest record json"))
+        .collect();
+    assert_eq!(records.len(), 2);
+    assert!(records.iter().all(|r| r.url.starts_with("file://")));
+    let mut titles = records.iter().map(|r| r.title.as_str()).collect::<Vec<_>>();
+    titles.sort();
+    assert_eq!(titles, vec!["Usage", "Welcome"]);
+
+    let toc_path = temp.path().join("toc.yaml");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "toc",
+        "create",
+        "--manifest",
+        manifest_path.to_str().unwrap(),
+        "--out",
+        toc_path.to_str().unwrap(),
+        "--book-title",
+        "Local Book",
+        "--engine",
+        "noop",
+    ])
+    .assert()
+    .success();
+
+    let book_dir = temp.path().join("book");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "book",
+        "init",
+        "--out",
+        book_dir.to_str().unwrap(),
+        "--title",
+        "Local Book",
+    ])
+    .assert()
+    .success();
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "book",
+        "render",
+        "--toc",
+        toc_path.to_str().unwrap(),
+        "--manifest",
+        manifest_path.to_str().unwrap(),
+        "--out",
+        book_dir.to_str().unwrap(),
+        "--engine",
+        "noop",
+    ])
+    .assert()
+    .success();
+
+    let usage = records.iter().find(|r| r.title == "Usage").unwrap();
+    let chapters_dir = book_dir.join("src").join("chapters");
+    let mut rendered = String::new();
+    for entry in fs::read_dir(&chapters_dir)? {
+        rendered.push_str(&fs::read_to_string(entry?.path())?);
+    }
+    assert!(rendered.contains("Run the tool."));
+    assert!(!rendered.contains("author: someone"));
+    assert!(
+        rendered.contains(&format!("#{}", usage.id)),
+        "expected link to local page to be rewritten"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use predicates::prelude::*;
+
+fn crawl_unreachable(temp: &std::path::Path, out: &str) -> assert_cmd::Command {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "crawl",
+        "--url",
+        "http://127.0.0.1:1/docs/",
+        "--out",
+        temp.join(out).to_str().unwrap(),
+        "--delay-ms",
+        "0",
+        "--page-url",
+        "http://127.0.0.1:1/docs/intro",
+    ])
+    .env_remove("SITEBOOKIFY_LOG_FORMAT")
+    .env_remove("HTTP_PROXY")
+    .env_remove("http_proxy");
+    cmd
+}
+
+#[test]
+fn log_format_json_emits_one_object_per_event() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+
+    let output = crawl_unreachable(temp.path(), "raw")
+        .args(["--log-format", "json"])
+        .output()?;
+    assert!(output.status.success());
+    let events = String::from_utf8(output.stderr)?
+        .lines()
+        .map(serde_json::from_str::<serde_json::Value>)
+        .collect::<Result<Vec<_>, _>>()?;
+    let warn = events
+        .iter()
+        .find(|event| event["message"] == "page fetch failed")
+        .expect("page fetch warning logged");
+    assert_eq!(warn["level"], "WARN");
+    assert_eq!(warn["fields"]["url"], "http://127.0.0.1:1/docs/intro");
+    assert!(
+        chrono::DateTime::parse_from_rfc3339(warn["timestamp"].as_str().unwrap_or_default())
+            .is_ok()
+    );
+
+    // The env var selects the same format; the flag is optional.
+    let output = crawl_unreachable(temp.path(), "raw2")
+        .env("SITEBOOKIFY_LOG_FORMAT", "json")
+        .output()?;
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(
+        stderr
+            .lines()
+            .all(|line| serde_json::from_str::<serde_json::Value>(line).is_ok())
+    );
+
+    crawl_unreachable(temp.path(), "raw3")
+        .env("SITEBOOKIFY_LOG_FORMAT", "yaml")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid SITEBOOKIFY_LOG_FORMAT: yaml",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn quiet_hides_progress_but_keeps_summaries_and_warnings() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+
+    crawl_unreachable(temp.path(), "raw")
+        .env("SITEBOOKIFY_PROGRESS_INTERVAL_SECS", "0")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("crawl: progress"));
+
+    crawl_unreachable(temp.path(), "raw2")
+        .env("SITEBOOKIFY_PROGRESS_INTERVAL_SECS", "0")
+        .arg("--quiet")
+        .assert()
+        .success()
+        .stderr(
+            predicate::str::contains("crawl: progress")
+                .not()
+                .and(predicate::str::contains("page fetch failed"))
+                .and(predicate::str::contains("crawl: fetched page list")),
+        );
+
+    crawl_unreachable(temp.path(), "raw3")
+        .env("SITEBOOKIFY_PROGRESS_INTERVAL_SECS", "soon")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid SITEBOOKIFY_PROGRESS_INTERVAL_SECS: soon",
+        ));
+
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use std::fs;
+
+use predicates::prelude::*;
+use sitebookify::formats::ManifestRecord;
+
+mod fixture;
+
+fn record(id: &str, url: &str, title: &str) -> ManifestRecord {
+    ManifestRecord {
+        id: id.to_owned(),
+        url: url.to_owned(),
+        title: title.to_owned(),
+        path: url::Url::parse(url).unwrap().path().to_owned(),
+        extracted_md: format!("extracted/pages/{id}.md"),
+        base_url: None,
+        discovery_index: None,
+    }
+}
+
+fn read_ids(path: &std::path::Path) -> anyhow::Result<Vec<String>> {
+    fs::read_to_string(path)?
+        .lines()
+        .map(|line| Ok(serde_json::from_str::<ManifestRecord>(line)?.id))
+        .collect()
+}
+
+#[test]
+fn manifest_merge_concatenates_and_dedupes_by_id() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let a = temp.path().join("a.jsonl");
+    let b = temp.path().join("b.jsonl");
+    fixture::write_manifest(
+        &a,
+        &[
+            record("p_a", "https://example.com/a", "Alpha"),
+            record("p_b", "https://example.com/b/", "Beta"),
+        ],
+    )?;
+    fixture::write_manifest(
+        &b,
+        &[
+            record("p_b", "https://example.com/b/", "Beta"),
+            record("p_b2", "https://example.com/b?utm_source=x", "Beta again"),
+            record("p_c", "https://example.com/c", "Gamma"),
+        ],
+    )?;
+
+    let merged = temp.path().join("merged.jsonl");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args(["manifest", "merge", "--inputs"])
+        .arg(&a)
+        .arg(&b)
+        .arg("--out")
+        .arg(&merged)
+        .assert()
+        .success();
+    assert_eq!(read_ids(&merged)?, ["p_a", "p_b", "p_b2", "p_c"]);
+
+    let merged = temp.path().join("merged-by-url.jsonl");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args(["manifest", "merge", "--dedupe-by-url", "--inputs"])
+        .arg(&a)
+        .arg(&b)
+        .arg("--out")
+        .arg(&merged)
+        .assert()
+        .success();
+    assert_eq!(read_ids(&merged)?, ["p_a", "p_b", "p_c"]);
+
+    Ok(())
+}
+
+#[test]
+fn manifest_merge_rejects_conflicting_ids() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let a = temp.path().join("a.jsonl");
+    let b = temp.path().join("b.jsonl");
+    fixture::write_manifest(&a, &[record("p_a", "https://example.com/a", "Alpha")])?;
+    fixture::write_manifest(&b, &[record("p_a", "https://example.com/a", "Renamed")])?;
+
+    let merged = temp.path().join("merged.jsonl");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args(["manifest", "merge", "--inputs"])
+        .arg(&a)
+        .arg(&b)
+        .arg("--out")
+        .arg(&merged)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "conflicting manifest records for id p_a",
+        ));
+    assert!(!merged.exists());
+
+    Ok(())
+}
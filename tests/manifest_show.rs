@@ -0,0 +1,126 @@
+use predicates::prelude::*;
+use sitebookify::formats::ManifestRecord;
+
+mod fixture;
+
+fn write_manifest(dir: &std::path::Path) -> anyhow::Result<std::path::PathBuf> {
+    let records = [("p_a", "Alpha"), ("p_b", "Beta")].map(|(id, title)| ManifestRecord {
+        id: id.to_owned(),
+        url: format!("https://example.com/{id}"),
+        title: title.to_owned(),
+        path: format!("/{id}"),
+        extracted_md: format!("extracted/pages/{id}.md"),
+        base_url: (id == "p_b").then(|| "https://example.com/base/".to_owned()),
+        discovery_index: (id == "p_a").then_some(0),
+    });
+    let path = dir.join("manifest.jsonl");
+    fixture::write_manifest(&path, &records)?;
+    Ok(path)
+}
+
+#[test]
+fn manifest_show_projects_fields() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let manifest = write_manifest(temp.path())?;
+    let manifest = manifest.to_str().unwrap();
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    let output = cmd
+        .args([
+            "manifest",
+            "show",
+            "--manifest",
+            manifest,
+            "--format",
+            "json",
+            "--pretty",
+            "--fields",
+            "id,title",
+        ])
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("\n  {\n    \"id\": \"p_a\",\n    \"title\": \"Alpha\"\n  }"));
+    let rows: serde_json::Value = serde_json::from_str(&stdout)?;
+    assert_eq!(rows.as_array().unwrap().len(), 2);
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args(["manifest", "show", "--manifest", manifest])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "{\"id\":\"p_a\",\"url\":\"https://example.com/p_a\",\"title\":\"Alpha\",\"path\":\"/p_a\",",
+        ));
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args(["manifest", "show", "--manifest", manifest])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "\"extracted_md\":\"extracted/pages/p_a.md\",\"discovery_index\":0}\n",
+        ))
+        .stdout(predicate::str::contains(
+            "\"extracted_md\":\"extracted/pages/p_b.md\",\"base_url\":\"https://example.com/base/\"}\n",
+        ));
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "manifest",
+        "show",
+        "--manifest",
+        manifest,
+        "--fields",
+        "id,base_url,discovery_index",
+    ])
+    .assert()
+    .success()
+    .stdout(
+        "{\"id\":\"p_a\",\"base_url\":null,\"discovery_index\":0}\n\
+{\"id\":\"p_b\",\"base_url\":\"https://example.com/base/\",\"discovery_index\":null}\n",
+    );
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "manifest",
+        "show",
+        "--manifest",
+        manifest,
+        "--format",
+        "table",
+        "--fields",
+        "id,discovery_index",
+    ])
+    .assert()
+    .success()
+    .stdout("id\tdiscovery_index\np_a\t0\np_b\t\n");
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "manifest",
+        "show",
+        "--manifest",
+        manifest,
+        "--format",
+        "table",
+    ])
+    .assert()
+    .success()
+    .stdout(
+        "id\turl\ttitle\np_a\thttps://example.com/p_a\tAlpha\np_b\thttps://example.com/p_b\tBeta\n",
+    );
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "manifest",
+        "show",
+        "--manifest",
+        manifest,
+        "--fields",
+        "id,nope",
+    ])
+    .assert()
+    .failure()
+    .stderr(predicate::str::contains("unknown manifest field: nope"));
+
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use std::fs;
+
+use predicates::prelude::*;
+use sitebookify::formats::ManifestRecord;
+
+mod fixture;
+
+fn record(id: &str, url: &str) -> ManifestRecord {
+    ManifestRecord {
+        id: id.to_owned(),
+        url: url.to_owned(),
+        title: id.to_owned(),
+        path: url::Url::parse(url)
+            .map(|url| url.path().to_owned())
+            .unwrap_or_default(),
+        extracted_md: format!("extracted/pages/{id}.md"),
+        base_url: None,
+        discovery_index: None,
+    }
+}
+
+fn read_ids(path: &std::path::Path) -> anyhow::Result<Vec<String>> {
+    fs::read_to_string(path)?
+        .lines()
+        .map(|line| Ok(serde_json::from_str::<ManifestRecord>(line)?.id))
+        .collect()
+}
+
+#[test]
+fn manifest_split_by_host_writes_one_file_per_host() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let manifest = temp.path().join("manifest.jsonl");
+    fixture::write_manifest(
+        &manifest,
+        &[
+            record("p_docs_a", "https://docs.example.com/a"),
+            record("p_blog_a", "https://blog.example.com/a"),
+            record("p_docs_b", "https://DOCS.example.com:8443/b"),
+            record("p_broken", "not a url"),
+        ],
+    )?;
+    let out_dir = temp.path().join("split");
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "manifest",
+        "split",
+        "--manifest",
+        manifest.to_str().unwrap(),
+        "--by",
+        "host",
+        "--out-dir",
+        out_dir.to_str().unwrap(),
+    ])
+    .assert()
+    .success()
+    .stderr(predicate::str::contains(
+        "skipping record without a parseable url host",
+    ));
+
+    let mut files = fs::read_dir(&out_dir)?
+        .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    files.sort();
+    assert_eq!(
+        files,
+        vec![
+            "manifest.blog.example.com.jsonl",
+            "manifest.docs.example.com.jsonl"
+        ]
+    );
+    assert_eq!(
+        read_ids(&out_dir.join("manifest.docs.example.com.jsonl"))?,
+        vec!["p_docs_a", "p_docs_b"]
+    );
+    assert_eq!(
+        read_ids(&out_dir.join("manifest.blog.example.com.jsonl"))?,
+        vec!["p_blog_a"]
+    );
+
+    // A second run refuses to overwrite and leaves the first output untouched.
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "manifest",
+        "split",
+        "--manifest",
+        manifest.to_str().unwrap(),
+        "--out-dir",
+        out_dir.to_str().unwrap(),
+    ])
+    .assert()
+    .failure()
+    .stderr(predicate::str::contains("manifest output already exists"));
+    Ok(())
+}
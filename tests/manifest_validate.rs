@@ -0,0 +1,93 @@
+use std::fs;
+use std::path::Path;
+
+use predicates::prelude::*;
+use sitebookify::formats::{ManifestRecord, TocPart};
+
+mod fixture;
+
+fn record(dir: &Path, id: &str) -> ManifestRecord {
+    ManifestRecord {
+        id: id.to_owned(),
+        url: format!("https://example.com/{id}"),
+        title: id.to_owned(),
+        path: format!("/{id}"),
+        extracted_md: dir.join(format!("{id}.md")).to_string_lossy().to_string(),
+        base_url: None,
+        discovery_index: None,
+    }
+}
+
+/// A manifest of `records` and a TOC whose one section lists `sources`.
+fn write_inputs(dir: &Path, records: &[ManifestRecord], sources: &[&str]) -> anyhow::Result<()> {
+    fixture::write_manifest(&dir.join("manifest.jsonl"), records)?;
+    let sources = sources
+        .iter()
+        .map(|id| (*id).to_owned())
+        .collect::<Vec<_>>();
+    fixture::write_toc(
+        &dir.join("toc.yaml"),
+        vec![TocPart {
+            title: "Part".to_owned(),
+            chapters: vec![fixture::chapter("ch01", "Chapter", &sources)],
+        }],
+    )
+}
+
+fn validate_cmd(dir: &Path) -> assert_cmd::Command {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "manifest",
+        "validate",
+        "--manifest",
+        dir.join("manifest.jsonl").to_str().unwrap(),
+        "--toc",
+        dir.join("toc.yaml").to_str().unwrap(),
+    ]);
+    cmd
+}
+
+#[test]
+fn manifest_validate_passes_and_warns_about_unreferenced_records() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let dir = temp.path();
+    for id in ["p_a", "p_b"] {
+        fs::write(dir.join(format!("{id}.md")), "# Page\n")?;
+    }
+    write_inputs(dir, &[record(dir, "p_a"), record(dir, "p_b")], &["p_a"])?;
+
+    validate_cmd(dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "warning: page not referenced by any section: p_b",
+        ))
+        .stdout(predicate::str::contains("0 error(s), 1 warning(s)"));
+    Ok(())
+}
+
+#[test]
+fn manifest_validate_fails_on_unknown_sources_and_missing_files() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let dir = temp.path();
+    fs::write(dir.join("p_a.md"), "# Page\n")?;
+    write_inputs(
+        dir,
+        &[record(dir, "p_a"), record(dir, "p_gone")],
+        &["p_a", "p_gone", "p_typo"],
+    )?;
+
+    validate_cmd(dir)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "error: unknown source id in chapter ch01: p_typo",
+        ))
+        .stdout(predicate::str::contains(
+            "error: extracted_md not found for p_gone",
+        ))
+        .stderr(predicate::str::contains(
+            "manifest validate failed: 2 error(s)",
+        ));
+    Ok(())
+}
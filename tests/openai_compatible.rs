@@ -0,0 +1,146 @@
+use std::fs;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use predicates::prelude::*;
+use sitebookify::formats::Toc;
+
+mod fixture;
+
+const SLUG: &str = "openai_compatible";
+/// `fixture::Page::id` of the `SLUG` page.
+const PAGE_ID: &str = "p_test_openai_compatible";
+
+/// Minimal OpenAI-compatible server: answers `/v1/responses` with a reasoning item followed by
+/// the message, and reports each request's `Authorization` header.
+fn spawn_compatible_server() -> (
+    String,
+    mpsc::Receiver<Option<String>>,
+    mpsc::Sender<()>,
+    thread::JoinHandle<()>,
+) {
+    let server = tiny_http::Server::http("127.0.0.1:0").expect("start tiny_http server");
+    let base_url = format!("http://{}/v1", server.server_addr());
+    let (auth_tx, auth_rx) = mpsc::channel::<Option<String>>();
+    let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
+    let handle = thread::spawn(move || {
+        while shutdown_rx.try_recv().is_err() {
+            let request = match server.recv_timeout(Duration::from_millis(50)) {
+                Ok(Some(req)) => req,
+                Ok(None) => continue,
+                Err(_) => break,
+            };
+            let authorization = request
+                .headers()
+                .iter()
+                .find(|h| h.field.equiv("Authorization"))
+                .map(|h| h.value.as_str().to_owned());
+            let _ = auth_tx.send(authorization);
+
+            let plan = serde_json::json!({
+                "book_title": "Local Book",
+                "chapters": [{
+                    "title": "Start",
+                    "intent": "Get going.",
+                    "reader_gains": ["Run it"],
+                    "sections": [{"title": "Setup", "sources": [PAGE_ID]}],
+                }],
+            });
+            let body = serde_json::json!({
+                "output": [
+                    {"type": "reasoning", "content": [{"type": "reasoning_text", "text": "draft: {\"chapters\": []}"}]},
+                    {"type": "message", "content": [{"type": "output_text", "text": plan.to_string()}]},
+                ],
+            });
+            let response = tiny_http::Response::from_string(body.to_string()).with_header(
+                tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                    .expect("build header"),
+            );
+            let _ = request.respond(response);
+        }
+    });
+    (base_url, auth_rx, shutdown_tx, handle)
+}
+
+fn toc_create(
+    manifest_path: &std::path::Path,
+    toc_path: &std::path::Path,
+    extra_args: &[&str],
+) -> assert_cmd::Command {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.env_remove("OPENAI_API_KEY")
+        .env_remove("SITEBOOKIFY_OPENAI_API_KEY")
+        .env_remove("SITEBOOKIFY_OPENAI_BASE_URL")
+        .env("SITEBOOKIFY_OPENAI_MAX_RETRIES", "0")
+        .args([
+            "toc",
+            "create",
+            "--manifest",
+            manifest_path.to_str().unwrap(),
+            "--out",
+            toc_path.to_str().unwrap(),
+            "--engine",
+            "openai",
+        ])
+        .args(extra_args);
+    cmd
+}
+
+#[test]
+fn compatible_server_works_without_api_key() -> anyhow::Result<()> {
+    let (base_url, auth_rx, shutdown_tx, handle) = spawn_compatible_server();
+    let temp = tempfile::TempDir::new()?;
+    let manifest_path =
+        fixture::write_page_manifest(temp.path(), &[fixture::Page::new(SLUG, "Body.")])?;
+
+    let toc_path = temp.path().join("toc.yaml");
+    toc_create(&manifest_path, &toc_path, &[])
+        .env("SITEBOOKIFY_OPENAI_BASE_URL", &base_url)
+        .assert()
+        .success();
+    let toc: Toc = serde_yaml::from_str(&fs::read_to_string(&toc_path)?)?;
+    assert_eq!(toc.book_title, "Local Book");
+    assert_eq!(auth_rx.try_iter().collect::<Vec<_>>(), vec![None]);
+
+    let toc_path = temp.path().join("toc-keyed.yaml");
+    toc_create(
+        &manifest_path,
+        &toc_path,
+        &["--openai-api-key-env", "LOCAL_LLM_KEY"],
+    )
+    .env("SITEBOOKIFY_OPENAI_BASE_URL", &base_url)
+    .env("LOCAL_LLM_KEY", "local-secret")
+    .assert()
+    .success();
+    assert_eq!(
+        auth_rx.try_iter().collect::<Vec<_>>(),
+        vec![Some("Bearer local-secret".to_owned())]
+    );
+
+    let _ = shutdown_tx.send(());
+    let _ = handle.join();
+    Ok(())
+}
+
+#[test]
+fn openai_base_url_still_requires_api_key() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let manifest_path =
+        fixture::write_page_manifest(temp.path(), &[fixture::Page::new(SLUG, "Body.")])?;
+
+    toc_create(&manifest_path, &temp.path().join("toc.yaml"), &[])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("missing OpenAI API key"));
+    toc_create(
+        &manifest_path,
+        &temp.path().join("toc.yaml"),
+        &["--openai-api-key-env", "LOCAL_LLM_KEY"],
+    )
+    .env_remove("LOCAL_LLM_KEY")
+    .assert()
+    .failure()
+    .stderr(predicate::str::contains("set LOCAL_LLM_KEY"));
+    Ok(())
+}
@@ -0,0 +1,192 @@
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use sitebookify::formats::Toc;
+
+mod fixture;
+mod openai_stub;
+
+fn page() -> fixture::Page {
+    fixture::Page::new("openai_retry", "Body text.")
+}
+
+#[test]
+fn toc_create_retries_transient_openai_failures() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let manifest_path = fixture::write_page_manifest(temp.path(), &[page()])?;
+
+    let openai = openai_stub::OpenAiStub::spawn(openai_stub::OpenAiStubConfig {
+        expected_reasoning_effort: Some("high".to_owned()),
+        rewrite_behavior: openai_stub::RewriteBehavior::EchoInput,
+        transient_failures: 2,
+    });
+
+    let toc_path = temp.path().join("toc.yaml");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.env("OPENAI_API_KEY", "test-key")
+        .env("SITEBOOKIFY_OPENAI_BASE_URL", &openai.base_url)
+        .env("SITEBOOKIFY_OPENAI_MODEL", "stub-model")
+        .args([
+            "toc",
+            "create",
+            "--manifest",
+            manifest_path.to_str().unwrap(),
+            "--out",
+            toc_path.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    assert_eq!(openai.failed_call_count(), 2);
+    let toc: Toc = serde_yaml::from_str(&fs::read_to_string(&toc_path)?)?;
+    assert!(!toc.parts.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn toc_create_gives_up_after_max_retries() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+
+    let manifest_path = fixture::write_page_manifest(temp.path(), &[page()])?;
+
+    let openai = openai_stub::OpenAiStub::spawn(openai_stub::OpenAiStubConfig {
+        expected_reasoning_effort: Some("high".to_owned()),
+        rewrite_behavior: openai_stub::RewriteBehavior::EchoInput,
+        transient_failures: usize::MAX,
+    });
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.env("OPENAI_API_KEY", "test-key")
+        .env("SITEBOOKIFY_OPENAI_BASE_URL", &openai.base_url)
+        .env("SITEBOOKIFY_OPENAI_MODEL", "stub-model")
+        .env("SITEBOOKIFY_OPENAI_MAX_RETRIES", "1")
+        .args([
+            "toc",
+            "create",
+            "--manifest",
+            manifest_path.to_str().unwrap(),
+            "--out",
+            temp.path().join("toc.yaml").to_str().unwrap(),
+        ])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("503"));
+
+    assert_eq!(openai.failed_call_count(), 2);
+    Ok(())
+}
+
+#[test]
+fn toc_create_sends_openai_account_headers() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let manifest_path = fixture::write_page_manifest(temp.path(), &[page()])?;
+
+    let openai = openai_stub::OpenAiStub::spawn(openai_stub::OpenAiStubConfig {
+        expected_reasoning_effort: Some("high".to_owned()),
+        rewrite_behavior: openai_stub::RewriteBehavior::EchoInput,
+        transient_failures: 0,
+    });
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.env("OPENAI_API_KEY", "test-key")
+        .env("SITEBOOKIFY_OPENAI_BASE_URL", &openai.base_url)
+        .env("SITEBOOKIFY_OPENAI_MODEL", "stub-model")
+        .env("OPENAI_ORG_ID", "org-from-env")
+        .env_remove("OPENAI_PROJECT_ID")
+        .args([
+            "toc",
+            "create",
+            "--manifest",
+            manifest_path.to_str().unwrap(),
+            "--out",
+            temp.path().join("toc.yaml").to_str().unwrap(),
+            "--openai-project",
+            "proj-from-flag",
+        ])
+        .assert()
+        .success();
+
+    assert_eq!(
+        openai.account_headers(),
+        vec![(
+            Some("org-from-env".to_owned()),
+            Some("proj-from-flag".to_owned())
+        )]
+    );
+    Ok(())
+}
+
+/// Answers the first `failures` requests with `429` and no `Retry-After`, then a fixed rewrite.
+/// Returns the base URL and the arrival time of every request.
+fn spawn_rate_limited_server(failures: usize) -> (String, Arc<Mutex<Vec<Instant>>>) {
+    let server = tiny_http::Server::http("127.0.0.1:0").expect("start server");
+    let base_url = format!("http://{}/v1", server.server_addr());
+    let arrivals = Arc::new(Mutex::new(Vec::new()));
+    let arrivals_in_server = Arc::clone(&arrivals);
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let mut arrivals = arrivals_in_server.lock().unwrap();
+            arrivals.push(Instant::now());
+            let response = if arrivals.len() <= failures {
+                tiny_http::Response::from_string("rate limited").with_status_code(429)
+            } else {
+                tiny_http::Response::from_string(r#"{"output_text":"Rewritten body."}"#)
+            };
+            drop(arrivals);
+            let _ = request.respond(response);
+        }
+    });
+    (base_url, arrivals)
+}
+
+#[test]
+fn book_render_backs_off_between_openai_retries() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let (toc_path, manifest_path) = fixture::write_inputs(temp.path(), &[page()])?;
+    let (base_url, arrivals) = spawn_rate_limited_server(2);
+
+    let book_dir = temp.path().join("book");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.env("OPENAI_API_KEY", "test-key")
+        .env("SITEBOOKIFY_OPENAI_BASE_URL", &base_url)
+        .env("SITEBOOKIFY_OPENAI_MODEL", "stub-model")
+        .env_remove("SITEBOOKIFY_OPENAI_MAX_RETRIES")
+        .args([
+            "book",
+            "render",
+            "--toc",
+            &toc_path,
+            "--manifest",
+            &manifest_path,
+            "--out",
+            book_dir.to_str().unwrap(),
+            "--engine",
+            "openai",
+            "--openai-retry-base-ms",
+            "200",
+            "--openai-retry-max-ms",
+            "300",
+        ])
+        .assert()
+        .success();
+
+    let ch01 = fs::read_to_string(book_dir.join("src").join("chapters").join("ch01.md"))?;
+    assert!(ch01.contains("Rewritten body."), "{ch01}");
+    let arrivals = arrivals.lock().unwrap().clone();
+    assert_eq!(arrivals.len(), 3);
+    // Jitter keeps each delay within [delay / 2, delay]: 200ms, then 400ms capped at 300ms.
+    let gaps = arrivals
+        .windows(2)
+        .map(|pair| pair[1] - pair[0])
+        .collect::<Vec<_>>();
+    assert!(gaps[0] >= Duration::from_millis(100), "{gaps:?}");
+    assert!(gaps[1] >= Duration::from_millis(150), "{gaps:?}");
+    assert!(
+        arrivals[2] - arrivals[0] >= Duration::from_millis(250),
+        "{gaps:?}"
+    );
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use std::fs;
+use std::path::Path;
+
+mod fixture;
+mod openai_stub;
+
+fn render(dir: &Path, name: &str, extra_args: &[&str]) -> anyhow::Result<(String, usize)> {
+    let page = fixture::Page {
+        markdown: "日本語の本文です。See https://example.com and `code` and [link](https://openai.com/).\n\n\
+```toml\n[dependencies]\nlwk_wollet = \"0.11.0\"\n```\n"
+            .to_owned(),
+        ..fixture::Page::new("openai_stream", "")
+    };
+    let (toc_path, manifest_path) = fixture::write_inputs(dir, &[page])?;
+    let openai = openai_stub::OpenAiStub::spawn(openai_stub::OpenAiStubConfig {
+        expected_reasoning_effort: Some("high".to_owned()),
+        rewrite_behavior: openai_stub::RewriteBehavior::EchoInput,
+        transient_failures: 0,
+    });
+
+    let book_dir = dir.join(name);
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.env("OPENAI_API_KEY", "test-key")
+        .env("SITEBOOKIFY_OPENAI_BASE_URL", &openai.base_url)
+        .env("SITEBOOKIFY_OPENAI_MODEL", "stub-model")
+        .args([
+            "book",
+            "render",
+            "--toc",
+            &toc_path,
+            "--manifest",
+            &manifest_path,
+            "--out",
+            book_dir.to_str().unwrap(),
+            "--engine",
+            "openai",
+        ])
+        .args(extra_args)
+        .assert()
+        .success();
+
+    let chapter = fs::read_to_string(book_dir.join("src").join("chapters").join("ch01.md"))?;
+    Ok((chapter, openai.streamed_call_count()))
+}
+
+#[test]
+fn openai_stream_accumulates_the_same_output_as_a_plain_response() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+
+    let (plain, plain_streamed) = render(temp.path(), "plain", &[])?;
+    let (streamed, streamed_calls) = render(temp.path(), "streamed", &["--openai-stream"])?;
+
+    assert_eq!(plain_streamed, 0);
+    assert_eq!(streamed_calls, 1);
+    assert_eq!(streamed, plain);
+    assert!(streamed.contains("lwk_wollet = \"0.11.0\""), "{streamed}");
+    assert!(streamed.contains("日本語の本文です。"), "{streamed}");
+    Ok(())
+}
@@ -1,16 +1,21 @@
-use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
 use anyhow::Context as _;
 use serde_json::Value;
 
+/// `(OpenAI-Organization, OpenAI-Project)` request headers.
+pub type AccountHeaders = (Option<String>, Option<String>);
+
 #[derive(Debug, Clone)]
 pub struct OpenAiStubConfig {
     pub expected_reasoning_effort: Option<String>,
     pub rewrite_behavior: RewriteBehavior,
+    /// Number of initial `/v1/responses` requests answered with `503` + `Retry-After: 0`.
+    pub transient_failures: usize,
 }
 
 #[allow(dead_code)]
@@ -19,6 +24,10 @@ pub enum RewriteBehavior {
     EchoInput,
     WrapTokens,
     DropTokens,
+    /// Drops tokens unless the request uses the given model (then echoes input).
+    DropTokensUnlessModel(&'static str),
+    /// Echoes input with ` [rewritten]` appended, so a rewrite can be told from its source.
+    MarkRewritten,
 }
 
 pub struct OpenAiStub {
@@ -27,6 +36,14 @@ pub struct OpenAiStub {
     handle: Option<thread::JoinHandle<()>>,
     #[allow(dead_code)]
     rewrite_calls: Arc<AtomicUsize>,
+    #[allow(dead_code)]
+    failed_calls: Arc<AtomicUsize>,
+    #[allow(dead_code)]
+    account_headers: Arc<Mutex<Vec<AccountHeaders>>>,
+    #[allow(dead_code)]
+    rewrite_prompts: Arc<Mutex<Vec<String>>>,
+    #[allow(dead_code)]
+    streamed_calls: Arc<AtomicUsize>,
 }
 
 impl OpenAiStub {
@@ -36,6 +53,14 @@ impl OpenAiStub {
         let base_url = format!("http://{addr}/v1");
         let rewrite_calls = Arc::new(AtomicUsize::new(0));
         let rewrite_calls_in_server = Arc::clone(&rewrite_calls);
+        let failed_calls = Arc::new(AtomicUsize::new(0));
+        let failed_calls_in_server = Arc::clone(&failed_calls);
+        let account_headers = Arc::new(Mutex::new(Vec::new()));
+        let account_headers_in_server = Arc::clone(&account_headers);
+        let rewrite_prompts = Arc::new(Mutex::new(Vec::new()));
+        let rewrite_prompts_in_server = Arc::clone(&rewrite_prompts);
+        let streamed_calls = Arc::new(AtomicUsize::new(0));
+        let streamed_calls_in_server = Arc::clone(&streamed_calls);
 
         let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
 
@@ -59,6 +84,30 @@ impl OpenAiStub {
                     continue;
                 }
 
+                let header_value = |name: &'static str| {
+                    request
+                        .headers()
+                        .iter()
+                        .find(|h| h.field.equiv(name))
+                        .map(|h| h.value.as_str().to_owned())
+                };
+                account_headers_in_server.lock().unwrap().push((
+                    header_value("OpenAI-Organization"),
+                    header_value("OpenAI-Project"),
+                ));
+
+                if failed_calls_in_server.load(Ordering::Relaxed) < config.transient_failures {
+                    failed_calls_in_server.fetch_add(1, Ordering::Relaxed);
+                    let header = tiny_http::Header::from_bytes(&b"Retry-After"[..], &b"0"[..])
+                        .expect("build header");
+                    let _ = request.respond(
+                        tiny_http::Response::from_string("service unavailable")
+                            .with_status_code(503)
+                            .with_header(header),
+                    );
+                    continue;
+                }
+
                 let mut body = String::new();
                 if request.as_reader().read_to_string(&mut body).is_err() {
                     let _ = request.respond(
@@ -116,7 +165,12 @@ impl OpenAiStub {
                     }
                 } else if prompt.contains("BEGIN_MARKDOWN") {
                     rewrite_calls_in_server.fetch_add(1, Ordering::Relaxed);
-                    match rewrite_response(prompt, config.rewrite_behavior) {
+                    rewrite_prompts_in_server
+                        .lock()
+                        .unwrap()
+                        .push(prompt.to_owned());
+                    let model = parsed.get("model").and_then(|v| v.as_str()).unwrap_or("");
+                    match rewrite_response(prompt, model, config.rewrite_behavior) {
                         Ok(text) => text,
                         Err(err) => {
                             let _ = request.respond(
@@ -136,6 +190,21 @@ impl OpenAiStub {
                     continue;
                 };
 
+                if parsed.get("stream").and_then(|v| v.as_bool()) == Some(true) {
+                    streamed_calls_in_server.fetch_add(1, Ordering::Relaxed);
+                    let header = tiny_http::Header::from_bytes(
+                        &b"Content-Type"[..],
+                        &b"text/event-stream"[..],
+                    )
+                    .expect("build header");
+                    let _ = request.respond(
+                        tiny_http::Response::from_string(event_stream_body(&output_text))
+                            .with_status_code(200)
+                            .with_header(header),
+                    );
+                    continue;
+                }
+
                 let response_body = serde_json::json!({
                     "id": "resp_stub",
                     "object": "response",
@@ -167,9 +236,36 @@ impl OpenAiStub {
             shutdown_tx: Some(shutdown_tx),
             handle: Some(handle),
             rewrite_calls,
+            failed_calls,
+            account_headers,
+            rewrite_prompts,
+            streamed_calls,
         }
     }
 
+    /// Number of `/v1/responses` requests that asked for `"stream": true`.
+    #[allow(dead_code)]
+    pub fn streamed_call_count(&self) -> usize {
+        self.streamed_calls.load(Ordering::Relaxed)
+    }
+
+    /// Prompts of every rewrite request, in arrival order.
+    #[allow(dead_code)]
+    pub fn rewrite_prompts(&self) -> Vec<String> {
+        self.rewrite_prompts.lock().unwrap().clone()
+    }
+
+    /// Account headers of every `/v1/responses` request.
+    #[allow(dead_code)]
+    pub fn account_headers(&self) -> Vec<AccountHeaders> {
+        self.account_headers.lock().unwrap().clone()
+    }
+
+    #[allow(dead_code)]
+    pub fn failed_call_count(&self) -> usize {
+        self.failed_calls.load(Ordering::Relaxed)
+    }
+
     #[allow(dead_code)]
     pub fn rewrite_call_count(&self) -> usize {
         self.rewrite_calls.load(Ordering::Relaxed)
@@ -187,6 +283,28 @@ impl Drop for OpenAiStub {
     }
 }
 
+/// Server-sent events for `output_text`, split into small deltas (so placeholder tokens span
+/// several chunks) and ending with `response.completed`.
+fn event_stream_body(output_text: &str) -> String {
+    let chars = output_text.chars().collect::<Vec<_>>();
+    let mut body = String::new();
+    for chunk in chars.chunks(7) {
+        let event = serde_json::json!({
+            "type": "response.output_text.delta",
+            "delta": chunk.iter().collect::<String>(),
+        });
+        body.push_str(&format!(
+            "event: response.output_text.delta\ndata: {event}\n\n"
+        ));
+    }
+    let completed = serde_json::json!({
+        "type": "response.completed",
+        "response": { "id": "resp_stub", "status": "completed" },
+    });
+    body.push_str(&format!("event: response.completed\ndata: {completed}\n\n"));
+    body
+}
+
 fn extract_between<'a>(text: &'a str, begin: &str, end: &str) -> Option<&'a str> {
     let start = text.find(begin)? + begin.len();
     let rest = &text[start..];
@@ -242,9 +360,17 @@ fn toc_response(prompt: &str) -> anyhow::Result<String> {
     .to_string())
 }
 
-fn rewrite_response(prompt: &str, behavior: RewriteBehavior) -> anyhow::Result<String> {
-    if matches!(behavior, RewriteBehavior::DropTokens) {
-        return Ok("short summary".to_owned());
+fn rewrite_response(
+    prompt: &str,
+    model: &str,
+    behavior: RewriteBehavior,
+) -> anyhow::Result<String> {
+    match behavior {
+        RewriteBehavior::DropTokens => return Ok("short summary".to_owned()),
+        RewriteBehavior::DropTokensUnlessModel(keep_model) if keep_model != model => {
+            return Ok("short summary".to_owned());
+        }
+        _ => {}
     }
 
     let begin = "BEGIN_MARKDOWN\n";
@@ -253,10 +379,9 @@ fn rewrite_response(prompt: &str, behavior: RewriteBehavior) -> anyhow::Result<S
         .ok_or_else(|| anyhow::anyhow!("missing markdown markers: {begin:?} .. {end:?}"))?;
 
     let out = match behavior {
-        RewriteBehavior::EchoInput => raw.to_owned(),
-        RewriteBehavior::WrapTokens => raw
-            .replace("{{SBY_TOKEN_", "{{{SBY_TOKEN_")
-            .replace("}}", "}}}"),
+        RewriteBehavior::EchoInput | RewriteBehavior::DropTokensUnlessModel(_) => raw.to_owned(),
+        RewriteBehavior::WrapTokens => raw.replace("{{SBY_", "{{{SBY_").replace("}}", "}}}"),
+        RewriteBehavior::MarkRewritten => format!("{raw} [rewritten]"),
         RewriteBehavior::DropTokens => unreachable!("handled above"),
     };
 
@@ -4,8 +4,7 @@ use sitebookify::formats::{ManifestRecord, Toc, TocChapter, TocPart, TocSection}
 
 mod openai_stub;
 
-#[test]
-fn rewrite_splits_page_markdown_by_headings() -> anyhow::Result<()> {
+fn render_heading_page(extra_args: &[&str]) -> anyhow::Result<(usize, String)> {
     let temp = tempfile::TempDir::new()?;
 
     let page_id =
@@ -37,6 +36,8 @@ Usage examples.\n"
         title: "Test Page".to_owned(),
         path: "/docs".to_owned(),
         extracted_md: extracted_path.to_string_lossy().to_string(),
+        base_url: None,
+        discovery_index: None,
     };
     fs::write(
         &manifest_path,
@@ -78,6 +79,7 @@ Usage examples.\n"
     let openai = openai_stub::OpenAiStub::spawn(openai_stub::OpenAiStubConfig {
         expected_reasoning_effort: Some("high".to_owned()),
         rewrite_behavior: openai_stub::RewriteBehavior::EchoInput,
+        transient_failures: 0,
     });
 
     let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
@@ -96,19 +98,36 @@ Usage examples.\n"
             "--engine",
             "openai",
         ])
+        .args(extra_args)
         .assert()
         .success();
 
-    assert!(
-        openai.rewrite_call_count() > 1,
-        "expected multiple rewrite calls, got {}",
-        openai.rewrite_call_count()
-    );
-
     let ch01_path = book_dir.join("src").join("chapters").join("ch01.md");
     let ch01 = fs::read_to_string(ch01_path)?;
+    Ok((openai.rewrite_call_count(), ch01))
+}
+
+#[test]
+fn rewrite_splits_page_markdown_by_headings() -> anyhow::Result<()> {
+    let (rewrite_calls, ch01) = render_heading_page(&[])?;
+
+    assert!(
+        rewrite_calls > 1,
+        "expected multiple rewrite calls, got {rewrite_calls}"
+    );
     assert!(ch01.contains("## Install"));
     assert!(ch01.contains("## Usage"));
 
     Ok(())
 }
+
+#[test]
+fn rewrite_page_granularity_uses_single_call() -> anyhow::Result<()> {
+    let (rewrite_calls, ch01) = render_heading_page(&["--rewrite-granularity", "page"])?;
+
+    assert_eq!(rewrite_calls, 1);
+    assert!(ch01.contains("Install steps."));
+    assert!(ch01.contains("Usage examples."));
+
+    Ok(())
+}
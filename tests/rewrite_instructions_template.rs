@@ -0,0 +1,140 @@
+use std::fs;
+use std::path::Path;
+
+use predicates::prelude::*;
+use sitebookify::formats::{TocChapter, TocPart};
+
+mod fixture;
+mod openai_stub;
+
+const TEMPLATE: &str = "\
+You edit {language} handbooks for {{team}} readers.
+
+Chapter: {chapter_title} / Section {section_index}: {section_title} ({scope}, tone: {tone})
+Source: {title} <{url}>
+Goal: {prompt}
+
+Rules:
+{heading_rules}{token_rules}- Keep product names in English.
+";
+
+fn render_cmd(toc_path: &str, manifest_path: &str, book_dir: &Path) -> assert_cmd::Command {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "book",
+        "render",
+        "--toc",
+        toc_path,
+        "--manifest",
+        manifest_path,
+        "--out",
+        book_dir.to_str().unwrap(),
+    ]);
+    cmd
+}
+
+/// One page under a chapter with an intent, so `{goal}` has something to expand to.
+fn write_inputs(dir: &Path) -> anyhow::Result<(String, String)> {
+    let page = fixture::Page::new("instructions_template", "Body text.");
+    let (toc, manifest) = fixture::write_inputs(dir, std::slice::from_ref(&page))?;
+    fixture::write_toc(
+        Path::new(&toc),
+        vec![TocPart {
+            title: "Part".to_owned(),
+            chapters: vec![TocChapter {
+                intent: "Teach the basics.".to_owned(),
+                ..fixture::chapter("ch01", "Chapter", &[page.id()])
+            }],
+        }],
+    )?;
+    Ok((toc, manifest))
+}
+
+#[test]
+fn instructions_template_replaces_built_in_rewrite_instructions() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let (toc_path, manifest_path) = write_inputs(temp.path())?;
+    let template_path = temp.path().join("instructions.txt");
+    fs::write(&template_path, TEMPLATE)?;
+
+    let openai = openai_stub::OpenAiStub::spawn(openai_stub::OpenAiStubConfig {
+        expected_reasoning_effort: Some("high".to_owned()),
+        rewrite_behavior: openai_stub::RewriteBehavior::EchoInput,
+        transient_failures: 0,
+    });
+
+    let book_dir = temp.path().join("book");
+    render_cmd(&toc_path, &manifest_path, &book_dir)
+        .env("OPENAI_API_KEY", "test-key")
+        .env("SITEBOOKIFY_OPENAI_BASE_URL", &openai.base_url)
+        .env("SITEBOOKIFY_OPENAI_MODEL", "stub-model")
+        .args([
+            "--engine",
+            "openai",
+            "--language",
+            "English",
+            "--instructions-template",
+            template_path.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    let prompts = openai.rewrite_prompts();
+    assert_eq!(prompts.len(), 1);
+    let prompt = &prompts[0];
+    assert!(
+        prompt.starts_with("You edit English handbooks for {{team}} readers.\n"),
+        "{prompt}"
+    );
+    assert!(
+        prompt.contains("Chapter: Chapter / Section 1: Section [source: Test Page] ("),
+        "{prompt}"
+    );
+    assert!(
+        prompt.contains("(a single book section, tone: "),
+        "{prompt}"
+    );
+    assert!(prompt.contains("Source: Test Page <"), "{prompt}");
+    assert!(
+        prompt.contains("<https://example.com/docs/instructions_template>\n"),
+        "{prompt}"
+    );
+    assert!(prompt.contains("Goal: Teach the basics.\n"), "{prompt}");
+    assert!(
+        prompt.contains("- Do NOT output Markdown headings"),
+        "{prompt}"
+    );
+    assert!(
+        prompt.contains("- You MUST preserve placeholder tokens of the form {{SBY_"),
+        "{prompt}"
+    );
+    assert!(prompt.contains("- Keep product names in English.\n"));
+    assert!(!prompt.contains("You are a book editor"), "{prompt}");
+    assert!(prompt.contains("BEGIN_MARKDOWN\n"), "{prompt}");
+
+    let ch01 = fs::read_to_string(book_dir.join("src").join("chapters").join("ch01.md"))?;
+    assert!(ch01.contains("Body text."));
+    Ok(())
+}
+
+#[test]
+fn instructions_template_must_keep_token_rules() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let (toc_path, manifest_path) = write_inputs(temp.path())?;
+    let template_path = temp.path().join("instructions.txt");
+    fs::write(&template_path, "Rewrite {section_title} freely.\n")?;
+
+    render_cmd(&toc_path, &manifest_path, &temp.path().join("book"))
+        .args([
+            "--engine",
+            "noop",
+            "--instructions-template",
+            template_path.to_str().unwrap(),
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "instructions template must contain the {token_rules} marker",
+        ));
+    Ok(())
+}
@@ -0,0 +1,225 @@
+use std::fs;
+
+use sitebookify::formats::{ManifestRecord, Toc, TocChapter, TocPart, TocSection};
+
+mod openai_stub;
+
+const ALPHA: &str = "Alpha paragraph explains the first idea in enough words to matter.";
+const BETA: &str = "Beta paragraph follows with the second idea and a little more detail.";
+const GAMMA_ONE: &str = "Gamma opens a long paragraph.";
+const GAMMA_TWO: &str = "It keeps going with a second sentence that pushes it over the limit.";
+
+fn render_long_section(extra_args: &[&str]) -> anyhow::Result<(Vec<String>, String)> {
+    render_long_section_with(openai_stub::RewriteBehavior::EchoInput, extra_args)
+}
+
+fn render_long_section_with(
+    rewrite_behavior: openai_stub::RewriteBehavior,
+    extra_args: &[&str],
+) -> anyhow::Result<(Vec<String>, String)> {
+    let temp = tempfile::TempDir::new()?;
+
+    let page_id =
+        "p_test_max_section_chars_00000000000000000000000000000000000000000000000000000000";
+    let extracted_path = temp.path().join("extracted.md");
+    let extracted = format!(
+        "---\n\
+id: {page_id}\n\
+url: https://example.com/docs\n\
+retrieved_at: 2026-01-01T00:00:00Z\n\
+raw_html_path: raw/index.html\n\
+title: Test Page\n\
+---\n\
+\n\
+# Test Page\n\
+\n\
+## Guide\n\
+\n\
+{ALPHA}\n\
+\n\
+{BETA}\n\
+\n\
+{GAMMA_ONE} {GAMMA_TWO}\n"
+    );
+    fs::write(&extracted_path, extracted)?;
+
+    let manifest_path = temp.path().join("manifest.jsonl");
+    let record = ManifestRecord {
+        id: page_id.to_owned(),
+        url: "https://example.com/docs".to_owned(),
+        title: "Test Page".to_owned(),
+        path: "/docs".to_owned(),
+        extracted_md: extracted_path.to_string_lossy().to_string(),
+        base_url: None,
+        discovery_index: None,
+    };
+    fs::write(
+        &manifest_path,
+        format!("{}\n", serde_json::to_string(&record)?),
+    )?;
+
+    let toc_path = temp.path().join("toc.yaml");
+    let toc = Toc {
+        book_title: "Test Book".to_owned(),
+        parts: vec![TocPart {
+            title: "Part".to_owned(),
+            chapters: vec![TocChapter {
+                id: "ch01".to_owned(),
+                title: "Chapter".to_owned(),
+                intent: "Intent".to_owned(),
+                reader_gains: vec!["Gain".to_owned()],
+                sections: vec![TocSection {
+                    title: "Section".to_owned(),
+                    sources: vec![page_id.to_owned()],
+                }],
+            }],
+        }],
+    };
+    fs::write(&toc_path, serde_yaml::to_string(&toc)?)?;
+
+    let openai = openai_stub::OpenAiStub::spawn(openai_stub::OpenAiStubConfig {
+        expected_reasoning_effort: Some("high".to_owned()),
+        rewrite_behavior,
+        transient_failures: 0,
+    });
+
+    let book_dir = temp.path().join("book");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.env("OPENAI_API_KEY", "test-key")
+        .env("SITEBOOKIFY_OPENAI_BASE_URL", &openai.base_url)
+        .env("SITEBOOKIFY_OPENAI_MODEL", "stub-model")
+        .args([
+            "book",
+            "render",
+            "--toc",
+            toc_path.to_str().unwrap(),
+            "--manifest",
+            manifest_path.to_str().unwrap(),
+            "--out",
+            book_dir.to_str().unwrap(),
+            "--engine",
+            "openai",
+        ])
+        .args(extra_args)
+        .assert()
+        .success();
+
+    let ch01 = fs::read_to_string(book_dir.join("src").join("chapters").join("ch01.md"))?;
+    Ok((openai.rewrite_prompts(), ch01))
+}
+
+#[test]
+fn max_section_chars_splits_long_sections_at_paragraphs() -> anyhow::Result<()> {
+    let (prompts, ch01) = render_long_section(&["--max-section-chars", "90"])?;
+
+    // `## Guide` + Alpha fit together; Beta alone; Gamma only splits at its sentence end.
+    // Look each unit up instead of relying on the order prompts arrive in.
+    assert_eq!(prompts.len(), 4, "{prompts:#?}");
+    let prompt_with = |text: &str| {
+        let matching = prompts
+            .iter()
+            .filter(|prompt| prompt.contains(text))
+            .collect::<Vec<_>>();
+        assert_eq!(matching.len(), 1, "{text}: {prompts:#?}");
+        matching[0].clone()
+    };
+    assert!(prompt_with(ALPHA).contains("## Guide"));
+    assert!(!prompt_with(BETA).contains(ALPHA));
+    assert!(!prompt_with(GAMMA_ONE).contains(GAMMA_TWO));
+    assert!(!prompt_with(GAMMA_TWO).contains(BETA));
+
+    let positions = [ALPHA, BETA, GAMMA_ONE, GAMMA_TWO]
+        .map(|text| ch01.find(text).expect("rewritten text kept"));
+    assert!(positions.is_sorted(), "{ch01}");
+    Ok(())
+}
+
+#[test]
+fn sections_are_not_split_by_default() -> anyhow::Result<()> {
+    let (prompts, ch01) = render_long_section(&[])?;
+
+    assert_eq!(prompts.len(), 1);
+    assert!(ch01.contains(&format!("{GAMMA_ONE} {GAMMA_TWO}")));
+    Ok(())
+}
+
+#[test]
+fn chunk_overlap_shows_previous_chunk_tail_as_context_only() -> anyhow::Result<()> {
+    let (prompts, ch01) = render_long_section(&[
+        "--max-section-chars",
+        "90",
+        "--openai-chunk-overlap-chars",
+        "30",
+    ])?;
+
+    assert_eq!(prompts.len(), 4, "{prompts:#?}");
+    let context_of = |text: &str| {
+        let prompt = prompts
+            .iter()
+            .find(|prompt| {
+                prompt
+                    .split_once("BEGIN_MARKDOWN")
+                    .is_some_and(|(_, input)| input.contains(text))
+            })
+            .unwrap_or_else(|| panic!("{text}: {prompts:#?}"));
+        prompt
+            .split_once("BEGIN_CONTEXT\n")
+            .and_then(|(_, rest)| rest.split_once("\nEND_CONTEXT"))
+            .map(|(context, _)| context.to_owned())
+    };
+    assert_eq!(context_of(ALPHA), None);
+    // The last 30 characters of the previous chunk, starting at a word boundary.
+    assert_eq!(
+        context_of(BETA).as_deref(),
+        Some("in enough words to matter.")
+    );
+    assert_eq!(
+        context_of(GAMMA_ONE).as_deref(),
+        Some("idea and a little more detail.")
+    );
+    assert!(
+        prompts
+            .iter()
+            .filter(|prompt| prompt.contains("BEGIN_CONTEXT"))
+            .all(|prompt| prompt.contains("CONTEXT ONLY — do not re-output"))
+    );
+
+    // The context never reaches the output twice.
+    for text in [ALPHA, BETA, GAMMA_ONE, GAMMA_TWO] {
+        assert_eq!(ch01.matches(text).count(), 1, "{text}: {ch01}");
+    }
+    Ok(())
+}
+
+#[test]
+fn chunk_overlap_context_is_the_previous_chunks_rewrite() -> anyhow::Result<()> {
+    let (prompts, ch01) = render_long_section_with(
+        openai_stub::RewriteBehavior::MarkRewritten,
+        &[
+            "--max-section-chars",
+            "90",
+            "--openai-chunk-overlap-chars",
+            "30",
+        ],
+    )?;
+
+    assert_eq!(prompts.len(), 4, "{prompts:#?}");
+    let contexts = prompts
+        .iter()
+        .filter_map(|prompt| {
+            prompt
+                .split_once("BEGIN_CONTEXT\n")
+                .and_then(|(_, rest)| rest.split_once("\nEND_CONTEXT"))
+                .map(|(context, _)| context.to_owned())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(contexts.len(), 3, "{prompts:#?}");
+    assert!(
+        contexts
+            .iter()
+            .all(|context| context.ends_with("[rewritten]")),
+        "{contexts:#?}"
+    );
+    assert_eq!(ch01.matches("[rewritten]").count(), 4, "{ch01}");
+    Ok(())
+}
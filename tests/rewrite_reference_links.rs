@@ -0,0 +1,124 @@
+use std::fs;
+
+use sitebookify::formats::{ManifestRecord, Toc, TocChapter, TocPart, TocSection};
+
+mod openai_stub;
+
+#[test]
+fn rewrite_protects_reference_style_links() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+
+    let page_id =
+        "p_test_reference_links_00000000000000000000000000000000000000000000000000000000000000";
+    let extracted_path = temp.path().join("extracted.md");
+    let extracted = format!(
+        "---\n\
+id: {page_id}\n\
+url: https://example.com/docs\n\
+retrieved_at: 2026-01-01T00:00:00Z\n\
+raw_html_path: raw/index.html\n\
+title: Test Page\n\
+---\n\
+\n\
+See the [install guide][install], the [FAQ][] and the [Glossary].\n\
+\n\
+[install]: https://example.com/docs/install \"Install guide\"\n\
+[faq]: <https://example.com/faq>\n\
+\x20 [Glossary]: /docs/glossary\n\
+"
+    );
+    fs::write(&extracted_path, extracted)?;
+
+    let manifest_path = temp.path().join("manifest.jsonl");
+    let record = ManifestRecord {
+        id: page_id.to_owned(),
+        url: "https://example.com/docs".to_owned(),
+        title: "Test Page".to_owned(),
+        path: "/docs".to_owned(),
+        extracted_md: extracted_path.to_string_lossy().to_string(),
+        base_url: None,
+        discovery_index: None,
+    };
+    fs::write(
+        &manifest_path,
+        format!("{}\n", serde_json::to_string(&record)?),
+    )?;
+
+    let toc_path = temp.path().join("toc.yaml");
+    let toc = Toc {
+        book_title: "Test Book".to_owned(),
+        parts: vec![TocPart {
+            title: "Part".to_owned(),
+            chapters: vec![TocChapter {
+                id: "ch01".to_owned(),
+                title: "Chapter".to_owned(),
+                intent: "Intent".to_owned(),
+                reader_gains: vec!["Gain".to_owned()],
+                sections: vec![TocSection {
+                    title: "Section".to_owned(),
+                    sources: vec![page_id.to_owned()],
+                }],
+            }],
+        }],
+    };
+    fs::write(&toc_path, serde_yaml::to_string(&toc)?)?;
+
+    let book_dir = temp.path().join("book");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "book",
+        "init",
+        "--out",
+        book_dir.to_str().unwrap(),
+        "--title",
+        "Test Book",
+    ])
+    .assert()
+    .success();
+
+    let openai = openai_stub::OpenAiStub::spawn(openai_stub::OpenAiStubConfig {
+        expected_reasoning_effort: Some("high".to_owned()),
+        rewrite_behavior: openai_stub::RewriteBehavior::EchoInput,
+        transient_failures: 0,
+    });
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.env("OPENAI_API_KEY", "test-key")
+        .env("SITEBOOKIFY_OPENAI_BASE_URL", &openai.base_url)
+        .env("SITEBOOKIFY_OPENAI_MODEL", "stub-model")
+        .args([
+            "book",
+            "render",
+            "--toc",
+            toc_path.to_str().unwrap(),
+            "--manifest",
+            manifest_path.to_str().unwrap(),
+            "--out",
+            book_dir.to_str().unwrap(),
+            "--engine",
+            "openai",
+        ])
+        .assert()
+        .success();
+
+    let ch01_path = book_dir.join("src").join("chapters").join("ch01.md");
+    let ch01 = fs::read_to_string(ch01_path)?;
+
+    assert!(ch01.contains("See the [install guide][install], the [FAQ][] and the [Glossary].\n"));
+    assert!(ch01.contains("[install]: https://example.com/docs/install \"Install guide\"\n"));
+    assert!(ch01.contains("[faq]: <https://example.com/faq>\n"));
+    assert!(ch01.contains("  [Glossary]: /docs/glossary\n"));
+
+    let prompts = openai.rewrite_prompts();
+    assert_eq!(prompts.len(), 1);
+    let prompt = &prompts[0];
+    assert!(!prompt.contains("example.com"));
+    assert!(!prompt.contains("/docs/glossary"));
+    assert!(!prompt.contains("[install]"));
+    assert!(!prompt.contains("[Glossary]"));
+    // Link text stays visible to the model.
+    assert!(prompt.contains("[install guide]"));
+    assert!(prompt.contains("[FAQ]"));
+
+    Ok(())
+}
@@ -4,8 +4,14 @@ use sitebookify::formats::{ManifestRecord, Toc, TocChapter, TocPart, TocSection}
 
 mod openai_stub;
 
-#[test]
-fn rewrite_keeps_output_when_openai_drops_tokens() -> anyhow::Result<()> {
+struct Fixture {
+    _temp: tempfile::TempDir,
+    toc_path: std::path::PathBuf,
+    manifest_path: std::path::PathBuf,
+    book_dir: std::path::PathBuf,
+}
+
+fn setup() -> anyhow::Result<Fixture> {
     let temp = tempfile::TempDir::new()?;
 
     let page_id =
@@ -31,6 +37,8 @@ Here is https://example.com and `code` and [link](https://openai.com/).\n"
         title: "Test Page".to_owned(),
         path: "/docs".to_owned(),
         extracted_md: extracted_path.to_string_lossy().to_string(),
+        base_url: None,
+        discovery_index: None,
     };
     fs::write(
         &manifest_path,
@@ -69,9 +77,22 @@ Here is https://example.com and `code` and [link](https://openai.com/).\n"
     .assert()
     .success();
 
+    Ok(Fixture {
+        _temp: temp,
+        toc_path,
+        manifest_path,
+        book_dir,
+    })
+}
+
+#[test]
+fn rewrite_keeps_output_when_openai_drops_tokens() -> anyhow::Result<()> {
+    let fixture = setup()?;
+
     let _openai = openai_stub::OpenAiStub::spawn(openai_stub::OpenAiStubConfig {
         expected_reasoning_effort: Some("high".to_owned()),
         rewrite_behavior: openai_stub::RewriteBehavior::DropTokens,
+        transient_failures: 0,
     });
 
     let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
@@ -82,21 +103,69 @@ Here is https://example.com and `code` and [link](https://openai.com/).\n"
             "book",
             "render",
             "--toc",
-            toc_path.to_str().unwrap(),
+            fixture.toc_path.to_str().unwrap(),
             "--manifest",
-            manifest_path.to_str().unwrap(),
+            fixture.manifest_path.to_str().unwrap(),
             "--out",
-            book_dir.to_str().unwrap(),
+            fixture.book_dir.to_str().unwrap(),
             "--engine",
             "openai",
         ])
         .assert()
         .success();
 
-    let ch01_path = book_dir.join("src").join("chapters").join("ch01.md");
+    let ch01_path = fixture
+        .book_dir
+        .join("src")
+        .join("chapters")
+        .join("ch01.md");
     let ch01 = fs::read_to_string(ch01_path)?;
     assert!(ch01.contains("short summary"));
     assert!(!ch01.contains("Here is https://example.com and `code`"));
 
     Ok(())
 }
+
+#[test]
+fn rewrite_retries_with_fallback_model_when_tokens_are_dropped() -> anyhow::Result<()> {
+    let fixture = setup()?;
+
+    let openai = openai_stub::OpenAiStub::spawn(openai_stub::OpenAiStubConfig {
+        expected_reasoning_effort: Some("high".to_owned()),
+        rewrite_behavior: openai_stub::RewriteBehavior::DropTokensUnlessModel("strong-model"),
+        transient_failures: 0,
+    });
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.env("OPENAI_API_KEY", "test-key")
+        .env("SITEBOOKIFY_OPENAI_BASE_URL", &openai.base_url)
+        .env("SITEBOOKIFY_OPENAI_MODEL", "stub-model")
+        .args([
+            "book",
+            "render",
+            "--toc",
+            fixture.toc_path.to_str().unwrap(),
+            "--manifest",
+            fixture.manifest_path.to_str().unwrap(),
+            "--out",
+            fixture.book_dir.to_str().unwrap(),
+            "--engine",
+            "openai",
+            "--openai-fallback-model",
+            "strong-model",
+        ])
+        .assert()
+        .success();
+
+    let ch01_path = fixture
+        .book_dir
+        .join("src")
+        .join("chapters")
+        .join("ch01.md");
+    let ch01 = fs::read_to_string(ch01_path)?;
+    assert!(!ch01.contains("short summary"));
+    assert!(ch01.contains("Here is https://example.com and `code`"));
+    assert_eq!(openai.rewrite_call_count(), 2);
+
+    Ok(())
+}
@@ -37,6 +37,8 @@ lwk_wollet = \"0.11.0\"\n\
         title: "Test Page".to_owned(),
         path: "/docs".to_owned(),
         extracted_md: extracted_path.to_string_lossy().to_string(),
+        base_url: None,
+        discovery_index: None,
     };
     fs::write(
         &manifest_path,
@@ -78,6 +80,7 @@ lwk_wollet = \"0.11.0\"\n\
     let _openai = openai_stub::OpenAiStub::spawn(openai_stub::OpenAiStubConfig {
         expected_reasoning_effort: Some("high".to_owned()),
         rewrite_behavior: openai_stub::RewriteBehavior::WrapTokens,
+        transient_failures: 0,
     });
 
     let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
@@ -0,0 +1,120 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod fixture;
+mod openai_stub;
+
+const EXAMPLES_YAML: &str = "\
+- input: |
+    Click the button to save.
+  output: |
+    保存するには、ボタンを押してください。
+- input: |
+    Errors are logged.
+  output: |
+    エラーはログに記録されます。
+";
+
+#[test]
+fn tone_examples_are_included_in_rewrite_prompt() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let (toc_path, manifest_path) = fixture::write_inputs(
+        temp.path(),
+        &[fixture::Page::new("tone_examples", "Body text.")],
+    )?;
+    let examples_path = temp.path().join("tone.yaml");
+    fs::write(&examples_path, EXAMPLES_YAML)?;
+
+    let openai = openai_stub::OpenAiStub::spawn(openai_stub::OpenAiStubConfig {
+        expected_reasoning_effort: Some("high".to_owned()),
+        rewrite_behavior: openai_stub::RewriteBehavior::EchoInput,
+        transient_failures: 0,
+    });
+
+    let book_dir = temp.path().join("book");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.env("OPENAI_API_KEY", "test-key")
+        .env("SITEBOOKIFY_OPENAI_BASE_URL", &openai.base_url)
+        .env("SITEBOOKIFY_OPENAI_MODEL", "stub-model")
+        .args([
+            "book",
+            "render",
+            "--toc",
+            &toc_path,
+            "--manifest",
+            &manifest_path,
+            "--out",
+            book_dir.to_str().unwrap(),
+            "--engine",
+            "openai",
+            "--tone-example-file",
+            examples_path.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    let prompts = openai.rewrite_prompts();
+    assert_eq!(prompts.len(), 1);
+    let prompt = &prompts[0];
+    assert!(prompt.contains("Tone examples:"), "{prompt}");
+    assert!(prompt.contains(
+        "EXAMPLE 1 INPUT\nClick the button to save.\nEXAMPLE 1 OUTPUT\n保存するには、ボタンを押してください。\n"
+    ));
+    assert!(prompt.contains("EXAMPLE 2 OUTPUT\nエラーはログに記録されます。\n"));
+    assert!(prompt.find("END_EXAMPLE 2") < prompt.find("BEGIN_MARKDOWN"));
+
+    let ch01 = fs::read_to_string(book_dir.join("src").join("chapters").join("ch01.md"))?;
+    assert!(ch01.contains("Body text."));
+    Ok(())
+}
+
+#[test]
+fn tone_example_file_must_hold_pairs() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let (toc_path, manifest_path) = fixture::write_inputs(
+        temp.path(),
+        &[fixture::Page::new("tone_examples", "Body text.")],
+    )?;
+
+    for (name, contents, message) in [
+        (
+            "missing.yaml",
+            "- input: only input\n",
+            "parse tone examples",
+        ),
+        ("empty.yaml", "[]\n", "has no pairs"),
+        (
+            "many.yaml",
+            &"- {input: a, output: b}\n".repeat(6),
+            "too many tone examples: 6",
+        ),
+        (
+            "long.yaml",
+            &format!("- input: {}\n  output: b\n", "x".repeat(9_000)),
+            "tone examples are too long",
+        ),
+    ] {
+        let examples_path = temp.path().join(name);
+        fs::write(&examples_path, contents)?;
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+        cmd.args([
+            "book",
+            "render",
+            "--toc",
+            &toc_path,
+            "--manifest",
+            &manifest_path,
+            "--out",
+            temp.path().join("book").to_str().unwrap(),
+            "--engine",
+            "noop",
+            "--tone-example-file",
+            examples_path.to_str().unwrap(),
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(message));
+    }
+    Ok(())
+}
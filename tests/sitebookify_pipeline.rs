@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 use std::fs;
-use std::io::Read as _;
+use std::io::{Read as _, Write as _};
 use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use std::thread;
@@ -196,6 +196,7 @@ fn pipeline_generates_mdbook_with_sources() -> anyhow::Result<()> {
     let _openai = openai_stub::OpenAiStub::spawn(openai_stub::OpenAiStubConfig {
         expected_reasoning_effort: Some("high".to_owned()),
         rewrite_behavior: openai_stub::RewriteBehavior::EchoInput,
+        transient_failures: 0,
     });
 
     let workspace_dir = temp.path().join("workspace");
@@ -490,3 +491,2194 @@ fn pipeline_generates_mdbook_with_sources() -> anyhow::Result<()> {
     let _ = server_handle.join();
     Ok(())
 }
+
+#[test]
+fn crawl_with_page_list_fetches_only_given_urls() -> anyhow::Result<()> {
+    let (base_url, shutdown_tx, server_handle) = spawn_docs_server();
+    let temp = tempfile::TempDir::new()?;
+    let raw_dir = temp.path().join("raw");
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "crawl",
+        "--url",
+        &format!("{base_url}/docs/"),
+        "--out",
+        raw_dir.to_str().unwrap(),
+        "--delay-ms",
+        "0",
+        "--page-url",
+        &format!("{base_url}/outside"),
+        "--page-url",
+        &format!("{base_url}/docs/intro"),
+        "--page-url",
+        &format!("{base_url}/docs/intro/#top"),
+    ])
+    .assert()
+    .success();
+
+    let crawl_records: Vec<CrawlRecord> = fs::read_to_string(raw_dir.join("crawl.jsonl"))?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).expect("parse crawl record json"))
+        .collect();
+    let urls: Vec<&str> = crawl_records
+        .iter()
+        .map(|record| record.normalized_url.as_str())
+        .collect();
+    // Records keep the `--page-url` order; the duplicate is dropped where it repeats.
+    assert_eq!(
+        urls,
+        vec![
+            format!("{base_url}/outside"),
+            format!("{base_url}/docs/intro"),
+        ]
+    );
+    assert!(crawl_records.iter().all(|r| r.raw_html_path.is_some()));
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "crawl",
+        "--url",
+        &format!("{base_url}/docs/"),
+        "--out",
+        temp.path().join("raw2").to_str().unwrap(),
+        "--page-url",
+        &format!("{base_url}/docs/intro"),
+        "--page-url",
+        "ftp://example.com/file",
+    ])
+    .assert()
+    .failure()
+    .stderr(predicate::str::contains("http/https"));
+
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.join();
+    Ok(())
+}
+
+#[test]
+fn crawl_verify_reports_corrupt_snapshot() -> anyhow::Result<()> {
+    let (base_url, shutdown_tx, server_handle) = spawn_docs_server();
+    let temp = tempfile::TempDir::new()?;
+    let raw_dir = temp.path().join("raw");
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "crawl",
+        "--url",
+        &format!("{base_url}/docs/"),
+        "--out",
+        raw_dir.to_str().unwrap(),
+        "--delay-ms",
+        "0",
+    ])
+    .assert()
+    .success();
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args(["crawl", "verify", "--raw", raw_dir.to_str().unwrap()])
+        .assert()
+        .success();
+
+    let crawl_records: Vec<CrawlRecord> = fs::read_to_string(raw_dir.join("crawl.jsonl"))?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).expect("parse crawl record json"))
+        .collect();
+    let saved: Vec<&str> = crawl_records
+        .iter()
+        .filter_map(|r| r.raw_html_path.as_deref())
+        .collect();
+    assert!(saved.len() >= 2, "expected >= 2 saved pages");
+    assert!(
+        crawl_records
+            .iter()
+            .filter(|r| r.raw_html_path.is_some())
+            .all(|r| r.raw_html_sha256.is_some())
+    );
+
+    fs::write(saved[0], "<html>tampered</html>")?;
+    fs::remove_file(saved[1])?;
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args(["crawl", "verify", "--raw", raw_dir.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("mismatched:"))
+        .stdout(predicate::str::contains("missing:"))
+        .stderr(predicate::str::contains("1 missing, 0 empty, 1 mismatched"));
+
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.join();
+    Ok(())
+}
+
+fn spawn_redirect_server() -> (String, mpsc::Sender<()>, thread::JoinHandle<()>) {
+    let server = tiny_http::Server::http("127.0.0.1:0").expect("start tiny_http server");
+    let base_url = format!("http://{}", server.server_addr());
+    let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
+    let handle = thread::spawn(move || {
+        let html_header = tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..])
+            .expect("build header");
+        while shutdown_rx.try_recv().is_err() {
+            let request = match server.recv_timeout(Duration::from_millis(50)) {
+                Ok(Some(req)) => req,
+                Ok(None) => continue,
+                Err(_) => break,
+            };
+            let response = match request.url() {
+                "/docs/" | "/docs" => tiny_http::Response::from_string(
+                    "<html><head><title>Docs</title></head><body><main><h1>Docs</h1>\
+<a href=\"/docs/old\">Old</a> <a href=\"/docs/new\">New</a></main></body></html>",
+                )
+                .with_header(html_header.clone()),
+                "/docs/old" => tiny_http::Response::from_string("moved")
+                    .with_status_code(301)
+                    .with_header(
+                        tiny_http::Header::from_bytes(&b"Location"[..], &b"/docs/new"[..])
+                            .expect("build header"),
+                    ),
+                "/docs/new" => tiny_http::Response::from_string(
+                    "<html><head><title>New</title></head><body><main><h1>New</h1>\
+<p>The page formerly known as old.</p></main></body></html>",
+                )
+                .with_header(html_header.clone()),
+                _ => tiny_http::Response::from_string("not found").with_status_code(404),
+            };
+            let _ = request.respond(response);
+        }
+    });
+    (base_url, shutdown_tx, handle)
+}
+
+fn read_crawl_records(raw_dir: &Path) -> anyhow::Result<Vec<CrawlRecord>> {
+    Ok(fs::read_to_string(raw_dir.join("crawl.jsonl"))?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).expect("parse crawl record json"))
+        .collect())
+}
+
+#[test]
+fn crawl_records_redirects_under_final_url() -> anyhow::Result<()> {
+    let (base_url, shutdown_tx, server_handle) = spawn_redirect_server();
+    let temp = tempfile::TempDir::new()?;
+
+    let raw_dir = temp.path().join("raw");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "crawl",
+        "--url",
+        &format!("{base_url}/docs/"),
+        "--out",
+        raw_dir.to_str().unwrap(),
+        "--delay-ms",
+        "0",
+    ])
+    .assert()
+    .success();
+    let records = read_crawl_records(&raw_dir)?;
+    assert!(
+        records
+            .iter()
+            .all(|r| !r.normalized_url.ends_with("/docs/old"))
+    );
+    let new_records = records
+        .iter()
+        .filter(|r| r.normalized_url == format!("{base_url}/docs/new"))
+        .collect::<Vec<_>>();
+    assert_eq!(new_records.len(), 1);
+    assert_eq!(
+        new_records[0].redirected_from,
+        vec![format!("{base_url}/docs/old")]
+    );
+
+    let list_dir = temp.path().join("raw-list");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "crawl",
+        "--url",
+        &format!("{base_url}/docs/"),
+        "--out",
+        list_dir.to_str().unwrap(),
+        "--delay-ms",
+        "0",
+        "--page-url",
+        &format!("{base_url}/docs/old"),
+        "--page-url",
+        &format!("{base_url}/docs/new"),
+        "--same-host-redirects-only",
+    ])
+    .assert()
+    .success();
+    let records = read_crawl_records(&list_dir)?;
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].normalized_url, format!("{base_url}/docs/new"));
+    assert_eq!(
+        records[0].redirected_from,
+        vec![format!("{base_url}/docs/old")]
+    );
+    assert!(records[0].raw_html_path.is_some());
+
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.join();
+    Ok(())
+}
+
+#[test]
+fn crawl_decodes_gzip_encoded_html() -> anyhow::Result<()> {
+    let server = tiny_http::Server::http("127.0.0.1:0").expect("start tiny_http server");
+    let base_url = format!("http://{}", server.server_addr());
+    let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
+    let server_handle = thread::spawn(move || {
+        let html = "<!doctype html><html><head><title>Gzip Page</title></head><body><main>\
+<h1>Gzip Page</h1><p>This page is only ever served gzip-encoded to the crawler.</p>\
+<p>Readability needs decoded HTML to find this paragraph.</p></main></body></html>";
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(html.as_bytes()).expect("gzip html");
+        let gzipped = encoder.finish().expect("finish gzip");
+
+        while shutdown_rx.try_recv().is_err() {
+            let request = match server.recv_timeout(Duration::from_millis(50)) {
+                Ok(Some(req)) => req,
+                Ok(None) => continue,
+                Err(_) => break,
+            };
+            let response = tiny_http::Response::from_data(gzipped.clone())
+                .with_header(
+                    tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..])
+                        .expect("build header"),
+                )
+                .with_header(
+                    tiny_http::Header::from_bytes(&b"Content-Encoding"[..], &b"gzip"[..])
+                        .expect("build header"),
+                );
+            let _ = request.respond(response);
+        }
+    });
+
+    let temp = tempfile::TempDir::new()?;
+    let raw_dir = temp.path().join("raw");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "crawl",
+        "--url",
+        &format!("{base_url}/docs/"),
+        "--out",
+        raw_dir.to_str().unwrap(),
+        "--delay-ms",
+        "0",
+        "--page-url",
+        &format!("{base_url}/docs/gzip"),
+    ])
+    .assert()
+    .success();
+
+    let crawl_records: Vec<CrawlRecord> = fs::read_to_string(raw_dir.join("crawl.jsonl"))?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).expect("parse crawl record json"))
+        .collect();
+    let raw_html_path = crawl_records[0]
+        .raw_html_path
+        .as_deref()
+        .expect("raw html saved");
+    assert!(fs::read_to_string(raw_html_path)?.contains("<title>Gzip Page</title>"));
+
+    let extracted_dir = temp.path().join("extracted");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "extract",
+        "--raw",
+        raw_dir.to_str().unwrap(),
+        "--out",
+        extracted_dir.to_str().unwrap(),
+    ])
+    .assert()
+    .success();
+    let pages = count_files_recursively(&extracted_dir.join("pages"))?;
+    assert_eq!(pages.len(), 1);
+    assert!(fs::read_to_string(&pages[0])?.contains("Readability needs decoded HTML"));
+
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.join();
+    Ok(())
+}
+
+#[test]
+fn crawl_page_list_respects_concurrency_per_host() -> anyhow::Result<()> {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let server = tiny_http::Server::http("127.0.0.1:0").expect("start tiny_http server");
+    let base_url = format!("http://{}", server.server_addr());
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let max_in_flight = Arc::new(AtomicUsize::new(0));
+    let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
+    let server_handle = thread::spawn({
+        let in_flight = in_flight.clone();
+        let max_in_flight = max_in_flight.clone();
+        move || {
+            while shutdown_rx.try_recv().is_err() {
+                let request = match server.recv_timeout(Duration::from_millis(50)) {
+                    Ok(Some(req)) => req,
+                    Ok(None) => continue,
+                    Err(_) => break,
+                };
+                let in_flight = in_flight.clone();
+                let max_in_flight = max_in_flight.clone();
+                thread::spawn(move || {
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_in_flight.fetch_max(now, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(100));
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    let response = tiny_http::Response::from_string(
+                        "<!doctype html><html><head><title>Slow</title></head><body></body></html>",
+                    )
+                    .with_header(
+                        tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..])
+                            .expect("build header"),
+                    );
+                    let _ = request.respond(response);
+                });
+            }
+        }
+    });
+
+    let temp = tempfile::TempDir::new()?;
+    let raw_dir = temp.path().join("raw");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "crawl",
+        "--url",
+        &format!("{base_url}/docs/"),
+        "--out",
+        raw_dir.to_str().unwrap(),
+        "--delay-ms",
+        "0",
+        "--concurrency",
+        "4",
+        "--concurrency-per-host",
+        "1",
+    ]);
+    for page in ["a", "b", "c", "d"] {
+        cmd.args(["--page-url", &format!("{base_url}/docs/{page}")]);
+    }
+    cmd.assert().success();
+
+    assert_eq!(read_crawl_records(&raw_dir)?.len(), 4);
+    assert_eq!(max_in_flight.load(Ordering::SeqCst), 1);
+
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.join();
+    Ok(())
+}
+
+#[test]
+fn crawl_page_list_caps_each_host_independently() -> anyhow::Result<()> {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::{Arc, Condvar, Mutex};
+
+    #[derive(Default)]
+    struct InFlight {
+        now: AtomicUsize,
+        max: AtomicUsize,
+    }
+
+    impl InFlight {
+        fn enter(&self) {
+            let now = self.now.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max.fetch_max(now, Ordering::SeqCst);
+        }
+
+        fn leave(&self) {
+            self.now.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    // Each host answers its n-th request only once the other host has received its n-th
+    // too, so the hosts move in lockstep without relying on sleeps. A shared cap would leave
+    // the other host's request queued and the wait would time out.
+    let arrivals = Arc::new((Mutex::new([0usize; 2]), Condvar::new()));
+    let total = Arc::new(InFlight::default());
+    let stop = Arc::new(AtomicBool::new(false));
+    let mut hosts = Vec::new();
+    for host in 0..2 {
+        let server = tiny_http::Server::http("127.0.0.1:0").expect("start tiny_http server");
+        let base_url = format!("http://{}", server.server_addr());
+        let per_host = Arc::new(InFlight::default());
+        let handle = thread::spawn({
+            let per_host = per_host.clone();
+            let total = total.clone();
+            let arrivals = arrivals.clone();
+            let stop = stop.clone();
+            move || {
+                while !stop.load(Ordering::SeqCst) {
+                    let request = match server.recv_timeout(Duration::from_millis(50)) {
+                        Ok(Some(req)) => req,
+                        Ok(None) => continue,
+                        Err(_) => break,
+                    };
+                    let per_host = per_host.clone();
+                    let total = total.clone();
+                    let arrivals = arrivals.clone();
+                    thread::spawn(move || {
+                        per_host.enter();
+                        total.enter();
+                        let (counts, arrived) = &*arrivals;
+                        let mut counts = counts.lock().unwrap();
+                        counts[host] += 1;
+                        let turn = counts[host];
+                        arrived.notify_all();
+                        let (counts, _) = arrived
+                            .wait_timeout_while(counts, Duration::from_secs(10), |counts| {
+                                counts[1 - host] < turn
+                            })
+                            .unwrap();
+                        drop(counts);
+                        total.leave();
+                        per_host.leave();
+                        let response = tiny_http::Response::from_string(
+                            "<!doctype html><html><head><title>Slow</title></head><body></body></html>",
+                        )
+                        .with_header(
+                            tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..])
+                                .expect("build header"),
+                        );
+                        let _ = request.respond(response);
+                    });
+                }
+            }
+        });
+        hosts.push((base_url, per_host, handle));
+    }
+
+    let temp = tempfile::TempDir::new()?;
+    let raw_dir = temp.path().join("raw");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "crawl",
+        "--url",
+        &format!("{}/docs/", hosts[0].0),
+        "--out",
+        raw_dir.to_str().unwrap(),
+        "--delay-ms",
+        "0",
+        "--concurrency",
+        "4",
+        "--per-host-concurrency",
+        "1",
+    ]);
+    for (base_url, _, _) in &hosts {
+        for page in ["a", "b", "c"] {
+            cmd.args(["--page-url", &format!("{base_url}/docs/{page}")]);
+        }
+    }
+    cmd.assert().success();
+
+    assert_eq!(read_crawl_records(&raw_dir)?.len(), 6);
+    for (base_url, per_host, _) in &hosts {
+        assert_eq!(per_host.max.load(Ordering::SeqCst), 1, "{base_url}");
+    }
+    // One host's cap does not hold back the other.
+    assert_eq!(total.max.load(Ordering::SeqCst), 2);
+
+    stop.store(true, Ordering::SeqCst);
+    for (_, _, handle) in hosts {
+        let _ = handle.join();
+    }
+    Ok(())
+}
+
+#[test]
+fn crawl_routes_requests_through_proxy() -> anyhow::Result<()> {
+    let proxy = tiny_http::Server::http("127.0.0.1:0").expect("start tiny_http server");
+    let proxy_url = format!("http://{}", proxy.server_addr());
+    let (seen_tx, seen_rx) = mpsc::channel::<String>();
+    let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
+    let server_handle = thread::spawn(move || {
+        while shutdown_rx.try_recv().is_err() {
+            let request = match proxy.recv_timeout(Duration::from_millis(50)) {
+                Ok(Some(req)) => req,
+                Ok(None) => continue,
+                Err(_) => break,
+            };
+            let _ = seen_tx.send(request.url().to_string());
+            let response = tiny_http::Response::from_string(
+                "<!doctype html><html><head><title>Proxied</title></head><body><main>\
+<h1>Proxied</h1><p>Served by the proxy.</p></main></body></html>",
+            )
+            .with_header(
+                tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..])
+                    .expect("build header"),
+            );
+            let _ = request.respond(response);
+        }
+    });
+
+    let temp = tempfile::TempDir::new()?;
+    let raw_dir = temp.path().join("raw");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "crawl",
+        "--url",
+        "http://docs.invalid/docs/",
+        "--out",
+        raw_dir.to_str().unwrap(),
+        "--delay-ms",
+        "0",
+        "--page-url",
+        "http://docs.invalid/docs/intro",
+        "--proxy",
+        &proxy_url,
+    ])
+    .env_remove("HTTP_PROXY")
+    .env_remove("HTTPS_PROXY")
+    .env_remove("ALL_PROXY")
+    .assert()
+    .success();
+
+    let records = read_crawl_records(&raw_dir)?;
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].status, 200);
+    assert_eq!(
+        seen_rx.try_iter().collect::<Vec<_>>(),
+        vec!["http://docs.invalid/docs/intro".to_string()]
+    );
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "crawl",
+        "--url",
+        "http://docs.invalid/docs/",
+        "--out",
+        temp.path().join("raw2").to_str().unwrap(),
+        "--page-url",
+        "http://docs.invalid/docs/intro",
+        "--proxy",
+        "ftp://127.0.0.1:1080",
+    ])
+    .assert()
+    .failure()
+    .stderr(predicate::str::contains("unsupported proxy scheme: ftp"));
+
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.join();
+    Ok(())
+}
+
+/// Minimal SOCKS5 proxy (no auth, CONNECT only) that answers every tunneled HTTP request
+/// itself and reports each requested `host:port`.
+fn spawn_socks5_proxy() -> (String, mpsc::Receiver<String>) {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind socks5 proxy");
+    let addr = listener.local_addr().expect("socks5 proxy addr");
+    let (seen_tx, seen_rx) = mpsc::channel::<String>();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { break };
+            let seen_tx = seen_tx.clone();
+            thread::spawn(move || -> std::io::Result<()> {
+                let mut header = [0u8; 2];
+                stream.read_exact(&mut header)?;
+                let mut methods = vec![0u8; header[1] as usize];
+                stream.read_exact(&mut methods)?;
+                stream.write_all(&[5, 0])?;
+
+                let mut request = [0u8; 4];
+                stream.read_exact(&mut request)?;
+                let host = match request[3] {
+                    1 => {
+                        let mut ip = [0u8; 4];
+                        stream.read_exact(&mut ip)?;
+                        std::net::Ipv4Addr::from(ip).to_string()
+                    }
+                    3 => {
+                        let mut len = [0u8; 1];
+                        stream.read_exact(&mut len)?;
+                        let mut name = vec![0u8; len[0] as usize];
+                        stream.read_exact(&mut name)?;
+                        String::from_utf8_lossy(&name).into_owned()
+                    }
+                    _ => return Ok(()),
+                };
+                let mut port = [0u8; 2];
+                stream.read_exact(&mut port)?;
+                let _ = seen_tx.send(format!("{host}:{}", u16::from_be_bytes(port)));
+                stream.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0])?;
+
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 1024];
+                while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let n = stream.read(&mut chunk)?;
+                    if n == 0 {
+                        return Ok(());
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+                let body = "<!doctype html><html><head><title>Socks</title></head><body><main>\
+<h1>Socks</h1><p>Served through SOCKS.</p></main></body></html>";
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\n\
+Connection: close\r\n\r\n{body}",
+                    body.len()
+                )
+            });
+        }
+    });
+    (format!("socks5h://{addr}"), seen_rx)
+}
+
+#[test]
+fn crawl_routes_requests_through_socks5_proxy() -> anyhow::Result<()> {
+    let (proxy_url, seen_rx) = spawn_socks5_proxy();
+    let temp = tempfile::TempDir::new()?;
+    let raw_dir = temp.path().join("raw");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "crawl",
+        "--url",
+        "http://docs.invalid/docs/",
+        "--out",
+        raw_dir.to_str().unwrap(),
+        "--delay-ms",
+        "0",
+        "--page-url",
+        "http://docs.invalid/docs/intro",
+        "--proxy",
+        &proxy_url,
+    ])
+    .env_remove("HTTP_PROXY")
+    .env_remove("HTTPS_PROXY")
+    .env_remove("ALL_PROXY")
+    .assert()
+    .success();
+
+    let records = read_crawl_records(&raw_dir)?;
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].status, 200);
+    // socks5h: the proxy is handed the host name, not a locally resolved address.
+    assert_eq!(
+        seen_rx.try_iter().collect::<Vec<_>>(),
+        vec!["docs.invalid:80".to_string()]
+    );
+    Ok(())
+}
+
+#[test]
+fn crawl_tls_flags_warn_and_validate_ca_cert() -> anyhow::Result<()> {
+    let (base_url, shutdown_tx, server_handle) = spawn_docs_server();
+    let temp = tempfile::TempDir::new()?;
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "crawl",
+        "--url",
+        &format!("{base_url}/docs/"),
+        "--out",
+        temp.path().join("raw").to_str().unwrap(),
+        "--delay-ms",
+        "0",
+        "--page-url",
+        &format!("{base_url}/docs/intro"),
+        "--insecure",
+    ])
+    .assert()
+    .success()
+    .stderr(predicate::str::contains(
+        "TLS certificate verification is DISABLED",
+    ));
+
+    let not_pem = temp.path().join("ca.pem");
+    fs::write(&not_pem, "not a certificate\n")?;
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "crawl",
+        "--url",
+        &format!("{base_url}/docs/"),
+        "--out",
+        temp.path().join("raw2").to_str().unwrap(),
+        "--page-url",
+        &format!("{base_url}/docs/intro"),
+        "--ca-cert",
+        not_pem.to_str().unwrap(),
+    ])
+    .assert()
+    .failure()
+    .stderr(predicate::str::contains("--ca-cert"));
+
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.join();
+    Ok(())
+}
+
+#[test]
+fn extract_min_content_chars_skips_short_pages() -> anyhow::Result<()> {
+    let (base_url, shutdown_tx, server_handle) = spawn_docs_server();
+    let temp = tempfile::TempDir::new()?;
+    let raw_dir = temp.path().join("raw");
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "crawl",
+        "--url",
+        &format!("{base_url}/docs/"),
+        "--out",
+        raw_dir.to_str().unwrap(),
+        "--delay-ms",
+        "0",
+    ])
+    .assert()
+    .success();
+
+    let kept_dir = temp.path().join("extracted-kept");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "extract",
+        "--raw",
+        raw_dir.to_str().unwrap(),
+        "--out",
+        kept_dir.to_str().unwrap(),
+    ])
+    .assert()
+    .success();
+    assert!(count_files_with_extension(&kept_dir.join("pages"), "md")? >= 3);
+
+    let skipped_dir = temp.path().join("extracted-skipped");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "extract",
+        "--raw",
+        raw_dir.to_str().unwrap(),
+        "--out",
+        skipped_dir.to_str().unwrap(),
+        "--min-content-chars",
+        "100000",
+    ])
+    .assert()
+    .success();
+    assert_eq!(
+        count_files_with_extension(&skipped_dir.join("pages"), "md")?,
+        0
+    );
+
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.join();
+    Ok(())
+}
+
+#[test]
+fn extract_if_modified_reuses_unchanged_pages() -> anyhow::Result<()> {
+    let (base_url, shutdown_tx, server_handle) = spawn_docs_server();
+    let temp = tempfile::TempDir::new()?;
+    let raw_dir = temp.path().join("raw");
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "crawl",
+        "--url",
+        &format!("{base_url}/docs/"),
+        "--out",
+        raw_dir.to_str().unwrap(),
+        "--delay-ms",
+        "0",
+    ])
+    .assert()
+    .success();
+
+    let prior_dir = temp.path().join("extracted-prior");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "extract",
+        "--raw",
+        raw_dir.to_str().unwrap(),
+        "--out",
+        prior_dir.to_str().unwrap(),
+    ])
+    .assert()
+    .success();
+
+    let mut prior_pages = count_files_recursively(&prior_dir.join("pages"))?;
+    prior_pages.sort();
+    let total = prior_pages.len();
+    assert!(total >= 3);
+
+    // Mark one reusable page so the copy is observable, and make another look stale.
+    let reused_page = &prior_pages[0];
+    let contents = fs::read_to_string(reused_page)?;
+    assert!(contents.contains("raw_html_sha256: "));
+    let contents = contents.replacen("\ntitle: ", "\nreviewed_by: alice\ntitle: ", 1);
+    fs::write(reused_page, format!("{}REUSED MARKER\n", contents))?;
+    let stale_page = &prior_pages[1];
+    let contents = fs::read_to_string(stale_page)?;
+    let stale = contents
+        .lines()
+        .map(|line| {
+            if line.starts_with("raw_html_sha256: ") {
+                "raw_html_sha256: stale".to_owned()
+            } else {
+                line.to_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    fs::write(stale_page, format!("{stale}\nSTALE MARKER\n"))?;
+
+    let next_dir = temp.path().join("extracted-next");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "extract",
+        "--raw",
+        raw_dir.to_str().unwrap(),
+        "--out",
+        next_dir.to_str().unwrap(),
+        "--if-modified",
+        prior_dir.to_str().unwrap(),
+    ])
+    .env("NO_COLOR", "1")
+    .assert()
+    .success()
+    .stderr(predicate::str::contains(format!(
+        "reused={} extracted=1",
+        total - 1
+    )));
+
+    let next_reused = fs::read_to_string(next_dir.join(reused_page.strip_prefix(&prior_dir)?))?;
+    assert!(next_reused.contains("REUSED MARKER"));
+    // Unknown front matter fields survive the round trip.
+    assert!(next_reused.contains("\nreviewed_by: alice\n"));
+    let next_stale = fs::read_to_string(next_dir.join(stale_page.strip_prefix(&prior_dir)?))?;
+    assert!(!next_stale.contains("STALE MARKER"));
+    assert!(!next_stale.contains("raw_html_sha256: stale"));
+
+    // A different --title-source changes titles, so nothing is reused.
+    let retitled_dir = temp.path().join("extracted-retitled");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "extract",
+        "--raw",
+        raw_dir.to_str().unwrap(),
+        "--out",
+        retitled_dir.to_str().unwrap(),
+        "--if-modified",
+        next_dir.to_str().unwrap(),
+        "--title-source",
+        "html-title",
+    ])
+    .env("NO_COLOR", "1")
+    .assert()
+    .success()
+    .stderr(predicate::str::contains(format!(
+        "reused=0 extracted={total}"
+    )));
+    let retitled = fs::read_to_string(retitled_dir.join(reused_page.strip_prefix(&prior_dir)?))?;
+    assert!(!retitled.contains("REUSED MARKER"));
+    assert!(retitled.contains("\ntitle_source: html_title\n"));
+
+    // Bodies depend on the --strip-repeated settings too.
+    let extract_again = |out: &str, prior: &Path, extra_args: &[&str], expected: String| {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+        cmd.args([
+            "extract",
+            "--raw",
+            raw_dir.to_str().unwrap(),
+            "--out",
+            temp.path().join(out).to_str().unwrap(),
+            "--if-modified",
+            prior.to_str().unwrap(),
+        ])
+        .args(extra_args)
+        .env("NO_COLOR", "1")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(expected));
+    };
+    extract_again(
+        "extracted-stripped",
+        &next_dir,
+        &["--strip-repeated"],
+        format!("reused=0 extracted={total}"),
+    );
+    let stripped_dir = temp.path().join("extracted-stripped");
+    extract_again(
+        "extracted-unstripped",
+        &stripped_dir,
+        &[],
+        format!("reused=0 extracted={total}"),
+    );
+    extract_again(
+        "extracted-other-threshold",
+        &stripped_dir,
+        &["--strip-repeated", "--strip-repeated-threshold", "50"],
+        format!("reused=0 extracted={total}"),
+    );
+    extract_again(
+        "extracted-restripped",
+        &stripped_dir,
+        &["--strip-repeated"],
+        format!("reused={total} extracted=0"),
+    );
+
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.join();
+    Ok(())
+}
+
+#[test]
+fn bundle_inserts_chapter_separator_outside_code_fences() -> anyhow::Result<()> {
+    let temp = tempfile::tempdir()?;
+    let book_dir = temp.path().join("book");
+    let chapters_dir = book_dir.join("src").join("chapters");
+    fs::create_dir_all(&chapters_dir)?;
+    fs::write(
+        book_dir.join("src").join("SUMMARY.md"),
+        "# Summary\n\n- Part\n  - [One](chapters/ch01.md)\n  - [Two](chapters/ch02.md)\n",
+    )?;
+    // ch01 ends inside an unterminated fence; the separator must not land inside it.
+    fs::write(
+        chapters_dir.join("ch01.md"),
+        "# One\n\nSee [two](ch02.md#p_two).\n\n```sh\necho hi\n",
+    )?;
+    fs::write(
+        chapters_dir.join("ch02.md"),
+        "# Two\n\n<span id=\"p_two\"></span>\nBody.\n",
+    )?;
+
+    let out_path = temp.path().join("book.md");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "book",
+        "bundle",
+        "--book",
+        book_dir.to_str().unwrap(),
+        "--out",
+        out_path.to_str().unwrap(),
+        "--chapter-separator",
+        "\\n<!-- pagebreak -->\\n",
+    ])
+    .assert()
+    .success();
+
+    let bundled = fs::read_to_string(&out_path)?;
+    assert_eq!(
+        bundled,
+        "# One\n\nSee [two](#p_two).\n\n```sh\necho hi\n```\n\n<!-- pagebreak -->\n\n# Two\n\n<span id=\"p_two\"></span>\nBody.\n"
+    );
+
+    // Without the flag the chapters are concatenated unchanged (no fence is closed).
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "book",
+        "bundle",
+        "--book",
+        book_dir.to_str().unwrap(),
+        "--out",
+        out_path.to_str().unwrap(),
+        "--force",
+    ])
+    .assert()
+    .success();
+    assert_eq!(
+        fs::read_to_string(&out_path)?,
+        "# One\n\nSee [two](#p_two).\n\n```sh\necho hi\n\n# Two\n\n<span id=\"p_two\"></span>\nBody.\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn bundle_front_matter_uses_book_toml_metadata() -> anyhow::Result<()> {
+    let temp = tempfile::tempdir()?;
+    let book_dir = temp.path().join("book");
+    let chapters_dir = book_dir.join("src").join("chapters");
+    fs::create_dir_all(&chapters_dir)?;
+    fs::write(
+        book_dir.join("book.toml"),
+        "[book]\ntitle = \"Test Book\"\nauthors = [\"Alice\", \"Bob\"]\n",
+    )?;
+    fs::write(
+        book_dir.join("src").join("SUMMARY.md"),
+        "# Summary\n\n- [One](chapters/ch01.md)\n",
+    )?;
+    fs::write(chapters_dir.join("ch01.md"), "# One\n\nBody.\n")?;
+
+    let out_path = temp.path().join("book.md");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "book",
+        "bundle",
+        "--book",
+        book_dir.to_str().unwrap(),
+        "--out",
+        out_path.to_str().unwrap(),
+        "--front-matter",
+        "--date",
+        "2026-01-02",
+    ])
+    .assert()
+    .success();
+
+    let bundled = fs::read_to_string(&out_path)?;
+    assert_eq!(
+        bundled,
+        "---\ntitle: Test Book\nauthor: Alice, Bob\ndate: 2026-01-02\n---\n\n# Test Book\n\n# One\n\nBody.\n"
+    );
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn bundle_copies_mutually_linked_asset_dirs_once() -> anyhow::Result<()> {
+    let temp = tempfile::tempdir()?;
+    let book_dir = temp.path().join("book");
+    let assets_dir = book_dir.join("src").join("assets");
+    fs::create_dir_all(book_dir.join("src").join("chapters"))?;
+    fs::create_dir_all(assets_dir.join("a"))?;
+    fs::create_dir_all(assets_dir.join("b"))?;
+    fs::write(
+        book_dir.join("src").join("SUMMARY.md"),
+        "# Summary\n\n- [One](chapters/ch01.md)\n",
+    )?;
+    fs::write(
+        book_dir.join("src").join("chapters").join("ch01.md"),
+        "# One\n",
+    )?;
+    fs::write(assets_dir.join("a").join("a.png"), b"a")?;
+    fs::write(assets_dir.join("b").join("b.png"), b"b")?;
+    std::os::unix::fs::symlink("../b", assets_dir.join("a").join("x"))?;
+    std::os::unix::fs::symlink("../a", assets_dir.join("b").join("y"))?;
+
+    let out_dir = temp.path().join("out");
+    fs::create_dir_all(&out_dir)?;
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "book",
+        "bundle",
+        "--book",
+        book_dir.to_str().unwrap(),
+        "--out",
+        out_dir.join("book.md").to_str().unwrap(),
+    ])
+    .timeout(Duration::from_secs(60))
+    .assert()
+    .success();
+
+    let copied = out_dir.join("assets");
+    for file in ["a/a.png", "a/x/b.png", "b/b.png", "b/y/a.png"] {
+        assert!(copied.join(file).is_file(), "{file}");
+    }
+    assert!(!copied.join("a/x/y").exists());
+    assert!(!copied.join("b/y/x").exists());
+    Ok(())
+}
+
+#[test]
+fn bundle_rewrites_links_for_any_listed_chapter_id() -> anyhow::Result<()> {
+    let temp = tempfile::tempdir()?;
+    let book_dir = temp.path().join("book");
+    let chapters_dir = book_dir.join("src").join("chapters");
+    fs::create_dir_all(&chapters_dir)?;
+    fs::write(
+        book_dir.join("src").join("SUMMARY.md"),
+        "# Summary\n\n- [Hundred](chapters/ch100.md)\n- [Slug](chapters/ch-getting-started.md)\n",
+    )?;
+    fs::write(
+        chapters_dir.join("ch100.md"),
+        "# Hundred\n\nSee [slug](ch-getting-started.md#p_b) and [unknown](ch999.md#p_c).\n",
+    )?;
+    fs::write(
+        chapters_dir.join("ch-getting-started.md"),
+        "# Slug\n\n<span id=\"p_b\"></span>\nBack to [hundred](ch100.md#p_a).\n",
+    )?;
+
+    let out_path = temp.path().join("book.md");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "book",
+        "bundle",
+        "--book",
+        book_dir.to_str().unwrap(),
+        "--out",
+        out_path.to_str().unwrap(),
+    ])
+    .assert()
+    .success();
+
+    let bundled = fs::read_to_string(&out_path)?;
+    assert!(bundled.contains("[slug](#p_b)"), "{bundled}");
+    assert!(bundled.contains("[hundred](#p_a)"), "{bundled}");
+    assert!(
+        bundled.contains("[unknown](ch999.md#p_c)"),
+        "links to chapters outside SUMMARY.md must be left as-is: {bundled}"
+    );
+    Ok(())
+}
+
+#[test]
+fn crawl_fetch_command_supplies_page_html() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    // A space in the path: the command is split into argv, not handed to a shell.
+    let script_dir = temp.path().join("fetch scripts");
+    fs::create_dir_all(&script_dir)?;
+    let script = script_dir.join("fetch.sh");
+    fs::write(
+        &script,
+        "case \"$1\" in\n\
+*missing*) echo 'render failed' >&2; exit 3;;\n\
+*gone*) printf 'Status: 404 Not Found\\n\\nno such page'; exit 0;;\n\
+*moved*) printf 'Status: 200\\nLocation: /docs/final\\n\\n';;\n\
+esac\n\
+printf '<!doctype html><html><head><title>Rendered</title></head><body><main>\
+<h1>Rendered</h1><p>Rendered %s</p></main></body></html>' \"$1\"\n",
+    )?;
+
+    let raw_dir = temp.path().join("raw");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "crawl",
+        "--url",
+        "http://docs.invalid/docs/",
+        "--out",
+        raw_dir.to_str().unwrap(),
+        "--delay-ms",
+        "0",
+        "--page-url",
+        "http://docs.invalid/docs/intro",
+        "--page-url",
+        "http://docs.invalid/docs/missing",
+        "--page-url",
+        "http://docs.invalid/docs/gone",
+        "--page-url",
+        "http://docs.invalid/docs/moved",
+        "--fetch-command",
+        &format!("sh '{}'", script.display()),
+    ])
+    .assert()
+    .success();
+
+    // Records follow the `--page-url` order.
+    let records = read_crawl_records(&raw_dir)?;
+    assert_eq!(records.len(), 4);
+    assert_eq!(records[0].url, "http://docs.invalid/docs/intro");
+    assert_eq!(records[0].status, 200);
+    assert_eq!(records[0].content_type.as_deref(), Some("text/html"));
+    let raw_html = fs::read_to_string(
+        records[0]
+            .raw_html_path
+            .as_deref()
+            .expect("raw html saved for rendered page"),
+    )?;
+    assert!(raw_html.contains("Rendered http://docs.invalid/docs/intro"));
+    assert_eq!(records[1].url, "http://docs.invalid/docs/missing");
+    assert_eq!(records[1].status, 0);
+    assert!(records[1].raw_html_path.is_none());
+    assert_eq!(records[2].url, "http://docs.invalid/docs/gone");
+    assert_eq!(records[2].status, 404);
+    assert!(records[2].raw_html_path.is_none());
+    assert_eq!(records[3].url, "http://docs.invalid/docs/final");
+    assert_eq!(records[3].status, 200);
+    assert_eq!(
+        records[3].redirected_from,
+        vec!["http://docs.invalid/docs/moved".to_string()]
+    );
+    let raw_html = fs::read_to_string(
+        records[3]
+            .raw_html_path
+            .as_deref()
+            .expect("raw html saved for redirected page"),
+    )?;
+    assert!(raw_html.contains("Rendered http://docs.invalid/docs/moved"));
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "crawl",
+        "--url",
+        "http://docs.invalid/docs/",
+        "--out",
+        temp.path().join("raw2").to_str().unwrap(),
+        "--fetch-command",
+        "true",
+    ])
+    .assert()
+    .failure()
+    .stderr(predicate::str::contains(
+        "--fetch-command needs --page-url: link crawling always fetches with the built-in HTTP client",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn extract_title_source_selects_page_title() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let script = temp.path().join("fetch.sh");
+    fs::write(
+        &script,
+        "printf '<!doctype html><html><head><title>Example Docs</title></head><body><main>\
+<h1>Getting Started</h1><p>Install the tool, then run the first command to see it work.</p>\
+</main></body></html>'\n",
+    )?;
+    let raw_dir = temp.path().join("raw");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "crawl",
+        "--url",
+        "http://docs.invalid/docs/",
+        "--out",
+        raw_dir.to_str().unwrap(),
+        "--delay-ms",
+        "0",
+        "--page-url",
+        "http://docs.invalid/docs/start",
+        "--fetch-command",
+        &format!("sh {}", script.display()),
+    ])
+    .assert()
+    .success();
+
+    for (source, expected) in [("html-title", "Example Docs"), ("h1", "Getting Started")] {
+        let out_dir = temp.path().join(format!("extracted-{source}"));
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+        cmd.args([
+            "extract",
+            "--raw",
+            raw_dir.to_str().unwrap(),
+            "--out",
+            out_dir.to_str().unwrap(),
+            "--title-source",
+            source,
+        ])
+        .assert()
+        .success();
+
+        let pages = count_files_recursively(&out_dir.join("pages"))?;
+        assert_eq!(pages.len(), 1);
+        let contents = fs::read_to_string(&pages[0])?;
+        assert!(
+            contents.contains(&format!("\ntitle: {expected}\n")),
+            "{source}: {contents}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn crawl_skips_links_with_excluded_extensions() -> anyhow::Result<()> {
+    let server = tiny_http::Server::http("127.0.0.1:0").expect("start tiny_http server");
+    let base_url = format!("http://{}", server.server_addr());
+    let (seen_tx, seen_rx) = mpsc::channel::<String>();
+    let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
+    let server_handle = thread::spawn(move || {
+        while shutdown_rx.try_recv().is_err() {
+            let request = match server.recv_timeout(Duration::from_millis(50)) {
+                Ok(Some(req)) => req,
+                Ok(None) => continue,
+                Err(_) => break,
+            };
+            let path = request.url().to_string();
+            let _ = seen_tx.send(path.clone());
+            let body = match path.as_str() {
+                "/docs/" | "/docs" => {
+                    "<!doctype html><html><head><title>Docs</title></head><body><main>\
+<h1>Docs</h1><a href=\"intro\">Intro</a> <a href=\"image.iso\">Image</a> \
+<a href=\"dump.DAT\">Dump</a></main></body></html>"
+                }
+                _ => {
+                    "<!doctype html><html><head><title>Page</title></head><body><main>\
+<h1>Page</h1><p>Body.</p></main></body></html>"
+                }
+            };
+            let response = tiny_http::Response::from_string(body).with_header(
+                tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..])
+                    .expect("build header"),
+            );
+            let _ = request.respond(response);
+        }
+    });
+
+    let temp = tempfile::TempDir::new()?;
+    let raw_dir = temp.path().join("raw");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "crawl",
+        "--url",
+        &format!("{base_url}/docs/"),
+        "--out",
+        raw_dir.to_str().unwrap(),
+        "--delay-ms",
+        "0",
+        "--exclude-extensions",
+        ".dat",
+    ])
+    .assert()
+    .success();
+
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.join();
+
+    let seen = seen_rx.try_iter().collect::<Vec<_>>();
+    assert!(seen.iter().any(|path| path == "/docs/intro"), "{seen:?}");
+    assert!(
+        !seen
+            .iter()
+            .any(|path| path.ends_with(".iso") || path.ends_with(".DAT")),
+        "{seen:?}"
+    );
+    let records = read_crawl_records(&raw_dir)?;
+    assert!(records.iter().all(|r| !r.url.ends_with(".iso")));
+    Ok(())
+}
+
+#[test]
+fn crawl_keep_query_dedupes_tracking_variants() -> anyhow::Result<()> {
+    let server = tiny_http::Server::http("127.0.0.1:0").expect("start tiny_http server");
+    let base_url = format!("http://{}", server.server_addr());
+    let (seen_tx, seen_rx) = mpsc::channel::<String>();
+    let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
+    let server_handle = thread::spawn(move || {
+        while shutdown_rx.try_recv().is_err() {
+            let request = match server.recv_timeout(Duration::from_millis(50)) {
+                Ok(Some(req)) => req,
+                Ok(None) => continue,
+                Err(_) => break,
+            };
+            let path = request.url().to_string();
+            let _ = seen_tx.send(path.clone());
+            let body = match path.as_str() {
+                "/docs/" | "/docs" => {
+                    "<!doctype html><html><head><title>Docs</title></head><body><main>\
+<h1>Docs</h1><a href=\"list?utm_source=x&amp;page=2\">Two</a> \
+<a href=\"list?page=2\">Two again</a> <a href=\"list?page=3&amp;fbclid=abc\">Three</a> \
+<a href=\"list?session=9&amp;page=3\">Three again</a></main></body></html>"
+                        .to_owned()
+                }
+                _ => format!(
+                    "<!doctype html><html><head><title>List</title></head><body><main>\
+<h1>List</h1><p>{path}</p></main></body></html>"
+                ),
+            };
+            let response = tiny_http::Response::from_string(body).with_header(
+                tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..])
+                    .expect("build header"),
+            );
+            let _ = request.respond(response);
+        }
+    });
+
+    let temp = tempfile::TempDir::new()?;
+    let raw_dir = temp.path().join("raw");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "crawl",
+        "--url",
+        &format!("{base_url}/docs/"),
+        "--out",
+        raw_dir.to_str().unwrap(),
+        "--delay-ms",
+        "0",
+        "--keep-query",
+        "--tracking-param",
+        "session",
+    ])
+    .assert()
+    .success();
+
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.join();
+
+    assert_eq!(
+        crawled_urls(&raw_dir)?,
+        vec![
+            format!("{base_url}/docs"),
+            format!("{base_url}/docs/list?page=2"),
+            format!("{base_url}/docs/list?page=3"),
+        ]
+    );
+    let seen = seen_rx.try_iter().collect::<Vec<_>>();
+    assert!(
+        seen.iter().all(|path| !["utm_", "fbclid", "session", "amp"]
+            .iter()
+            .any(|param| path.contains(param))),
+        "{seen:?}"
+    );
+    let records = read_crawl_records(&raw_dir)?;
+    let saved = records
+        .iter()
+        .filter_map(|record| record.raw_html_path.as_deref())
+        .map(std::fs::read_to_string)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert!(saved.iter().any(|html| html.contains("/docs/list?page=2")));
+    assert!(saved.iter().any(|html| html.contains("/docs/list?page=3")));
+    Ok(())
+}
+
+#[test]
+fn book_render_resolves_relative_links_against_base_href() -> anyhow::Result<()> {
+    let temp = tempfile::TempDir::new()?;
+    let script = temp.path().join("fetch.sh");
+    fs::write(
+        &script,
+        "case \"$1\" in\n\
+*/docs/start) printf '<!doctype html><html><head><title>Start</title><base href=\"/v2/\"></head>\
+<body><main><h1>Start</h1><p>Continue with the <a href=\"guide\">guide</a> once the tool is installed \
+and configured for your project.</p></main></body></html>' ;;\n\
+*) printf '<!doctype html><html><head><title>Guide</title></head><body><main><h1>Guide</h1>\
+<p>The guide walks through every command in detail, one step at a time.</p></main></body></html>' ;;\n\
+esac\n",
+    )?;
+
+    let raw_dir = temp.path().join("raw");
+    let extracted_dir = temp.path().join("extracted");
+    let manifest_path = temp.path().join("manifest.jsonl");
+    let toc_path = temp.path().join("toc.yaml");
+    let book_dir = temp.path().join("book");
+    let fetch_command = format!("sh {}", script.display());
+    let steps: Vec<Vec<&str>> = vec![
+        vec![
+            "crawl",
+            "--url",
+            "http://docs.invalid/",
+            "--out",
+            raw_dir.to_str().unwrap(),
+            "--delay-ms",
+            "0",
+            "--page-url",
+            "http://docs.invalid/docs/start",
+            "--page-url",
+            "http://docs.invalid/v2/guide",
+            "--fetch-command",
+            &fetch_command,
+        ],
+        vec![
+            "extract",
+            "--raw",
+            raw_dir.to_str().unwrap(),
+            "--out",
+            extracted_dir.to_str().unwrap(),
+        ],
+        vec![
+            "manifest",
+            "--extracted",
+            extracted_dir.to_str().unwrap(),
+            "--out",
+            manifest_path.to_str().unwrap(),
+        ],
+        vec![
+            "toc",
+            "create",
+            "--manifest",
+            manifest_path.to_str().unwrap(),
+            "--out",
+            toc_path.to_str().unwrap(),
+            "--engine",
+            "noop",
+        ],
+        vec![
+            "book",
+            "render",
+            "--toc",
+            toc_path.to_str().unwrap(),
+            "--manifest",
+            manifest_path.to_str().unwrap(),
+            "--out",
+            book_dir.to_str().unwrap(),
+            "--engine",
+            "noop",
+        ],
+    ];
+    for args in steps {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+        cmd.args(&args).assert().success();
+    }
+
+    let manifest_records: Vec<ManifestRecord> = fs::read_to_string(&manifest_path)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    let start = manifest_records
+        .iter()
+        .find(|r| r.path == "/docs/start")
+        .expect("start page in manifest");
+    assert_eq!(start.base_url.as_deref(), Some("http://docs.invalid/v2/"));
+    let guide = manifest_records
+        .iter()
+        .find(|r| r.path == "/v2/guide")
+        .expect("guide page in manifest");
+    assert_eq!(guide.base_url, None);
+
+    let chapter = fs::read_to_string(book_dir.join("src").join("chapters").join("ch01.md"))?;
+    assert!(
+        chapter.contains(&format!("[guide](#{})", guide.id)),
+        "{chapter}"
+    );
+    Ok(())
+}
+
+#[test]
+fn toc_create_page_order_follows_crawl_discovery_or_sitemap() -> anyhow::Result<()> {
+    let server = tiny_http::Server::http("127.0.0.1:0").expect("start tiny_http server");
+    let base_url = format!("http://{}", server.server_addr());
+    let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
+    let server_handle = thread::spawn(move || {
+        while shutdown_rx.try_recv().is_err() {
+            let request = match server.recv_timeout(Duration::from_millis(50)) {
+                Ok(Some(req)) => req,
+                Ok(None) => continue,
+                Err(_) => break,
+            };
+            // A chain, so discovery order (docs, zeta, alpha, middle) differs from path order.
+            let (title, next) = match request.url() {
+                "/docs/" | "/docs" => ("Docs", Some("zeta")),
+                "/docs/zeta" => ("Zeta", Some("alpha")),
+                "/docs/alpha" => ("Alpha", Some("middle")),
+                _ => ("Middle", None),
+            };
+            let link = next
+                .map(|next| format!("<p>Next: <a href=\"/docs/{next}\">{next}</a></p>"))
+                .unwrap_or_default();
+            let body = format!(
+                "<!doctype html><html><head><title>{title}</title></head><body><main>\
+<h1>{title}</h1><p>The {title} page explains one part of the tool in a few sentences.</p>\
+{link}</main></body></html>"
+            );
+            let response = tiny_http::Response::from_string(body).with_header(
+                tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..])
+                    .expect("build header"),
+            );
+            let _ = request.respond(response);
+        }
+    });
+
+    let temp = tempfile::TempDir::new()?;
+    let raw_dir = temp.path().join("raw");
+    let extracted_dir = temp.path().join("extracted");
+    let manifest_path = temp.path().join("manifest.jsonl");
+    let steps: Vec<Vec<String>> = vec![
+        vec![
+            "crawl".into(),
+            "--url".into(),
+            format!("{base_url}/docs/"),
+            "--out".into(),
+            raw_dir.to_string_lossy().into_owned(),
+            "--delay-ms".into(),
+            "0".into(),
+        ],
+        vec![
+            "extract".into(),
+            "--raw".into(),
+            raw_dir.to_string_lossy().into_owned(),
+            "--out".into(),
+            extracted_dir.to_string_lossy().into_owned(),
+        ],
+        vec![
+            "manifest".into(),
+            "--extracted".into(),
+            extracted_dir.to_string_lossy().into_owned(),
+            "--out".into(),
+            manifest_path.to_string_lossy().into_owned(),
+        ],
+    ];
+    for args in steps {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+        cmd.args(&args).assert().success();
+    }
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.join();
+
+    let records = read_crawl_records(&raw_dir)?;
+    assert!(
+        records.iter().all(|r| r.discovery_index.is_some()),
+        "{records:?}"
+    );
+
+    let sitemap_path = temp.path().join("sitemap.xml");
+    fs::write(
+        &sitemap_path,
+        format!(
+            "<?xml version=\"1.0\"?><urlset><url><loc>{base_url}/docs/middle</loc></url>\
+<url><loc>{base_url}/docs/alpha</loc></url></urlset>"
+        ),
+    )?;
+
+    let section_titles = |name: &str, extra: &[&str]| -> anyhow::Result<Vec<String>> {
+        let toc_path = temp.path().join(name);
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+        cmd.args([
+            "toc",
+            "create",
+            "--manifest",
+            manifest_path.to_str().unwrap(),
+            "--out",
+            toc_path.to_str().unwrap(),
+            "--engine",
+            "noop",
+        ])
+        .args(extra)
+        .assert()
+        .success();
+        let toc: Toc = serde_yaml::from_str(&fs::read_to_string(&toc_path)?)?;
+        Ok(toc.parts[0].chapters[0]
+            .sections
+            .iter()
+            .map(|section| section.title.clone())
+            .collect())
+    };
+
+    assert_eq!(
+        section_titles("toc-path.yaml", &[])?,
+        vec!["Docs", "Alpha", "Middle", "Zeta"]
+    );
+    assert_eq!(
+        section_titles("toc-crawl.yaml", &["--page-order", "crawl"])?,
+        vec!["Docs", "Zeta", "Alpha", "Middle"]
+    );
+    assert_eq!(
+        section_titles(
+            "toc-sitemap.yaml",
+            &[
+                "--page-order",
+                "sitemap",
+                "--sitemap",
+                sitemap_path.to_str().unwrap()
+            ]
+        )?,
+        vec!["Middle", "Alpha", "Docs", "Zeta"]
+    );
+    Ok(())
+}
+
+/// `/docs/` links to three guides that all redirect to `/login`, like a site behind SSO.
+fn spawn_login_wall_server() -> (String, mpsc::Sender<()>, thread::JoinHandle<()>) {
+    let server = tiny_http::Server::http("127.0.0.1:0").expect("start tiny_http server");
+    let base_url = format!("http://{}", server.server_addr());
+    let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
+    let handle = thread::spawn(move || {
+        while shutdown_rx.try_recv().is_err() {
+            let request = match server.recv_timeout(Duration::from_millis(50)) {
+                Ok(Some(req)) => req,
+                Ok(None) => continue,
+                Err(_) => break,
+            };
+            let html_header =
+                tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..])
+                    .expect("build header");
+            let response = match request.url() {
+                "/docs/" | "/docs" => tiny_http::Response::from_string(
+                    "<!doctype html><html><head><title>Docs</title></head><body><main>\
+<h1>Docs</h1><a href=\"/docs/a\">A</a> <a href=\"/docs/b\">B</a> <a href=\"/docs/c\">C</a>\
+</main></body></html>",
+                )
+                .with_header(html_header),
+                path if path.starts_with("/login") => tiny_http::Response::from_string(
+                    "<!doctype html><html><head><title>Sign in</title></head><body>\
+<form><p>Please sign in to continue.</p><input name=\"csrf\" value=\"12345\"></form></body></html>",
+                )
+                .with_header(html_header),
+                path => tiny_http::Response::from_string("")
+                    .with_status_code(302)
+                    .with_header(
+                        tiny_http::Header::from_bytes(
+                            &b"Location"[..],
+                            format!("/login?next={path}").as_bytes(),
+                        )
+                        .expect("build header"),
+                    ),
+            };
+            let _ = request.respond(response);
+        }
+    });
+    (base_url, shutdown_tx, handle)
+}
+
+#[test]
+fn crawl_warns_or_fails_on_login_wall() -> anyhow::Result<()> {
+    let (base_url, shutdown_tx, server_handle) = spawn_login_wall_server();
+    let temp = tempfile::TempDir::new()?;
+    let crawl = |name: &str| {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+        cmd.args([
+            "crawl",
+            "--url",
+            &format!("{base_url}/docs/"),
+            "--out",
+            temp.path().join(name).to_str().unwrap(),
+            "--delay-ms",
+            "0",
+        ]);
+        cmd
+    };
+
+    crawl("raw-warn")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("LOGIN WALL SUSPECTED"))
+        .stderr(predicate::str::contains("redirected to a login page"))
+        .stderr(predicate::str::contains("have the same short body"));
+
+    crawl("raw-fail")
+        .arg("--fail-on-login-wall")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("crawl looks like a login wall"));
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.join();
+
+    // A site whose pages differ passes quietly.
+    let (base_url, shutdown_tx, server_handle) = spawn_docs_server();
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+    cmd.args([
+        "crawl",
+        "--url",
+        &format!("{base_url}/docs/"),
+        "--out",
+        temp.path().join("raw-ok").to_str().unwrap(),
+        "--delay-ms",
+        "0",
+        "--fail-on-login-wall",
+    ])
+    .assert()
+    .success()
+    .stderr(predicate::str::contains("LOGIN WALL").not());
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.join();
+    Ok(())
+}
+
+/// `/docs/` links to its guide through `localhost`, like a page linking to the `www.` host.
+fn spawn_host_alias_server() -> (String, mpsc::Sender<()>, thread::JoinHandle<()>) {
+    let server = tiny_http::Server::http("127.0.0.1:0").expect("start tiny_http server");
+    let port = server.server_addr().to_ip().expect("ip listener").port();
+    let base_url = format!("http://127.0.0.1:{port}");
+    let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
+    let handle = thread::spawn(move || {
+        while shutdown_rx.try_recv().is_err() {
+            let request = match server.recv_timeout(Duration::from_millis(50)) {
+                Ok(Some(req)) => req,
+                Ok(None) => continue,
+                Err(_) => break,
+            };
+            let body = match request.url() {
+                "/docs/" | "/docs" => format!(
+                    "<!doctype html><html><head><title>Docs</title></head><body><main>\
+<h1>Docs</h1><a href=\"http://localhost:{port}/docs/guide\">Guide</a></main></body></html>"
+                ),
+                "/docs/guide" => "<!doctype html><html><head><title>Guide</title></head><body>\
+<main><h1>Guide</h1><p>Read me.</p></main></body></html>"
+                    .to_owned(),
+                _ => {
+                    let _ = request.respond(
+                        tiny_http::Response::from_string("not found").with_status_code(404),
+                    );
+                    continue;
+                }
+            };
+            let _ = request.respond(
+                tiny_http::Response::from_string(body).with_header(
+                    tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..])
+                        .expect("build header"),
+                ),
+            );
+        }
+    });
+    (base_url, shutdown_tx, handle)
+}
+
+#[test]
+fn crawl_follows_links_to_an_aliased_host() -> anyhow::Result<()> {
+    let (base_url, shutdown_tx, server_handle) = spawn_host_alias_server();
+    let temp = tempfile::TempDir::new()?;
+    let crawl = |name: &str, extra_args: &[&str]| -> anyhow::Result<Vec<String>> {
+        let raw_dir = temp.path().join(name);
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+        cmd.args([
+            "crawl",
+            "--url",
+            &format!("{base_url}/docs/"),
+            "--out",
+            raw_dir.to_str().unwrap(),
+            "--delay-ms",
+            "0",
+        ])
+        .args(extra_args)
+        .assert()
+        .success();
+        let mut urls = read_crawl_records(&raw_dir)?
+            .into_iter()
+            .map(|record| record.url)
+            .collect::<Vec<_>>();
+        urls.sort();
+        Ok(urls)
+    };
+
+    assert_eq!(crawl("raw-plain", &[])?, vec![format!("{base_url}/docs")]);
+    assert_eq!(
+        crawl("raw-alias", &["--host-alias", "localhost=127.0.0.1"])?,
+        vec![format!("{base_url}/docs"), format!("{base_url}/docs/guide")]
+    );
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.join();
+    Ok(())
+}
+
+/// `/docs/` links only to `/docs/linked`; the sitemap index's child sitemap also lists
+/// `/docs/hidden` and an out-of-scope `/blog/post`.
+fn spawn_sitemap_server() -> (String, mpsc::Sender<()>, thread::JoinHandle<()>) {
+    let server = tiny_http::Server::http("127.0.0.1:0").expect("start tiny_http server");
+    let base_url = format!("http://{}", server.server_addr());
+    let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
+    let sitemap_base = base_url.clone();
+    let handle = thread::spawn(move || {
+        while shutdown_rx.try_recv().is_err() {
+            let request = match server.recv_timeout(Duration::from_millis(50)) {
+                Ok(Some(req)) => req,
+                Ok(None) => continue,
+                Err(_) => break,
+            };
+            let page = |title: &str, body: &str| {
+                format!(
+                    "<!doctype html><html><head><title>{title}</title></head><body><main>\
+<h1>{title}</h1>{body}</main></body></html>"
+                )
+            };
+            let (body, content_type) = match request.url() {
+                "/docs/" | "/docs" => (
+                    page("Docs", "<a href=\"/docs/linked\">Linked</a>"),
+                    "text/html",
+                ),
+                "/docs/linked" => (page("Linked", "<p>Linked page.</p>"), "text/html"),
+                "/docs/hidden" => (page("Hidden", "<p>Only in the sitemap.</p>"), "text/html"),
+                "/blog/post" => (page("Post", "<p>Not docs.</p>"), "text/html"),
+                "/sitemap.xml" => (
+                    format!(
+                        "<?xml version=\"1.0\"?><sitemapindex>\
+<sitemap><loc>{sitemap_base}/sitemap-docs.xml</loc></sitemap></sitemapindex>"
+                    ),
+                    "application/xml",
+                ),
+                "/sitemap-docs.xml" => (
+                    format!(
+                        "<?xml version=\"1.0\"?><urlset>\
+<url><loc>{sitemap_base}/docs/linked</loc></url>\
+<url><loc>{sitemap_base}/docs/hidden/</loc></url>\
+<url><loc>{sitemap_base}/blog/post</loc></url></urlset>"
+                    ),
+                    "application/xml",
+                ),
+                _ => {
+                    let _ = request.respond(
+                        tiny_http::Response::from_string("not found").with_status_code(404),
+                    );
+                    continue;
+                }
+            };
+            let _ = request.respond(
+                tiny_http::Response::from_string(body).with_header(
+                    tiny_http::Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes())
+                        .expect("build header"),
+                ),
+            );
+        }
+    });
+    (base_url, shutdown_tx, handle)
+}
+
+fn crawled_urls(raw_dir: &Path) -> anyhow::Result<Vec<String>> {
+    let mut urls = read_crawl_records(raw_dir)?
+        .into_iter()
+        .map(|record| record.url)
+        .collect::<Vec<_>>();
+    urls.sort();
+    Ok(urls)
+}
+
+#[test]
+fn crawl_seeds_from_sitemap_and_falls_back_without_one() -> anyhow::Result<()> {
+    let (base_url, shutdown_tx, server_handle) = spawn_sitemap_server();
+    let temp = tempfile::TempDir::new()?;
+    let crawl = |base_url: &str, name: &str, extra_args: &[&str]| {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+        cmd.args([
+            "crawl",
+            "--url",
+            &format!("{base_url}/docs/"),
+            "--out",
+            temp.path().join(name).to_str().unwrap(),
+            "--delay-ms",
+            "0",
+        ])
+        .args(extra_args);
+        cmd
+    };
+
+    crawl(&base_url, "raw-links", &[]).assert().success();
+    assert_eq!(
+        crawled_urls(&temp.path().join("raw-links"))?,
+        vec![
+            format!("{base_url}/docs"),
+            format!("{base_url}/docs/linked")
+        ]
+    );
+
+    crawl(&base_url, "raw-sitemap", &["--seed-from-sitemap"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("seeded from sitemap"));
+    assert_eq!(
+        crawled_urls(&temp.path().join("raw-sitemap"))?,
+        vec![
+            format!("{base_url}/docs"),
+            format!("{base_url}/docs/hidden"),
+            format!("{base_url}/docs/linked"),
+        ]
+    );
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.join();
+
+    // No sitemap: the crawl still follows links and says why it did not seed.
+    let (base_url, shutdown_tx, server_handle) = spawn_host_alias_server();
+    crawl(&base_url, "raw-fallback", &["--seed-from-sitemap"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "--seed-from-sitemap found no usable sitemap",
+        ));
+    assert_eq!(
+        crawled_urls(&temp.path().join("raw-fallback"))?,
+        vec![format!("{base_url}/docs")]
+    );
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.join();
+    Ok(())
+}
+
+/// `/docs/` links to `a` and `b`, `a` links to `c`; every requested path is sent on the channel.
+fn spawn_resume_server() -> (
+    String,
+    mpsc::Receiver<String>,
+    mpsc::Sender<()>,
+    thread::JoinHandle<()>,
+) {
+    let server = tiny_http::Server::http("127.0.0.1:0").expect("start tiny_http server");
+    let base_url = format!("http://{}", server.server_addr());
+    let (seen_tx, seen_rx) = mpsc::channel::<String>();
+    let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
+    let handle = thread::spawn(move || {
+        while shutdown_rx.try_recv().is_err() {
+            let request = match server.recv_timeout(Duration::from_millis(50)) {
+                Ok(Some(req)) => req,
+                Ok(None) => continue,
+                Err(_) => break,
+            };
+            let _ = seen_tx.send(request.url().to_owned());
+            let links = match request.url() {
+                "/docs/" | "/docs" => "<a href=\"/docs/a\">A</a> <a href=\"b\">B</a>",
+                "/docs/a" => "<a href=\"/docs/c#part\">C</a>",
+                "/docs/b" | "/docs/c" => "<p>Leaf.</p>",
+                "/docs/old" => {
+                    let _ = request.respond(
+                        tiny_http::Response::from_string("")
+                            .with_status_code(301)
+                            .with_header(
+                                tiny_http::Header::from_bytes(&b"Location"[..], &b"/docs/a"[..])
+                                    .expect("build header"),
+                            ),
+                    );
+                    continue;
+                }
+                _ => {
+                    let _ = request.respond(
+                        tiny_http::Response::from_string("not found").with_status_code(404),
+                    );
+                    continue;
+                }
+            };
+            let body = format!(
+                "<!doctype html><html><head><title>{0}</title></head><body><main>\
+<h1>{0}</h1>{links}</main></body></html>",
+                request.url()
+            );
+            let _ = request.respond(
+                tiny_http::Response::from_string(body).with_header(
+                    tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..])
+                        .expect("build header"),
+                ),
+            );
+        }
+    });
+    (base_url, seen_rx, shutdown_tx, handle)
+}
+
+#[test]
+fn crawl_resume_page_list_replaces_stale_html_and_skips_known_redirect_targets()
+-> anyhow::Result<()> {
+    let (base_url, _seen_rx, shutdown_tx, server_handle) = spawn_resume_server();
+    let temp = tempfile::TempDir::new()?;
+    let raw_dir = temp.path().join("raw");
+    let crawl = |extra_args: &[&str]| {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+        cmd.args([
+            "crawl",
+            "--url",
+            &format!("{base_url}/docs/"),
+            "--out",
+            raw_dir.to_str().unwrap(),
+            "--delay-ms",
+            "0",
+        ])
+        .args(extra_args);
+        cmd
+    };
+
+    crawl(&["--page-url", &format!("{base_url}/docs/a")])
+        .assert()
+        .success();
+    let first = read_crawl_records(&raw_dir)?;
+    assert_eq!(first.len(), 1);
+
+    // HTML an interrupted run saved without writing its record.
+    let a_html = Path::new(first[0].raw_html_path.as_deref().expect("raw html for a"));
+    let stale_b = a_html
+        .parent()
+        .and_then(Path::parent)
+        .expect("docs dir")
+        .join("b")
+        .join("index.html");
+    fs::create_dir_all(stale_b.parent().expect("b dir"))?;
+    fs::write(&stale_b, "stale")?;
+
+    // `/docs/old` redirects to `/docs/a`, which the snapshot already holds.
+    crawl(&[
+        "--resume",
+        "--page-url",
+        &format!("{base_url}/docs/old"),
+        "--page-url",
+        &format!("{base_url}/docs/b"),
+    ])
+    .assert()
+    .success();
+
+    let records = read_crawl_records(&raw_dir)?;
+    let urls = records
+        .iter()
+        .map(|record| record.normalized_url.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        urls,
+        vec![format!("{base_url}/docs/a"), format!("{base_url}/docs/b")]
+    );
+    let b_html = fs::read_to_string(&stale_b)?;
+    assert!(b_html.contains("<h1>/docs/b</h1>"), "{b_html}");
+
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.join();
+    Ok(())
+}
+
+#[test]
+fn crawl_resume_fetches_only_pages_missing_from_the_snapshot() -> anyhow::Result<()> {
+    let (base_url, seen_rx, shutdown_tx, server_handle) = spawn_resume_server();
+    let temp = tempfile::TempDir::new()?;
+    let raw_dir = temp.path().join("raw");
+    let crawl = |extra_args: &[&str]| {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sitebookify");
+        cmd.args([
+            "crawl",
+            "--url",
+            &format!("{base_url}/docs/"),
+            "--out",
+            raw_dir.to_str().unwrap(),
+            "--delay-ms",
+            "0",
+        ])
+        .args(extra_args);
+        cmd
+    };
+    let requested_pages = || {
+        let mut paths = seen_rx
+            .try_iter()
+            .filter(|path| path.starts_with("/docs"))
+            .map(|path| path.trim_end_matches('/').to_owned())
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths
+    };
+
+    crawl(&["--max-pages", "2"]).assert().success();
+    let first = read_crawl_records(&raw_dir)?;
+    assert_eq!(first.len(), 2);
+    let first_pages = requested_pages();
+
+    // Without --resume the existing snapshot is still refused.
+    crawl(&[]).assert().failure();
+    // A record cut short by an interrupted run is dropped.
+    fs::OpenOptions::new()
+        .append(true)
+        .open(raw_dir.join("crawl.jsonl"))?
+        .write_all(b"{\"url\":\"http://trunc")?;
+
+    crawl(&["--resume"]).assert().success();
+    let mut all_pages = first_pages.clone();
+    all_pages.extend(requested_pages());
+    all_pages.sort();
+    assert_eq!(all_pages, vec!["/docs", "/docs/a", "/docs/b", "/docs/c"]);
+
+    let records = read_crawl_records(&raw_dir)?;
+    let as_json = |records: &[CrawlRecord]| {
+        records
+            .iter()
+            .map(|record| serde_json::to_string(record).expect("serialize crawl record"))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        as_json(&records[..2]),
+        as_json(&first),
+        "earlier records are kept as written"
+    );
+    let mut urls = records
+        .iter()
+        .map(|record| record.normalized_url.clone())
+        .collect::<Vec<_>>();
+    urls.sort();
+    assert_eq!(
+        urls,
+        ["docs", "docs/a", "docs/b", "docs/c"]
+            .iter()
+            .map(|path| format!("{base_url}/{path}"))
+            .collect::<Vec<_>>()
+    );
+    let depth_of = |url: &str| {
+        records
+            .iter()
+            .find(|record| record.normalized_url == format!("{base_url}{url}"))
+            .map(|record| record.depth)
+    };
+    assert_eq!(depth_of("/docs/c"), Some(2));
+    let mut indices = records
+        .iter()
+        .filter_map(|record| record.discovery_index)
+        .collect::<Vec<_>>();
+    indices.sort();
+    indices.dedup();
+    assert_eq!(indices.len(), 4);
+    for record in &records {
+        assert!(
+            record
+                .raw_html_path
+                .as_deref()
+                .is_some_and(|path| Path::new(path).is_file())
+        );
+    }
+
+    // Nothing left: no page is fetched and no record is added.
+    crawl(&["--resume"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("no unfetched page"));
+    assert!(requested_pages().is_empty());
+    assert_eq!(read_crawl_records(&raw_dir)?.len(), 4);
+
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.join();
+    Ok(())
+}
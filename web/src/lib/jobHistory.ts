@@ -2,7 +2,7 @@ const STORAGE_KEY = "sitebookify.job_history.v1";
 const MAX_AGE_MS = 24 * 60 * 60 * 1000;
 const MAX_ITEMS = 200;
 
-export type StoredJobState = "queued" | "running" | "done" | "error" | "unknown";
+export type StoredJobState = "queued" | "running" | "done" | "error" | "cancelled" | "unknown";
 
 export type StoredJob = {
   jobId: string;
@@ -47,7 +47,11 @@ function normalizeJob(v: unknown): StoredJob | null {
       : undefined;
 
   const state =
-    v.state === "queued" || v.state === "running" || v.state === "done" || v.state === "error"
+    v.state === "queued" ||
+    v.state === "running" ||
+    v.state === "done" ||
+    v.state === "error" ||
+    v.state === "cancelled"
       ? v.state
       : "unknown";
 
@@ -29,7 +29,13 @@ type Preview = {
   source: "sitemap" | "sitemap_index" | "links";
   estimated_pages: number;
   estimated_chapters: number;
-  chapters: Array<{ title: string; pages: number }>;
+  chapters: Array<{
+    title: string;
+    pages: number;
+    estimated_characters: number;
+    estimated_input_tokens: number;
+    estimated_output_tokens: number;
+  }>;
   sample_urls: string[];
   notes: string[];
   total_characters: number;
@@ -91,7 +97,9 @@ export function HomePage({ client, navigate }: Props) {
         ? "Your last book is ready"
         : recentJob.state === "error"
           ? "Your last book failed"
-          : "A book is being generated";
+          : recentJob.state === "cancelled"
+            ? "Your last book was cancelled"
+            : "A book is being generated";
     const subtitle =
       recentJob.sourceUrl && recentJob.sourceUrl.length > 0
         ? recentJob.sourceUrl
@@ -292,7 +300,7 @@ export function HomePage({ client, navigate }: Props) {
             <div className="row wrap" style={{ marginTop: 8 }}>
               {preview.chapters.slice(0, 10).map((ch) => (
                 <span key={ch.title} className="pill">
-                  {ch.title} &middot; {ch.pages}p
+                  {ch.title} &middot; {ch.pages}p &middot; ~{formatInt(ch.estimated_characters)} chars
                 </span>
               ))}
             </div>
@@ -347,7 +355,11 @@ export function HomePage({ client, navigate }: Props) {
                   <span className="pill">status</span>
                   <span
                     className={
-                      j.state === "error" ? "error" : j.state === "done" ? "success" : "muted"
+                      j.state === "error" || j.state === "cancelled"
+                        ? "error"
+                        : j.state === "done"
+                          ? "success"
+                          : "muted"
                     }
                   >
                     {j.state ?? "unknown"}
@@ -66,6 +66,8 @@ function storedState(state?: Job_State) {
       return "done" as const;
     case Job_State.ERROR:
       return "error" as const;
+    case Job_State.CANCELLED:
+      return "cancelled" as const;
     default:
       return "unknown" as const;
   }
@@ -73,7 +75,7 @@ function storedState(state?: Job_State) {
 
 function getPhase(job: Job | null): VisualPhase {
   if (!job) return "loading";
-  if (job.state === Job_State.ERROR) return "error";
+  if (job.state === Job_State.ERROR || job.state === Job_State.CANCELLED) return "error";
   if (job.state === Job_State.DONE) return "done";
   if (job.state === Job_State.RUNNING && (job.progressPercent ?? 0) > 0) return "compiling";
   return "processing";
@@ -230,6 +232,7 @@ export function JobPage({ client, jobId, navigate }: Props) {
     if (!cleanJobId) return;
     if (job?.state === Job_State.ERROR) return;
     if (job?.state === Job_State.DONE) return;
+    if (job?.state === Job_State.CANCELLED) return;
     let stopped = false;
 
     const tick = async () => {